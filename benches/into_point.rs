@@ -0,0 +1,140 @@
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs::File;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use libstorage::ir::{TsPoint, TsValue};
+use libstorage::scaleio::{SystemStatistics, VolumeSelectedStatisticsResponse};
+use libstorage::IntoPoint;
+
+// Counts allocations so the point constructors can be compared by more
+// than wall time
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// A system statistics sized point, 112 fields.  The names are leaked
+// once so only the maps themselves allocate while it's built
+fn field_names() -> Vec<&'static str> {
+    (0..112)
+        .map(|i| &*Box::leak(format!("field_{}", i).into_boxed_str()))
+        .collect()
+}
+
+fn build_point(p: &mut TsPoint, names: &[&'static str]) {
+    for (i, name) in names.iter().enumerate() {
+        p.add_field(*name, TsValue::Long(i as u64));
+    }
+}
+
+fn allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn read_fixture(path: &str) -> String {
+    let mut f = File::open(path).unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+    buff
+}
+
+// A hand written conversion with static field names
+fn system_statistics(c: &mut Criterion) {
+    let stats: SystemStatistics =
+        serde_json::from_str(&read_fixture("tests/scaleio/system_statistics.json")).unwrap();
+    c.bench_function("system_statistics_into_point", |b| {
+        b.iter(|| black_box(&stats).into_point(Some("scaleio_system"), true))
+    });
+}
+
+// Derived conversions with BWC fields
+fn volume_statistics(c: &mut Criterion) {
+    let stats: VolumeSelectedStatisticsResponse = serde_json::from_str(&read_fixture(
+        "tests/scaleio/volumeSelectedStatisticsResponse.json",
+    ))
+    .unwrap();
+    c.bench_function("volume_statistics_into_point", |b| {
+        b.iter(|| black_box(&stats).into_point(None, true))
+    });
+}
+
+// Growing the maps as fields are added against sizing them up front
+fn point_capacity(c: &mut Criterion) {
+    let names = field_names();
+    let grown = allocations(|| {
+        let mut p = TsPoint::new("scaleio_sys_stats", true);
+        build_point(&mut p, &names);
+        black_box(p);
+    });
+    let sized = allocations(|| {
+        let mut p = TsPoint::with_capacity("scaleio_sys_stats", true, 112, 0);
+        build_point(&mut p, &names);
+        black_box(p);
+    });
+    println!(
+        "112 field point allocations: new {}, with_capacity {}",
+        grown, sized
+    );
+
+    c.bench_function("ts_point_new", |b| {
+        b.iter(|| {
+            let mut p = TsPoint::new("scaleio_sys_stats", true);
+            build_point(&mut p, &names);
+            p
+        })
+    });
+    c.bench_function("ts_point_with_capacity", |b| {
+        b.iter(|| {
+            let mut p = TsPoint::with_capacity("scaleio_sys_stats", true, 112, 0);
+            build_point(&mut p, &names);
+            p
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    system_statistics,
+    volume_statistics,
+    point_capacity
+);
+criterion_main!(benches);
@@ -18,11 +18,19 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use proc_macro2::{Ident, Span};
+use proc_macro2::{Ident, TokenStream as TokenStream2};
 use quote::quote;
 use syn::{parse_macro_input, DeriveInput};
 
-#[proc_macro_derive(IntoPoint)]
+/// Fields and tags are named after the rust field unless the struct has
+/// `#[point(serde_names)]`, then they are named after what serde reads the
+/// field from.  That follows `#[serde(rename_all = "...")]` on the struct
+/// and `#[serde(rename = "...")]` on a field.  `#[point(extend_with = "f")]`
+/// on the struct calls `f(&self, &mut point)` once the fields are added.
+/// `#[point(latency)]` on a BWC field leaves its average off the point
+/// when no io was sampled and adds the `{field}_microsec` latency.
+/// `#[point(rename = "...")]` on a field names it explicitly either way.
+#[proc_macro_derive(IntoPoint, attributes(point))]
 pub fn point_derive(input: TokenStream) -> TokenStream {
     // Parse the string representation
     let ast = parse_macro_input!(input as DeriveInput);
@@ -34,7 +42,7 @@ pub fn point_derive(input: TokenStream) -> TokenStream {
     TokenStream::from(generated)
 }
 
-#[proc_macro_derive(IntoChildPoint)]
+#[proc_macro_derive(IntoChildPoint, attributes(point))]
 pub fn child_point_derive(input: TokenStream) -> TokenStream {
     // Parse the string representation
     let ast = parse_macro_input!(input as DeriveInput);
@@ -49,7 +57,17 @@ pub fn child_point_derive(input: TokenStream) -> TokenStream {
 fn impl_point(ast: &DeriveInput, child: bool) -> TokenStream {
     let name = &ast.ident;
     match ast.data {
-        syn::Data::Struct(ref data) => impl_struct_point_fields(name, &data.fields, child),
+        syn::Data::Struct(ref data) => {
+            // With #[point(serde_names)] fields and tags are named after the
+            // serde (api) name of the field instead of the rust identifier
+            let names = if has_point_word(&ast.attrs, "serde_names") {
+                FieldNames::Serde(find_serde_value(&ast.attrs, "rename_all"))
+            } else {
+                FieldNames::Rust
+            };
+            let extend_with = find_point_path(&ast.attrs, "extend_with");
+            impl_struct_point_fields(name, &data.fields, child, &names, extend_with)
+        }
         syn::Data::Enum(ref data) => {
             println!("into_enum_point_fields called");
             impl_enum_point_fields(name, &data.variants.iter().collect())
@@ -58,348 +76,399 @@ fn impl_point(ast: &DeriveInput, child: bool) -> TokenStream {
     }
 }
 
-fn find_optional_type(field: syn::Field) -> Option<Ident> {
-    match field.clone().ty {
-        syn::Type::Path(p) => {
-            if let Some(i) = p.path.segments.clone().into_iter().next() {
-                match i.arguments {
-                    syn::PathArguments::AngleBracketed(a) => {
-                        //println!("{:?}", a);
-                        for ty in a.args {
-                            match ty {
-                                syn::GenericArgument::Type(p2) => match p2 {
-                                    syn::Type::Path(p2) => {
-                                        if let Some(i2) =
-                                            p2.path.segments.clone().into_iter().next()
-                                        {
-                                            return Some(i2.ident);
-                                        } else {
-                                            return None;
-                                        }
-                                    }
-                                    _ => return None,
-                                },
-                                _ => return None,
-                            }
-                        }
-                    }
-                    _ => {
-                        return None;
-                    }
+// The last segment of a type path and its first type argument, ie
+// (Option, Some(Vec<String>)) for Option<Vec<String>>.  Matching on the
+// last segment lets std::time::Duration or uuid::Uuid be spelled out
+fn split_type(ty: &syn::Type) -> Option<(Ident, Option<&syn::Type>)> {
+    let segment = match ty {
+        syn::Type::Path(p) => p.path.segments.iter().last()?,
+        _ => return None,
+    };
+    let arg = match segment.arguments {
+        syn::PathArguments::AngleBracketed(ref a) => a.args.iter().find_map(|arg| match arg {
+            syn::GenericArgument::Type(t) => Some(t),
+            _ => None,
+        }),
+        _ => None,
+    };
+    Some((segment.ident.clone(), arg))
+}
+
+// Whether a value is a field of the struct or the contents of an Option
+// field
+#[derive(Clone, Copy, PartialEq)]
+enum Presence {
+    Required,
+    Optional,
+}
+
+// The statements adding a value of type ty to the point p under key.
+// value is an expression borrowing it, ie `(&self.field)` or the `v` of
+// `if let Some(v) = &self.field`, so bare and optional fields share the
+// handling of every type.  Types that aren't known return None and are
+// left off the point.
+//
+// Two types differ when optional, as points have always been written
+// that way: an optional BWC only emits its average, and an optional Vec
+// is a field where a bare Vec is a tag
+fn value_quote(
+    key: &str,
+    ty: &syn::Type,
+    value: &TokenStream2,
+    presence: Presence,
+) -> Option<TokenStream2> {
+    let (ident, arg) = split_type(ty)?;
+    let q = match ident.to_string().as_str() {
+        "BWC" if presence == Presence::Optional => quote! {
+            p.add_field(#key, TsValue::Long(#value.average()));
+        },
+        "BWC" => {
+            // The compound names are built here so the generated
+            // code only hands static strs to the point
+            let total_weight_key = format!("{}_total_weight_in_kb", key);
+            let num_seconds_key = format!("{}_num_seconds", key);
+            let num_occured_key = format!("{}_num_occured", key);
+            quote! {
+                p.add_field(#key, TsValue::Long(#value.average()));
+                p.add_field(#total_weight_key, TsValue::Long(#value.total_weight_in_kb));
+                p.add_field(#num_seconds_key, TsValue::Long(#value.num_seconds));
+                p.add_field(#num_occured_key, TsValue::Long(#value.num_occured));
+            }
+        }
+        "String" => quote! {
+            if !#value.is_empty() {
+                p.add_tag(#key, TsValue::String(#value.clone()));
+            }
+        },
+        "HostAddress" => quote! {
+            p.add_tag(#key, TsValue::String(#value.to_string()));
+        },
+        "Duration" => quote! {
+            p.add_field(#key, TsValue::Duration(*#value));
+        },
+        "i16" => quote! {
+            p.add_field(#key, TsValue::Integer(i32::from(*#value)));
+        },
+        "i32" => quote! {
+            p.add_field(#key, TsValue::Integer(*#value));
+        },
+        "i64" => quote! {
+            p.add_field(#key, TsValue::SignedLong(*#value));
+        },
+        "Uuid" => quote! {
+            p.add_field(#key, TsValue::String(#value.to_string()));
+        },
+        "u8" => quote! {
+            p.add_field(#key, TsValue::Byte(*#value));
+        },
+        "u16" => quote! {
+            p.add_field(#key, TsValue::Short(*#value));
+        },
+        "u64" => quote! {
+            p.add_field(#key, TsValue::Long(*#value));
+        },
+        "f64" => quote! {
+            p.add_field(#key, TsValue::Float(*#value));
+        },
+        "bool" => quote! {
+            p.add_field(#key, TsValue::Boolean(*#value));
+        },
+        "Vec" => {
+            let (item, _) = split_type(arg?)?;
+            let values = match item.to_string().as_str() {
+                "String" => quote! { TsValue::StringVec(#value.clone()) },
+                "u64" => quote! { TsValue::LongVec(#value.clone()) },
+                "Uuid" => quote! {
+                    TsValue::StringVec(
+                        #value.iter().map(|i| i.to_string()).collect::<Vec<String>>(),
+                    )
+                },
+                _ => return None,
+            };
+            match presence {
+                Presence::Required => quote! { p.add_tag(#key, #values); },
+                Presence::Optional => quote! { p.add_field(#key, #values); },
+            }
+        }
+        "Option" if presence == Presence::Required => {
+            let inner = value_quote(key, arg?, &quote!(v), Presence::Optional)?;
+            quote! {
+                if let Some(v) = #value {
+                    #inner
                 }
-                return None;
-            } else {
-                return None;
             }
         }
         _ => return None,
+    };
+    Some(q)
+}
+
+// The statements adding a #[point(latency)] BWC or Option<BWC>.  A
+// latency meter that saw no io averages to 0, which would read as a
+// genuinely fast device, so the average and the latency in microseconds
+// are only added once something was sampled.  A bare BWC still adds its
+// raw counters like any other
+fn latency_quote(key: &str, ty: &syn::Type, value: &TokenStream2) -> TokenStream2 {
+    let microsec_key = format!("{}_microsec", key);
+    let sampled = |value: &TokenStream2| {
+        quote! {
+            if let Some(latency) = #value.iosize_or_latency() {
+                p.add_field(#key, TsValue::Long(#value.average()));
+                p.add_field(#microsec_key, TsValue::Long(latency));
+            }
+        }
+    };
+    match split_type(ty) {
+        Some((ident, Some(arg))) if ident == "Option" => {
+            if !matches!(split_type(arg), Some((inner, _)) if inner == "BWC") {
+                panic!("point(latency) expects a BWC or Option<BWC> field");
+            }
+            let inner = sampled(&quote!(v));
+            quote! {
+                if let Some(v) = #value {
+                    #inner
+                }
+            }
+        }
+        Some((ident, _)) if ident == "BWC" => {
+            let inner = sampled(value);
+            let total_weight_key = format!("{}_total_weight_in_kb", key);
+            let num_seconds_key = format!("{}_num_seconds", key);
+            let num_occured_key = format!("{}_num_occured", key);
+            quote! {
+                #inner
+                p.add_field(#total_weight_key, TsValue::Long(#value.total_weight_in_kb));
+                p.add_field(#num_seconds_key, TsValue::Long(#value.num_seconds));
+                p.add_field(#num_occured_key, TsValue::Long(#value.num_occured));
+            }
+        }
+        _ => panic!("point(latency) expects a BWC or Option<BWC> field"),
     }
 }
 
-// TODO: Merge this with find_optional_type()
-fn find_optional_vec_type(field: syn::Field) -> Option<Ident> {
-    match field.clone().ty {
-        syn::Type::Path(p) => {
-            if let Some(i) = p.path.segments.clone().into_iter().next() {
-                match i.arguments {
-                    syn::PathArguments::AngleBracketed(a) => {
-                        for ty in a.args {
-                            match ty {
-                                syn::GenericArgument::Type(p2) => match p2 {
-                                    syn::Type::Path(p2) => {
-                                        if let Some(i2) =
-                                            p2.path.segments.clone().into_iter().next()
-                                        {
-                                            match i2.arguments {
-                                                syn::PathArguments::AngleBracketed(a2) => {
-                                                    for ty2 in a2.args {
-                                                        match ty2 {
-                                                            syn::GenericArgument::Type(p3) => {
-                                                                match p3 {
-                                                                    syn::Type::Path(p3) => {
-                                                                        if let Some(i3) = p3
-                                                                            .path
-                                                                            .segments
-                                                                            .clone()
-                                                                            .into_iter()
-                                                                            .next()
-                                                                        {
-                                                                            return Some(i3.ident);
-                                                                        } else {
-                                                                            return None;
-                                                                        }
-                                                                    }
-                                                                    _ => return None,
-                                                                }
-                                                            }
-                                                            _ => return None,
-                                                        }
-                                                    } // ends for ty2
-                                                }
-                                                _ => return None,
-                                            }
-                                            return None;
-                                        } else {
-                                            return None;
-                                        }
-                                    }
-                                    _ => return None,
-                                },
-                                _ => return None,
-                            }
-                        }
+// Look for #[point(key = "path::to::fn")].  On a field, flatten_with names
+// a function with the signature fn(&FieldType, &mut TsPoint) that is called
+// in place of the type based handling below.  On the struct, extend_with
+// names a function fn(&Self, &mut TsPoint) called after all of the fields,
+// for values computed from more than one field
+fn find_point_path(attrs: &[syn::Attribute], key: &str) -> Option<syn::Path> {
+    for attr in attrs {
+        if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+            if list.ident != "point" {
+                continue;
+            }
+            for nested in list.nested.iter() {
+                if let syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) = *nested {
+                    if nv.ident != key {
+                        continue;
                     }
-                    _ => {
-                        return None;
+                    if let syn::Lit::Str(ref lit) = nv.lit {
+                        match lit.parse::<syn::Path>() {
+                            Ok(path) => return Some(path),
+                            Err(_) => panic!(
+                                "point({}) expects a function path, found {:?}",
+                                key,
+                                lit.value()
+                            ),
+                        }
                     }
                 }
-                return None;
-            } else {
-                return None;
             }
         }
-        _ => return None,
     }
+    None
 }
 
-fn impl_struct_point_fields(name: &syn::Ident, fields: &syn::Fields, child: bool) -> TokenStream {
-    let _bool: Ident = Ident::new("bool", Span::call_site());
-    let bwc: Ident = Ident::new("BWC", Span::call_site());
-    let f_64: Ident = Ident::new("f64", Span::call_site());
-    let i_32: Ident = Ident::new("i32", Span::call_site());
-    let i_64: Ident = Ident::new("i64", Span::call_site());
-    let optional: Ident = Ident::new("Option", Span::call_site());
-    let s: Ident = Ident::new("String", Span::call_site());
-    let u_8: Ident = Ident::new("u8", Span::call_site());
-    let u_16: Ident = Ident::new("u16", Span::call_site());
-    let u_64: Ident = Ident::new("u64", Span::call_site());
-    let uuid: Ident = Ident::new("Uuid", Span::call_site());
-    let _value: Ident = Ident::new("Value", Span::call_site());
-    let _vec: Ident = Ident::new("Vec", Span::call_site());
+// How the derive names the fields and tags of a point
+enum FieldNames {
+    // The rust identifier, the default
+    Rust,
+    // What serde reads the field from, given the container's rename_all
+    Serde(Option<String>),
+}
 
-    let mut result = Vec::new();
-    for field in fields {
-        let ident = &field.ident;
-        let ident_type = match field.clone().ty {
-            syn::Type::Path(p) => {
-                if let Some(i) = p.path.segments.clone().into_iter().next() {
-                    Some(i.ident)
-                } else {
-                    None
+impl FieldNames {
+    fn key(&self, field: &syn::Field) -> String {
+        if let Some(rename) = find_point_str(&field.attrs, "rename") {
+            return rename;
+        }
+        let ident = field
+            .ident
+            .as_ref()
+            .map(|i| i.to_string())
+            .unwrap_or_default();
+        match self {
+            FieldNames::Rust => ident,
+            FieldNames::Serde(rename_all) => {
+                if let Some(rename) = find_serde_value(&field.attrs, "rename") {
+                    return rename;
+                }
+                match rename_all {
+                    Some(rule) => apply_rename_all(rule, ident.trim_start_matches("r#")),
+                    None => ident,
                 }
             }
-            _ => None,
-        };
+        }
+    }
+}
 
-        // In the case of optional types like Option<String> we need to
-        // find the second parameter or we won't know what to do below
-        let angle_type: Option<Ident> = if let Some(i_type) = ident_type.clone() {
-            if i_type == optional {
-                find_optional_type(field.clone())
-            } else {
-                None
+// Look for #[point(key = "value")]
+fn find_point_str(attrs: &[syn::Attribute], key: &str) -> Option<String> {
+    for attr in attrs {
+        if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+            if list.ident != "point" {
+                continue;
             }
-        } else {
-            None
-        };
+            for nested in list.nested.iter() {
+                if let syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) = *nested {
+                    if nv.ident != key {
+                        continue;
+                    }
+                    if let syn::Lit::Str(ref lit) = nv.lit {
+                        return Some(lit.value());
+                    }
+                }
+            }
+        }
+    }
+    None
+}
 
-        let vec_angle_type: Option<Ident> = if let Some(i_type) = ident_type.clone() {
-            if i_type == _vec {
-                find_optional_type(field.clone())
-            } else {
-                None
+// Look for #[point(word)]
+fn has_point_word(attrs: &[syn::Attribute], word: &str) -> bool {
+    for attr in attrs {
+        if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+            if list.ident != "point" {
+                continue;
             }
-        } else {
-            None
-        };
+            for nested in list.nested.iter() {
+                if let syn::NestedMeta::Meta(syn::Meta::Word(ref w)) = *nested {
+                    if w == word {
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+    false
+}
 
-        match ident_type {
-            Some(i_type) => {
-                if i_type == bwc {
-                    result.push(quote! {
-                        p.add_field(stringify!(#ident), TsValue::Long(self.#ident.average()));
-                        p.add_field(format!("{}_total_weight_in_kb",stringify!(#ident)), TsValue::Long(self.#ident.total_weight_in_kb));
-                        p.add_field(format!("{}_num_seconds",stringify!(#ident)), TsValue::Long(self.#ident.num_seconds));
-                        p.add_field(format!("{}_num_occured",stringify!(#ident)), TsValue::Long(self.#ident.num_occured));
-                    });
-                } else if i_type == s {
-                    result.push(quote! {
-                        if !self.#ident.is_empty(){
-                            p.add_tag(stringify!(#ident), TsValue::String(self.#ident.clone()));
-                        }
-                    });
-                } else if i_type == i_32 {
-                    result.push(quote! {
-                        p.add_field(stringify!(#ident), TsValue::Integer(self.#ident));
-                    });
-                } else if i_type == i_64 {
-                    result.push(quote! {
-                        p.add_field(stringify!(#ident), TsValue::SignedLong(self.#ident));
-                    });
-                } else if i_type == uuid {
-                    result.push(quote! {
-                        p.add_field(stringify!(#ident), TsValue::String(self.#ident.to_string()));
-                    });
-                } else if i_type == u_8 {
-                    result.push(quote! {
-                        p.add_field(stringify!(#ident), TsValue::Byte(self.#ident));
-                    });
-                } else if i_type == u_16 {
-                    result.push(quote! {
-                        p.add_field(stringify!(#ident), TsValue::Short(self.#ident));
-                    });
-                } else if i_type == u_64 {
-                    result.push(quote! {
-                        p.add_field(stringify!(#ident), TsValue::Long(self.#ident));
-                    });
-                } else if i_type == f_64 {
-                    result.push(quote! {
-                        p.add_field(stringify!(#ident), TsValue::Float(self.#ident));
-                    });
-                } else if i_type == _bool {
-                    result.push(quote! {
-                        p.add_field(stringify!(#ident), TsValue::Boolean(self.#ident));
-                    });
-                } else if i_type == _vec {
-                    match &vec_angle_type {
-                        Some(ref vec_type) => {
-                            if *vec_type == s {
-                                result.push(quote! {
-                                    p.add_tag(stringify!(#ident), TsValue::StringVec(
-                                        self.#ident.clone()
-                                    ));
-                                });
-                            } else if *vec_type == u_64 {
-                                result.push(quote! {
-                                    p.add_tag(stringify!(#ident), TsValue::LongVec(
-                                        self.#ident.clone()
-                                    ));
-                                });
-                            } else if *vec_type == uuid {
-                                result.push(quote! {
-                                    p.add_tag(stringify!(#ident), TsValue::StringVec(
-                                        self.#ident.iter().map(|i| i.to_string()).collect::<Vec<String>>(),
-                                    ));
-                                });
-                            } else {
-                                //println!("vec found {} with inner: {:?}", i_type, vec_angle_type);
-                            }
-                        }
-                        None => {
-                            // Unable to identify this type
-                            println!(
-                                "Unable to identify vec type for {:?} {:?} {:?}",
-                                ident, i_type, vec_angle_type
-                            );
+// Look for #[serde(key = "value")] or #[serde(key(deserialize = "value"))]
+fn find_serde_value(attrs: &[syn::Attribute], key: &str) -> Option<String> {
+    for attr in attrs {
+        if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+            if list.ident != "serde" {
+                continue;
+            }
+            for nested in list.nested.iter() {
+                match *nested {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if nv.ident == key => {
+                        if let syn::Lit::Str(ref lit) = nv.lit {
+                            return Some(lit.value());
                         }
                     }
-                } else if i_type == optional {
-                    //println!("optional type: {:?} {:?} {:?}", ident, i_type, angle_type,);
-                    match angle_type {
-                        Some(option_type) => {
-                            if option_type == s {
-                                result.push(quote! {
-                                    if let Some(ref s) = self.#ident{
-                                        if !s.is_empty(){
-                                            p.add_tag(stringify!(#ident),
-                                                TsValue::String(s.clone()));
-                                        }
-                                    }
-                                });
-                            } else if option_type == _bool {
-                                result.push(quote! {
-                                    if self.#ident.is_some(){
-                                        p.add_field(stringify!(#ident),
-                                            TsValue::Boolean(self.#ident.unwrap()));
-                                    }
-                                });
-                            } else if option_type == bwc {
-                                result.push(quote! {
-                                    if self.#ident.is_some(){
-                                        let bwc_val = self.#ident.clone().unwrap();
-                                        p.add_field(stringify!(#ident),
-                                            TsValue::Long(bwc_val.average()));
-                                    }
-                                });
-                            } else if option_type == i_32 {
-                                result.push(quote! {
-                                    if self.#ident.is_some(){
-                                        p.add_field(stringify!(#ident),
-                                            TsValue::Integer(self.#ident.unwrap()));
-                                    }
-                                });
-                            } else if option_type == i_64 {
-                                result.push(quote! {
-                                    if self.#ident.is_some(){
-                                        p.add_field(stringify!(#ident),
-                                            TsValue::SignedLong(self.#ident.unwrap()));
-                                    }
-                                });
-                            } else if option_type == uuid {
-                                result.push(quote! {
-                                    if self.#ident.is_some(){
-                                        p.add_field(stringify!(#ident),
-                                            TsValue::String(self.#ident.unwrap().to_string()));
-                                    }
-                                });
-                            } else if option_type == u_64 {
-                                result.push(quote! {
-                                    if self.#ident.is_some(){
-                                        p.add_field(stringify!(#ident),
-                                            TsValue::Long(self.#ident.unwrap()));
-                                    }
-                                });
-                            } else if option_type == f_64 {
-                                result.push(quote! {
-                                    if self.#ident.is_some(){
-                                        p.add_field(stringify!(#ident),
-                                            TsValue::Float(self.#ident.unwrap()));
-                                    }
-                                });
-                            } else if option_type == _vec {
-                                let inner_vec_angle_type: Option<Ident> =
-                                    find_optional_vec_type(field.clone());
-                                match &inner_vec_angle_type {
-                                    Some(ref vec_type) => {
-                                        if *vec_type == s {
-                                            result.push(quote! {
-                                                if self.#ident.is_some() {
-                                            p.add_field(stringify!(#ident), TsValue::StringVec(self.#ident.clone().unwrap()));
-                                                }
-                                });
-                                        } // TODO: add other types here
-                                    }
-                                    None => {
-                                        // Unable to identify this type
-                                        println!(
-                                            "Unable to identify vec type for option_type = {:?} ident= {:?} i_type= {:?} vec_angle_type = {:?}",
-                                            option_type, ident, i_type, inner_vec_angle_type
-                                        );
-                                    }
+                    syn::NestedMeta::Meta(syn::Meta::List(ref inner)) if inner.ident == key => {
+                        for n in inner.nested.iter() {
+                            if let syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) = *n {
+                                if nv.ident != "deserialize" {
+                                    continue;
+                                }
+                                if let syn::Lit::Str(ref lit) = nv.lit {
+                                    return Some(lit.value());
                                 }
-                            } else {
-                                //println!("optional else: {:?}", option_type);
                             }
                         }
-                        None => {
-                            // Unable to identify this type
-                            println!(
-                                "Unable to identify optional type for {:?} {:?} {:?}",
-                                ident, i_type, angle_type
-                            );
-                        }
                     }
-                } else {
-                    // Uncomment me to debug why some fields may be missing
-                    //println!("else: {:?} {:?} {:?}", ident, i_type, field.clone().ty);
+                    _ => {}
                 }
             }
+        }
+    }
+    None
+}
+
+// Rename a snake_case field the way serde's rename_all does
+fn apply_rename_all(rule: &str, field: &str) -> String {
+    let pascal = || {
+        let mut out = String::new();
+        let mut upper = true;
+        for c in field.chars() {
+            if c == '_' {
+                upper = true;
+            } else if upper {
+                out.extend(c.to_uppercase());
+                upper = false;
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    };
+    match rule {
+        "lowercase" | "snake_case" => field.to_string(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field.to_ascii_uppercase(),
+        "PascalCase" => pascal(),
+        "camelCase" => {
+            let p = pascal();
+            let mut chars = p.chars();
+            match chars.next() {
+                Some(first) => first.to_lowercase().chain(chars).collect(),
+                None => p,
+            }
+        }
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.to_ascii_uppercase().replace('_', "-"),
+        _ => panic!("unknown serde rename_all rule {:?}", rule),
+    }
+}
+
+fn impl_struct_point_fields(
+    name: &syn::Ident,
+    fields: &syn::Fields,
+    child: bool,
+    names: &FieldNames,
+    extend_with: Option<syn::Path>,
+) -> TokenStream {
+    let mut result = Vec::new();
+    for field in fields {
+        let ident = &field.ident;
+        let key = names.key(field);
+        if let Some(flatten_with) = find_point_path(&field.attrs, "flatten_with") {
+            if child {
+                result.push(quote! {
+                    #flatten_with(&self.#ident, p);
+                });
+            } else {
+                result.push(quote! {
+                    #flatten_with(&self.#ident, &mut p);
+                });
+            }
+            continue;
+        }
+        let value = quote!((&self.#ident));
+        if has_point_word(&field.attrs, "latency") {
+            result.push(latency_quote(&key, &field.ty, &value));
+            continue;
+        }
+        match value_quote(&key, &field.ty, &value, Presence::Required) {
+            Some(q) => result.push(q),
             None => {
-                // Unable to identify this type
-                println!("Unable to identify type for {:?}", ident);
+                // Uncomment me to debug why some fields may be missing
+                //println!("else: {:?} {:?}", ident, field.ty);
             }
         }
     }
+    if let Some(extend_with) = extend_with {
+        if child {
+            result.push(quote! {
+                #extend_with(self, p);
+            });
+        } else {
+            result.push(quote! {
+                #extend_with(self, &mut p);
+            });
+        }
+    }
     if child {
         TokenStream::from(quote! {
             impl ChildPoint for #name {
@@ -1,9 +1,10 @@
+use crate::collector::Collector;
 use crate::error::{MetricsResult, StorageError};
 use crate::ir::{TsPoint, TsValue};
-use crate::IntoPoint;
+use crate::{read_text, IntoPoint, DEFAULT_MAX_RESPONSE_BYTES};
 use chrono::offset::Utc;
 use chrono::DateTime;
-use log::{error, trace};
+use log::{debug, error, trace};
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT};
 use serde::de::DeserializeOwned;
 /**
@@ -40,6 +41,15 @@ pub struct BrocadeConfig {
     pub root_certificate: Option<String>,
     /// The region this cluster is located in
     pub region: String,
+    /// Virtual fabric ids to collect from on directors with virtual
+    /// fabrics enabled.  Without them only the default switch is seen
+    pub virtual_fabrics: Option<Vec<u16>>,
+    /// Largest response body to read, in bytes, counting every chunk of a
+    /// chunked response together.  Defaults to DEFAULT_MAX_RESPONSE_BYTES
+    pub max_response_bytes: Option<u64>,
+    /// Milliseconds to wait before each request so the switch's request
+    /// queue isn't overrun.  Defaults to 5000
+    pub request_delay_ms: Option<u64>,
 }
 
 pub struct Brocade {
@@ -60,6 +70,22 @@ impl Brocade {
     }
 }
 
+impl Collector for Brocade {
+    fn backend(&self) -> &str {
+        "brocade"
+    }
+
+    fn array_id(&self) -> &str {
+        &self.config.endpoint
+    }
+
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut points = self.get_fc_fabrics(t)?;
+        points.extend(self.get_fc_switches(t)?);
+        Ok(points)
+    }
+}
+
 impl Drop for Brocade {
     fn drop(&mut self) {
         if let Err(e) = self.logout() {
@@ -108,7 +134,7 @@ fn parse_fc_fabrics() {
     use std::fs::File;
     use std::io::Read;
 
-    sleep_the_collections();
+    sleep_the_collections(DEFAULT_REQUEST_DELAY_MS);
 
     let mut f = File::open("tests/brocade/fcfabrics.json").unwrap();
     let mut buff = String::new();
@@ -525,10 +551,10 @@ pub fn login(client: &reqwest::blocking::Client, config: &BrocadeConfig) -> Metr
 // get their queue over-ran with requests until they can upgrade to newer version
 // which deals with that issue otherwise switch soft resets can occur
 // Added the 'use' statement here to be localized so this can all be removed later
-fn sleep_the_collections() {
+fn sleep_the_collections(delay_ms: u64) {
     use std::{thread, time};
 
-    let sleep_time = time::Duration::from_millis(5000);
+    let sleep_time = time::Duration::from_millis(delay_ms);
     let now = time::Instant::now();
 
     thread::sleep(sleep_time);
@@ -536,6 +562,27 @@ fn sleep_the_collections() {
     assert!(now.elapsed() >= sleep_time);
 }
 
+// The pause before every request when request_delay_ms isn't set
+const DEFAULT_REQUEST_DELAY_MS: u64 = 5000;
+
+// Large responses are split into chunks.  While a response carries this
+// header the same request is sent again with the header's value to get
+// the next chunk, and the bodies are joined back together
+const RETRIEVE_REMAINING: &str = "Retrieve-Remaining";
+// Give up on a response that never stops asking for more
+const MAX_RESPONSE_CHUNKS: usize = 1000;
+
+// Add the vf-id query parameter for a virtual fabric to an api call
+fn vf_api_call(api_call: &str, vf_id: Option<u16>) -> String {
+    match vf_id {
+        Some(vf_id) => {
+            let sep = if api_call.contains('?') { '&' } else { '?' };
+            format!("{}{}vf-id={}", api_call, sep, vf_id)
+        }
+        None => api_call.to_string(),
+    }
+}
+
 impl Brocade {
     // Deletes the client session
     pub fn logout(&self) -> MetricsResult<()> {
@@ -557,7 +604,21 @@ impl Brocade {
         Ok(())
     }
 
-    fn get_server_response<T>(&self, api_call: &str, ws_token: &str) -> MetricsResult<T>
+    // The virtual fabrics to make each request against.  None is a single
+    // request without a vf-id
+    fn virtual_fabrics(&self) -> Vec<Option<u16>> {
+        match self.config.virtual_fabrics {
+            Some(ref ids) => ids.iter().map(|id| Some(*id)).collect(),
+            None => vec![None],
+        }
+    }
+
+    fn get_server_response<T>(
+        &self,
+        api_call: &str,
+        ws_token: &str,
+        vf_id: Option<u16>,
+    ) -> MetricsResult<T>
     where
         T: DeserializeOwned + Debug,
     {
@@ -568,38 +629,113 @@ impl Brocade {
                 None => "http",
             },
             self.config.endpoint,
-            api_call
+            vf_api_call(api_call, vf_id)
         );
-        let resp = self
-            .client
-            .get(&url)
-            .header(
-                ACCEPT,
-                "application/vnd.brocade.networkadvisor+json;version=v1",
-            )
-            .header("WStoken", HeaderValue::from_str(ws_token)?)
-            .send()?
-            .error_for_status()?
-            .text()?;
-        trace!("server returned: {}", resp);
-        let json: Result<T, serde_json::Error> = serde_json::from_str(&resp);
-        trace!("json result: {:?}", json);
-        Ok(json?)
+        let limit = self
+            .config
+            .max_response_bytes
+            .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+        let mut resp = String::new();
+        let mut remaining: Option<String> = None;
+        for _ in 0..MAX_RESPONSE_CHUNKS {
+            // Every chunk and every virtual fabric is its own request
+            sleep_the_collections(
+                self.config
+                    .request_delay_ms
+                    .unwrap_or(DEFAULT_REQUEST_DELAY_MS),
+            );
+            let mut req = self
+                .client
+                .get(&url)
+                .header(
+                    ACCEPT,
+                    "application/vnd.brocade.networkadvisor+json;version=v1",
+                )
+                .header("WStoken", HeaderValue::from_str(ws_token)?);
+            if let Some(ref remaining) = remaining {
+                req = req.header(RETRIEVE_REMAINING, HeaderValue::from_str(remaining)?);
+            }
+            let chunk = req.send()?.error_for_status()?;
+            remaining = match chunk.headers().get(RETRIEVE_REMAINING) {
+                Some(v) => Some(v.to_str()?.to_string()).filter(|v| !v.is_empty()),
+                None => None,
+            };
+            // The limit covers the whole response, not each chunk
+            let left = limit.saturating_sub(resp.len() as u64);
+            let text = read_text(chunk, Some(left)).map_err(|e| {
+                e.with_context(format!(
+                    "{} after {} bytes of earlier chunks",
+                    url,
+                    resp.len()
+                ))
+            })?;
+            resp.push_str(&text);
+            if remaining.is_none() {
+                trace!("server returned: {}", resp);
+                let json: Result<T, serde_json::Error> = serde_json::from_str(&resp);
+                trace!("json result: {:?}", json);
+                return Ok(json?);
+            }
+            debug!("{} has more to retrieve", url);
+        }
+        Err(StorageError::new(format!(
+            "{} was still sending chunks after {}",
+            url, MAX_RESPONSE_CHUNKS
+        )))
     }
 
-    pub fn get_fc_fabrics(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
-        sleep_the_collections();
-        let result =
-            self.get_server_response::<FcFabrics>("resourcegroups/All/fcfabrics", &self.token)?;
-        let mut points = result
-            .fc_fabrics
-            .iter()
-            .flat_map(|fabric| fabric.into_point(Some("brocade_fc_fabric"), true))
-            .collect::<Vec<TsPoint>>();
-        for point in &mut points {
-            point.timestamp = Some(t)
+    // Request api_call from every virtual fabric and turn the responses into
+    // points stamped with t.  Points from a virtual fabric are tagged with
+    // its vf_id.  With skip_missing a virtual fabric answering 404 is
+    // skipped, for objects that only exist in one of them
+    fn get_points<T, F>(
+        &self,
+        api_call: &str,
+        t: DateTime<Utc>,
+        skip_missing: bool,
+        points: F,
+    ) -> MetricsResult<Vec<TsPoint>>
+    where
+        T: DeserializeOwned + Debug,
+        F: Fn(&T) -> Vec<TsPoint>,
+    {
+        let mut all = Vec::new();
+        for vf_id in self.virtual_fabrics() {
+            let result = match self.get_server_response::<T>(api_call, &self.token, vf_id) {
+                Ok(result) => result,
+                Err(ref e)
+                    if skip_missing
+                        && vf_id.is_some()
+                        && e.status() == Some(reqwest::StatusCode::NOT_FOUND) =>
+                {
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            for mut point in points(&result) {
+                point.timestamp = Some(t);
+                if let Some(vf_id) = vf_id {
+                    point.add_tag("vf_id", TsValue::String(vf_id.to_string()));
+                }
+                all.push(point);
+            }
         }
-        Ok(points)
+        Ok(all)
+    }
+
+    pub fn get_fc_fabrics(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        self.get_points(
+            "resourcegroups/All/fcfabrics",
+            t,
+            false,
+            |result: &FcFabrics| {
+                result
+                    .fc_fabrics
+                    .iter()
+                    .flat_map(|fabric| fabric.into_point(Some("brocade_fc_fabric"), true))
+                    .collect()
+            },
+        )
     }
 
     pub fn get_fc_switch_timeseries(
@@ -608,7 +744,6 @@ impl Brocade {
         timeseries: TimeSeries,
     ) -> MetricsResult<()> {
         // TODO: Not sure if these performance metrics need to be enabled on the switches first
-        sleep_the_collections();
         let _url = format!(
             "resourcegroups/All/fcswitches/{}/{}?duration=360",
             switch_key,
@@ -626,7 +761,6 @@ impl Brocade {
         timeseries: &FabricTimeSeries,
     ) -> MetricsResult<()> {
         // TODO: Not sure if these performance metrics need to be enabled on the switches first
-        sleep_the_collections();
         let _url = format!(
             "resourcegroups/All/fcfabrics/{}/{}?duration=360",
             fabric_key,
@@ -637,69 +771,201 @@ impl Brocade {
     }
 
     pub fn get_fc_fabric_ids(&self) -> MetricsResult<Vec<String>> {
-        sleep_the_collections();
-        let result = self
-            .get_server_response::<FcFabrics>("resourcegroups/All/fcfabrics", &self.token)
-            .map(|fabrics| {
-                let fabrics: Vec<String> = fabrics
-                    .fc_fabrics
-                    .iter()
-                    .map(|fabric| fabric.key.clone())
-                    .collect::<Vec<String>>();
-                fabrics
-            })?;
-        Ok(result)
+        let mut ids = Vec::new();
+        for vf_id in self.virtual_fabrics() {
+            let fabrics = self.get_server_response::<FcFabrics>(
+                "resourcegroups/All/fcfabrics",
+                &self.token,
+                vf_id,
+            )?;
+            ids.extend(fabrics.fc_fabrics.iter().map(|fabric| fabric.key.clone()));
+        }
+        Ok(ids)
     }
 
+    /// The ports of a switch.  With virtual fabrics configured the switch
+    /// is looked for in each of them, and fabrics it isn't part of are
+    /// skipped
     pub fn get_fc_ports(&self, fabric_key: &str, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
-        sleep_the_collections();
-        let result = self.get_server_response::<FcPorts>(
-            &format!("resourcegroups/All/fcswitches/{}/fcports", fabric_key),
-            &self.token,
-        )?;
-        let mut points = result
-            .fc_ports
-            .iter()
-            .flat_map(|port| port.into_point(Some("brocade_fc_port"), true))
-            .collect::<Vec<TsPoint>>();
-        for point in &mut points {
-            point.timestamp = Some(t)
-        }
-        Ok(points)
+        let api_call = format!("resourcegroups/All/fcswitches/{}/fcports", fabric_key);
+        self.get_points(&api_call, t, true, |result: &FcPorts| {
+            result
+                .fc_ports
+                .iter()
+                .flat_map(|port| port.into_point(Some("brocade_fc_port"), true))
+                .collect()
+        })
     }
 
     pub fn get_fc_switch_ids(&self) -> MetricsResult<Vec<String>> {
-        sleep_the_collections();
-        let result = self
-            .get_server_response::<FcSwitches>("resourcegroups/All/fcswitches", &self.token)
-            .map(|switches| {
-                let switches: Vec<String> = switches
-                    .fc_switches
-                    .iter()
-                    .map(|switch| switch.key.clone())
-                    .collect::<Vec<String>>();
-                switches
-            })?;
-        Ok(result)
+        let mut ids = Vec::new();
+        for vf_id in self.virtual_fabrics() {
+            let switches = self.get_server_response::<FcSwitches>(
+                "resourcegroups/All/fcswitches",
+                &self.token,
+                vf_id,
+            )?;
+            ids.extend(switches.fc_switches.iter().map(|switch| switch.key.clone()));
+        }
+        Ok(ids)
     }
 
     pub fn get_fc_switches(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
-        sleep_the_collections();
-        let result =
-            self.get_server_response::<FcSwitches>("resourcegroups/All/fcswitches", &self.token)?;
-        let mut points = result
-            .fc_switches
-            .iter()
-            .flat_map(|switch| switch.into_point(Some("brocade_fc_switch"), true))
-            .collect::<Vec<TsPoint>>();
-        for point in &mut points {
-            point.timestamp = Some(t)
-        }
-        Ok(points)
+        self.get_points(
+            "resourcegroups/All/fcswitches",
+            t,
+            false,
+            |result: &FcSwitches| {
+                result
+                    .fc_switches
+                    .iter()
+                    .flat_map(|switch| switch.into_point(Some("brocade_fc_switch"), true))
+                    .collect()
+            },
+        )
     }
 
     pub fn get_resource_groups(&self) -> MetricsResult<ResourceGroups> {
-        let result = self.get_server_response::<ResourceGroups>("resourcegroups", &self.token)?;
+        let result =
+            self.get_server_response::<ResourceGroups>("resourcegroups", &self.token, None)?;
         Ok(result)
     }
 }
+
+#[cfg(test)]
+fn mock_brocade(port: u16, virtual_fabrics: Option<Vec<u16>>) -> Brocade {
+    Brocade {
+        client: reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap(),
+        config: BrocadeConfig {
+            endpoint: format!("127.0.0.1:{}", port),
+            user: "admin".into(),
+            password: "token".into(),
+            certificate: Some("cert.der".into()),
+            root_certificate: None,
+            region: "test".into(),
+            virtual_fabrics,
+            max_response_bytes: None,
+            request_delay_ms: Some(0),
+        },
+        token: "token".into(),
+    }
+}
+
+#[test]
+fn test_virtual_fabrics() {
+    use std::fs::read;
+    use std::sync::{Arc, Mutex};
+
+    let switches = read("tests/brocade/fcswitches.json").unwrap();
+    let ports = read("tests/brocade/fcports.json").unwrap();
+    let paths = Arc::new(Mutex::new(vec![]));
+    let recorded = paths.clone();
+    let port = crate::mock_https_server(move |req| {
+        recorded.lock().unwrap().push(req.path.clone());
+        if req.path.contains("/fcports") {
+            // The switch is only part of the second fabric
+            if req.path.ends_with("vf-id=10") {
+                return (404, String::new(), vec![]);
+            }
+            return (200, String::new(), ports.clone());
+        }
+        if req.path.contains("/fcswitches") {
+            return (200, String::new(), switches.clone());
+        }
+        (200, String::new(), vec![])
+    });
+    let brocade = mock_brocade(port, Some(vec![10, 20]));
+    let t = Utc::now();
+
+    let points = brocade.get_fc_switches(t).unwrap();
+    assert_eq!(points.len(), 4);
+    let vf_ids: Vec<&TsValue> = points.iter().map(|p| &p.tags["vf_id"]).collect();
+    assert_eq!(
+        vf_ids,
+        vec![
+            &TsValue::String("10".into()),
+            &TsValue::String("10".into()),
+            &TsValue::String("20".into()),
+            &TsValue::String("20".into()),
+        ]
+    );
+    assert!(points.iter().all(|p| p.timestamp == Some(t)));
+
+    let points = brocade.get_fc_ports("10:00:00:27:F8:22:75:1B", t).unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].tags["vf_id"], TsValue::String("20".into()));
+
+    let paths = paths.lock().unwrap();
+    assert_eq!(
+        paths[..2],
+        [
+            "/rest/resourcegroups/All/fcswitches?vf-id=10",
+            "/rest/resourcegroups/All/fcswitches?vf-id=20",
+        ]
+    );
+}
+
+#[test]
+fn test_chunked_response() {
+    use std::fs::read;
+    use std::sync::{Arc, Mutex};
+
+    // Split the response into three chunks
+    let body = read("tests/brocade/resource_groups.json").unwrap();
+    let third = body.len() / 3;
+    let chunks = [
+        body[..third].to_vec(),
+        body[third..2 * third].to_vec(),
+        body[2 * third..].to_vec(),
+    ];
+    let asked = Arc::new(Mutex::new(vec![]));
+    let recorded = asked.clone();
+    let port = crate::mock_https_server(move |req| {
+        let remaining = req
+            .head
+            .lines()
+            .find_map(|l| l.strip_prefix("retrieve-remaining: "))
+            .map(|v| v.trim().to_string());
+        recorded.lock().unwrap().push(remaining.clone());
+        match remaining.as_deref() {
+            None if req.method == "GET" => (
+                200,
+                "Retrieve-Remaining: chunk-2\r\n".into(),
+                chunks[0].clone(),
+            ),
+            Some("chunk-2") => (
+                200,
+                "Retrieve-Remaining: chunk-3\r\n".into(),
+                chunks[1].clone(),
+            ),
+            Some("chunk-3") => (200, String::new(), chunks[2].clone()),
+            _ => (200, String::new(), vec![]),
+        }
+    });
+    let mut brocade = mock_brocade(port, None);
+    let groups = brocade.get_resource_groups().unwrap();
+    assert!(!groups.resource_groups.is_empty());
+    assert_eq!(
+        asked.lock().unwrap()[..3],
+        [
+            None,
+            Some("chunk-2".to_string()),
+            Some("chunk-3".to_string())
+        ]
+    );
+
+    // Every chunk fits under the limit but all three together don't
+    brocade.config.max_response_bytes = Some(body.len() as u64 - 1);
+    let err = brocade.get_resource_groups().unwrap_err().to_string();
+    assert!(err.contains("exceeds max_response_bytes"), "{}", err);
+
+    // The delay comes before each chunk, not once per call
+    brocade.config.max_response_bytes = None;
+    brocade.config.request_delay_ms = Some(100);
+    let start = std::time::Instant::now();
+    brocade.get_resource_groups().unwrap();
+    assert!(start.elapsed() >= std::time::Duration::from_millis(300));
+}
@@ -0,0 +1,524 @@
+//! Runs collectors against many arrays at once.  Every collection run gets a
+//! heartbeat point appended to its batch describing how it went.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashSet;
+use std::time::Instant;
+
+use crate::error::MetricsResult;
+use crate::ir::guard::{GuardDecision, PointGuard};
+use crate::ir::health::Heartbeat;
+use crate::ir::tags::TagInjector;
+use crate::ir::TsPoint;
+use chrono::{DateTime, Utc};
+use log::{error, warn};
+use rayon::prelude::*;
+
+/// Something that can gather points from a single configured array
+pub trait Collector: Send + Sync {
+    /// The backend kind, ie scaleio or vnx
+    fn backend(&self) -> &str;
+    /// Identifies the array this collector talks to
+    fn array_id(&self) -> &str;
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>>;
+}
+
+// Collectors are run as Vec<Box<dyn Collector>>, one per configured array
+// whatever its backend.  This stops compiling if the trait isn't object safe
+const _: Option<&dyn Collector> = None;
+
+/// Run one collection and append its heartbeat to the resulting batch.
+/// A failed collection is logged and yields a batch holding only the
+/// heartbeat.
+pub fn run_collector(collector: &dyn Collector, t: DateTime<Utc>) -> Vec<TsPoint> {
+    run_guarded_collector(collector, t, None)
+}
+
+/// Like `run_collector` but the collected points are checked by the guard
+/// first, after any tags over its distinct value limit are demoted.  A
+/// rejected batch is treated as a failed collection with an error_kind of
+/// guard_rejected.  The heartbeat itself is never guarded
+pub fn run_guarded_collector(
+    collector: &dyn Collector,
+    t: DateTime<Utc>,
+    guard: Option<&PointGuard>,
+) -> Vec<TsPoint> {
+    run_tagged_collector(collector, t, guard, &TagInjector::default())
+}
+
+/// Like `run_guarded_collector` but the global tags of tags are added to
+/// every point of the batch afterwards, the heartbeat included
+pub fn run_tagged_collector(
+    collector: &dyn Collector,
+    t: DateTime<Utc>,
+    guard: Option<&PointGuard>,
+    tags: &TagInjector,
+) -> Vec<TsPoint> {
+    let start = Instant::now();
+    let res = collector.collect(t);
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    let (mut points, error_kind) = match res {
+        Ok(points) => (points, None),
+        Err(e) => {
+            error!(
+                "{} collection from {} failed: {}",
+                collector.backend(),
+                collector.array_id(),
+                e
+            );
+            (vec![], Some(e.kind().to_string()))
+        }
+    };
+    if let Some(guard) = guard {
+        guard.limit_tag_values(&mut points);
+    }
+    let (mut points, error_kind) = match guard.map(|g| g.check(&points)) {
+        None | Some(GuardDecision::Pass) => (points, error_kind),
+        Some(GuardDecision::Truncate(kept)) => {
+            warn!(
+                "{} collection from {} truncated from {} to {} points",
+                collector.backend(),
+                collector.array_id(),
+                points.len(),
+                kept.len()
+            );
+            (kept, error_kind)
+        }
+        Some(GuardDecision::Reject(reason)) => {
+            error!(
+                "{} collection from {} rejected: {}",
+                collector.backend(),
+                collector.array_id(),
+                reason
+            );
+            (vec![], Some("guard_rejected".to_string()))
+        }
+    };
+    let heartbeat = Heartbeat {
+        backend: collector.backend().to_string(),
+        array_id: collector.array_id().to_string(),
+        success: error_kind.is_none(),
+        duration_ms,
+        points_emitted: points.len() as u64,
+        error_kind,
+        ts: t,
+    };
+    points.push(heartbeat.to_point());
+    tags.apply(&mut points);
+
+    points
+}
+
+/// Run all the collectors concurrently.  One batch is returned per
+/// collector, in the same order, each ending with its heartbeat point.
+pub fn run_parallel(collectors: &[Box<dyn Collector>], t: DateTime<Utc>) -> Vec<Vec<TsPoint>> {
+    run_parallel_guarded(collectors, t, None)
+}
+
+/// Run all the collectors concurrently with every batch checked by the same
+/// guard.  Emit `guard.to_point` afterwards to report what was dropped
+pub fn run_parallel_guarded(
+    collectors: &[Box<dyn Collector>],
+    t: DateTime<Utc>,
+    guard: Option<&PointGuard>,
+) -> Vec<Vec<TsPoint>> {
+    run_parallel_tagged(collectors, t, guard, &TagInjector::default())
+}
+
+/// `run_parallel_guarded` with the global tags added to every batch
+pub fn run_parallel_tagged(
+    collectors: &[Box<dyn Collector>],
+    t: DateTime<Utc>,
+    guard: Option<&PointGuard>,
+    tags: &TagInjector,
+) -> Vec<Vec<TsPoint>> {
+    collectors
+        .par_iter()
+        .map(|c| run_tagged_collector(c.as_ref(), t, guard, tags))
+        .collect()
+}
+
+/// Capacity and inventory measurements whose points describe the state of
+/// the array at collection time rather than carrying a sample time of their
+/// own
+pub const DEFAULT_ALIGNED_MEASUREMENTS: &[&str] = &[
+    "hitachi_ldev",
+    "hitachi_pool",
+    "isilon_usage",
+    "netapp_qtree",
+    "netapp_quota",
+    "netapp_volume",
+    "openstack_volume",
+    "pool",
+    "scaleio_drive",
+    "scaleio_pool",
+    "scaleio_pool_stats",
+    "scaleio_sdc",
+    "scaleio_sds",
+    "scaleio_volume",
+    "solidfire_cluster_capacity",
+    "srp",
+    "storage_group",
+    "vmax_system_capacity",
+    "vnx_filesystem_capacity",
+    "xtremio_brick",
+    "xtremio_ssd",
+    "xtremio_ssd_endurance",
+];
+
+/// One polling cycle over a set of collectors.  Every collector is handed
+/// the same cycle time and points of the aligned measurements are stamped
+/// with it afterwards, so capacity from different backends lines up
+/// exactly.  Points of other measurements keep whatever timestamp the
+/// collector gave them, such as the sample times of vnx or vmax perf data
+pub struct PollCycle {
+    t: DateTime<Utc>,
+    aligned: HashSet<String>,
+    tags: TagInjector,
+}
+
+impl PollCycle {
+    /// Start a cycle now with DEFAULT_ALIGNED_MEASUREMENTS aligned
+    pub fn new() -> Self {
+        PollCycle::at(Utc::now())
+    }
+
+    /// Start a cycle at t with DEFAULT_ALIGNED_MEASUREMENTS aligned
+    pub fn at(t: DateTime<Utc>) -> Self {
+        PollCycle {
+            t,
+            aligned: DEFAULT_ALIGNED_MEASUREMENTS
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+            tags: TagInjector::default(),
+        }
+    }
+
+    /// Replace the aligned measurements
+    pub fn with_aligned<I, S>(mut self, measurements: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.aligned = measurements.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Add these global tags to every point collected in this cycle
+    pub fn with_tags(mut self, tags: TagInjector) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// The time handed to every collector in this cycle
+    pub fn time(&self) -> DateTime<Utc> {
+        self.t
+    }
+
+    pub fn is_aligned(&self, measurement: &str) -> bool {
+        self.aligned.contains(measurement)
+    }
+
+    /// Stamp the points of aligned measurements with the cycle time
+    pub fn align(&self, points: &mut [TsPoint]) {
+        for p in points
+            .iter_mut()
+            .filter(|p| self.is_aligned(&p.measurement))
+        {
+            p.timestamp = Some(self.t);
+        }
+    }
+
+    /// Run the collectors concurrently at the cycle time.  See run_parallel
+    pub fn run(&self, collectors: &[Box<dyn Collector>]) -> Vec<Vec<TsPoint>> {
+        self.run_guarded(collectors, None)
+    }
+
+    /// Run the collectors concurrently at the cycle time with every batch
+    /// checked by the guard.  See run_parallel_guarded
+    pub fn run_guarded(
+        &self,
+        collectors: &[Box<dyn Collector>],
+        guard: Option<&PointGuard>,
+    ) -> Vec<Vec<TsPoint>> {
+        let mut batches = run_parallel_tagged(collectors, self.t, guard, &self.tags);
+        for batch in batches.iter_mut() {
+            self.align(batch);
+        }
+        batches
+    }
+}
+
+impl Default for PollCycle {
+    fn default() -> Self {
+        PollCycle::new()
+    }
+}
+
+#[cfg(test)]
+struct FakeCollector {
+    array_id: String,
+    result: fn() -> MetricsResult<Vec<TsPoint>>,
+}
+
+#[cfg(test)]
+impl Collector for FakeCollector {
+    fn backend(&self) -> &str {
+        "fake"
+    }
+    fn array_id(&self) -> &str {
+        &self.array_id
+    }
+    fn collect(&self, _t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        (self.result)()
+    }
+}
+
+#[test]
+fn test_run_parallel_heartbeats() {
+    use crate::error::StorageError;
+    use crate::ir::TsValue;
+
+    let collectors: Vec<Box<dyn Collector>> = vec![
+        Box::new(FakeCollector {
+            array_id: "good".into(),
+            result: || {
+                Ok(vec![
+                    TsPoint::new("fake_stat", true),
+                    TsPoint::new("fake_stat", true),
+                    TsPoint::new("fake_stat", true),
+                ])
+            },
+        }),
+        Box::new(FakeCollector {
+            array_id: "bad".into(),
+            result: || {
+                let e: serde_json::Error = serde_json::from_str::<u64>("{").unwrap_err();
+                Err(StorageError::from(e).with_context("fake"))
+            },
+        }),
+        Box::new(FakeCollector {
+            array_id: "worse".into(),
+            result: || Err(StorageError::new("no idea".into())),
+        }),
+    ];
+    let t = Utc::now();
+    let batches = run_parallel(&collectors, t);
+    println!("batches: {:#?}", batches);
+    assert_eq!(batches.len(), 3);
+
+    // The heartbeat comes last and doesn't count itself
+    let good = batches[0].last().unwrap();
+    assert_eq!(batches[0].len(), 4);
+    assert_eq!(good.measurement, "collector_heartbeat");
+    assert_eq!(good.tags["array_id"], TsValue::String("good".into()));
+    assert_eq!(good.fields["success"], TsValue::Boolean(true));
+    assert_eq!(good.fields["points_emitted"], TsValue::Long(3));
+    assert!(!good.tags.contains_key("error_kind"));
+
+    let bad = &batches[1][0];
+    assert_eq!(batches[1].len(), 1);
+    assert_eq!(bad.fields["success"], TsValue::Boolean(false));
+    assert_eq!(bad.fields["points_emitted"], TsValue::Long(0));
+    assert_eq!(bad.tags["error_kind"], TsValue::String("json".into()));
+
+    let worse = &batches[2][0];
+    assert_eq!(worse.tags["error_kind"], TsValue::String("unknown".into()));
+}
+
+#[test]
+fn test_run_parallel_guarded() {
+    use crate::ir::TsValue;
+
+    fn series(n: u64) -> MetricsResult<Vec<TsPoint>> {
+        Ok((0..n)
+            .map(|i| {
+                let mut p = TsPoint::new("fake_stat", true);
+                p.add_tag("id", TsValue::String(format!("{}", n - i)));
+                p.add_field("value", TsValue::Long(i));
+                p
+            })
+            .collect())
+    }
+    let collectors: Vec<Box<dyn Collector>> = vec![
+        Box::new(FakeCollector {
+            array_id: "small".into(),
+            result: || series(3),
+        }),
+        Box::new(FakeCollector {
+            array_id: "wide".into(),
+            result: || series(6),
+        }),
+        Box::new(FakeCollector {
+            array_id: "huge".into(),
+            result: || series(50),
+        }),
+    ];
+    let guard = PointGuard::new(20, 4);
+    let batches = run_parallel_guarded(&collectors, Utc::now(), Some(&guard));
+
+    // Passed through untouched, in the same order
+    let values: Vec<&TsValue> = batches[0][..3].iter().map(|p| &p.fields["value"]).collect();
+    assert_eq!(
+        values,
+        vec![&TsValue::Long(0), &TsValue::Long(1), &TsValue::Long(2)]
+    );
+    assert_eq!(batches[0][3].fields["points_emitted"], TsValue::Long(3));
+
+    // Cut down to the first 4 series, the heartbeat still goes out
+    assert_eq!(batches[1].len(), 5);
+    assert_eq!(batches[1][4].fields["points_emitted"], TsValue::Long(4));
+    assert_eq!(batches[1][4].fields["success"], TsValue::Boolean(true));
+
+    // Truncation happens first so this fits under the point limit
+    assert_eq!(batches[2].len(), 5);
+
+    // Without the series limit it doesn't and is dropped
+    let guard = PointGuard::new(20, 100);
+    let batches = run_parallel_guarded(&collectors[2..], Utc::now(), Some(&guard));
+    let heartbeat = &batches[0][0];
+    assert_eq!(batches[0].len(), 1);
+    assert_eq!(heartbeat.fields["success"], TsValue::Boolean(false));
+    assert_eq!(
+        heartbeat.tags["error_kind"],
+        TsValue::String("guard_rejected".into())
+    );
+    assert_eq!(
+        guard.to_point(Utc::now()).fields["batches_rejected"],
+        TsValue::Long(1)
+    );
+}
+
+#[test]
+fn test_poll_cycle_alignment() {
+    use chrono::{Duration, TimeZone};
+
+    // Each stamps its points a little after the time it was handed, the way
+    // the backends stamp with Utc::now() while paging through results
+    fn scaleio_pools(t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut p = TsPoint::new("scaleio_pool", true);
+        p.timestamp = Some(t + Duration::seconds(4));
+        Ok(vec![p])
+    }
+    fn vnx_pools(t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut pool = TsPoint::new("pool", true);
+        pool.timestamp = Some(t + Duration::seconds(9));
+        // Perf samples carry the time the array took them
+        let mut sample = TsPoint::new("vnx_mover_stats", true);
+        sample.timestamp = Some(Utc.timestamp_opt(1_577_836_800, 0).unwrap());
+        Ok(vec![pool, sample])
+    }
+    struct TimedCollector {
+        array_id: &'static str,
+        collect: fn(DateTime<Utc>) -> MetricsResult<Vec<TsPoint>>,
+    }
+    impl Collector for TimedCollector {
+        fn backend(&self) -> &str {
+            "fake"
+        }
+        fn array_id(&self) -> &str {
+            self.array_id
+        }
+        fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+            (self.collect)(t)
+        }
+    }
+    let collectors: Vec<Box<dyn Collector>> = vec![
+        Box::new(TimedCollector {
+            array_id: "scaleio01",
+            collect: scaleio_pools,
+        }),
+        Box::new(TimedCollector {
+            array_id: "vnx01",
+            collect: vnx_pools,
+        }),
+    ];
+
+    let cycle = PollCycle::new().with_aligned(vec!["scaleio_pool", "pool"]);
+    let t = cycle.time();
+    let batches = cycle.run(&collectors);
+    let scaleio_pool = &batches[0][0];
+    let vnx_pool = &batches[1][0];
+    let sample = &batches[1][1];
+    assert_eq!(scaleio_pool.timestamp, Some(t));
+    assert_eq!(vnx_pool.timestamp, scaleio_pool.timestamp);
+    assert_eq!(
+        sample.timestamp,
+        Some(Utc.timestamp_opt(1_577_836_800, 0).unwrap())
+    );
+    // Heartbeats were already stamped with the cycle time
+    assert_eq!(batches[1][2].timestamp, Some(t));
+
+    // Both pools are aligned by default
+    let batches = PollCycle::at(t).run(&collectors);
+    assert_eq!(batches[0][0].timestamp, Some(t));
+    assert_eq!(batches[1][0].timestamp, Some(t));
+    for m in &[
+        crate::scaleio::PoolInstanceResponse::MEASUREMENT,
+        crate::scaleio::ClusterSelectedStatisticsResponse::MEASUREMENT,
+    ] {
+        assert!(DEFAULT_ALIGNED_MEASUREMENTS.contains(m), "{}", m);
+    }
+
+    // Measurements left out keep the time they were stamped with
+    let batches = PollCycle::at(t).with_aligned(vec!["pool"]).run(&collectors);
+    assert_eq!(batches[0][0].timestamp, Some(t + Duration::seconds(4)));
+    assert_eq!(batches[1][0].timestamp, Some(t));
+}
+
+#[test]
+fn test_run_tagged_collector() {
+    use crate::ir::TsValue;
+
+    let collector = FakeCollector {
+        array_id: "scaleio01".into(),
+        result: || {
+            let mut p = TsPoint::new("fake_stat", true);
+            p.add_tag("env", TsValue::String("lab".into()));
+            p.add_field("value", TsValue::Long(1));
+            Ok(vec![p])
+        },
+    };
+    let tags = TagInjector::new(
+        vec![
+            ("tenant".to_string(), "video".to_string()),
+            ("env".to_string(), "prod".to_string()),
+        ]
+        .into_iter()
+        .collect(),
+    )
+    .unwrap();
+
+    let points = run_tagged_collector(&collector, Utc::now(), None, &tags);
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].tags["env"], TsValue::String("lab".into()));
+    // The heartbeat is tagged too
+    for p in &points {
+        assert_eq!(p.tags["tenant"], TsValue::String("video".into()));
+    }
+
+    let collectors: Vec<Box<dyn Collector>> = vec![Box::new(collector)];
+    let batches = PollCycle::new().run(&collectors);
+    assert!(!batches[0][0].tags.contains_key("tenant"));
+    let batches = PollCycle::new()
+        .with_tags(tags.with_overwrite(true))
+        .run(&collectors);
+    assert_eq!(batches[0][0].tags["env"], TsValue::String("prod".into()));
+}
@@ -0,0 +1,194 @@
+//! Credentials for logging in to arrays.  Clients ask a CredentialsProvider
+//! for them when they log in instead of keeping the password from their
+//! config, so a rotated password is picked up without a restart.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::fmt;
+
+use crate::error::MetricsResult;
+use log::warn;
+
+/// A user and password for one array
+#[derive(Clone, PartialEq)]
+pub struct Credential {
+    pub user: String,
+    pub password: String,
+}
+
+impl Credential {
+    pub fn new(user: impl Into<String>, password: impl Into<String>) -> Self {
+        Credential {
+            user: user.into(),
+            password: password.into(),
+        }
+    }
+}
+
+// Keep passwords out of debug logs
+impl fmt::Debug for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credential")
+            .field("user", &self.user)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Hands out the current credential for an array.  backend is the client
+/// asking, ie "scaleio" or "vmax", and array is the endpoint from its
+/// config.  Clients call get every time they log in, and once more if the
+/// array rejects the credential, so implementations should be cheap or
+/// cache.
+pub trait CredentialsProvider: Send + Sync {
+    fn get(&self, backend: &str, array: &str) -> MetricsResult<Credential>;
+}
+
+/// Always returns the same credential.  This is what the clients use when
+/// they're built from a config with a user and password.
+#[derive(Clone, Debug)]
+pub struct StaticCredentials {
+    credential: Credential,
+}
+
+impl StaticCredentials {
+    pub fn new(user: impl Into<String>, password: impl Into<String>) -> Self {
+        StaticCredentials {
+            credential: Credential::new(user, password),
+        }
+    }
+}
+
+impl CredentialsProvider for StaticCredentials {
+    fn get(&self, _backend: &str, _array: &str) -> MetricsResult<Credential> {
+        Ok(self.credential.clone())
+    }
+}
+
+/// Calls a function for every credential, ie one that reads the current
+/// password from a secrets store.
+pub struct CallbackCredentials<F> {
+    callback: F,
+}
+
+impl<F> CallbackCredentials<F>
+where
+    F: Fn(&str, &str) -> MetricsResult<Credential> + Send + Sync,
+{
+    pub fn new(callback: F) -> Self {
+        CallbackCredentials { callback }
+    }
+}
+
+impl<F> CredentialsProvider for CallbackCredentials<F>
+where
+    F: Fn(&str, &str) -> MetricsResult<Credential> + Send + Sync,
+{
+    fn get(&self, backend: &str, array: &str) -> MetricsResult<Credential> {
+        (self.callback)(backend, array)
+    }
+}
+
+/// Run f with the current credential.  If the array answers with a 401 the
+/// credential is fetched again and, if it changed in the meantime, f is
+/// retried once with the new one.  This covers a password being rotated
+/// between the fetch and the login.
+pub(crate) fn with_credential<T, F>(
+    provider: &dyn CredentialsProvider,
+    backend: &str,
+    array: &str,
+    mut f: F,
+) -> MetricsResult<T>
+where
+    F: FnMut(&Credential) -> MetricsResult<T>,
+{
+    let credential = provider.get(backend, array)?;
+    match f(&credential) {
+        Err(e) if e.is_unauthorized() => {
+            let refetched = provider.get(backend, array)?;
+            if refetched == credential {
+                return Err(e);
+            }
+            warn!(
+                "{} {} rejected the credential for {}, retrying with a refetched one",
+                backend, array, credential.user
+            );
+            f(&refetched)
+        }
+        res => res,
+    }
+}
+
+#[test]
+fn test_with_credential_refetch() {
+    use crate::error::StorageError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A provider that has rotated the password after the first fetch
+    let fetches = AtomicUsize::new(0);
+    let provider = CallbackCredentials::new(|backend: &str, array: &str| {
+        assert_eq!((backend, array), ("test", "10.0.0.1"));
+        match fetches.fetch_add(1, Ordering::SeqCst) {
+            0 => Ok(Credential::new("admin", "old")),
+            _ => Ok(Credential::new("admin", "new")),
+        }
+    });
+    let port = crate::mock_https_server(|req| {
+        // admin:new
+        if req.head.contains("Basic YWRtaW46bmV3") {
+            (200, String::new(), b"{}".to_vec())
+        } else {
+            (401, String::new(), vec![])
+        }
+    });
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let url = format!("https://127.0.0.1:{}/", port);
+    let mut used = vec![];
+    let res: MetricsResult<serde_json::Value> =
+        with_credential(&provider, "test", "10.0.0.1", |c| {
+            used.push(c.password.clone());
+            crate::get(&client, &url, &c.user, Some(&c.password))
+        });
+    assert!(res.is_ok(), "{:?}", res);
+    assert_eq!(used, vec!["old", "new"]);
+
+    // Nothing changed so there's no point in trying again
+    let provider = StaticCredentials::new("admin", "old");
+    let mut attempts = 0;
+    let err = with_credential(&provider, "test", "10.0.0.1", |c| {
+        attempts += 1;
+        crate::get::<serde_json::Value>(&client, &url, &c.user, Some(&c.password))
+    })
+    .unwrap_err();
+    assert!(err.is_unauthorized());
+    assert_eq!(attempts, 1);
+
+    // Other errors are returned as is
+    let err = with_credential(&provider, "test", "10.0.0.1", |_| -> MetricsResult<()> {
+        Err(StorageError::new("boom".into()))
+    })
+    .unwrap_err();
+    assert_eq!(err.to_string(), "boom");
+
+    assert_eq!(
+        format!("{:?}", Credential::new("admin", "hunter2")),
+        r#"Credential { user: "admin", password: "<redacted>" }"#
+    );
+}
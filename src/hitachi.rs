@@ -20,31 +20,90 @@
 use std::collections::HashMap;
 use std::str;
 use std::str::FromStr;
+use std::sync::Arc;
 
+use crate::collector::Collector;
 use crate::error::{MetricsResult, StorageError};
 use crate::ir::{TsPoint, TsValue};
 use crate::IntoPoint;
+use crate::{decode_json, read_text};
+use crate::{deserialize_string_or_float, deserialize_string_or_uint};
 
 use chrono::offset::Utc;
 use chrono::DateTime;
 use csv::Reader;
 use log::{error, trace, warn};
+use rayon::prelude::*;
 use reqwest::header::ACCEPT;
 
 pub struct Hitachi {
     client: reqwest::blocking::Client,
     config: HitachiConfig,
+    // Requests the host group wwns, None to request them one at a time
+    pool: Option<Arc<rayon::ThreadPool>>,
 }
 
+// How many host groups have their wwns requested at once by default
+const DEFAULT_HOST_GROUP_CONCURRENCY: usize = 4;
+
 impl Hitachi {
     pub fn new(client: &reqwest::blocking::Client, config: HitachiConfig) -> Self {
+        let threads = config
+            .host_group_concurrency
+            .unwrap_or(DEFAULT_HOST_GROUP_CONCURRENCY);
+        let pool = if threads > 1 {
+            match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+                Ok(pool) => Some(Arc::new(pool)),
+                Err(e) => {
+                    error!(
+                        "Unable to start {} hitachi threads, requesting host groups one at a time: {}",
+                        threads, e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
         Hitachi {
             client: client.clone(),
             config,
+            pool,
         }
     }
 }
 
+impl Collector for Hitachi {
+    fn backend(&self) -> &str {
+        "hitachi"
+    }
+
+    fn array_id(&self) -> &str {
+        &self.config.endpoint
+    }
+
+    /// The ldevs, pools and ports of each of storage_ids.  Note this only
+    /// works with ConfigurationManager
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let storage_ids = match self.config.storage_ids {
+            Some(ref ids) => ids.clone(),
+            None => self
+                .get_storage()?
+                .data
+                .into_iter()
+                .map(|s| s.storage_device_id)
+                .collect(),
+        };
+        let mut points = Vec::new();
+        for id in &storage_ids {
+            points.extend(self.get_ldev(id)?);
+            points.extend(self.get_pools(id)?);
+            points.extend(self.get_ports(id, t)?);
+        }
+        Ok(points)
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Collection {
     pub items: Vec<HashMap<String, serde_json::Value>>,
@@ -55,6 +114,7 @@ pub struct Collection {
 pub struct ConfigManagerStorage {
     pub storage_device_id: String,
     pub model: String,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
     pub serial_number: u64,
     pub svp_ip: String,
 }
@@ -96,6 +156,7 @@ pub struct StorageLdev {
     pub clpr_id: u64,
     pub emulation_type: String,
     pub byte_format_capacity: String,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
     pub block_capacity: u64,
     pub num_of_ports: u64,
     pub ports: Vec<LdevPort>,
@@ -104,10 +165,13 @@ pub struct StorageLdev {
     pub mp_blade_id: u64,
     pub ssid: String,
     pub pool_id: u64,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
     pub num_of_used_block: u64,
     pub is_relocation_enabled: bool,
     pub tier_level: String,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
     pub used_capacity_per_tier_level1: u64,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
     pub used_capacity_per_tier_level2: u64,
     pub used_capacity_per_tier_level3: Option<u64>,
     pub tier_level_for_new_page_allocation: String,
@@ -195,6 +259,309 @@ impl IntoPoint for StorageLdev {
     }
 }
 
+// Configuration Manager reports rates as whole percents and uses -1 when a
+// rate doesn't apply.  Those are dropped and the rest kept within 0-100
+fn rate_to_percent(rate: f64) -> Option<f64> {
+    if rate.is_nan() || rate < 0.0 {
+        None
+    } else {
+        Some(rate.min(100.0))
+    }
+}
+
+/// A Dynamic Provisioning or Thin Image pool.  Capacities are in MB.  Older
+/// firmware returns the capacities, rates and thresholds as strings
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoragePool {
+    pub pool_id: u64,
+    pub pool_name: String,
+    pub pool_type: String,
+    pub pool_status: String,
+    #[serde(deserialize_with = "deserialize_string_or_float")]
+    pub used_capacity_rate: f64,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
+    pub available_volume_capacity: u64,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
+    pub total_pool_capacity: u64,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
+    pub total_located_capacity: u64,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
+    pub snapshot_used_capacity: u64,
+    #[serde(deserialize_with = "deserialize_string_or_uint")]
+    pub num_of_ldevs: u64,
+    #[serde(deserialize_with = "deserialize_string_or_float")]
+    pub warning_threshold: f64,
+    #[serde(deserialize_with = "deserialize_string_or_float")]
+    pub depletion_threshold: f64,
+}
+
+impl IntoPoint for StoragePool {
+    /// Rates and thresholds are emitted as 0-100 floats named
+    /// used_capacity_percent, warning_threshold_percent and
+    /// depletion_threshold_percent
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("hitachi_pool"), is_time_series);
+        p.add_tag("pool_id", TsValue::String(self.pool_id.to_string()));
+        p.add_tag("pool_name", TsValue::String(self.pool_name.clone()));
+        p.add_tag("pool_type", TsValue::String(self.pool_type.clone()));
+        p.add_tag("pool_status", TsValue::String(self.pool_status.clone()));
+        p.add_field(
+            "available_volume_capacity",
+            TsValue::Long(self.available_volume_capacity),
+        );
+        p.add_field(
+            "total_pool_capacity",
+            TsValue::Long(self.total_pool_capacity),
+        );
+        p.add_field(
+            "total_located_capacity",
+            TsValue::Long(self.total_located_capacity),
+        );
+        p.add_field(
+            "snapshot_used_capacity",
+            TsValue::Long(self.snapshot_used_capacity),
+        );
+        p.add_field("num_of_ldevs", TsValue::Long(self.num_of_ldevs));
+        let rates = [
+            ("used_capacity_percent", self.used_capacity_rate),
+            ("warning_threshold_percent", self.warning_threshold),
+            ("depletion_threshold_percent", self.depletion_threshold),
+        ];
+        for (field, rate) in rates.iter() {
+            if let Some(percent) = rate_to_percent(*rate) {
+                p.add_field(*field, TsValue::Float(percent));
+            }
+        }
+
+        vec![p]
+    }
+}
+
+/// A host wwn logged into a port
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortLogin {
+    pub login_wwn: String,
+    pub wwn_nick_name: Option<String>,
+}
+
+/// A front end port.  logins is only filled in when the ports are requested
+/// with detailInfoType=logins
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Port {
+    pub port_id: String,
+    pub port_type: String,
+    pub loop_id: Option<String>,
+    pub fabric_mode: Option<bool>,
+    pub port_connection: Option<String>,
+    pub lun_security_setting: Option<bool>,
+    /// The wwn of the port itself.  Not set on iscsi ports
+    pub wwn: Option<String>,
+    #[serde(default)]
+    pub logins: Vec<PortLogin>,
+}
+
+impl IntoPoint for Port {
+    /// The wwns logged into the port are emitted as the attached_wwns field
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("hitachi_port"), is_time_series);
+        p.add_tag("port_id", TsValue::String(self.port_id.clone()));
+        p.add_tag("port_type", TsValue::String(self.port_type.clone()));
+        if let Some(loop_id) = &self.loop_id {
+            p.add_tag("loop_id", TsValue::String(loop_id.clone()));
+        }
+        if let Some(connection) = &self.port_connection {
+            p.add_tag("port_connection", TsValue::String(connection.clone()));
+        }
+        if let Some(wwn) = &self.wwn {
+            p.add_tag("wwn", TsValue::String(wwn.clone()));
+        }
+        if let Some(fabric_mode) = self.fabric_mode {
+            p.add_field("fabric_mode", TsValue::Boolean(fabric_mode));
+        }
+        if let Some(lun_security) = self.lun_security_setting {
+            p.add_field("lun_security_setting", TsValue::Boolean(lun_security));
+        }
+        p.add_field(
+            "attached_wwns",
+            TsValue::StringVec(self.logins.iter().map(|l| l.login_wwn.clone()).collect()),
+        );
+
+        vec![p]
+    }
+}
+
+/// A host wwn registered to a host group
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostWwn {
+    pub port_id: String,
+    pub host_group_number: u64,
+    pub host_wwn: String,
+    pub wwn_nickname: Option<String>,
+}
+
+/// A host group on a port.  wwns isn't part of the host-groups response,
+/// `Hitachi::host_groups` fills it in from the host-wwns of each group
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostGroup {
+    pub port_id: String,
+    pub host_group_number: u64,
+    pub host_group_name: String,
+    pub host_mode: String,
+    #[serde(default)]
+    pub wwns: Vec<String>,
+}
+
+impl IntoPoint for HostGroup {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("hitachi_host_group"), is_time_series);
+        p.add_tag("port_id", TsValue::String(self.port_id.clone()));
+        p.add_tag(
+            "host_group_name",
+            TsValue::String(self.host_group_name.clone()),
+        );
+        p.add_tag("host_mode", TsValue::String(self.host_mode.clone()));
+        p.add_field("host_group_number", TsValue::Long(self.host_group_number));
+        p.add_field("wwns", TsValue::StringVec(self.wwns.clone()));
+
+        vec![p]
+    }
+}
+
+/// One hitachi_host_group_wwn point per host group and wwn registered to
+/// it, for joining hosts to the ports they reach the array through
+pub fn host_group_topology(host_groups: &[HostGroup], t: DateTime<Utc>) -> Vec<TsPoint> {
+    host_groups
+        .iter()
+        .flat_map(|group| {
+            group.wwns.iter().map(move |wwn| {
+                let mut p = TsPoint::new("hitachi_host_group_wwn", true);
+                p.add_tag("port_id", TsValue::String(group.port_id.clone()));
+                p.add_tag(
+                    "host_group_name",
+                    TsValue::String(group.host_group_name.clone()),
+                );
+                p.add_tag("wwn", TsValue::String(wwn.clone()));
+                p.add_field("host_group_number", TsValue::Long(group.host_group_number));
+                p.set_time(t)
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_ports_and_host_groups() {
+    use chrono::TimeZone;
+
+    let ports = include_str!("../tests/hitachi/ports.json");
+    let host_groups = include_str!("../tests/hitachi/host_groups.json");
+    let host_wwns = include_str!("../tests/hitachi/host_wwns.json");
+    let port = crate::mock_http_server(move |req| {
+        let path = req
+            .path
+            .strip_prefix("/ConfigurationManager/v1/objects/storages/886000123456/")
+            .unwrap_or_else(|| panic!("unexpected request {}", req.path));
+        let body = match path {
+            "ports?detailInfoType=logins" | "ports" => ports,
+            "host-groups?portId=CL1-A" => host_groups,
+            // A port with no host groups
+            "host-groups?portId=CL1-B" => r#"{"data": []}"#,
+            "host-wwns?portId=CL1-A&hostGroupNumber=0" => host_wwns,
+            // Deleted between listing the host groups and asking for its
+            // wwns
+            "host-wwns?portId=CL1-A&hostGroupNumber=1" => {
+                return (
+                    404,
+                    String::new(),
+                    br#"{"errorSource": "host-wwns"}"#.to_vec(),
+                )
+            }
+            p => panic!("unexpected request {}", p),
+        };
+        (200, String::new(), body.as_bytes().to_vec())
+    });
+    let config = HitachiConfig {
+        endpoint: format!("127.0.0.1:{}", port),
+        user: "admin".into(),
+        password: "secret".into(),
+        region: "test".into(),
+        max_response_bytes: None,
+        host_group_concurrency: None,
+        storage_ids: None,
+    };
+    let hitachi = Hitachi::new(&reqwest::blocking::Client::new(), config.clone());
+    let storage_id = "886000123456";
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+
+    let ports = hitachi.get_ports(storage_id, t).unwrap();
+    assert_eq!(ports.len(), 2);
+    assert_eq!(ports[0].measurement, "hitachi_port");
+    assert_eq!(ports[0].timestamp, Some(t));
+    assert_eq!(
+        ports[0].tags["wwn"],
+        TsValue::String("50060e8012277d00".into())
+    );
+    assert_eq!(
+        ports[0].fields["attached_wwns"],
+        TsValue::StringVec(vec!["210003e08b0256f9".into(), "210003e08b0256fa".into()])
+    );
+    assert_eq!(ports[1].fields["attached_wwns"], TsValue::StringVec(vec![]));
+    assert_eq!(
+        ports[1].tags["storage_device_id"],
+        TsValue::String(storage_id.into())
+    );
+
+    let groups = hitachi.host_groups(storage_id, None).unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].wwns, vec!["210003e08b0256f9", "210003e08b0256fa"]);
+    assert!(groups[1].wwns.is_empty());
+    assert!(hitachi
+        .host_groups(storage_id, Some("CL1-B"))
+        .unwrap()
+        .is_empty());
+
+    // One at a time gives the same groups
+    let sequential = Hitachi::new(
+        &reqwest::blocking::Client::new(),
+        HitachiConfig {
+            host_group_concurrency: Some(1),
+            ..config
+        },
+    );
+    assert!(sequential.pool.is_none());
+    let one_at_a_time = sequential.host_groups(storage_id, None).unwrap();
+    assert_eq!(one_at_a_time.len(), 2);
+    assert_eq!(one_at_a_time[0].wwns, groups[0].wwns);
+
+    let points = hitachi
+        .get_host_groups(storage_id, Some("CL1-A"), t)
+        .unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(
+        points[0].tags["storage_device_id"],
+        TsValue::String(storage_id.into())
+    );
+    assert_eq!(
+        points[1].tags["host_mode"],
+        TsValue::String("VMWARE_EX".into())
+    );
+    assert_eq!(points[1].fields["host_group_number"], TsValue::Long(1));
+
+    let topology = host_group_topology(&groups, t);
+    assert_eq!(topology.len(), 2);
+    assert!(topology.iter().all(
+        |p| p.tags["host_group_name"] == TsValue::String("1A-G00".into()) && p.timestamp == Some(t)
+    ));
+    assert_eq!(
+        topology[1].tags["wwn"],
+        TsValue::String("210003e08b0256fa".into())
+    );
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Version {
     pub productName: String,
@@ -339,6 +706,82 @@ fn test_get_ldev() {
     println!("Result: {:#?}", points);
 }
 
+// The measurement, tag names and field names with their value types
+#[cfg(test)]
+fn point_schema(points: &[TsPoint]) -> Vec<(String, Vec<String>, Vec<String>)> {
+    points
+        .iter()
+        .map(|p| {
+            let mut tags: Vec<String> = p.tags.keys().map(|k| k.to_string()).collect();
+            tags.sort();
+            let mut fields: Vec<String> = p
+                .fields
+                .iter()
+                .map(|(k, v)| format!("{}:{:?}", k, std::mem::discriminant(v)))
+                .collect();
+            fields.sort();
+            (p.measurement.clone(), tags, fields)
+        })
+        .collect()
+}
+
+#[test]
+fn test_get_ldev_firmware_variants() {
+    let new_fw = include_str!("../tests/hitachi/storage_ldev.json");
+    let old_fw = include_str!("../tests/hitachi/storage_ldev_old_fw.json");
+    let new_fw: ServerResult<StorageLdev> = serde_json::from_str(new_fw).unwrap();
+    let old_fw: ServerResult<StorageLdev> = serde_json::from_str(old_fw).unwrap();
+    let new_points: Vec<TsPoint> = new_fw
+        .data
+        .iter()
+        .flat_map(|s| s.into_point(Some("hitachi_ldev"), false))
+        .collect();
+    let old_points: Vec<TsPoint> = old_fw
+        .data
+        .iter()
+        .flat_map(|s| s.into_point(Some("hitachi_ldev"), false))
+        .collect();
+    assert_eq!(point_schema(&new_points), point_schema(&old_points));
+    assert_eq!(new_points[1].fields, old_points[1].fields);
+    assert_eq!(old_points[1].fields["block_capacity"], TsValue::Long(10));
+}
+
+#[test]
+fn test_get_pools_firmware_variants() {
+    let new_fw = include_str!("../tests/hitachi/storage_pools.json");
+    let old_fw = include_str!("../tests/hitachi/storage_pools_old_fw.json");
+    let new_fw: ServerResult<StoragePool> = serde_json::from_str(new_fw).unwrap();
+    let old_fw: ServerResult<StoragePool> = serde_json::from_str(old_fw).unwrap();
+    println!("Result: {:#?}", old_fw);
+    let new_points: Vec<TsPoint> = new_fw
+        .data
+        .iter()
+        .flat_map(|s| s.into_point(Some("hitachi_pool"), false))
+        .collect();
+    let old_points: Vec<TsPoint> = old_fw
+        .data
+        .iter()
+        .flat_map(|s| s.into_point(Some("hitachi_pool"), false))
+        .collect();
+    assert_eq!(point_schema(&new_points), point_schema(&old_points));
+    for (new, old) in new_points.iter().zip(old_points.iter()) {
+        assert_eq!(new.tags, old.tags);
+        assert_eq!(new.fields, old.fields);
+    }
+    assert_eq!(
+        new_points[0].fields["used_capacity_percent"],
+        TsValue::Float(42.0)
+    );
+    assert_eq!(
+        new_points[0].fields["warning_threshold_percent"],
+        TsValue::Float(70.0)
+    );
+    // A -1 threshold means none is set
+    assert!(!new_points[1]
+        .fields
+        .contains_key("depletion_threshold_percent"));
+}
+
 #[test]
 fn test_new_parser() {
     use std::fs::File;
@@ -529,6 +972,15 @@ pub struct HitachiConfig {
     pub password: String,
     /// The region this cluster is located in
     pub region: String,
+    /// Largest response body to read, in bytes.  Defaults to
+    /// DEFAULT_MAX_RESPONSE_BYTES
+    pub max_response_bytes: Option<u64>,
+    /// How many host groups to request the wwns of at once.  Defaults to 4,
+    /// 1 requests them one at a time
+    pub host_group_concurrency: Option<usize>,
+    /// Storage systems `Collector::collect` gathers, ie "886000123456".
+    /// Defaults to every storage system ConfigurationManager knows of
+    pub storage_ids: Option<Vec<String>>,
 }
 #[test]
 fn test_convert_base() {
@@ -562,49 +1014,64 @@ fn convert_to_base16(num: u64) -> String {
 impl Hitachi {
     /// This request obtains the detailed version of the API
     pub fn get_version(&self) -> MetricsResult<Version> {
-        let version: Version = self
+        let url = format!(
+            "http://@{endpoint}/TuningManager/v1/configuration/Version",
+            endpoint = self.config.endpoint
+        );
+        let resp = self
             .client
-            .get(&format!(
-                "http://@{endpoint}/TuningManager/v1/configuration/Version",
-                endpoint = self.config.endpoint
-            ))
+            .get(&url)
             .basic_auth(&self.config.user, Some(&self.config.password))
             .header(ACCEPT, "application/json")
             .send()?
-            .error_for_status()?
-            .json()?;
+            .error_for_status()?;
+        let version: Version = decode_json(
+            "GET",
+            &url,
+            &read_text(resp, self.config.max_response_bytes)?,
+        )?;
         Ok(version)
     }
 
     pub fn get_agent_for_raid(&self) -> MetricsResult<Collection> {
-        let agents: Collection = self
+        let url = format!(
+            "http://{}/TuningManager/v1/objects/AgentForRAID",
+            self.config.endpoint
+        );
+        let resp = self
             .client
-            .get(&format!(
-                "http://{}/TuningManager/v1/objects/AgentForRAID",
-                self.config.endpoint
-            ))
+            .get(&url)
             .basic_auth(&self.config.user, Some(&self.config.password))
             .header(ACCEPT, "application/json")
             .send()?
-            .error_for_status()?
-            .json()?;
+            .error_for_status()?;
+        let agents: Collection = decode_json(
+            "GET",
+            &url,
+            &read_text(resp, self.config.max_response_bytes)?,
+        )?;
 
         Ok(agents)
     }
 
     // HDS NAS have a specific search criteria for the instance_name than what AgentFroRaid is. Alternate is to use agentType=ALL and will see everything
     pub fn get_agent_for_nas(&self) -> MetricsResult<Collection> {
-        let agentnas: Collection = self
+        let url = format!(
+            "http://{}/TuningManager/v1/objects/Agents?agentType=NAS",
+            self.config.endpoint
+        );
+        let resp = self
             .client
-            .get(&format!(
-                "http://{}/TuningManager/v1/objects/Agents?agentType=NAS",
-                self.config.endpoint
-            ))
+            .get(&url)
             .basic_auth(&self.config.user, Some(&self.config.password))
             .header(ACCEPT, "application/json")
             .send()?
-            .error_for_status()?
-            .json()?;
+            .error_for_status()?;
+        let agentnas: Collection = decode_json(
+            "GET",
+            &url,
+            &read_text(resp, self.config.max_response_bytes)?,
+        )?;
 
         Ok(agentnas)
     }
@@ -623,8 +1090,9 @@ impl Hitachi {
             ))
             .basic_auth(&self.config.user, Some(&self.config.password))
             .send()?
-            .error_for_status()?
-            .text()?;
+            .error_for_status()
+            .map_err(StorageError::from)
+            .and_then(|r| read_text(r, self.config.max_response_bytes))?;
         trace!("server response: {}", content);
         Ok(content)
     }
@@ -635,11 +1103,12 @@ impl Hitachi {
             "http://{}/ConfigurationManager/v1/objects/storages",
             self.config.endpoint
         );
-        let s: ServerResult<ConfigManagerStorage> = super::get(
+        let s: ServerResult<ConfigManagerStorage> = super::get_limited(
             &self.client,
             &endpoint,
             &self.config.user,
             Some(&self.config.password),
+            self.config.max_response_bytes,
         )?;
         Ok(s)
     }
@@ -650,11 +1119,12 @@ impl Hitachi {
             "http://{}/ConfigurationManager/v1/objects/storages/{}/ldevs?ldevOption=dpVolume",
             self.config.endpoint, storage_id
         );
-        let s: ServerResult<StorageLdev> = super::get(
+        let s: ServerResult<StorageLdev> = super::get_limited(
             &self.client,
             &endpoint,
             &self.config.user,
             Some(&self.config.password),
+            self.config.max_response_bytes,
         )?;
         let points = s
             .data
@@ -671,6 +1141,135 @@ impl Hitachi {
 
         Ok(points)
     }
+
+    /// The objects of storage_id at api_call.  Note this only works with
+    /// ConfigurationManager
+    fn get_config_manager<T>(&self, storage_id: &str, api_call: &str) -> MetricsResult<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned + std::fmt::Debug,
+    {
+        let endpoint = format!(
+            "http://{}/ConfigurationManager/v1/objects/storages/{}/{}",
+            self.config.endpoint, storage_id, api_call
+        );
+        let s: ServerResult<T> = super::get_limited(
+            &self.client,
+            &endpoint,
+            &self.config.user,
+            Some(&self.config.password),
+            self.config.max_response_bytes,
+        )?;
+        Ok(s.data)
+    }
+
+    /// The front end ports of storage_id along with the wwns logged into
+    /// them.  Note this only works with ConfigurationManager
+    pub fn get_ports(&self, storage_id: &str, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let ports: Vec<Port> =
+            self.get_config_manager(storage_id, "ports?detailInfoType=logins")?;
+        Ok(ports
+            .iter()
+            .flat_map(|port| port.into_point(Some("hitachi_port"), true))
+            .map(|mut point| {
+                point.add_tag("storage_device_id", TsValue::String(storage_id.to_string()));
+                point.set_time(t)
+            })
+            .collect())
+    }
+
+    /// The host groups of storage_id on port_id, or on every port when it's
+    /// None, with their wwns filled in.  The wwns of host_group_concurrency
+    /// groups are requested at a time and a group that 404s, usually
+    /// because it was deleted in the meantime, is left without any.  Note
+    /// this only works with ConfigurationManager
+    pub fn host_groups(
+        &self,
+        storage_id: &str,
+        port_id: Option<&str>,
+    ) -> MetricsResult<Vec<HostGroup>> {
+        let port_ids = match port_id {
+            Some(port_id) => vec![port_id.to_string()],
+            None => self
+                .get_config_manager::<Port>(storage_id, "ports")?
+                .into_iter()
+                .map(|port| port.port_id)
+                .collect(),
+        };
+        let mut host_groups = Vec::new();
+        for port_id in port_ids {
+            host_groups.extend(self.get_config_manager::<HostGroup>(
+                storage_id,
+                &format!("host-groups?portId={}", port_id),
+            )?);
+        }
+        let fill_wwns = |mut group: HostGroup| {
+            let api_call = format!(
+                "host-wwns?portId={}&hostGroupNumber={}",
+                group.port_id, group.host_group_number
+            );
+            match self.get_config_manager::<HostWwn>(storage_id, &api_call) {
+                Ok(wwns) => group.wwns = wwns.into_iter().map(|w| w.host_wwn).collect(),
+                Err(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                    warn!(
+                        "host group {} {} has no host-wwns: {}",
+                        group.port_id, group.host_group_name, e
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+            Ok(group)
+        };
+        match self.pool {
+            Some(ref pool) => pool.install(|| host_groups.into_par_iter().map(fill_wwns).collect()),
+            None => host_groups.into_iter().map(fill_wwns).collect(),
+        }
+    }
+
+    /// Points for `host_groups`.  Use `host_group_topology` for a point per
+    /// host group and wwn
+    pub fn get_host_groups(
+        &self,
+        storage_id: &str,
+        port_id: Option<&str>,
+        t: DateTime<Utc>,
+    ) -> MetricsResult<Vec<TsPoint>> {
+        Ok(self
+            .host_groups(storage_id, port_id)?
+            .iter()
+            .flat_map(|group| group.into_point(Some("hitachi_host_group"), true))
+            .map(|mut point| {
+                point.add_tag("storage_device_id", TsValue::String(storage_id.to_string()));
+                point.set_time(t)
+            })
+            .collect())
+    }
+
+    /// Note this only works with ConfigurationManager
+    pub fn get_pools(&self, storage_id: &str) -> MetricsResult<Vec<TsPoint>> {
+        let endpoint = format!(
+            "http://{}/ConfigurationManager/v1/objects/storages/{}/pools",
+            self.config.endpoint, storage_id
+        );
+        let s: ServerResult<StoragePool> = super::get_limited(
+            &self.client,
+            &endpoint,
+            &self.config.user,
+            Some(&self.config.password),
+            self.config.max_response_bytes,
+        )?;
+        let points = s
+            .data
+            .iter()
+            .flat_map(|s| s.into_point(Some("hitachi_pool"), false))
+            // Tag each with storage_device_id
+            .map(|mut point| {
+                point.add_tag("storage_device_id", TsValue::String(storage_id.to_string()));
+                point
+            })
+            .collect();
+
+        Ok(points)
+    }
 }
 
 pub fn csv_to_points(
@@ -0,0 +1,272 @@
+//! Pre-aggregation of samples over a time window, for polling arrays more
+//! often than their samples need to be kept.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::ir::{TsPoint, TsValue};
+use chrono::{DateTime, Duration, Utc};
+
+/// How the samples of a field within a window are combined
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AggregateFn {
+    Min,
+    Max,
+    Avg,
+    /// The value of the latest sample, unconverted
+    Last,
+}
+
+impl AggregateFn {
+    pub fn name(self) -> &'static str {
+        match self {
+            AggregateFn::Min => "min",
+            AggregateFn::Max => "max",
+            AggregateFn::Avg => "avg",
+            AggregateFn::Last => "last",
+        }
+    }
+}
+
+impl fmt::Display for AggregateFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug)]
+struct FieldStats {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u64,
+    last: TsValue,
+}
+
+#[derive(Debug)]
+struct Series {
+    measurement: String,
+    tags: HashMap<Cow<'static, str>, TsValue>,
+    fields: HashMap<Cow<'static, str>, FieldStats>,
+    // Fields without a numeric value, ie strings.  The latest is kept
+    other: HashMap<Cow<'static, str>, TsValue>,
+    samples: u64,
+    last_seen: DateTime<Utc>,
+}
+
+/// Pre-aggregates points over a time window so collectors can poll faster
+/// than the samples need to be stored.  Points are grouped into series by
+/// measurement and tags.  When the window ends one point per series is
+/// flushed holding `{field}_{fn}` for every numeric field and aggregation
+/// function, the latest value of every other field and a `sample_count`.
+/// Flushed points are stamped with the time of their latest sample
+#[derive(Debug)]
+pub struct Aggregator {
+    window: Duration,
+    default_fns: Vec<AggregateFn>,
+    field_fns: HashMap<String, Vec<AggregateFn>>,
+    window_start: Option<DateTime<Utc>>,
+    series: BTreeMap<String, Series>,
+}
+
+// Identifies a series by its measurement and sorted tags
+fn series_key(point: &TsPoint) -> String {
+    let tags: BTreeMap<&str, String> = point
+        .tags
+        .iter()
+        .map(|(k, v)| (k.as_ref(), v.to_string()))
+        .collect();
+    let mut key = point.measurement.clone();
+    for (k, v) in tags {
+        key.push_str(&format!(",{}={}", k, v));
+    }
+    key
+}
+
+impl Aggregator {
+    /// Aggregate over window with avg, max and last for every field
+    pub fn new(window: Duration) -> Self {
+        Aggregator {
+            window,
+            default_fns: vec![AggregateFn::Avg, AggregateFn::Max, AggregateFn::Last],
+            field_fns: HashMap::new(),
+            window_start: None,
+            series: BTreeMap::new(),
+        }
+    }
+
+    /// Replace the functions used for fields without their own
+    pub fn with_default_fns(mut self, fns: &[AggregateFn]) -> Self {
+        self.default_fns = fns.to_vec();
+        self
+    }
+
+    /// Use fns for field in every measurement
+    pub fn with_field_fns(mut self, field: &str, fns: &[AggregateFn]) -> Self {
+        self.field_fns.insert(field.to_string(), fns.to_vec());
+        self
+    }
+
+    /// Add a batch of samples.  If a sample falls after the end of the
+    /// current window the window is flushed first and its points returned,
+    /// otherwise the result is empty.  Points without a timestamp count as
+    /// sampled now
+    pub fn add(&mut self, points: &[TsPoint]) -> Vec<TsPoint> {
+        let mut flushed = Vec::new();
+        for point in points {
+            let t = point.timestamp.unwrap_or_else(Utc::now);
+            match self.window_start {
+                Some(start) if t >= start + self.window => {
+                    flushed.extend(self.flush());
+                    self.window_start = Some(t);
+                }
+                Some(_) => {}
+                None => self.window_start = Some(t),
+            }
+            self.accumulate(point, t);
+        }
+        flushed
+    }
+
+    fn accumulate(&mut self, point: &TsPoint, t: DateTime<Utc>) {
+        let series = self
+            .series
+            .entry(series_key(point))
+            .or_insert_with(|| Series {
+                measurement: point.measurement.clone(),
+                tags: point.tags.clone(),
+                fields: HashMap::new(),
+                other: HashMap::new(),
+                samples: 0,
+                last_seen: t,
+            });
+        series.samples += 1;
+        series.last_seen = series.last_seen.max(t);
+        for (name, value) in &point.fields {
+            let v = match value.as_f64() {
+                Some(v) if !v.is_nan() => v,
+                _ => {
+                    series.other.insert(name.clone(), value.clone());
+                    continue;
+                }
+            };
+            let stats = series
+                .fields
+                .entry(name.clone())
+                .or_insert_with(|| FieldStats {
+                    min: v,
+                    max: v,
+                    sum: 0.0,
+                    count: 0,
+                    last: value.clone(),
+                });
+            stats.min = stats.min.min(v);
+            stats.max = stats.max.max(v);
+            stats.sum += v;
+            stats.count += 1;
+            stats.last = value.clone();
+        }
+    }
+
+    /// Flush the current window, one point per series, and start a new one
+    /// with the next sample added
+    pub fn flush(&mut self) -> Vec<TsPoint> {
+        self.window_start = None;
+        let series = std::mem::take(&mut self.series);
+        series
+            .into_values()
+            .map(|s| {
+                let mut p = TsPoint::new(&s.measurement, true);
+                p.tags = s.tags;
+                for (name, stats) in s.fields {
+                    let fns = self
+                        .field_fns
+                        .get(name.as_ref())
+                        .unwrap_or(&self.default_fns);
+                    for f in fns {
+                        let value = match f {
+                            AggregateFn::Min => TsValue::Float(stats.min),
+                            AggregateFn::Max => TsValue::Float(stats.max),
+                            AggregateFn::Avg => TsValue::Float(stats.sum / stats.count as f64),
+                            AggregateFn::Last => stats.last.clone(),
+                        };
+                        p.add_field(format!("{}_{}", name, f), value);
+                    }
+                }
+                for (name, value) in s.other {
+                    p.add_field(name, value);
+                }
+                p.add_field("sample_count", TsValue::Long(s.samples));
+                p.set_time(s.last_seen)
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_aggregator() {
+    use chrono::TimeZone;
+
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let sample = |secs: i64, pool: &str, read_iops: u64, latency: f64| {
+        let mut p = TsPoint::new("scaleio_pool_stats", true);
+        p.add_tag("pool", TsValue::String(pool.to_string()));
+        p.add_field("read_iops", TsValue::Long(read_iops));
+        p.add_field("read_latency", TsValue::Float(latency));
+        p.add_field("state", TsValue::String(format!("state{}", secs)));
+        p.set_time(t + Duration::seconds(secs))
+    };
+    let mut agg = Aggregator::new(Duration::seconds(60))
+        .with_field_fns("read_latency", &[AggregateFn::Min, AggregateFn::Max]);
+
+    assert!(agg
+        .add(&[sample(0, "a", 100, 1.5), sample(0, "b", 7, 9.0)])
+        .is_empty());
+    assert!(agg.add(&[sample(20, "a", 300, 0.5)]).is_empty());
+    assert!(agg.add(&[sample(40, "a", 200, 2.5)]).is_empty());
+    // Past the end of the window, flushes the first three samples
+    let flushed = agg.add(&[sample(60, "a", 1000, 4.0)]);
+    assert_eq!(flushed.len(), 2);
+
+    let a = &flushed[0];
+    assert_eq!(a.tags["pool"], TsValue::String("a".into()));
+    assert_eq!(a.timestamp, Some(t + Duration::seconds(40)));
+    assert_eq!(a.fields["read_iops_avg"], TsValue::Float(200.0));
+    assert_eq!(a.fields["read_iops_max"], TsValue::Float(300.0));
+    assert_eq!(a.fields["read_iops_last"], TsValue::Long(200));
+    assert_eq!(a.fields["read_latency_min"], TsValue::Float(0.5));
+    assert_eq!(a.fields["read_latency_max"], TsValue::Float(2.5));
+    assert!(!a.fields.contains_key("read_latency_avg"));
+    assert_eq!(a.fields["state"], TsValue::String("state40".into()));
+    assert_eq!(a.fields["sample_count"], TsValue::Long(3));
+
+    let b = &flushed[1];
+    assert_eq!(b.tags["pool"], TsValue::String("b".into()));
+    assert_eq!(b.fields["read_iops_avg"], TsValue::Float(7.0));
+    assert_eq!(b.fields["sample_count"], TsValue::Long(1));
+
+    // The sample that closed the window starts the next one
+    let rest = agg.flush();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].fields["read_iops_last"], TsValue::Long(1000));
+    assert_eq!(rest[0].timestamp, Some(t + Duration::seconds(60)));
+    assert!(agg.flush().is_empty());
+}
@@ -0,0 +1,128 @@
+//! Filtering of the fields a backend emits, so deployments can drop the
+//! stats they don't care about before they reach the TSDB.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use crate::ir::TsPoint;
+
+/// Match name against a glob where `*` matches any run of characters and
+/// `?` matches exactly one
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // Where the last * was seen and how much of name it has taken
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((star_pi, star_ni)) = star {
+            // Let the * take one more character and try again
+            pi = star_pi + 1;
+            ni = star_ni + 1;
+            star = Some((star_pi, star_ni + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("read_bwc", "read_bwc"));
+    assert!(!glob_match("read_bwc", "read_bwc_num_seconds"));
+    assert!(glob_match("*_bwc*", "fwd_rebuild_read_bwc_num_seconds"));
+    assert!(glob_match("rmcache_*", "rmcache_size_in_kb"));
+    assert!(!glob_match("rmcache_*", "rfcache_size_in_kb"));
+    assert!(glob_match("?fcache*", "rfcache_size_in_kb"));
+    assert!(glob_match("*", ""));
+    assert!(!glob_match("?", ""));
+    assert!(glob_match("a*b*c", "aXXbYYbc"));
+    assert!(!glob_match("a*b*c", "aXXbYYbd"));
+}
+
+/// Which fields of a point to emit.  With an allow list only fields
+/// matching one of its globs are kept, then any matching a deny glob are
+/// dropped.  The default emits everything
+#[derive(Clone, Debug, Default)]
+pub struct FieldFilter {
+    allow: Option<Vec<String>>,
+    deny: Option<Vec<String>>,
+}
+
+impl FieldFilter {
+    pub fn new(allow: Option<Vec<String>>, deny: Option<Vec<String>>) -> Self {
+        FieldFilter { allow, deny }
+    }
+
+    pub fn emits(&self, field: &str) -> bool {
+        let matches = |globs: &[String]| globs.iter().any(|g| glob_match(g, field));
+        self.allow.as_deref().map(matches).unwrap_or(true)
+            && !self.deny.as_deref().map(matches).unwrap_or(false)
+    }
+
+    /// Drop the fields this filter doesn't emit.  A point left with no
+    /// fields is dropped entirely since it no longer carries anything
+    pub fn filter_point(&self, mut point: TsPoint) -> Option<TsPoint> {
+        if self.allow.is_none() && self.deny.is_none() {
+            return Some(point);
+        }
+        let had_fields = !point.fields.is_empty();
+        point.fields.retain(|name, _| self.emits(name));
+        if had_fields && point.fields.is_empty() {
+            return None;
+        }
+        Some(point)
+    }
+}
+
+#[test]
+fn test_field_filter() {
+    use crate::ir::TsValue;
+
+    let mut p = TsPoint::new("scaleio_sds_stat", false);
+    p.add_tag("sds_id", TsValue::String("6c9b2a8c00000001".into()));
+    p.add_field("read_bwc", TsValue::Long(1));
+    p.add_field("write_bwc", TsValue::Long(2));
+    p.add_field("rmcache_size_in_kb", TsValue::Long(3));
+
+    let fields = |filter: &FieldFilter| {
+        let mut names: Vec<String> = filter
+            .filter_point(p.clone())
+            .map(|p| p.field_keys().map(|k| k.to_string()).collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    };
+    assert_eq!(
+        fields(&FieldFilter::default()),
+        vec!["read_bwc", "rmcache_size_in_kb", "write_bwc"]
+    );
+    // Allow wins first and deny trims what it let through
+    let both = FieldFilter::new(Some(vec!["*_bwc".into()]), Some(vec!["write_*".into()]));
+    assert_eq!(fields(&both), vec!["read_bwc"]);
+    // Tags are never filtered and an emptied point is dropped
+    let p = both.filter_point(p.clone()).unwrap();
+    assert_eq!(p.tags.len(), 1);
+    let none = FieldFilter::new(Some(vec!["nothing".into()]), None);
+    assert!(none.filter_point(p).is_none());
+}
@@ -0,0 +1,362 @@
+//! A safety valve between collectors and the time series database.  A
+//! misconfigured collector can emit far more points or series than the
+//! database relay can take, so batches are checked against limits first.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::ir::{TsPoint, TsValue};
+use chrono::{DateTime, Utc};
+use log::warn;
+
+/// What to do with a batch of points
+#[derive(Clone, Debug)]
+pub enum GuardDecision {
+    /// The batch is within the limits and can be sent as is
+    Pass,
+    /// Measurements with too many series were cut down to their first
+    /// series.  Holds the points that were kept, in their original order
+    Truncate(Vec<TsPoint>),
+    /// The batch should be dropped entirely
+    Reject(String),
+}
+
+/// What happens to a tag that takes too many distinct values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TagLimitAction {
+    /// Move the tag's value to a field of the same name so the data is kept
+    /// without creating a series per value.  If the point already has a
+    /// field by that name the tag is dropped
+    Demote,
+    /// Remove the tag
+    Drop,
+}
+
+/// Limits the size of point batches.  A batch with more than
+/// `max_series_per_measurement` distinct tag sets for a measurement is
+/// truncated to the first series seen for it, and a batch still holding more
+/// than `max_points_per_batch` points after that is rejected.  Counts of
+/// what was dropped are kept across batches and can be emitted with
+/// `to_point`
+#[derive(Debug)]
+pub struct PointGuard {
+    max_points_per_batch: usize,
+    max_series_per_measurement: usize,
+    max_tag_values: Option<(usize, TagLimitAction)>,
+    batches_checked: AtomicU64,
+    batches_truncated: AtomicU64,
+    batches_rejected: AtomicU64,
+    points_dropped: AtomicU64,
+    tags_limited: AtomicU64,
+}
+
+// Identifies a series by its tags.  Tags are sorted so insertion order into
+// the tag map doesn't matter
+fn series_key(point: &TsPoint) -> Vec<(&str, String)> {
+    let tags: BTreeMap<&str, &TsValue> = point.tags.iter().map(|(k, v)| (k.as_ref(), v)).collect();
+    tags.into_iter().map(|(k, v)| (k, v.to_string())).collect()
+}
+
+impl PointGuard {
+    pub fn new(max_points_per_batch: usize, max_series_per_measurement: usize) -> Self {
+        PointGuard {
+            max_points_per_batch,
+            max_series_per_measurement,
+            max_tag_values: None,
+            batches_checked: AtomicU64::new(0),
+            batches_truncated: AtomicU64::new(0),
+            batches_rejected: AtomicU64::new(0),
+            points_dropped: AtomicU64::new(0),
+            tags_limited: AtomicU64::new(0),
+        }
+    }
+
+    /// Also limit each tag of a measurement to max distinct values per
+    /// batch.  See `limit_tag_values`
+    pub fn with_max_tag_values(mut self, max: usize, action: TagLimitAction) -> Self {
+        self.max_tag_values = Some((max, action));
+        self
+    }
+
+    /// Demote or drop every tag that takes more distinct values within its
+    /// measurement than the configured maximum, across the whole batch.
+    /// Returns the (measurement, tag) pairs that were over the limit.  Does
+    /// nothing unless `with_max_tag_values` was set.
+    ///
+    /// Points that only differed by a limited tag end up in the same
+    /// series.  The database keeps one point per series and timestamp, so
+    /// all but one of them are overwritten unless the remaining tags or
+    /// the timestamps still tell them apart
+    pub fn limit_tag_values(&self, points: &mut [TsPoint]) -> Vec<(String, String)> {
+        let (max, action) = match self.max_tag_values {
+            Some(limit) => limit,
+            None => return vec![],
+        };
+        let mut values: HashMap<(&str, &str), HashSet<String>> = HashMap::new();
+        for p in points.iter() {
+            for (tag, value) in &p.tags {
+                values
+                    .entry((p.measurement.as_str(), tag.as_ref()))
+                    .or_default()
+                    .insert(value.to_string());
+            }
+        }
+        let mut over: Vec<(String, String)> = values
+            .into_iter()
+            .filter(|(_, v)| v.len() > max)
+            .map(|((m, t), v)| {
+                warn!(
+                    "tag {} of {} has {} distinct values, over the limit of {}",
+                    t,
+                    m,
+                    v.len(),
+                    max
+                );
+                (m.to_string(), t.to_string())
+            })
+            .collect();
+        if over.is_empty() {
+            return over;
+        }
+        over.sort();
+        let mut limited: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for (m, t) in &over {
+            limited.entry(m.as_str()).or_default().insert(t.as_str());
+        }
+        for p in points.iter_mut() {
+            let limited_tags = match limited.get(p.measurement.as_str()) {
+                Some(tags) => tags,
+                None => continue,
+            };
+            let tags: Vec<_> = p
+                .tags
+                .keys()
+                .filter(|t| limited_tags.contains(t.as_ref()))
+                .cloned()
+                .collect();
+            for tag in tags {
+                let value = p.tags.remove(&tag).unwrap();
+                if action == TagLimitAction::Demote && !p.fields.contains_key(&tag) {
+                    p.fields.insert(tag, value);
+                }
+            }
+        }
+        self.tags_limited
+            .fetch_add(over.len() as u64, Ordering::Relaxed);
+        over
+    }
+
+    pub fn check(&self, points: &[TsPoint]) -> GuardDecision {
+        self.batches_checked.fetch_add(1, Ordering::Relaxed);
+
+        // Admit series per measurement in the order they're first seen
+        let mut series: HashMap<&str, HashSet<Vec<(&str, String)>>> = HashMap::new();
+        let keep: Vec<bool> = points
+            .iter()
+            .map(|p| {
+                let seen = series.entry(p.measurement.as_str()).or_default();
+                let key = series_key(p);
+                if seen.contains(&key) {
+                    true
+                } else if seen.len() < self.max_series_per_measurement {
+                    seen.insert(key);
+                    true
+                } else {
+                    false
+                }
+            })
+            .collect();
+        let kept = keep.iter().filter(|k| **k).count();
+
+        if kept > self.max_points_per_batch {
+            self.batches_rejected.fetch_add(1, Ordering::Relaxed);
+            self.points_dropped
+                .fetch_add(points.len() as u64, Ordering::Relaxed);
+            return GuardDecision::Reject(format!(
+                "batch of {} points exceeds the limit of {} points",
+                kept, self.max_points_per_batch
+            ));
+        }
+        if kept == points.len() {
+            return GuardDecision::Pass;
+        }
+        self.batches_truncated.fetch_add(1, Ordering::Relaxed);
+        self.points_dropped
+            .fetch_add((points.len() - kept) as u64, Ordering::Relaxed);
+        GuardDecision::Truncate(
+            points
+                .iter()
+                .zip(keep)
+                .filter(|(_, k)| *k)
+                .map(|(p, _)| p.clone())
+                .collect(),
+        )
+    }
+
+    /// The guard's counters since it was created as a "collector_guard"
+    /// point
+    pub fn to_point(&self, t: DateTime<Utc>) -> TsPoint {
+        let mut p = TsPoint::new("collector_guard", true);
+        p.timestamp = Some(t);
+        p.add_field(
+            "batches_checked",
+            TsValue::Long(self.batches_checked.load(Ordering::Relaxed)),
+        );
+        p.add_field(
+            "batches_truncated",
+            TsValue::Long(self.batches_truncated.load(Ordering::Relaxed)),
+        );
+        p.add_field(
+            "batches_rejected",
+            TsValue::Long(self.batches_rejected.load(Ordering::Relaxed)),
+        );
+        p.add_field(
+            "points_dropped",
+            TsValue::Long(self.points_dropped.load(Ordering::Relaxed)),
+        );
+        p.add_field(
+            "tags_limited",
+            TsValue::Long(self.tags_limited.load(Ordering::Relaxed)),
+        );
+
+        p
+    }
+}
+
+#[cfg(test)]
+fn guard_point(measurement: &str, volume: &str, value: u64) -> TsPoint {
+    let mut p = TsPoint::new(measurement, true);
+    p.add_tag("volume", TsValue::String(volume.into()));
+    p.add_field("value", TsValue::Long(value));
+    p
+}
+
+#[test]
+fn test_guard_truncates_per_measurement() {
+    let guard = PointGuard::new(100, 2);
+    // vol_a, vol_b and vol_c for each measurement, interleaved with repeats
+    let points = vec![
+        guard_point("read", "vol_c", 0),
+        guard_point("write", "vol_a", 1),
+        guard_point("read", "vol_a", 2),
+        guard_point("read", "vol_c", 3),
+        guard_point("read", "vol_b", 4),
+        guard_point("write", "vol_b", 5),
+        guard_point("write", "vol_c", 6),
+        guard_point("read", "vol_a", 7),
+    ];
+    let kept = match guard.check(&points) {
+        GuardDecision::Truncate(kept) => kept,
+        other => panic!("expected truncate, got {:?}", other),
+    };
+    // Each measurement keeps its own first two series: read keeps vol_c and
+    // vol_a, write keeps vol_a and vol_b
+    let values: Vec<TsValue> = kept.iter().map(|p| p.fields["value"].clone()).collect();
+    assert_eq!(
+        values,
+        vec![
+            TsValue::Long(0),
+            TsValue::Long(1),
+            TsValue::Long(2),
+            TsValue::Long(3),
+            TsValue::Long(5),
+            TsValue::Long(7),
+        ]
+    );
+
+    let p = guard.to_point(Utc::now());
+    assert_eq!(p.measurement, "collector_guard");
+    assert_eq!(p.fields["batches_truncated"], TsValue::Long(1));
+    assert_eq!(p.fields["points_dropped"], TsValue::Long(2));
+}
+
+#[test]
+fn test_guard_passes_and_rejects() {
+    let guard = PointGuard::new(3, 10);
+    let points = vec![
+        guard_point("read", "vol_b", 0),
+        guard_point("read", "vol_a", 1),
+        guard_point("write", "vol_b", 2),
+    ];
+    match guard.check(&points) {
+        GuardDecision::Pass => {}
+        other => panic!("expected pass, got {:?}", other),
+    }
+
+    let mut too_many = points.clone();
+    too_many.push(guard_point("write", "vol_a", 3));
+    match guard.check(&too_many) {
+        GuardDecision::Reject(reason) => {
+            assert_eq!(reason, "batch of 4 points exceeds the limit of 3 points")
+        }
+        other => panic!("expected reject, got {:?}", other),
+    }
+
+    let p = guard.to_point(Utc::now());
+    assert_eq!(p.fields["batches_checked"], TsValue::Long(2));
+    assert_eq!(p.fields["batches_rejected"], TsValue::Long(1));
+    assert_eq!(p.fields["points_dropped"], TsValue::Long(4));
+}
+
+#[test]
+fn test_guard_limits_tag_values() {
+    crate::capture_logs();
+    let guard = PointGuard::new(100, 100).with_max_tag_values(3, TagLimitAction::Demote);
+    let mut points: Vec<TsPoint> = (0..5)
+        .map(|i| {
+            let mut p = guard_point("read", &format!("vol_{}", i), i);
+            p.add_tag("pool", TsValue::String("pool_a".into()));
+            p
+        })
+        .collect();
+    // Another measurement with few volumes is left alone
+    points.push(guard_point("write", "vol_0", 5));
+
+    let over = guard.limit_tag_values(&mut points);
+    assert_eq!(over, vec![("read".to_string(), "volume".to_string())]);
+    let logs = crate::captured_logs("tag volume of read has 5 distinct values");
+    assert_eq!(logs.len(), 1);
+    for (i, p) in points[..5].iter().enumerate() {
+        assert!(!p.tags.contains_key("volume"));
+        assert_eq!(p.fields["volume"], TsValue::String(format!("vol_{}", i)));
+        assert_eq!(p.tags["pool"], TsValue::String("pool_a".into()));
+    }
+    assert_eq!(points[5].tags["volume"], TsValue::String("vol_0".into()));
+    // Demoting collapses the volumes into one series
+    match guard.check(&points) {
+        GuardDecision::Pass => {}
+        other => panic!("expected pass, got {:?}", other),
+    }
+    assert_eq!(
+        guard.to_point(Utc::now()).fields["tags_limited"],
+        TsValue::Long(1)
+    );
+
+    let guard = PointGuard::new(100, 100).with_max_tag_values(3, TagLimitAction::Drop);
+    guard.limit_tag_values(&mut points);
+    assert!(!points[0].tags.contains_key("volume"));
+    assert_eq!(points[0].fields["volume"], TsValue::String("vol_0".into()));
+    let mut fresh: Vec<TsPoint> = (0..4)
+        .map(|i| guard_point("read", &format!("vol_{}", i), i))
+        .collect();
+    guard.limit_tag_values(&mut fresh);
+    assert!(fresh
+        .iter()
+        .all(|p| p.tags.is_empty() && p.fields.len() == 1));
+}
@@ -0,0 +1,73 @@
+//! Collector health reporting.  A heartbeat is emitted for every collection
+//! run so alerting can key off failures instead of missing data.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use crate::ir::{TsPoint, TsValue};
+use chrono::{DateTime, Utc};
+
+/// The outcome of a single collection run against one array
+#[derive(Clone, Debug)]
+pub struct Heartbeat {
+    /// The backend kind, ie scaleio or vnx
+    pub backend: String,
+    /// Identifies the array that was collected from
+    pub array_id: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    pub points_emitted: u64,
+    /// Classification of the failure, see StorageError::kind
+    pub error_kind: Option<String>,
+    pub ts: DateTime<Utc>,
+}
+
+impl Heartbeat {
+    pub fn to_point(&self) -> TsPoint {
+        let mut p = TsPoint::new("collector_heartbeat", true);
+        p.timestamp = Some(self.ts);
+        p.add_tag("backend", TsValue::String(self.backend.clone()));
+        p.add_tag("array_id", TsValue::String(self.array_id.clone()));
+        if let Some(ref kind) = self.error_kind {
+            p.add_tag("error_kind", TsValue::String(kind.clone()));
+        }
+        p.add_field("success", TsValue::Boolean(self.success));
+        p.add_field("duration_ms", TsValue::Long(self.duration_ms));
+        p.add_field("points_emitted", TsValue::Long(self.points_emitted));
+
+        p
+    }
+}
+
+#[test]
+fn test_heartbeat_to_point() {
+    let ts = Utc::now();
+    let h = Heartbeat {
+        backend: "scaleio".into(),
+        array_id: "cluster1".into(),
+        success: false,
+        duration_ms: 1500,
+        points_emitted: 0,
+        error_kind: Some("timeout".into()),
+        ts,
+    };
+    let p = h.to_point();
+    assert_eq!(p.measurement, "collector_heartbeat");
+    assert_eq!(p.timestamp, Some(ts));
+    assert_eq!(p.tags["error_kind"], TsValue::String("timeout".into()));
+    assert_eq!(p.fields["success"], TsValue::Boolean(false));
+    assert_eq!(p.fields["duration_ms"], TsValue::Long(1500));
+}
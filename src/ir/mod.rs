@@ -19,16 +19,33 @@ use crate::error::{MetricsResult, StorageError};
 *
 * SPDX-License-Identifier: Apache-2.0
 */
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use influx_db_client::keys::{Point, Value};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::time::Duration;
+
+pub mod aggregate;
+pub mod filter;
+pub mod guard;
+pub mod health;
+pub mod provenance;
+pub mod query;
+pub mod registry;
+pub mod sink;
+pub mod tags;
+pub mod units;
 
 /// An intermediate representation of time series data points
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct TsPoint {
     pub measurement: String,
-    pub tags: HashMap<String, TsValue>,
-    pub fields: HashMap<String, TsValue>,
+    /// Keys are usually static names from the derive, so they're only
+    /// allocated when built at runtime
+    pub tags: HashMap<Cow<'static, str>, TsValue>,
+    pub fields: HashMap<Cow<'static, str>, TsValue>,
     /// This field is generally used for indexing
     pub timestamp: Option<DateTime<Utc>>,
     /// Optionally specify a field that should be used for indexing values.
@@ -38,10 +55,21 @@ pub struct TsPoint {
 
 impl TsPoint {
     pub fn new(measurement: &str, is_time_series: bool) -> TsPoint {
+        TsPoint::with_capacity(measurement, is_time_series, 0, 0)
+    }
+
+    /// Like new but with room for field_cap fields and tag_cap tags, so
+    /// the big statistics points don't rehash while they're built
+    pub fn with_capacity(
+        measurement: &str,
+        is_time_series: bool,
+        field_cap: usize,
+        tag_cap: usize,
+    ) -> TsPoint {
         TsPoint {
             measurement: String::from(measurement),
-            tags: HashMap::new(),
-            fields: HashMap::new(),
+            tags: HashMap::with_capacity(tag_cap),
+            fields: HashMap::with_capacity(field_cap),
             timestamp: if is_time_series {
                 Some(Utc::now())
             } else {
@@ -51,14 +79,16 @@ impl TsPoint {
         }
     }
 
-    /// Add a field and its value
-    pub fn add_field<T: ToString>(&mut self, field: T, value: TsValue) {
-        self.fields.insert(field.to_string(), value);
+    /// Add a field and its value.  A `&'static str` name is stored without
+    /// allocating
+    pub fn add_field<T: Into<Cow<'static, str>>>(&mut self, field: T, value: TsValue) {
+        self.fields.insert(field.into(), value);
     }
 
-    /// Add a tag and its value
-    pub fn add_tag<T: ToString>(&mut self, tag: T, value: TsValue) {
-        self.tags.insert(tag.to_string(), value);
+    /// Add a tag and its value.  A `&'static str` name is stored without
+    /// allocating
+    pub fn add_tag<T: Into<Cow<'static, str>>>(&mut self, tag: T, value: TsValue) {
+        self.tags.insert(tag.into(), value);
     }
 
     /// Set the field to be used for indexing if supported
@@ -79,9 +109,73 @@ impl TsPoint {
         self.timestamp = Some(t);
         self
     }
+
+    /// The measurement this point belongs to
+    pub fn measurement(&self) -> &str {
+        &self.measurement
+    }
+
+    /// The names of this point's tags, in no particular order
+    pub fn tag_keys(&self) -> impl Iterator<Item = &str> {
+        self.tags.keys().map(|k| k.as_ref())
+    }
+
+    /// The names of this point's fields, in no particular order
+    pub fn field_keys(&self) -> impl Iterator<Item = &str> {
+        self.fields.keys().map(|k| k.as_ref())
+    }
 }
 
-#[derive(Clone, Debug)]
+#[test]
+fn test_point_with_capacity() {
+    let mut p = TsPoint::with_capacity("scaleio_sys_stats", true, 128, 2);
+    assert!(p.fields.capacity() >= 128);
+    assert!(p.tags.capacity() >= 2);
+    assert!(p.timestamp.is_some());
+    assert!(TsPoint::with_capacity("scaleio_sys_stats", false, 0, 0)
+        .timestamp
+        .is_none());
+
+    let mut q = TsPoint::new("scaleio_sys_stats", true);
+    q.timestamp = p.timestamp;
+    for i in 0..128 {
+        p.add_field(format!("field_{}", i), TsValue::Long(i));
+        q.add_field(format!("field_{}", i), TsValue::Long(i));
+    }
+    p.add_tag("system_id", TsValue::String("4a54a8ba6df0690f".into()));
+    q.add_tag("system_id", TsValue::String("4a54a8ba6df0690f".into()));
+    assert_eq!(p, q);
+}
+
+#[test]
+fn test_point_keys() {
+    let mut p = TsPoint::new("scaleio_volume", true);
+    p.add_tag("volume_id", TsValue::String("c9e7bd3e00000003".into()));
+    p.add_tag(
+        "storage_pool_id",
+        TsValue::String("b6b0b24600000000".into()),
+    );
+    p.add_field("size_in_kb", TsValue::Long(8388608));
+    p.add_field("num_of_mapped_sdcs", TsValue::Long(2));
+    p.add_field("is_obfuscated", TsValue::Boolean(false));
+
+    assert_eq!(p.measurement(), "scaleio_volume");
+    let mut tags: Vec<&str> = p.tag_keys().collect();
+    tags.sort();
+    assert_eq!(tags, vec!["storage_pool_id", "volume_id"]);
+    let mut fields: Vec<&str> = p.field_keys().collect();
+    fields.sort();
+    assert_eq!(
+        fields,
+        vec!["is_obfuscated", "num_of_mapped_sdcs", "size_in_kb"]
+    );
+
+    let empty = TsPoint::new("empty", false);
+    assert_eq!(empty.tag_keys().count(), 0);
+    assert_eq!(empty.field_keys().count(), 0);
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum TsValue {
     Boolean(bool),
     BooleanVec(Vec<bool>),
@@ -100,32 +194,625 @@ pub enum TsValue {
     SignedLongVec(Vec<i64>),
     String(String),
     StringVec(Vec<String>),
+    Duration(Duration),
+    Timestamp(DateTime<Utc>),
 }
 
-/// Convert InfluxDB Points to TsPoints
-pub fn point_to_ts(points: Vec<Point>) -> Vec<TsPoint> {
-    let mut ts_points: Vec<TsPoint> = Vec::with_capacity(points.len());
-    for p in points {
+impl fmt::Display for TsValue {
+    /// Vectors are rendered comma separated
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn join<T: ToString>(v: &[T]) -> String {
+            v.iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        }
+        match self {
+            TsValue::Boolean(b) => b.fmt(f),
+            TsValue::BooleanVec(v) => f.write_str(&join(v)),
+            TsValue::Byte(b) => b.fmt(f),
+            TsValue::ByteVec(v) => f.write_str(&join(v)),
+            TsValue::Integer(i) => i.fmt(f),
+            TsValue::IntegerVec(v) => f.write_str(&join(v)),
+            TsValue::Float(n) => n.fmt(f),
+            TsValue::FloatVec(v) => f.write_str(&join(v)),
+            TsValue::Long(l) => l.fmt(f),
+            TsValue::LongVec(v) => f.write_str(&join(v)),
+            TsValue::Short(s) => s.fmt(f),
+            TsValue::ShortVec(v) => f.write_str(&join(v)),
+            TsValue::SignedShortVec(v) => f.write_str(&join(v)),
+            TsValue::SignedLong(l) => l.fmt(f),
+            TsValue::SignedLongVec(v) => f.write_str(&join(v)),
+            TsValue::String(s) => f.write_str(s),
+            TsValue::StringVec(v) => f.write_str(&v.join(",")),
+            TsValue::Duration(d) => d.as_secs_f64().fmt(f),
+            TsValue::Timestamp(t) => f.write_str(&t.to_rfc3339()),
+        }
+    }
+}
+
+impl TsValue {
+    /// The value as a number.  Durations are in seconds.  Timestamps,
+    /// strings and vectors aren't quantities and give None
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            TsValue::Boolean(_)
+            | TsValue::BooleanVec(_)
+            | TsValue::ByteVec(_)
+            | TsValue::IntegerVec(_)
+            | TsValue::FloatVec(_)
+            | TsValue::LongVec(_)
+            | TsValue::ShortVec(_)
+            | TsValue::SignedShortVec(_)
+            | TsValue::SignedLongVec(_)
+            | TsValue::String(_)
+            | TsValue::StringVec(_)
+            | TsValue::Timestamp(_) => None,
+            TsValue::Byte(b) => Some(f64::from(*b)),
+            TsValue::Integer(i) => Some(f64::from(*i)),
+            TsValue::Float(n) => Some(*n),
+            TsValue::Long(l) => Some(*l as f64),
+            TsValue::Short(s) => Some(f64::from(*s)),
+            TsValue::SignedLong(l) => Some(*l as f64),
+            TsValue::Duration(d) => Some(d.as_secs_f64()),
+        }
+    }
+}
+
+#[test]
+fn test_duration_and_timestamp_values() {
+    use crate::IntoPoint;
+
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let mut p = TsPoint::new("scaleio_volume", true).set_time(t);
+    p.add_field("latency", TsValue::Duration(Duration::from_micros(1500)));
+    p.add_field("created", TsValue::Timestamp(t));
+
+    assert_eq!(p.fields["latency"].to_string(), "0.0015");
+    assert_eq!(p.fields["latency"].as_f64(), Some(0.0015));
+    assert_eq!(p.fields["created"].to_string(), "2020-01-01T00:00:00+00:00");
+    assert_eq!(p.fields["created"].as_f64(), None);
+    assert_eq!(TsValue::Short(7).as_f64(), Some(7.0));
+
+    // Numeric exports only carry the duration
+    assert_eq!(
+        to_graphite(&[p.clone()]),
+        "scaleio_volume.latency 0.0015 1577836800\n"
+    );
+    let prometheus = to_prometheus(&[p.clone()], &PrometheusOptions::default());
+    assert!(prometheus.contains("scaleio_volume_latency 0.0015\n"));
+    assert!(!prometheus.contains("created"));
+    assert_eq!(
+        to_line_protocol(&[p.clone()]),
+        "scaleio_volume created=\"2020-01-01T00:00:00+00:00\",latency=0.0015 \
+         1577836800000000000\n"
+    );
+    let mut buff: Vec<u8> = Vec::new();
+    write_ndjson(&[p.clone()], &mut buff).unwrap();
+    assert_eq!(read_ndjson(&buff[..]).unwrap(), vec![p]);
+
+    #[derive(IntoPoint)]
+    struct Rebuild {
+        elapsed: std::time::Duration,
+        eta: Option<Duration>,
+        remaining: Option<Duration>,
+    }
+    let r = Rebuild {
+        elapsed: Duration::from_secs(90),
+        eta: Some(Duration::from_millis(250)),
+        remaining: None,
+    };
+    let p = &r.into_point(Some("rebuild"), true)[0];
+    assert_eq!(
+        p.fields["elapsed"],
+        TsValue::Duration(Duration::from_secs(90))
+    );
+    assert_eq!(
+        p.fields["eta"],
+        TsValue::Duration(Duration::from_millis(250))
+    );
+    assert!(!p.fields.contains_key("remaining"));
+}
+
+#[test]
+fn test_to_graphite() {
+    use chrono::TimeZone;
+
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let mut p = TsPoint::new("scaleio_sds_stat", true).set_time(t);
+    p.add_tag("sds_id", TsValue::String("6c9b2a8c00000001".into()));
+    p.add_tag("ip", TsValue::String("10.0.0.1".into()));
+    p.add_field("read.bwc", TsValue::Long(1024));
+    p.add_field("latency", TsValue::Float(1.5));
+    p.add_field("state", TsValue::String("Normal".into()));
+    p.add_field("rmcache_enabled", TsValue::Boolean(true));
+    p.add_field("ip_list", TsValue::LongVec(vec![1, 2]));
+
+    let mut q = TsPoint::new("vnx disk/info", true).set_time(t);
+    q.add_field("capacity", TsValue::SignedLong(-1));
+
+    let output = to_graphite(&[p, q]);
+    println!("graphite: {}", output);
+    let mut lines: Vec<&str> = output.lines().collect();
+    lines.sort();
+    // Tag values are ordered by tag name: ip then sds_id
+    assert_eq!(
+        lines,
+        vec![
+            "scaleio_sds_stat.10_0_0_1.6c9b2a8c00000001.latency 1.5 1577836800",
+            "scaleio_sds_stat.10_0_0_1.6c9b2a8c00000001.read_bwc 1024 1577836800",
+            "vnx_disk_info.capacity -1 1577836800",
+        ]
+    );
+}
+
+// Graphite uses dots as the path separator and whitespace as the field
+// separator so neither can appear in a path component
+fn sanitize_graphite(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '.' | '/' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Render points in the graphite plaintext protocol.  Each numeric field
+/// becomes a `measurement.tag_values.field value timestamp` line with the
+/// tag values ordered by tag name.  Durations are written in seconds, non
+/// numeric fields are skipped and points without a timestamp use the
+/// current time
+pub fn to_graphite(points: &[TsPoint]) -> String {
+    let mut output = String::new();
+    for point in points {
+        let mut tag_names: Vec<&Cow<str>> = point.tags.keys().collect();
+        tag_names.sort();
+        let mut prefix = sanitize_graphite(&point.measurement);
+        for name in tag_names {
+            prefix.push('.');
+            prefix.push_str(&sanitize_graphite(&point.tags[name].to_string()));
+        }
+        let timestamp = point.timestamp.unwrap_or_else(Utc::now).timestamp();
+        for (field, value) in &point.fields {
+            let value = match value {
+                TsValue::Byte(_)
+                | TsValue::Integer(_)
+                | TsValue::Float(_)
+                | TsValue::Long(_)
+                | TsValue::Short(_)
+                | TsValue::SignedLong(_)
+                | TsValue::Duration(_) => value.to_string(),
+                _ => continue,
+            };
+            output.push_str(&format!(
+                "{}.{} {} {}\n",
+                prefix,
+                sanitize_graphite(field),
+                value,
+                timestamp
+            ));
+        }
+    }
+    output
+}
+
+#[test]
+fn test_to_prometheus() {
+    let mut p = TsPoint::new("scaleio_sds_stat", false);
+    p.add_tag("sds_id", TsValue::String("6c9b2a8c00000001".into()));
+    p.add_tag("ip.address", TsValue::String("10.0.0.\"1\"".into()));
+    p.add_field("read_bwc", TsValue::Long(1024));
+    p.add_field("state", TsValue::String("Normal".into()));
+    let mut q = TsPoint::new("scaleio_sds_stat", false);
+    q.add_tag("sds_id", TsValue::String("6c9b2a8d00000002".into()));
+    q.add_field("read_bwc", TsValue::Long(2048));
+    let mut r = TsPoint::new("vnx disk-info", false);
+    r.add_field("capacity", TsValue::Float(1.5));
+    r.add_field("max_latency", TsValue::Float(f64::INFINITY));
+    r.add_field("min_latency", TsValue::Float(f64::NEG_INFINITY));
+    let points = vec![p, q, r];
+
+    // Without headers only the samples are rendered
+    let plain = to_prometheus(&points, &PrometheusOptions::default());
+    println!("prometheus: {}", plain);
+    assert!(!plain.contains('#'));
+    assert!(plain.contains(
+        "scaleio_sds_stat_read_bwc{ip_address=\"10.0.0.\\\"1\\\"\",sds_id=\"6c9b2a8c00000001\"} 1024\n"
+    ));
+    assert!(plain.contains("vnx_disk_info_max_latency +Inf\n"));
+    assert!(plain.contains("vnx_disk_info_min_latency -Inf\n"));
+
+    let mut options = PrometheusOptions {
+        headers: true,
+        ..Default::default()
+    };
+    options.help.insert(
+        "vnx_disk_info_capacity".into(),
+        "Capacity of the disk".into(),
+    );
+    let output = to_prometheus(&points, &options);
+    println!("prometheus: {}", output);
+    assert_eq!(
+        output,
+        "# HELP scaleio_sds_stat_read_bwc read_bwc reported by scaleio_sds_stat\n\
+         # TYPE scaleio_sds_stat_read_bwc gauge\n\
+         scaleio_sds_stat_read_bwc{ip_address=\"10.0.0.\\\"1\\\"\",sds_id=\"6c9b2a8c00000001\"} 1024\n\
+         scaleio_sds_stat_read_bwc{sds_id=\"6c9b2a8d00000002\"} 2048\n\
+         # HELP vnx_disk_info_capacity Capacity of the disk\n\
+         # TYPE vnx_disk_info_capacity gauge\n\
+         vnx_disk_info_capacity 1.5\n\
+         # HELP vnx_disk_info_max_latency max_latency reported by vnx disk-info\n\
+         # TYPE vnx_disk_info_max_latency gauge\n\
+         vnx_disk_info_max_latency +Inf\n\
+         # HELP vnx_disk_info_min_latency min_latency reported by vnx disk-info\n\
+         # TYPE vnx_disk_info_min_latency gauge\n\
+         vnx_disk_info_min_latency -Inf\n\
+         # EOF\n"
+    );
+}
+
+/// Options for rendering points in the Prometheus text exposition format
+#[derive(Clone, Debug, Default)]
+pub struct PrometheusOptions {
+    /// Emit `# HELP` and `# TYPE` lines ahead of each metric
+    pub headers: bool,
+    /// HELP text keyed by metric name.  Metrics without an entry get a
+    /// generic description built from the measurement and field name
+    pub help: HashMap<String, String>,
+}
+
+// Metric and label names may only contain [a-zA-Z0-9_] and can't start
+// with a digit.  Metric names may also contain colons but those are
+// reserved for recording rules
+fn sanitize_prometheus(s: &str) -> String {
+    let mut name: String = s
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+fn escape_label_value(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render points in the Prometheus text exposition format.  Each numeric
+/// field becomes a `measurement_field` metric labeled with the point's
+/// tags.  Durations are in seconds and non numeric fields are skipped.
+/// TsPoint carries no metric type
+/// information so every metric is reported as a gauge.  Samples that share
+/// a metric name are grouped under a single header block.  With headers the
+/// output is OpenMetrics and ends with `# EOF`.
+pub fn to_prometheus(points: &[TsPoint], options: &PrometheusOptions) -> String {
+    // metric name -> (help, samples)
+    let mut metrics: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    for point in points {
+        let mut tag_names: Vec<&Cow<str>> = point.tags.keys().collect();
+        tag_names.sort();
+        let labels = tag_names
+            .iter()
+            .map(|name| {
+                format!(
+                    "{}=\"{}\"",
+                    sanitize_prometheus(name),
+                    escape_label_value(&point.tags[*name].to_string())
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        for (field, value) in &point.fields {
+            let value = match value {
+                // Rust renders infinity as inf, the exposition formats
+                // spell it +Inf and -Inf
+                TsValue::Float(f) if f.is_infinite() => {
+                    if f.is_sign_positive() {
+                        "+Inf".to_string()
+                    } else {
+                        "-Inf".to_string()
+                    }
+                }
+                TsValue::Byte(_)
+                | TsValue::Integer(_)
+                | TsValue::Float(_)
+                | TsValue::Long(_)
+                | TsValue::Short(_)
+                | TsValue::SignedLong(_)
+                | TsValue::Duration(_) => value.to_string(),
+                _ => continue,
+            };
+            let name = sanitize_prometheus(&format!("{}_{}", point.measurement, field));
+            let sample = if labels.is_empty() {
+                format!("{} {}", name, value)
+            } else {
+                format!("{}{{{}}} {}", name, labels, value)
+            };
+            metrics
+                .entry(name)
+                .or_insert_with(|| {
+                    (
+                        format!("{} reported by {}", field, point.measurement),
+                        vec![],
+                    )
+                })
+                .1
+                .push(sample);
+        }
+    }
+
+    let mut output = String::new();
+    for (name, (help, samples)) in metrics {
+        if options.headers {
+            let help = options.help.get(&name).unwrap_or(&help);
+            // HELP text escapes backslashes and newlines
+            output.push_str(&format!(
+                "# HELP {} {}\n",
+                name,
+                help.replace('\\', "\\\\").replace('\n', "\\n")
+            ));
+            output.push_str(&format!("# TYPE {} gauge\n", name));
+        }
+        for sample in samples {
+            output.push_str(&sample);
+            output.push('\n');
+        }
+    }
+    // OpenMetrics requires the exposition to end with an EOF marker
+    if options.headers {
+        output.push_str("# EOF\n");
+    }
+    output
+}
+
+// Line protocol escapes commas and spaces everywhere, equal signs in tag
+// keys, tag values and field keys as well.  Newlines can't be escaped so
+// they become spaces
+fn escape_line_key(s: &str, escape_equals: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            ',' | ' ' => out.push('\\'),
+            '=' if escape_equals => out.push('\\'),
+            '\n' => {
+                out.push_str("\\ ");
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn line_field_value(value: &TsValue) -> Option<String> {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    match value {
+        TsValue::Byte(_)
+        | TsValue::Integer(_)
+        | TsValue::Long(_)
+        | TsValue::Short(_)
+        | TsValue::SignedLong(_) => Some(format!("{}i", value)),
+        // NaN and infinity can't be represented
+        TsValue::Float(f) if !f.is_finite() => None,
+        // Durations are float seconds
+        TsValue::Float(_) | TsValue::Boolean(_) | TsValue::Duration(_) => Some(value.to_string()),
+        TsValue::String(s) => Some(quote(s)),
+        TsValue::Timestamp(t) => Some(quote(&t.to_rfc3339())),
+        // Vectors are sent as their comma separated string form
+        _ => Some(quote(&value.to_string())),
+    }
+}
+
+/// Render points in the InfluxDB line protocol, one point per line.
+/// Integers carry the `i` suffix, durations are float seconds, timestamp
+/// values are RFC 3339 strings and vectors are written as comma separated
+/// strings.  Point timestamps are in nanoseconds.  Points without one
+/// are left for the server to stamp and points without any representable
+/// field are skipped since influx would reject them
+pub fn to_line_protocol(points: &[TsPoint]) -> String {
+    let mut output = String::new();
+    for point in points {
+        let mut fields: Vec<(&Cow<str>, String)> = point
+            .fields
+            .iter()
+            .filter_map(|(name, value)| line_field_value(value).map(|v| (name, v)))
+            .collect();
+        if fields.is_empty() {
+            continue;
+        }
+        fields.sort();
+        output.push_str(&escape_line_key(&point.measurement, false));
+        // Influx recommends tags sorted by key for the best performance
+        let mut tags: Vec<(&Cow<str>, String)> = point
+            .tags
+            .iter()
+            .map(|(name, value)| (name, value.to_string()))
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+        tags.sort();
+        for (name, value) in tags {
+            output.push_str(&format!(
+                ",{}={}",
+                escape_line_key(name, true),
+                escape_line_key(&value, true)
+            ));
+        }
+        for (i, (name, value)) in fields.into_iter().enumerate() {
+            output.push(if i == 0 { ' ' } else { ',' });
+            output.push_str(&escape_line_key(name, true));
+            output.push('=');
+            output.push_str(&value);
+        }
+        if let Some(nanos) = point.timestamp.and_then(|t| t.timestamp_nanos_opt()) {
+            output.push_str(&format!(" {}", nanos));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[test]
+fn test_to_line_protocol() {
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let mut p = TsPoint::new("scaleio_sds_stat", true).set_time(t);
+    p.add_tag("sds_id", TsValue::String("6c9b2a8c00000001".into()));
+    p.add_tag("name", TsValue::String("sds 1,a=b".into()));
+    p.add_tag("empty", TsValue::String(String::new()));
+    p.add_field("read.bwc", TsValue::Long(1024));
+    p.add_field("latency", TsValue::Float(1.5));
+    p.add_field("state", TsValue::String(r#"say "hi" \o/"#.into()));
+    p.add_field("mapped", TsValue::Boolean(true));
+    p.add_field("ports", TsValue::ShortVec(vec![7072, 9099]));
+    p.add_field("ratio", TsValue::Float(f64::NAN));
+
+    let mut q = TsPoint::new("vnx disk", false);
+    q.add_field("capacity", TsValue::SignedLong(-1));
+    // Nothing influx can store
+    let mut r = TsPoint::new("scaleio_sds_stat", false);
+    r.add_field("ratio", TsValue::Float(f64::INFINITY));
+
+    assert_eq!(
+        to_line_protocol(&[p, q, r]),
+        "scaleio_sds_stat,name=sds\\ 1\\,a\\=b,sds_id=6c9b2a8c00000001 \
+         latency=1.5,mapped=true,ports=\"7072,9099\",read.bwc=1024i,\
+         state=\"say \\\"hi\\\" \\\\o/\" 1577836800000000000\n\
+         vnx\\ disk capacity=-1i\n"
+    );
+}
+
+/// Write points as newline delimited json, one point per line
+pub fn write_ndjson<W: Write>(points: &[TsPoint], mut w: W) -> MetricsResult<()> {
+    for point in points {
+        serde_json::to_writer(&mut w, point)?;
+        w.write_all(b"\n")?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Read points written by `write_ndjson`.  Blank lines are skipped and a
+/// truncated or corrupt line fails with its line number
+pub fn read_ndjson<R: Read>(r: R) -> MetricsResult<Vec<TsPoint>> {
+    let mut points = Vec::new();
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let line =
+            line.map_err(|e| StorageError::from(e).with_context(format!("line {}", i + 1)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let point = serde_json::from_str(&line)
+            .map_err(|e| StorageError::from(e).with_context(format!("line {}", i + 1)))?;
+        points.push(point);
+    }
+    Ok(points)
+}
+
+#[test]
+fn test_ndjson_round_trip() {
+    let mut a = TsPoint::new("scaleio_volume", true);
+    a.add_tag("volume_id", TsValue::String("c9e7bd3e00000003".into()));
+    a.add_field("size_in_kb", TsValue::Long(8388608));
+    a.add_field("latency", TsValue::Float(0.25));
+    a.add_field("offset", TsValue::SignedLong(-4));
+    a.add_field("ports", TsValue::ShortVec(vec![7072, 9099]));
+    a.add_field("mapped", TsValue::Boolean(true));
+    a.set_index_field("size_in_kb").unwrap();
+    let mut b = TsPoint::new("vnx_disk", false);
+    b.add_field("remapped_blocks", TsValue::Integer(12));
+    b.add_field("wwn", TsValue::String("06:00:00:00:05:00:00:00".into()));
+    let points = vec![a, b];
+
+    let mut buff: Vec<u8> = Vec::new();
+    write_ndjson(&points, &mut buff).unwrap();
+    assert_eq!(buff.iter().filter(|b| **b == b'\n').count(), 2);
+    assert_eq!(read_ndjson(&buff[..]).unwrap(), points);
+
+    // A run cut off part way through a line
+    let mut truncated = buff.clone();
+    truncated.extend_from_slice(b"\n");
+    truncated.extend_from_slice(&buff[..buff.len() / 4]);
+    let err = read_ndjson(&truncated[..]).unwrap_err();
+    assert!(err.to_string().starts_with("line 4: "));
+}
+
+impl From<Value> for TsValue {
+    fn from(v: Value) -> TsValue {
+        match v {
+            Value::String(s) => TsValue::String(s),
+            Value::Float(f) => TsValue::Float(f),
+            Value::Integer(i) => TsValue::SignedLong(i),
+            Value::Boolean(b) => TsValue::Boolean(b),
+        }
+    }
+}
+
+/// The timestamp of an InfluxDB Point is taken to be in nanoseconds,
+/// influx's default precision.  Points without one are stamped with the
+/// current time
+impl From<Point> for TsPoint {
+    fn from(p: Point) -> TsPoint {
         let mut ts = TsPoint::new(&p.measurement, true);
+        if let Some(nanos) = p.timestamp {
+            ts.timestamp = Some(Utc.timestamp_nanos(nanos));
+        }
         for (t_name, t_val) in p.tags {
-            let v = match t_val {
-                Value::String(s) => TsValue::String(s),
-                Value::Float(f) => TsValue::Float(f),
-                Value::Integer(i) => TsValue::SignedLong(i),
-                Value::Boolean(b) => TsValue::Boolean(b),
-            };
-            ts.tags.insert(t_name, v);
+            ts.tags.insert(t_name.into(), t_val.into());
         }
         for (f_name, f_val) in p.fields {
-            let v = match f_val {
-                Value::String(s) => TsValue::String(s),
-                Value::Float(f) => TsValue::Float(f),
-                Value::Integer(i) => TsValue::SignedLong(i),
-                Value::Boolean(b) => TsValue::Boolean(b),
-            };
-            ts.fields.insert(f_name, v);
+            ts.fields.insert(f_name.into(), f_val.into());
         }
-        ts_points.push(ts);
+        ts
     }
-    ts_points
+}
+
+#[test]
+fn test_ts_point_from_point() {
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let p = Point::new("vnx_disk")
+        .add_tag("disk", Value::String("0_0_1".into()))
+        .add_tag("bus", Value::Integer(0))
+        .add_field("state", Value::String("enabled".into()))
+        .add_field("busy_percent", Value::Float(12.5))
+        .add_field("remapped_blocks", Value::Integer(-1))
+        .add_field("hot_spare", Value::Boolean(false))
+        .add_timestamp(t.timestamp_nanos_opt().unwrap());
+
+    let ts = TsPoint::from(p);
+    assert_eq!(ts.measurement, "vnx_disk");
+    assert_eq!(ts.timestamp, Some(t));
+    assert_eq!(ts.tags.len(), 2);
+    assert_eq!(ts.tags["disk"], TsValue::String("0_0_1".into()));
+    assert_eq!(ts.tags["bus"], TsValue::SignedLong(0));
+    assert_eq!(ts.fields.len(), 4);
+    assert_eq!(ts.fields["state"], TsValue::String("enabled".into()));
+    assert_eq!(ts.fields["busy_percent"], TsValue::Float(12.5));
+    assert_eq!(ts.fields["remapped_blocks"], TsValue::SignedLong(-1));
+    assert_eq!(ts.fields["hot_spare"], TsValue::Boolean(false));
+}
+
+/// Convert InfluxDB Points to TsPoints with `TsPoint::from`
+pub fn point_to_ts(points: Vec<Point>) -> Vec<TsPoint> {
+    points.into_iter().map(TsPoint::from).collect()
+}
+
+#[test]
+fn test_point_to_ts_timestamp() {
+    let t = Utc.timestamp_opt(1_577_836_800, 250_000_000).unwrap();
+    let stamped = Point::new("vnx_disk")
+        .add_tag("disk", Value::String("0_0_1".into()))
+        .add_field("remapped_blocks", Value::Integer(12))
+        .add_timestamp(t.timestamp_nanos_opt().unwrap());
+    let unstamped = Point::new("vnx_disk").add_field("remapped_blocks", Value::Integer(3));
+
+    let before = Utc::now();
+    let points = point_to_ts(vec![stamped, unstamped]);
+    assert_eq!(points[0].timestamp, Some(t));
+    assert_eq!(points[0].tags["disk"], TsValue::String("0_0_1".into()));
+    assert_eq!(points[0].fields["remapped_blocks"], TsValue::SignedLong(12));
+    assert!(points[1].timestamp.unwrap() >= before);
 }
@@ -0,0 +1,290 @@
+//! Which collector build and config produced a point.  The provenance is
+//! carried as tags and a field under a reserved prefix so it survives
+//! storage, and can be stripped by exporters that must not forward it.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::{MetricsResult, StorageError};
+use crate::ir::{TsPoint, TsValue};
+
+/// The prefix attach_provenance uses unless told otherwise
+pub const DEFAULT_PROVENANCE_PREFIX: &str = "_prov_";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Provenance {
+    /// Defaults to the version of this crate
+    pub collector_version: String,
+    pub host: String,
+    /// See config_hash
+    pub config_hash: String,
+    pub collected_at: DateTime<Utc>,
+    /// Prefix of the tags and field added to points.  Defaults to
+    /// DEFAULT_PROVENANCE_PREFIX
+    pub prefix: String,
+}
+
+impl Provenance {
+    pub fn new(host: &str, config_hash: &str, collected_at: DateTime<Utc>) -> Self {
+        Provenance {
+            collector_version: env!("CARGO_PKG_VERSION").to_string(),
+            host: host.to_string(),
+            config_hash: config_hash.to_string(),
+            collected_at,
+            prefix: DEFAULT_PROVENANCE_PREFIX.to_string(),
+        }
+    }
+
+    /// Record the version of the binary doing the collecting rather than
+    /// the version of this crate
+    pub fn with_collector_version(mut self, version: &str) -> Self {
+        self.collector_version = version.to_string();
+        self
+    }
+
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+}
+
+// Keys holding credentials, matched anywhere in a key name so
+// management_password and api_token are caught too.  FNV is fast to
+// brute force, so a hash over a password would leak it
+const CREDENTIAL_KEYS: &[&str] = &["password", "secret", "token", "credential"];
+
+fn is_credential(key: &str) -> bool {
+    let key = key.to_lowercase();
+    CREDENTIAL_KEYS.iter().any(|c| key.contains(c))
+}
+
+// Rebuild objects with their keys inserted in order, so the serialized
+// form doesn't depend on field order even when serde_json preserves it.
+// Credentials are left out along the way
+fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> =
+                map.into_iter().filter(|(k, _)| !is_credential(k)).collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, canonicalize(v)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(canonicalize).collect()),
+        other => other,
+    }
+}
+
+/// A hash of config that only changes when a setting does: the config is
+/// serialized to json with sorted keys and hashed with 64 bit FNV-1a,
+/// which unlike the std hasher is stable across rust releases.  Keys
+/// naming a password, secret, token or credential are skipped, so
+/// rotating one keeps the hash and the hash gives nothing away about
+/// them.  Returned as 16 hex digits
+pub fn config_hash<T: Serialize>(config: &T) -> MetricsResult<String> {
+    let canonical = serde_json::to_vec(&canonicalize(serde_json::to_value(config)?))?;
+    let hash = canonical
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    Ok(format!("{:016x}", hash))
+}
+
+/// Add the collector_version, host and config_hash tags and the
+/// collected_at field, each named with the provenance prefix.  Fails
+/// without touching any point if one already has a tag or field with
+/// that prefix
+pub fn attach_provenance(points: &mut [TsPoint], p: &Provenance) -> MetricsResult<()> {
+    if p.prefix.is_empty() {
+        return Err(StorageError::new(
+            "provenance prefix must not be empty".into(),
+        ));
+    }
+    for point in points.iter() {
+        if let Some(key) = point
+            .tags
+            .keys()
+            .chain(point.fields.keys())
+            .find(|k| k.starts_with(p.prefix.as_str()))
+        {
+            return Err(StorageError::new(format!(
+                "{} already has {}, which uses the reserved provenance prefix {}",
+                point.measurement, key, p.prefix
+            )));
+        }
+    }
+    for point in points.iter_mut() {
+        point.add_tag(
+            format!("{}collector_version", p.prefix),
+            TsValue::String(p.collector_version.clone()),
+        );
+        point.add_tag(format!("{}host", p.prefix), TsValue::String(p.host.clone()));
+        point.add_tag(
+            format!("{}config_hash", p.prefix),
+            TsValue::String(p.config_hash.clone()),
+        );
+        point.add_field(
+            format!("{}collected_at", p.prefix),
+            TsValue::Timestamp(p.collected_at),
+        );
+    }
+    Ok(())
+}
+
+/// The provenance attach_provenance added to point, if it's all there
+pub fn read_provenance(point: &TsPoint, prefix: &str) -> Option<Provenance> {
+    let tag = |name: &str| match point.tags.get(format!("{}{}", prefix, name).as_str()) {
+        Some(TsValue::String(s)) => Some(s.clone()),
+        _ => None,
+    };
+    let collected_at = match point.fields.get(format!("{}collected_at", prefix).as_str()) {
+        Some(TsValue::Timestamp(t)) => *t,
+        _ => return None,
+    };
+    Some(Provenance {
+        collector_version: tag("collector_version")?,
+        host: tag("host")?,
+        config_hash: tag("config_hash")?,
+        collected_at,
+        prefix: prefix.to_string(),
+    })
+}
+
+/// Remove every tag and field named with prefix, for exporters that must
+/// not forward the provenance
+pub fn strip_provenance(points: &mut [TsPoint], prefix: &str) {
+    if prefix.is_empty() {
+        return;
+    }
+    for point in points.iter_mut() {
+        point.tags.retain(|k, _| !k.starts_with(prefix));
+        point.fields.retain(|k, _| !k.starts_with(prefix));
+    }
+}
+
+#[cfg(test)]
+fn provenance_points() -> Vec<TsPoint> {
+    let mut a = TsPoint::new("scaleio_sds", true);
+    a.add_tag("sds_id", TsValue::String("4fdf0e0700000000".into()));
+    a.add_field("capacity_in_use_in_kb", TsValue::Long(1024));
+    let mut b = TsPoint::new("vnx_disk_info", false);
+    b.add_field("is_healthy", TsValue::Boolean(true));
+    vec![a, b]
+}
+
+#[test]
+fn test_provenance_round_trip() {
+    use chrono::TimeZone;
+
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let provenance = Provenance::new("collector-01", "0123456789abcdef", t);
+    assert_eq!(provenance.collector_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(provenance.prefix, "_prov_");
+
+    let original = provenance_points();
+    let mut points = original.clone();
+    attach_provenance(&mut points, &provenance).unwrap();
+    assert_eq!(
+        points[0].tags["_prov_host"],
+        TsValue::String("collector-01".into())
+    );
+    assert_eq!(
+        points[1].fields["_prov_collected_at"],
+        TsValue::Timestamp(t)
+    );
+    for point in &points {
+        assert_eq!(read_provenance(point, "_prov_"), Some(provenance.clone()));
+    }
+    strip_provenance(&mut points, "_prov_");
+    assert_eq!(points, original);
+    assert_eq!(read_provenance(&points[0], "_prov_"), None);
+
+    // An overridden version and prefix
+    let provenance = provenance
+        .with_collector_version("storage-collector 2.1.0")
+        .with_prefix("provenance.");
+    let mut points = original.clone();
+    attach_provenance(&mut points, &provenance).unwrap();
+    assert_eq!(
+        points[0].tags["provenance.collector_version"],
+        TsValue::String("storage-collector 2.1.0".into())
+    );
+    assert_eq!(read_provenance(&points[0], "provenance."), Some(provenance));
+    strip_provenance(&mut points, "provenance.");
+    assert_eq!(points, original);
+}
+
+#[test]
+fn test_provenance_prefix_collision() {
+    use chrono::TimeZone;
+
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let provenance = Provenance::new("collector-01", "0123456789abcdef", t);
+    let mut points = provenance_points();
+    points[1].add_field("_prov_note", TsValue::String("from upstream".into()));
+    let before = points.clone();
+    let err = attach_provenance(&mut points, &provenance).unwrap_err();
+    assert!(err.to_string().contains("_prov_note"), "{}", err);
+    // Nothing was added to the first point either
+    assert_eq!(points, before);
+
+    let err = attach_provenance(&mut points, &provenance.with_prefix("")).unwrap_err();
+    assert!(err.to_string().contains("empty"), "{}", err);
+}
+
+#[test]
+fn test_config_hash() {
+    let a: Value = serde_json::from_str(
+        r#"{"endpoint": "10.0.0.1", "user": "admin", "limits": {"iops": 100, "bandwidth": 50}, "ids": [3, 1]}"#,
+    )
+    .unwrap();
+    let b: Value = serde_json::from_str(
+        r#"{"ids": [3, 1], "limits": {"bandwidth": 50, "iops": 100}, "user": "admin", "endpoint": "10.0.0.1"}"#,
+    )
+    .unwrap();
+    let hash = config_hash(&a).unwrap();
+    assert_eq!(hash.len(), 16);
+    assert_eq!(config_hash(&b).unwrap(), hash);
+    // Pinned so a change to the hashing is noticed
+    assert_eq!(
+        config_hash(&serde_json::json!({})).unwrap(),
+        "08f44b07b5901a25"
+    );
+
+    // Array order and values still count
+    let mut c = b.clone();
+    c["ids"] = serde_json::json!([1, 3]);
+    assert_ne!(config_hash(&c).unwrap(), hash);
+    let mut d = b.clone();
+    d["limits"]["iops"] = serde_json::json!(101);
+    assert_ne!(config_hash(&d).unwrap(), hash);
+
+    // Credentials, nested ones included, don't count
+    let mut e = b;
+    e["password"] = serde_json::json!("hunter2");
+    e["limits"]["management_password"] = serde_json::json!("hunter3");
+    e["API_Token"] = serde_json::json!("abc");
+    assert_eq!(config_hash(&e).unwrap(), hash);
+}
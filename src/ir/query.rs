@@ -0,0 +1,234 @@
+//! Simple in-memory queries over a batch of points, for poking at dumped
+//! collections and for test assertions.  Everything borrows the points.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::error::{MetricsResult, StorageError};
+use crate::ir::{TsPoint, TsValue};
+
+/// The points of measurement, or of any measurement if None, that have
+/// every tag in tag_eq with the given value.  Tag values are compared as
+/// they display, so vector tags match their comma separated form
+pub fn filter<'a>(
+    points: &'a [TsPoint],
+    measurement: Option<&str>,
+    tag_eq: &[(&str, &str)],
+) -> Vec<&'a TsPoint> {
+    points
+        .iter()
+        .filter(|p| match measurement {
+            Some(m) => p.measurement == m,
+            None => true,
+        })
+        .filter(|p| {
+            tag_eq.iter().all(|(key, value)| match p.tags.get(*key) {
+                Some(TsValue::String(s)) => s == value,
+                Some(v) => v.to_string() == *value,
+                None => false,
+            })
+        })
+        .collect()
+}
+
+// The value of field as a number.  None when the point doesn't have it,
+// an error when it isn't a quantity
+fn numeric_field(p: &TsPoint, field: &str) -> MetricsResult<Option<f64>> {
+    match p.fields.get(field) {
+        None => Ok(None),
+        Some(v) => v.as_f64().map(Some).ok_or_else(|| {
+            StorageError::new(format!(
+                "{} field {} is not numeric: {:?}",
+                p.measurement, field, v
+            ))
+        }),
+    }
+}
+
+// Combine field per value of group_tag.  Points without the tag or the
+// field are left out
+fn group_by<'a, I, F>(
+    points: I,
+    group_tag: &str,
+    field: &str,
+    combine: F,
+) -> MetricsResult<HashMap<String, f64>>
+where
+    I: IntoIterator<Item = &'a TsPoint>,
+    F: Fn(f64, f64) -> f64,
+{
+    let mut groups: HashMap<String, f64> = HashMap::new();
+    for p in points {
+        let value = match numeric_field(p, field)? {
+            Some(value) => value,
+            None => continue,
+        };
+        let group = match p.tags.get(group_tag) {
+            Some(tag) => tag.to_string(),
+            None => continue,
+        };
+        let combined = match groups.get(&group) {
+            Some(current) => combine(*current, value),
+            None => value,
+        };
+        groups.insert(group, combined);
+    }
+    Ok(groups)
+}
+
+/// The largest value of field per value of group_tag, ie the max latency
+/// per sds.  Points missing the tag or the field are excluded and a field
+/// that isn't numeric is an error
+pub fn group_max<'a, I>(
+    points: I,
+    group_tag: &str,
+    field: &str,
+) -> MetricsResult<HashMap<String, f64>>
+where
+    I: IntoIterator<Item = &'a TsPoint>,
+{
+    group_by(points, group_tag, field, f64::max)
+}
+
+/// The total of field per value of group_tag.  Points missing the tag or
+/// the field are excluded and a field that isn't numeric is an error
+pub fn group_sum<'a, I>(
+    points: I,
+    group_tag: &str,
+    field: &str,
+) -> MetricsResult<HashMap<String, f64>>
+where
+    I: IntoIterator<Item = &'a TsPoint>,
+{
+    group_by(points, group_tag, field, |a, b| a + b)
+}
+
+/// The n points with the largest values of field, largest first.  Points
+/// with equal values keep their order from points.  Points missing the
+/// field are excluded and a field that isn't numeric is an error
+pub fn top_n<'a, I>(points: I, field: &str, n: usize) -> MetricsResult<Vec<&'a TsPoint>>
+where
+    I: IntoIterator<Item = &'a TsPoint>,
+{
+    let mut ranked: Vec<(f64, &TsPoint)> = Vec::new();
+    for p in points {
+        if let Some(value) = numeric_field(p, field)? {
+            ranked.push((value, p));
+        }
+    }
+    // A stable sort so ties come out in input order
+    ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    Ok(ranked.into_iter().take(n).map(|(_, p)| p).collect())
+}
+
+#[cfg(test)]
+fn sds_stat(sds: &str, pool: &str, latency: Option<u64>) -> TsPoint {
+    let mut p = TsPoint::new("scaleio_sds_stat", false);
+    p.add_tag("sds_id", TsValue::String(sds.into()));
+    p.add_tag("pool", TsValue::String(pool.into()));
+    if let Some(latency) = latency {
+        p.add_field("read_latency", TsValue::Long(latency));
+    }
+    p.add_field("state", TsValue::String("normal".into()));
+    p
+}
+
+#[cfg(test)]
+fn batch() -> Vec<TsPoint> {
+    let mut volume = TsPoint::new("scaleio_volume", false);
+    volume.add_tag("sds_id", TsValue::String("sds-a".into()));
+    volume.add_field("read_latency", TsValue::Long(9000));
+    vec![
+        sds_stat("sds-a", "pool1", Some(300)),
+        sds_stat("sds-a", "pool1", Some(700)),
+        sds_stat("sds-b", "pool1", Some(700)),
+        sds_stat("sds-b", "pool2", None),
+        sds_stat("sds-c", "pool2", Some(100)),
+        volume,
+    ]
+}
+
+#[test]
+fn test_filter() {
+    let points = batch();
+    assert_eq!(filter(&points, None, &[]).len(), 6);
+    assert_eq!(filter(&points, Some("scaleio_sds_stat"), &[]).len(), 5);
+    let a = filter(&points, Some("scaleio_sds_stat"), &[("sds_id", "sds-a")]);
+    assert_eq!(a.len(), 2);
+    assert!(std::ptr::eq(a[0], &points[0]));
+    assert_eq!(
+        filter(&points, None, &[("sds_id", "sds-b"), ("pool", "pool2")]).len(),
+        1
+    );
+    assert!(filter(&points, None, &[("missing", "sds-a")]).is_empty());
+}
+
+#[test]
+fn test_group_aggregates() {
+    let points = batch();
+    let stats = filter(&points, Some("scaleio_sds_stat"), &[]);
+    let max = group_max(stats.iter().copied(), "sds_id", "read_latency").unwrap();
+    // sds-b's point without the field doesn't count as a 0
+    assert_eq!(max.len(), 3);
+    assert_eq!(max["sds-a"], 700.0);
+    assert_eq!(max["sds-b"], 700.0);
+    assert_eq!(max["sds-c"], 100.0);
+
+    let sum = group_sum(&points, "pool", "read_latency").unwrap();
+    // The volume point has no pool tag
+    assert_eq!(sum.len(), 2);
+    assert_eq!(sum["pool1"], 1700.0);
+    assert_eq!(sum["pool2"], 100.0);
+
+    assert!(group_max(&points, "sds_id", "missing").unwrap().is_empty());
+}
+
+#[test]
+fn test_top_n() {
+    let points = batch();
+    let top = top_n(&points, "read_latency", 3).unwrap();
+    assert_eq!(top.len(), 3);
+    assert!(std::ptr::eq(top[0], &points[5]));
+    // The two 700s tie and keep their input order
+    assert!(std::ptr::eq(top[1], &points[1]));
+    assert!(std::ptr::eq(top[2], &points[2]));
+
+    // Points without the field are left out even when n is larger
+    assert_eq!(top_n(&points, "read_latency", 10).unwrap().len(), 5);
+    assert!(top_n(&points, "read_latency", 0).unwrap().is_empty());
+}
+
+#[test]
+fn test_non_numeric_field() {
+    let points = batch();
+    for err in [
+        group_max(&points, "sds_id", "state").unwrap_err(),
+        group_sum(&points, "sds_id", "state").unwrap_err(),
+        top_n(&points, "state", 1).unwrap_err(),
+    ]
+    .iter()
+    {
+        assert!(
+            err.to_string()
+                .contains("scaleio_sds_stat field state is not numeric"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! A registry of named json to point converters.  This allows simple REST
+//! sources to be collected from config, by naming a url and a converter,
+//! without writing a whole module for them.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashMap;
+
+use crate::error::{MetricsResult, StorageError};
+use crate::ir::{TsPoint, TsValue};
+use serde_json::{Map, Value};
+
+/// Converts a decoded json response into points
+pub type Converter = Box<dyn Fn(&Value) -> MetricsResult<Vec<TsPoint>> + Send + Sync>;
+
+#[derive(Default)]
+pub struct ConverterRegistry {
+    converters: HashMap<String, Converter>,
+}
+
+impl ConverterRegistry {
+    pub fn new() -> Self {
+        ConverterRegistry::default()
+    }
+
+    /// Register a converter under name, replacing any existing one
+    pub fn register<F>(&mut self, name: &str, converter: F)
+    where
+        F: Fn(&Value) -> MetricsResult<Vec<TsPoint>> + Send + Sync + 'static,
+    {
+        self.converters
+            .insert(name.to_string(), Box::new(converter));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.converters.contains_key(name)
+    }
+
+    /// Convert value with the converter registered under name
+    pub fn convert(&self, name: &str, value: &Value) -> MetricsResult<Vec<TsPoint>> {
+        match self.converters.get(name) {
+            Some(converter) => converter(value),
+            None => Err(StorageError::new(format!(
+                "no converter registered as {}",
+                name
+            ))),
+        }
+    }
+
+    /// GET a json document from url and convert it with the converter
+    /// registered under name
+    pub fn fetch(
+        &self,
+        client: &reqwest::blocking::Client,
+        url: &str,
+        user: &str,
+        pass: Option<&str>,
+        name: &str,
+    ) -> MetricsResult<Vec<TsPoint>> {
+        let value: Value = crate::get(client, url, user, pass)?;
+        self.convert(name, &value)
+    }
+}
+
+// Strings become tags, like the IntoPoint derive does with String fields.
+// Numbers and bools become fields.  Nested objects and arrays are rejected
+fn flat_object_point(measurement: &str, obj: &Map<String, Value>) -> MetricsResult<TsPoint> {
+    let mut p = TsPoint::new(measurement, true);
+    for (key, value) in obj {
+        match value {
+            Value::Null => {}
+            Value::Bool(b) => p.add_field(key.clone(), TsValue::Boolean(*b)),
+            Value::Number(n) => {
+                if let Some(u) = n.as_u64() {
+                    p.add_field(key.clone(), TsValue::Long(u));
+                } else if let Some(i) = n.as_i64() {
+                    p.add_field(key.clone(), TsValue::SignedLong(i));
+                } else if let Some(f) = n.as_f64() {
+                    p.add_field(key.clone(), TsValue::Float(f));
+                }
+            }
+            Value::String(s) => {
+                if !s.is_empty() {
+                    p.add_tag(key.clone(), TsValue::String(s.clone()));
+                }
+            }
+            Value::Array(_) | Value::Object(_) => {
+                return Err(StorageError::new(format!(
+                    "{} is nested.  Only flat json objects can be converted",
+                    key
+                )));
+            }
+        }
+    }
+    Ok(p)
+}
+
+/// Converts a flat json object into a single point
+pub fn flat_object_converter(measurement: &str) -> Converter {
+    let measurement = measurement.to_string();
+    Box::new(move |value: &Value| match value.as_object() {
+        Some(obj) => Ok(vec![flat_object_point(&measurement, obj)?]),
+        None => Err(StorageError::new(format!(
+            "{}: expected a json object",
+            measurement
+        ))),
+    })
+}
+
+/// Converts an array of flat json objects into one point per object
+pub fn flat_array_converter(measurement: &str) -> Converter {
+    let measurement = measurement.to_string();
+    Box::new(move |value: &Value| match value.as_array() {
+        Some(items) => items
+            .iter()
+            .map(|item| match item.as_object() {
+                Some(obj) => flat_object_point(&measurement, obj),
+                None => Err(StorageError::new(format!(
+                    "{}: expected an array of json objects",
+                    measurement
+                ))),
+            })
+            .collect(),
+        None => Err(StorageError::new(format!(
+            "{}: expected a json array",
+            measurement
+        ))),
+    })
+}
+
+#[test]
+fn test_converter_registry() {
+    use serde_json::json;
+
+    let mut registry = ConverterRegistry::new();
+    registry.register("pdu", flat_object_converter("pdu_status"));
+    registry.register("ports", flat_array_converter("switch_port"));
+    registry.register("custom", |_: &Value| Ok(vec![TsPoint::new("custom", true)]));
+
+    let points = registry
+        .convert(
+            "pdu",
+            &json!({
+                "name": "pdu-a1",
+                "serial": "",
+                "outlets": 24,
+                "temperature": 21.5,
+                "offset": -3,
+                "healthy": true,
+                "comment": null,
+            }),
+        )
+        .unwrap();
+    println!("points: {:#?}", points);
+    assert_eq!(points.len(), 1);
+    let p = &points[0];
+    assert_eq!(p.measurement, "pdu_status");
+    assert_eq!(p.tags["name"], TsValue::String("pdu-a1".into()));
+    assert!(!p.tags.contains_key("serial"));
+    assert_eq!(p.fields["outlets"], TsValue::Long(24));
+    assert_eq!(p.fields["temperature"], TsValue::Float(21.5));
+    assert_eq!(p.fields["offset"], TsValue::SignedLong(-3));
+    assert_eq!(p.fields["healthy"], TsValue::Boolean(true));
+    assert!(!p.fields.contains_key("comment"));
+
+    let points = registry
+        .convert(
+            "ports",
+            &json!([
+                {"port": "1/1", "rx_bytes": 100},
+                {"port": "1/2", "rx_bytes": 200},
+            ]),
+        )
+        .unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[1].fields["rx_bytes"], TsValue::Long(200));
+
+    assert_eq!(registry.convert("custom", &Value::Null).unwrap().len(), 1);
+    assert!(registry.convert("missing", &Value::Null).is_err());
+}
+
+#[test]
+fn test_converter_rejects_nested() {
+    use serde_json::json;
+
+    let object = flat_object_converter("nested");
+    let err = object(&json!({"name": "a", "stats": {"reads": 1}})).unwrap_err();
+    assert!(err.to_string().contains("stats is nested"));
+    assert!(object(&json!({"ids": [1, 2]})).is_err());
+    assert!(object(&json!([{"name": "a"}])).is_err());
+
+    let array = flat_array_converter("nested");
+    assert!(array(&json!([{"name": "a"}, {"name": "b", "child": {}}])).is_err());
+    assert!(array(&json!([1, 2])).is_err());
+    assert!(array(&json!({"name": "a"})).is_err());
+}
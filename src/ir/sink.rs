@@ -0,0 +1,306 @@
+//! Sinks ship collected points somewhere so callers don't each need to
+//! write their own exporter.  Backends keep returning Vec<TsPoint> and the
+//! caller hands those to whichever sink fits their setup.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use crate::error::{MetricsResult, StorageError};
+use crate::ir::{to_line_protocol, TsPoint};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::ops::Range;
+use std::path::Path;
+
+/// Somewhere to send points
+pub trait PointSink {
+    fn write(&mut self, points: &[TsPoint]) -> MetricsResult<()>;
+}
+
+/// Writes points as InfluxDB line protocol to anything that implements
+/// Write.  Each call is flushed so a file tail or socket listener sees
+/// whole batches
+pub struct LineProtocolSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> LineProtocolSink<W> {
+    pub fn new(writer: W) -> Self {
+        LineProtocolSink { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl LineProtocolSink<File> {
+    /// Append to the file at path, creating it if needed
+    pub fn to_file<P: AsRef<Path>>(path: P) -> MetricsResult<Self> {
+        let f = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(LineProtocolSink::new(f))
+    }
+}
+
+impl LineProtocolSink<TcpStream> {
+    /// Connect to a tcp line protocol listener such as telegraf's
+    /// socket_listener
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> MetricsResult<Self> {
+        Ok(LineProtocolSink::new(TcpStream::connect(addr)?))
+    }
+}
+
+impl<W: Write> PointSink for LineProtocolSink<W> {
+    fn write(&mut self, points: &[TsPoint]) -> MetricsResult<()> {
+        self.writer.write_all(to_line_protocol(points).as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Posts points to the write endpoint of an InfluxDB 1.x server
+pub struct InfluxSink {
+    client: reqwest::blocking::Client,
+    url: String,
+    database: String,
+    retention_policy: Option<String>,
+    credentials: Option<(String, String)>,
+}
+
+impl InfluxSink {
+    /// url is the base url of the server, ie http://localhost:8086
+    pub fn new(client: &reqwest::blocking::Client, url: &str, database: &str) -> Self {
+        InfluxSink {
+            client: client.clone(),
+            url: url.trim_end_matches('/').to_string(),
+            database: database.to_string(),
+            retention_policy: None,
+            credentials: None,
+        }
+    }
+
+    pub fn with_retention_policy(mut self, retention_policy: &str) -> Self {
+        self.retention_policy = Some(retention_policy.to_string());
+        self
+    }
+
+    pub fn with_credentials(mut self, user: &str, password: &str) -> Self {
+        self.credentials = Some((user.to_string(), password.to_string()));
+        self
+    }
+}
+
+impl PointSink for InfluxSink {
+    fn write(&mut self, points: &[TsPoint]) -> MetricsResult<()> {
+        let body = to_line_protocol(points);
+        // Influx answers an empty write with a 400
+        if body.is_empty() {
+            return Ok(());
+        }
+        let mut query = vec![("db", self.database.as_str()), ("precision", "ns")];
+        if let Some(rp) = &self.retention_policy {
+            query.push(("rp", rp));
+        }
+        let mut req = self
+            .client
+            .post(format!("{}/write", self.url))
+            .query(&query)
+            .body(body);
+        if let Some((user, password)) = &self.credentials {
+            req = req.basic_auth(user, Some(password));
+        }
+        req.send()?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// A batch that write_batched failed to write
+#[derive(Debug)]
+pub struct BatchError {
+    /// Index of the batch
+    pub batch: usize,
+    /// The points of the batch as a range of the slice given
+    pub points: Range<usize>,
+    pub error: StorageError,
+}
+
+/// Write points in batches of at most batch_size so a large run doesn't
+/// exceed request size limits.  Every batch is attempted and the ones that
+/// failed are returned, an empty Vec means everything was written
+pub fn write_batched<S: PointSink + ?Sized>(
+    sink: &mut S,
+    points: &[TsPoint],
+    batch_size: usize,
+) -> Vec<BatchError> {
+    let batch_size = batch_size.max(1);
+    let mut errors = Vec::new();
+    for (batch, chunk) in points.chunks(batch_size).enumerate() {
+        if let Err(error) = sink.write(chunk) {
+            let start = batch * batch_size;
+            errors.push(BatchError {
+                batch,
+                points: start..start + chunk.len(),
+                error,
+            });
+        }
+    }
+    errors
+}
+
+#[test]
+fn test_write_batched() {
+    use crate::ir::TsValue;
+
+    // Fails the third batch it's given
+    struct Flaky {
+        calls: usize,
+        written: Vec<TsPoint>,
+    }
+    impl PointSink for Flaky {
+        fn write(&mut self, points: &[TsPoint]) -> MetricsResult<()> {
+            self.calls += 1;
+            if self.calls == 3 {
+                return Err(StorageError::new("request entity too large".into()));
+            }
+            self.written.extend_from_slice(points);
+            Ok(())
+        }
+    }
+    let points: Vec<TsPoint> = (0..10)
+        .map(|i| {
+            let mut p = TsPoint::new("scaleio_volume", false);
+            p.add_field("size_in_kb", TsValue::Long(i));
+            p
+        })
+        .collect();
+    let mut sink = Flaky {
+        calls: 0,
+        written: vec![],
+    };
+    let errors = write_batched(&mut sink, &points, 3);
+    assert_eq!(sink.calls, 4);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].batch, 2);
+    assert_eq!(errors[0].points, 6..9);
+    assert_eq!(errors[0].error.to_string(), "request entity too large");
+    let mut expected = points[..6].to_vec();
+    expected.push(points[9].clone());
+    assert_eq!(sink.written, expected);
+
+    let mut memory = MemorySink::default();
+    assert!(write_batched(&mut memory, &points, 0).is_empty());
+    assert_eq!(memory.points, points);
+}
+
+/// Discards everything it's given
+#[derive(Clone, Debug, Default)]
+pub struct NullSink;
+
+impl PointSink for NullSink {
+    fn write(&mut self, _points: &[TsPoint]) -> MetricsResult<()> {
+        Ok(())
+    }
+}
+
+/// Keeps every point written to it.  Handy in tests
+#[derive(Clone, Debug, Default)]
+pub struct MemorySink {
+    pub points: Vec<TsPoint>,
+}
+
+impl PointSink for MemorySink {
+    fn write(&mut self, points: &[TsPoint]) -> MetricsResult<()> {
+        self.points.extend_from_slice(points);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_line_protocol_file_sink() {
+    use crate::ir::TsValue;
+    use chrono::{TimeZone, Utc};
+
+    let path = std::env::temp_dir().join(format!("libstorage-sink-{}.lp", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let mut p = TsPoint::new("scaleio_volume", true).set_time(t);
+    p.add_tag("volume_id", TsValue::String("c9e7bd3e00000003".into()));
+    p.add_field("size_in_kb", TsValue::Long(8388608));
+    let mut q = TsPoint::new("vnx_disk", true).set_time(t);
+    q.add_field("state", TsValue::String("Enabled".into()));
+
+    let mut sinks: Vec<Box<dyn PointSink>> = vec![
+        Box::new(LineProtocolSink::to_file(&path).unwrap()),
+        Box::new(NullSink),
+    ];
+    for sink in sinks.iter_mut() {
+        sink.write(&[p.clone()]).unwrap();
+    }
+    // Reopening appends
+    let mut sink = LineProtocolSink::to_file(&path).unwrap();
+    sink.write(&[q]).unwrap();
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        written.lines().collect::<Vec<&str>>(),
+        vec![
+            "scaleio_volume,volume_id=c9e7bd3e00000003 size_in_kb=8388608i 1577836800000000000",
+            "vnx_disk state=\"Enabled\" 1577836800000000000",
+        ]
+    );
+
+    let mut memory = MemorySink::default();
+    memory.write(&[p.clone()]).unwrap();
+    assert_eq!(memory.points, vec![p]);
+}
+
+#[test]
+fn test_influx_sink() {
+    let port = crate::mock_https_server(|req| {
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/write?db=storage&precision=ns&rp=weekly");
+        assert!(req.head.contains("authorization: Basic "));
+        if req.body.starts_with("scaleio_sds") {
+            (204, String::new(), vec![])
+        } else {
+            (
+                400,
+                String::new(),
+                br#"{"error":"unable to parse"}"#.to_vec(),
+            )
+        }
+    });
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let mut sink = InfluxSink::new(&client, &format!("https://127.0.0.1:{}/", port), "storage")
+        .with_retention_policy("weekly")
+        .with_credentials("admin", "password");
+
+    let mut p = TsPoint::new("scaleio_sds", true);
+    p.add_field("up", crate::ir::TsValue::Boolean(true));
+    sink.write(&[p]).unwrap();
+    // Nothing to write doesn't touch the server
+    sink.write(&[]).unwrap();
+
+    let mut bad = TsPoint::new("bad", true);
+    bad.add_field("up", crate::ir::TsValue::Boolean(true));
+    let err = sink.write(&[bad]).unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::BAD_REQUEST));
+}
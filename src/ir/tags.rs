@@ -0,0 +1,160 @@
+//! Tags added to every point a collector emits, ie the tenant and site a
+//! collector runs for, so downstream access control can filter on them.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashMap;
+
+use crate::error::{MetricsResult, StorageError};
+use crate::ir::{TsPoint, TsValue};
+use serde::de::{Deserialize, Deserializer, Error};
+
+/// Fail unless key is usable as a tag name: not empty and without
+/// whitespace
+pub fn validate_tag_key(key: &str) -> MetricsResult<()> {
+    if key.is_empty() {
+        return Err(StorageError::new("tag key must not be empty".into()));
+    }
+    if key.chars().any(char::is_whitespace) {
+        return Err(StorageError::new(format!(
+            "tag key `{}` must not contain whitespace",
+            key
+        )));
+    }
+    Ok(())
+}
+
+/// Add tags to every point.  Unless overwrite is set a tag the point
+/// already has is left alone, so backend tags win.  An empty tags map
+/// doesn't touch the points at all
+pub fn inject_tags(points: &mut [TsPoint], tags: &HashMap<String, String>, overwrite: bool) {
+    if tags.is_empty() {
+        return;
+    }
+    for p in points.iter_mut() {
+        p.tags.reserve(tags.len());
+        for (key, value) in tags {
+            if overwrite || !p.tags.contains_key(key.as_str()) {
+                p.add_tag(key.clone(), TsValue::String(value.clone()));
+            }
+        }
+    }
+}
+
+fn deserialize_global_tags<'de, D>(deserializer: D) -> Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let tags = HashMap::<String, String>::deserialize(deserializer)?;
+    for key in tags.keys() {
+        validate_tag_key(key).map_err(D::Error::custom)?;
+    }
+    Ok(tags)
+}
+
+/// Global tags, ie tenant, environment and site, for the collector runner
+/// to add to every point.  Keys are validated when the config is loaded
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TagInjector {
+    #[serde(default, deserialize_with = "deserialize_global_tags")]
+    pub global_tags: HashMap<String, String>,
+    /// Replace tags the backend already set.  Defaults to false
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+impl TagInjector {
+    pub fn new(global_tags: HashMap<String, String>) -> MetricsResult<Self> {
+        for key in global_tags.keys() {
+            validate_tag_key(key)?;
+        }
+        Ok(TagInjector {
+            global_tags,
+            overwrite: false,
+        })
+    }
+
+    pub fn with_overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    pub fn apply(&self, points: &mut [TsPoint]) {
+        inject_tags(points, &self.global_tags, self.overwrite);
+    }
+}
+
+#[test]
+fn test_inject_tags() {
+    let point = || {
+        let mut p = TsPoint::new("scaleio_sds", true);
+        p.add_tag("site", TsValue::String("array".into()));
+        p.add_field("state", TsValue::Long(1));
+        p
+    };
+    let tags: HashMap<String, String> = vec![
+        ("tenant".to_string(), "video".to_string()),
+        ("site".to_string(), "ho-1".to_string()),
+    ]
+    .into_iter()
+    .collect();
+
+    let mut points = vec![point(), point()];
+    inject_tags(&mut points, &tags, false);
+    for p in &points {
+        assert_eq!(p.tags["tenant"], TsValue::String("video".into()));
+        // The backend's tag wins
+        assert_eq!(p.tags["site"], TsValue::String("array".into()));
+    }
+
+    let mut points = vec![point()];
+    inject_tags(&mut points, &tags, true);
+    assert_eq!(points[0].tags["site"], TsValue::String("ho-1".into()));
+
+    // Nothing is touched, not even the tag maps' capacity
+    let mut points = vec![point()];
+    let capacity = points[0].tags.capacity();
+    inject_tags(&mut points, &HashMap::new(), true);
+    assert_eq!(points[0].tags.capacity(), capacity);
+    assert_eq!(points[0].tags.len(), 1);
+}
+
+#[test]
+fn test_tag_injector_validates_keys() {
+    let injector: TagInjector =
+        serde_json::from_str(r#"{"global_tags": {"tenant": "video", "env": "prod"}}"#).unwrap();
+    assert_eq!(injector.global_tags.len(), 2);
+    assert!(!injector.overwrite);
+
+    for bad in &[
+        r#"{"global_tags": {"": "video"}}"#,
+        r#"{"global_tags": {"business unit": "video"}}"#,
+    ] {
+        let err = serde_json::from_str::<TagInjector>(bad)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("tag key"), "{}", err);
+    }
+    let tags = vec![("my env".to_string(), "dev".to_string())]
+        .into_iter()
+        .collect();
+    assert!(TagInjector::new(tags).is_err());
+    assert!(serde_json::from_str::<TagInjector>("{}")
+        .unwrap()
+        .global_tags
+        .is_empty());
+}
@@ -0,0 +1,404 @@
+//! Field names carry their units in inconsistent ways, `_in_kb`,
+//! `InMB` or `_microsec`.  A `UnitMap` recognizes those suffixes so
+//! `convert_units` can rewrite points into one set of units with
+//! canonical `_bytes` and `_seconds` names.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashSet;
+
+use crate::error::{MetricsResult, StorageError};
+use crate::ir::{TsPoint, TsValue};
+use log::warn;
+
+/// Units that can be recognized from a field name
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Bytes,
+    KiB,
+    MiB,
+    GiB,
+    Micros,
+    Millis,
+    Seconds,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Dimension {
+    Size,
+    Time,
+}
+
+impl Unit {
+    fn dimension(self) -> Dimension {
+        match self {
+            Unit::Bytes | Unit::KiB | Unit::MiB | Unit::GiB => Dimension::Size,
+            Unit::Micros | Unit::Millis | Unit::Seconds => Dimension::Time,
+        }
+    }
+
+    /// How many bytes or microseconds one of this unit is
+    fn base(self) -> u64 {
+        match self {
+            Unit::Bytes | Unit::Micros => 1,
+            Unit::KiB => 1024,
+            Unit::MiB => 1024 * 1024,
+            Unit::GiB => 1024 * 1024 * 1024,
+            Unit::Millis => 1_000,
+            Unit::Seconds => 1_000_000,
+        }
+    }
+
+    /// The suffix a field is given once it has been converted to this unit
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Unit::Bytes => "_bytes",
+            Unit::KiB => "_kib",
+            Unit::MiB => "_mib",
+            Unit::GiB => "_gib",
+            Unit::Micros => "_micros",
+            Unit::Millis => "_millis",
+            Unit::Seconds => "_seconds",
+        }
+    }
+}
+
+/// Maps field names to the unit their values are reported in.  Suffix
+/// rules are matched case insensitively and the longest matching suffix
+/// wins, so a suffix mapped to None can carve out fields that only look
+/// like they carry a unit.  Field rules name a single field of a single
+/// measurement and take precedence over the suffixes.
+#[derive(Clone, Debug, Default)]
+pub struct UnitMap {
+    suffixes: Vec<(String, Option<Unit>)>,
+    fields: Vec<(String, String, Unit)>,
+}
+
+impl UnitMap {
+    /// An empty map that recognizes nothing
+    pub fn new() -> Self {
+        UnitMap::default()
+    }
+
+    /// Fields ending in `suffix` are reported in `unit`
+    pub fn with_suffix(mut self, suffix: &str, unit: Unit) -> Self {
+        self.suffixes
+            .push((suffix.to_ascii_lowercase(), Some(unit)));
+        self
+    }
+
+    /// Fields ending in `suffix` are left alone even if a shorter suffix
+    /// would match them
+    pub fn without_suffix(mut self, suffix: &str) -> Self {
+        self.suffixes.push((suffix.to_ascii_lowercase(), None));
+        self
+    }
+
+    /// `field` of `measurement` is reported in `unit`
+    pub fn with_field(mut self, measurement: &str, field: &str, unit: Unit) -> Self {
+        self.fields
+            .push((measurement.to_string(), field.to_string(), unit));
+        self
+    }
+
+    /// Looks up the unit of a field and the part of its name to keep
+    fn lookup<'a>(&self, measurement: &str, field: &'a str) -> Option<(Unit, &'a str)> {
+        if let Some((_, _, unit)) = self
+            .fields
+            .iter()
+            .find(|(m, f, _)| m == measurement && f == field)
+        {
+            return Some((*unit, field));
+        }
+        let lower = field.to_ascii_lowercase();
+        let (suffix, unit) = self
+            .suffixes
+            .iter()
+            .filter(|(s, _)| lower.len() > s.len() && lower.ends_with(s.as_str()))
+            .max_by_key(|(s, _)| s.len())?;
+        // Ascii lowercasing keeps byte offsets so the stem can be cut from field
+        unit.map(|u| (u, &field[..field.len() - suffix.len()]))
+    }
+
+    /// The unit vocabulary of the ScaleIO and VNX collectors
+    pub fn default_table() -> Self {
+        UnitMap::new()
+            .with_suffix("_in_kb", Unit::KiB)
+            .with_suffix("InKb", Unit::KiB)
+            .with_suffix("_in_bytes", Unit::Bytes)
+            .with_suffix("InBytes", Unit::Bytes)
+            .with_suffix("_bytes", Unit::Bytes)
+            .with_suffix("_in_mb", Unit::MiB)
+            .with_suffix("InMB", Unit::MiB)
+            .with_suffix("_in_gb", Unit::GiB)
+            .with_suffix("_gb", Unit::GiB)
+            .with_suffix("_in_microsec", Unit::Micros)
+            .with_suffix("InMicrosec", Unit::Micros)
+            .with_suffix("_millis", Unit::Millis)
+            .with_suffix("_in_msec", Unit::Millis)
+            .with_suffix("_msec", Unit::Millis)
+            .with_suffix("_in_sec", Unit::Seconds)
+            .with_suffix("_seconds", Unit::Seconds)
+            // The rfcache histograms count ios slower than a threshold
+            .without_suffix("_greater500_millis")
+            .without_suffix("_g10_millis")
+            .without_suffix("_g1_millis")
+            // The vnx xml api reports filesystem sizes in megabytes
+            .with_field("vnx_filesystem_capacity", "space_total", Unit::MiB)
+            .with_field("vnx_filesystem_capacity", "space_used", Unit::MiB)
+            .with_field("vnx_filesystem_capacity", "volume_size", Unit::MiB)
+    }
+}
+
+/// The units fields should be converted to
+#[derive(Clone, Debug)]
+pub struct TargetUnits {
+    size: Unit,
+    time: Unit,
+    units: UnitMap,
+}
+
+impl Default for TargetUnits {
+    /// Bytes and seconds, recognized with the default table
+    fn default() -> Self {
+        TargetUnits {
+            size: Unit::Bytes,
+            time: Unit::Seconds,
+            units: UnitMap::default_table(),
+        }
+    }
+}
+
+impl TargetUnits {
+    pub fn new(size: Unit, time: Unit) -> MetricsResult<Self> {
+        if size.dimension() != Dimension::Size {
+            return Err(StorageError::new(format!(
+                "{:?} is not a unit of size",
+                size
+            )));
+        }
+        if time.dimension() != Dimension::Time {
+            return Err(StorageError::new(format!(
+                "{:?} is not a unit of time",
+                time
+            )));
+        }
+        Ok(TargetUnits {
+            size,
+            time,
+            units: UnitMap::default_table(),
+        })
+    }
+
+    /// Recognize units with `units` instead of the default table
+    pub fn with_units(mut self, units: UnitMap) -> Self {
+        self.units = units;
+        self
+    }
+
+    fn target(&self, unit: Unit) -> Unit {
+        match unit.dimension() {
+            Dimension::Size => self.size,
+            Dimension::Time => self.time,
+        }
+    }
+}
+
+/// Scale a value from one unit to another.  Values only stay integers when
+/// the conversion multiplies and doesn't overflow, anything else would
+/// truncate so it becomes a Float.  Non numeric values and Durations, which
+/// carry their own unit, are left as they are.
+fn convert_value(value: TsValue, from: Unit, to: Unit) -> TsValue {
+    let (from, to) = (from.base(), to.base());
+    let factor = if from % to == 0 {
+        Some(from / to)
+    } else {
+        None
+    };
+    let float = |v: f64| TsValue::Float(v * from as f64 / to as f64);
+    match value {
+        TsValue::Long(v) => match factor.and_then(|f| v.checked_mul(f)) {
+            Some(v) => TsValue::Long(v),
+            None => float(v as f64),
+        },
+        TsValue::SignedLong(v) => match factor.and_then(|f| v.checked_mul(f as i64)) {
+            Some(v) => TsValue::SignedLong(v),
+            None => float(v as f64),
+        },
+        TsValue::Integer(v) => match factor.and_then(|f| i64::from(v).checked_mul(f as i64)) {
+            Some(v) => TsValue::SignedLong(v),
+            None => float(f64::from(v)),
+        },
+        TsValue::Short(v) => match factor.and_then(|f| u64::from(v).checked_mul(f)) {
+            Some(v) => TsValue::Long(v),
+            None => float(f64::from(v)),
+        },
+        TsValue::Byte(v) => match factor.and_then(|f| u64::from(v).checked_mul(f)) {
+            Some(v) => TsValue::Long(v),
+            None => float(f64::from(v)),
+        },
+        TsValue::Float(v) => float(v),
+        other => other,
+    }
+}
+
+/// Rewrite the fields of `points` whose names carry a unit so they're in
+/// the target units, renaming them to the target's suffix as they go.
+/// `capacity_limit_in_kb` becomes `capacity_limit_bytes` and
+/// `avg_read_latency_in_microsec` becomes `avg_read_latency_seconds`.
+/// A field whose new name is already taken, by a field of the point or one
+/// converted before it, is logged and left as it was.  Fields are converted
+/// in name order so the same one wins every time.
+pub fn convert_units(points: &mut [TsPoint], target: &TargetUnits) {
+    for point in points.iter_mut() {
+        let mut fields: Vec<_> = std::mem::take(&mut point.fields).into_iter().collect();
+        fields.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut taken: HashSet<String> = fields.iter().map(|(k, _)| k.to_string()).collect();
+        for (name, value) in fields {
+            let (name, value) = match target.units.lookup(&point.measurement, &name) {
+                Some((unit, stem)) => {
+                    let to = target.target(unit);
+                    let key = format!("{}{}", stem, to.suffix());
+                    if key != name && !taken.insert(key.clone()) {
+                        warn!(
+                            "not converting {} of {}, {} already exists",
+                            name, point.measurement, key
+                        );
+                        (name, value)
+                    } else {
+                        (key.into(), convert_value(value, unit, to))
+                    }
+                }
+                None => (name, value),
+            };
+            point.fields.insert(name, value);
+        }
+    }
+}
+
+#[test]
+fn test_convert_units() {
+    use crate::scaleio::SystemStatistics;
+    use crate::IntoPoint;
+    use std::fs::File;
+    use std::io::Read;
+    use std::time::Duration;
+
+    let mut f = File::open("tests/scaleio/system_statistics.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+    let stats: SystemStatistics = serde_json::from_str(&buff).unwrap();
+    let mut points = stats.into_point(Some("scaleio_system"), true);
+    let original = points[0].fields.clone();
+
+    convert_units(&mut points, &TargetUnits::default());
+    let fields = &points[0].fields;
+    assert_eq!(fields.len(), original.len());
+
+    // Sizes scale up and stay integers
+    let capacity = match original["capacity_limit_in_kb"] {
+        TsValue::Long(v) => v,
+        ref v => panic!("unexpected value {:?}", v),
+    };
+    assert!(!fields.contains_key("capacity_limit_in_kb"));
+    assert_eq!(
+        fields.get("capacity_limit_bytes"),
+        Some(&TsValue::Long(capacity * 1024))
+    );
+    assert!(fields.keys().all(|k| !k.ends_with("_in_kb")));
+
+    // Fields already in the target unit keep their name and value
+    assert_eq!(
+        fields.get("primary_read_bwc_num_seconds"),
+        original.get("primary_read_bwc_num_seconds")
+    );
+    assert!(fields.keys().all(|k| !k.ends_with("_in_microsec")));
+
+    let mut p = TsPoint::new("scaleio_sds", true);
+    p.add_field("avg_read_latency_in_microsec", TsValue::Long(1500));
+    p.add_field(
+        "rebuild_io_priority_quiet_period_in_msec",
+        TsValue::Integer(2),
+    );
+    p.add_field("rfcache_fd_read_time_greater500_millis", TsValue::Long(3));
+    p.add_field("BackgroundScannedInMB", TsValue::Short(2));
+    p.add_field(
+        "rebuild_wait_in_msec",
+        TsValue::Duration(Duration::from_millis(2500)),
+    );
+    let mut points = vec![p];
+    convert_units(&mut points, &TargetUnits::default());
+    let fields = &points[0].fields;
+    assert_eq!(
+        fields.get("avg_read_latency_seconds"),
+        Some(&TsValue::Float(0.0015))
+    );
+    assert_eq!(
+        fields.get("rebuild_io_priority_quiet_period_seconds"),
+        Some(&TsValue::Float(0.002))
+    );
+    assert_eq!(
+        fields.get("rfcache_fd_read_time_greater500_millis"),
+        Some(&TsValue::Long(3))
+    );
+    assert_eq!(
+        fields.get("BackgroundScanned_bytes"),
+        Some(&TsValue::Long(2 * 1024 * 1024))
+    );
+    // Durations carry their own unit, only the name changes
+    assert_eq!(
+        fields.get("rebuild_wait_seconds"),
+        Some(&TsValue::Duration(Duration::from_millis(2500)))
+    );
+    assert_eq!(fields["rebuild_wait_seconds"].to_string(), "2.5");
+
+    // A converted name that's taken leaves the field alone
+    crate::capture_logs();
+    let mut p = TsPoint::new("scaleio_sds", true);
+    p.add_field("capacity_in_kb", TsValue::Long(2));
+    p.add_field("capacity_bytes", TsValue::Long(5));
+    p.add_field("used_in_kb", TsValue::Long(3));
+    p.add_field("used_in_mb", TsValue::Long(1));
+    let mut points = vec![p];
+    convert_units(&mut points, &TargetUnits::default());
+    let fields = &points[0].fields;
+    assert_eq!(fields.len(), 4);
+    assert_eq!(fields.get("capacity_bytes"), Some(&TsValue::Long(5)));
+    assert_eq!(fields.get("capacity_in_kb"), Some(&TsValue::Long(2)));
+    assert_eq!(fields.get("used_bytes"), Some(&TsValue::Long(3 * 1024)));
+    assert_eq!(fields.get("used_in_mb"), Some(&TsValue::Long(1)));
+    assert_eq!(
+        crate::captured_logs("not converting capacity_in_kb of scaleio_sds").len(),
+        1
+    );
+
+    // Measurement scoped rules append the suffix to the whole name
+    let mut p = TsPoint::new("vnx_filesystem_capacity", true);
+    p.add_field("space_total", TsValue::Long(10));
+    p.add_field("space_free", TsValue::Long(4));
+    let mut points = vec![p];
+    let target = TargetUnits::new(Unit::KiB, Unit::Millis).unwrap();
+    convert_units(&mut points, &target);
+    let fields = &points[0].fields;
+    assert_eq!(
+        fields.get("space_total_kib"),
+        Some(&TsValue::Long(10 * 1024))
+    );
+    assert_eq!(fields.get("space_free"), Some(&TsValue::Long(4)));
+
+    assert!(TargetUnits::new(Unit::Seconds, Unit::Millis).is_err());
+}
@@ -16,11 +16,13 @@
 *
 * SPDX-License-Identifier: Apache-2.0
 */
+use std::fmt::Debug;
 use std::io::Write;
 use std::str::FromStr;
 
+use crate::collector::Collector;
 use crate::error::*;
-use crate::IntoPoint;
+use crate::{fetch_all, read_text, Cursor, IntoPoint, Page, PageFetcher, Severity};
 
 use crate::ir::{TsPoint, TsValue};
 use chrono::offset::Utc;
@@ -28,6 +30,7 @@ use chrono::DateTime;
 use log::debug;
 use reqwest::blocking::Client;
 use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use serde::de::DeserializeOwned;
 use treexml::Document;
 use uuid::Uuid;
 use xml::writer::{EventWriter, XmlEvent};
@@ -50,6 +53,9 @@ pub struct NetappConfig {
     /// Optional certificate file to use against the server
     /// der encoded
     pub certificate: Option<String>,
+    /// Largest response body to read, in bytes.  Defaults to
+    /// DEFAULT_MAX_RESPONSE_BYTES
+    pub max_response_bytes: Option<u64>,
 }
 
 pub struct Netapp {
@@ -66,6 +72,24 @@ impl Netapp {
     }
 }
 
+impl Collector for Netapp {
+    fn backend(&self) -> &str {
+        "netapp"
+    }
+
+    fn array_id(&self) -> &str {
+        &self.config.endpoint
+    }
+
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut points = self.get_volume_performance(t)?;
+        points.extend(self.get_volume_usage(t)?);
+        points.extend(self.get_qtrees(t)?);
+        points.extend(self.get_quota_reports(t)?);
+        Ok(points)
+    }
+}
+
 pub trait FromXml {
     fn from_xml(data: &str) -> MetricsResult<Self>
     where
@@ -696,6 +720,660 @@ impl FromXml for OnTapVersion {
     }
 }
 
+/// A page of records returned by the ONTAP REST api
+#[derive(Debug, Deserialize)]
+pub struct OntapRecords<T> {
+    pub records: Vec<T>,
+    pub num_records: Option<u64>,
+    #[serde(rename = "_links")]
+    pub links: Option<OntapLinks>,
+}
+
+/// Upper bounds on a single ONTAP REST listing.  Collections are
+/// requested 1000 records at a time
+const REST_MAX_PAGES: usize = 1000;
+const REST_MAX_RECORDS: usize = 1_000_000;
+
+/// The pages of an ONTAP REST collection, linked by `_links.next`
+struct OntapPages<'a> {
+    client: &'a Client,
+    config: &'a NetappConfig,
+    api: &'a str,
+}
+
+impl<'a> OntapPages<'a> {
+    fn page<T>(&self, href: &str) -> MetricsResult<Page<T>>
+    where
+        T: DeserializeOwned + Debug,
+    {
+        let page: OntapRecords<T> = crate::get_limited(
+            self.client,
+            &format!("https://{}{}", self.config.endpoint, href),
+            &self.config.user,
+            Some(&self.config.password),
+            self.config.max_response_bytes,
+        )?;
+        Ok(Page {
+            items: page.records,
+            next: page.links.and_then(|l| l.next).map(|n| Cursor(n.href)),
+        })
+    }
+}
+
+impl<'a, T> PageFetcher<T> for OntapPages<'a>
+where
+    T: DeserializeOwned + Debug,
+{
+    fn first(&self) -> MetricsResult<Page<T>> {
+        self.page(self.api)
+    }
+
+    fn next(&self, cursor: &Cursor) -> MetricsResult<Page<T>> {
+        self.page(&cursor.0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OntapLinks {
+    /// Present when there are more records to fetch
+    pub next: Option<OntapHref>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OntapHref {
+    pub href: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OntapReference {
+    pub name: String,
+    pub uuid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OntapExportPolicy {
+    pub name: String,
+    pub id: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Qtree {
+    pub svm: OntapReference,
+    pub volume: OntapReference,
+    pub id: Option<u64>,
+    /// The volume's default qtree has an empty name
+    pub name: String,
+    pub security_style: Option<String>,
+    pub export_policy: Option<OntapExportPolicy>,
+}
+
+// Influx can't store an empty tag value.  The default qtree, and
+// default tree quotas, have an empty qtree name so the tag is left off
+fn add_qtree_tag(p: &mut TsPoint, qtree: &str) {
+    if !qtree.is_empty() {
+        p.add_tag("qtree", TsValue::String(qtree.to_string()));
+    }
+}
+
+impl IntoPoint for Qtree {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("netapp_qtree"), is_time_series);
+        p.add_tag("svm", TsValue::String(self.svm.name.clone()));
+        p.add_tag("volume", TsValue::String(self.volume.name.clone()));
+        add_qtree_tag(&mut p, &self.name);
+        if let Some(id) = self.id {
+            p.add_field("id", TsValue::Long(id));
+        }
+        if let Some(ref style) = self.security_style {
+            p.add_field("security_style", TsValue::String(style.clone()));
+        }
+        if let Some(ref policy) = self.export_policy {
+            p.add_field("export_policy", TsValue::String(policy.name.clone()));
+        }
+
+        vec![p]
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuotaQtree {
+    pub name: String,
+    pub id: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuotaUsed {
+    pub total: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuotaUsage {
+    /// Absent or 0 when the quota has no limit
+    pub hard_limit: Option<u64>,
+    pub soft_limit: Option<u64>,
+    pub used: QuotaUsed,
+}
+
+impl QuotaUsage {
+    /// Percent of the hard limit used.  None if there is no limit
+    pub fn used_percent(&self) -> Option<f64> {
+        match self.hard_limit {
+            Some(limit) if limit > 0 => Some(self.used.total as f64 / limit as f64 * 100.0),
+            _ => None,
+        }
+    }
+
+    fn add_fields(&self, p: &mut TsPoint, prefix: &str) {
+        p.add_field(format!("{}used", prefix), TsValue::Long(self.used.total));
+        if let Some(limit) = self.hard_limit {
+            p.add_field(format!("{}hard_limit", prefix), TsValue::Long(limit));
+        }
+        if let Some(limit) = self.soft_limit {
+            p.add_field(format!("{}soft_limit", prefix), TsValue::Long(limit));
+        }
+        if let Some(percent) = self.used_percent() {
+            p.add_field(format!("{}used_percent", prefix), TsValue::Float(percent));
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuotaReport {
+    pub svm: OntapReference,
+    pub volume: OntapReference,
+    pub index: Option<u64>,
+    /// tree, user or group
+    #[serde(rename = "type")]
+    pub quota_type: String,
+    pub qtree: Option<QuotaQtree>,
+    pub users: Option<Vec<OntapReference>>,
+    pub group: Option<OntapReference>,
+    pub space: Option<QuotaUsage>,
+    pub files: Option<QuotaUsage>,
+}
+
+impl IntoPoint for QuotaReport {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("netapp_quota"), is_time_series);
+        p.add_tag("svm", TsValue::String(self.svm.name.clone()));
+        p.add_tag("volume", TsValue::String(self.volume.name.clone()));
+        p.add_tag("type", TsValue::String(self.quota_type.clone()));
+        if let Some(ref qtree) = self.qtree {
+            add_qtree_tag(&mut p, &qtree.name);
+        }
+        if let Some(ref users) = self.users {
+            if !users.is_empty() {
+                let names: Vec<&str> = users.iter().map(|u| u.name.as_str()).collect();
+                p.add_tag("users", TsValue::String(names.join(",")));
+            }
+        }
+        if let Some(ref group) = self.group {
+            p.add_tag("group", TsValue::String(group.name.clone()));
+        }
+        if let Some(ref space) = self.space {
+            space.add_fields(&mut p, "");
+        }
+        if let Some(ref files) = self.files {
+            files.add_fields(&mut p, "files_");
+        }
+
+        vec![p]
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmsMessage {
+    pub name: String,
+    pub severity: String,
+}
+
+/// An event logged by the ONTAP event management system
+#[derive(Debug, Deserialize)]
+pub struct EmsEvent {
+    /// Increases with every event the cluster logs
+    pub index: u64,
+    pub time: Option<DateTime<Utc>>,
+    pub node: Option<OntapReference>,
+    pub message: EmsMessage,
+    pub log_message: Option<String>,
+}
+
+impl IntoPoint for EmsEvent {
+    /// The point is stamped with the time of the event when it has one
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("netapp_ems_event"), is_time_series);
+        if let Some(time) = self.time {
+            p.timestamp = Some(time);
+        }
+        p.add_tag("severity", TsValue::String(self.message.severity.clone()));
+        p.add_tag("message_name", TsValue::String(self.message.name.clone()));
+        if let Some(node) = &self.node {
+            p.add_tag("node", TsValue::String(node.name.clone()));
+        }
+        p.add_field("index", TsValue::Long(self.index));
+        if let Some(log_message) = &self.log_message {
+            p.add_field("log_message", TsValue::String(log_message.clone()));
+        }
+
+        vec![p]
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeController {
+    /// over or normal
+    pub over_temperature: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeBattery {
+    pub state: Option<String>,
+}
+
+/// A node of the cluster from /api/cluster/nodes
+#[derive(Debug, Deserialize)]
+pub struct ClusterNode {
+    pub name: String,
+    pub uuid: Option<String>,
+    /// up, booting, down, taken_over, waiting_for_giveback, degraded or
+    /// unknown
+    pub state: Option<String>,
+    pub health: Option<bool>,
+    /// Seconds since the node booted
+    pub uptime: Option<u64>,
+    pub model: Option<String>,
+    pub serial_number: Option<String>,
+    pub controller: Option<NodeController>,
+    pub battery: Option<NodeBattery>,
+}
+
+impl ClusterNode {
+    pub fn is_over_temperature(&self) -> bool {
+        matches!(
+            self.controller
+                .as_ref()
+                .and_then(|c| c.over_temperature.as_deref()),
+            Some("over")
+        )
+    }
+
+    /// The health as a number so it can be graphed and alerted on.  0 up
+    /// and healthy, 1 degraded, ie booting, waiting for giveback, over
+    /// temperature or reporting itself unhealthy, and 2 down or taken over
+    pub fn health_code(&self) -> u64 {
+        match self.state.as_deref() {
+            Some("down") | Some("taken_over") => 2,
+            Some("up") if self.health != Some(false) && !self.is_over_temperature() => 0,
+            _ => 1,
+        }
+    }
+}
+
+impl IntoPoint for ClusterNode {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("netapp_node"), is_time_series);
+        p.add_tag("node", TsValue::String(self.name.clone()));
+        if let Some(ref model) = self.model {
+            p.add_tag("model", TsValue::String(model.clone()));
+        }
+        if let Some(ref serial_number) = self.serial_number {
+            p.add_tag("serial_number", TsValue::String(serial_number.clone()));
+        }
+        if let Some(ref state) = self.state {
+            p.add_field("state", TsValue::String(state.clone()));
+        }
+        p.add_field("health", TsValue::Long(self.health_code()));
+        if let Some(uptime) = self.uptime {
+            p.add_field("uptime_seconds", TsValue::Long(uptime));
+        }
+        p.add_field(
+            "over_temperature",
+            TsValue::Boolean(self.is_over_temperature()),
+        );
+        if let Some(state) = self.battery.as_ref().and_then(|b| b.state.as_ref()) {
+            p.add_field("battery_state", TsValue::String(state.clone()));
+        }
+
+        vec![p]
+    }
+}
+
+// Shelves, drawers and FRUs report ok, error or unknown.  As a number 0
+// ok, 1 unknown and 2 error
+fn ontap_state_code(state: Option<&str>) -> u64 {
+    match state {
+        Some("ok") => 0,
+        Some("error") => 2,
+        _ => 1,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShelfDrawer {
+    pub id: u64,
+    pub state: Option<String>,
+    pub closed: Option<bool>,
+    pub disk_count: Option<u64>,
+}
+
+/// A field replaceable unit of a shelf: a module, psu, fan or sensor
+#[derive(Debug, Deserialize)]
+pub struct ShelfFru {
+    #[serde(rename = "type")]
+    pub fru_type: String,
+    pub id: u64,
+    pub state: Option<String>,
+    pub serial_number: Option<String>,
+    pub installed: Option<bool>,
+}
+
+/// A disk shelf from /api/storage/shelves
+#[derive(Debug, Deserialize)]
+pub struct Shelf {
+    pub id: String,
+    pub name: Option<String>,
+    pub uid: Option<String>,
+    pub state: Option<String>,
+    #[serde(default)]
+    pub drawers: Vec<ShelfDrawer>,
+    #[serde(default)]
+    pub frus: Vec<ShelfFru>,
+}
+
+impl IntoPoint for Shelf {
+    /// A point for the shelf followed by a netapp_shelf_fru point for each
+    /// of its FRUs
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let failed = |states: Vec<Option<&str>>| {
+            states
+                .into_iter()
+                .filter(|s| ontap_state_code(*s) == 2)
+                .count() as u64
+        };
+        let mut p = TsPoint::new(name.unwrap_or("netapp_shelf"), is_time_series);
+        p.add_tag("shelf", TsValue::String(self.id.clone()));
+        if let Some(ref state) = self.state {
+            p.add_field("state", TsValue::String(state.clone()));
+        }
+        p.add_field(
+            "health",
+            TsValue::Long(ontap_state_code(self.state.as_deref())),
+        );
+        p.add_field("drawer_count", TsValue::Long(self.drawers.len() as u64));
+        p.add_field(
+            "failed_drawers",
+            TsValue::Long(failed(
+                self.drawers.iter().map(|d| d.state.as_deref()).collect(),
+            )),
+        );
+        p.add_field("fru_count", TsValue::Long(self.frus.len() as u64));
+        p.add_field(
+            "failed_frus",
+            TsValue::Long(failed(
+                self.frus.iter().map(|f| f.state.as_deref()).collect(),
+            )),
+        );
+        let mut points = vec![p];
+
+        for fru in &self.frus {
+            let mut p = TsPoint::new("netapp_shelf_fru", is_time_series);
+            p.add_tag("shelf", TsValue::String(self.id.clone()));
+            p.add_tag("type", TsValue::String(fru.fru_type.clone()));
+            p.add_tag("fru_id", TsValue::String(fru.id.to_string()));
+            if let Some(ref state) = fru.state {
+                p.add_field("state", TsValue::String(state.clone()));
+            }
+            p.add_field(
+                "health",
+                TsValue::Long(ontap_state_code(fru.state.as_deref())),
+            );
+            if let Some(installed) = fru.installed {
+                p.add_field("installed", TsValue::Boolean(installed));
+            }
+            if let Some(ref serial_number) = fru.serial_number {
+                p.add_field("serial_number", TsValue::String(serial_number.clone()));
+            }
+            points.push(p);
+        }
+
+        points
+    }
+}
+
+// The ONTAP names of every EMS severity at or above min
+fn ems_severities(min: Severity) -> Vec<&'static str> {
+    [
+        (Severity::Emergency, "emergency"),
+        (Severity::Alert, "alert"),
+        (Severity::Error, "error"),
+        (Severity::Notice, "notice"),
+        (Severity::Info, "informational"),
+        (Severity::Debug, "debug"),
+    ]
+    .iter()
+    .filter(|(severity, _)| *severity >= min)
+    .map(|(_, name)| *name)
+    .collect()
+}
+
+#[test]
+fn test_ems_severities() {
+    assert_eq!(
+        ems_severities(Severity::Error),
+        vec!["emergency", "alert", "error"]
+    );
+    // There's no warning level in EMS
+    assert_eq!(
+        ems_severities(Severity::Warning),
+        ems_severities(Severity::Error)
+    );
+    assert_eq!(ems_severities(Severity::Ok).len(), 6);
+}
+
+#[test]
+fn test_qtrees() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let data = {
+        let mut s = String::new();
+        let mut f = File::open("tests/netapp/qtrees.json").unwrap();
+        f.read_to_string(&mut s).unwrap();
+        s
+    };
+    let res: OntapRecords<Qtree> = serde_json::from_str(&data).unwrap();
+    println!("res: {:#?}", res);
+    assert_eq!(res.records.len(), 2);
+    assert!(res.links.unwrap().next.unwrap().href.contains("start.id=1"));
+
+    let points: Vec<TsPoint> = res
+        .records
+        .iter()
+        .flat_map(|q| q.into_point(None, true))
+        .collect();
+    println!("points: {:#?}", points);
+    assert!(!points[0].tags.contains_key("qtree"));
+    assert_eq!(
+        points[1].tags["qtree"],
+        TsValue::String("engineering".into())
+    );
+    assert_eq!(
+        points[1].fields["export_policy"],
+        TsValue::String("eng_policy".into())
+    );
+}
+
+#[test]
+fn test_nodes_and_shelves() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let read = |name: &str| {
+        let mut s = String::new();
+        File::open(format!("tests/netapp/{}", name))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        s
+    };
+    let nodes: OntapRecords<ClusterNode> = serde_json::from_str(&read("nodes.json")).unwrap();
+    let codes: Vec<u64> = nodes.records.iter().map(|n| n.health_code()).collect();
+    assert_eq!(codes, vec![0, 2, 1]);
+
+    let points = nodes.records[1].into_point(None, true);
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].measurement, "netapp_node");
+    assert_eq!(
+        points[0].tags["node"],
+        TsValue::String("cluster1-02".into())
+    );
+    assert_eq!(points[0].fields["health"], TsValue::Long(2));
+    assert_eq!(points[0].fields["over_temperature"], TsValue::Boolean(true));
+    assert_eq!(points[0].fields["uptime_seconds"], TsValue::Long(0));
+    assert_eq!(
+        points[0].fields["battery_state"],
+        TsValue::String("battery_near_end_of_life".into())
+    );
+    let points = nodes.records[2].into_point(None, true);
+    assert_eq!(
+        points[0].fields["over_temperature"],
+        TsValue::Boolean(false)
+    );
+    assert!(!points[0].fields.contains_key("battery_state"));
+
+    let shelves: OntapRecords<Shelf> = serde_json::from_str(&read("shelves.json")).unwrap();
+    let points = shelves.records[0].into_point(None, true);
+    assert_eq!(points.len(), 5);
+    assert_eq!(points[0].fields["health"], TsValue::Long(0));
+
+    // The failed psu is one of four FRU points
+    let points = shelves.records[1].into_point(None, true);
+    assert_eq!(points.len(), 5);
+    assert_eq!(points[0].measurement, "netapp_shelf");
+    assert_eq!(points[0].tags["shelf"], TsValue::String("2.0".into()));
+    assert_eq!(points[0].fields["health"], TsValue::Long(2));
+    assert_eq!(points[0].fields["failed_frus"], TsValue::Long(1));
+    assert_eq!(points[0].fields["failed_drawers"], TsValue::Long(1));
+    let frus: Vec<&TsPoint> = points
+        .iter()
+        .filter(|p| p.measurement == "netapp_shelf_fru")
+        .collect();
+    assert_eq!(frus.len(), 4);
+    let failed: Vec<&&TsPoint> = frus
+        .iter()
+        .filter(|p| p.fields["health"] == TsValue::Long(2))
+        .collect();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].tags["type"], TsValue::String("psu".into()));
+    assert_eq!(failed[0].tags["fru_id"], TsValue::String("2".into()));
+    assert_eq!(failed[0].tags["shelf"], TsValue::String("2.0".into()));
+}
+
+#[cfg(test)]
+// A Netapp talking to a mock server on port
+fn mock_netapp(port: u16) -> Netapp {
+    Netapp {
+        client: Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap(),
+        config: NetappConfig {
+            endpoint: format!("127.0.0.1:{}", port),
+            user: "admin".into(),
+            password: "password".into(),
+            region: "test".into(),
+            certificate: None,
+            max_response_bytes: None,
+        },
+    }
+}
+
+#[test]
+fn test_rest_records_pages() {
+    use std::fs::File;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let mut first = String::new();
+    File::open("tests/netapp/qtrees.json")
+        .unwrap()
+        .read_to_string(&mut first)
+        .unwrap();
+    // The second page points back at itself once `looping` is set
+    let looping = Arc::new(AtomicBool::new(false));
+    let looping_server = looping.clone();
+    let port = crate::mock_https_server(move |req| {
+        let body = if req.path.contains("start.id=1") {
+            let next = if looping_server.load(Ordering::SeqCst) {
+                format!(r#", "_links": {{"next": {{"href": "{}"}}}}"#, req.path)
+            } else {
+                String::new()
+            };
+            format!(
+                r#"{{"records": [{{"svm": {{"name": "svm1"}}, "volume": {{"name": "vol_projects"}}, "id": 2, "name": "finance", "security_style": "unix"}}], "num_records": 1{}}}"#,
+                next
+            )
+        } else {
+            first.clone()
+        };
+        (200, String::new(), body.into_bytes())
+    });
+    let netapp = mock_netapp(port);
+
+    let qtrees: Vec<Qtree> = netapp.get_rest_records("/api/storage/qtrees").unwrap();
+    assert_eq!(qtrees.len(), 3);
+
+    looping.store(true, Ordering::SeqCst);
+    let err = netapp
+        .get_rest_records::<Qtree>("/api/storage/qtrees")
+        .unwrap_err()
+        .to_string();
+    assert!(err.starts_with("netapp: "));
+    assert!(err.contains("twice"));
+}
+
+#[test]
+fn test_quota_reports() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let data = {
+        let mut s = String::new();
+        let mut f = File::open("tests/netapp/quota_reports.json").unwrap();
+        f.read_to_string(&mut s).unwrap();
+        s
+    };
+    let res: OntapRecords<QuotaReport> = serde_json::from_str(&data).unwrap();
+    println!("res: {:#?}", res);
+    assert!(res.links.unwrap().next.is_none());
+
+    let points: Vec<TsPoint> = res
+        .records
+        .iter()
+        .flat_map(|q| q.into_point(None, true))
+        .collect();
+    println!("points: {:#?}", points);
+
+    // Default tree quota: no qtree name and no limits
+    assert!(!points[0].tags.contains_key("qtree"));
+    assert_eq!(points[0].fields["used"], TsValue::Long(4096));
+    assert!(!points[0].fields.contains_key("used_percent"));
+    assert!(!points[0].fields.contains_key("files_used_percent"));
+
+    assert_eq!(
+        points[1].tags["qtree"],
+        TsValue::String("engineering".into())
+    );
+    assert_eq!(points[1].fields["hard_limit"], TsValue::Long(1_073_741_824));
+    assert_eq!(points[1].fields["used_percent"], TsValue::Float(25.0));
+    assert_eq!(points[1].fields["files_used_percent"], TsValue::Float(25.0));
+
+    // A hard limit of 0 means unlimited
+    assert_eq!(points[2].tags["users"], TsValue::String("jsmith".into()));
+    assert!(!points[2].fields.contains_key("used_percent"));
+}
+
 fn api_request<T>(client: &Client, config: &NetappConfig, req: Vec<u8>) -> MetricsResult<T>
 where
     T: FromXml,
@@ -709,7 +1387,7 @@ where
         .send()?
         .error_for_status()?;
 
-    let data = s.text()?;
+    let data = read_text(s, config.max_response_bytes)?;
     debug!("api_request response: {}", data);
     let res = T::from_xml(&data)?;
 
@@ -888,4 +1566,259 @@ impl Netapp {
         let res: OnTapVersion = api_request(&self.client, &self.config, output)?;
         Ok(res)
     }
+
+    // Fetch every record from an ONTAP REST collection, following the
+    // next links until the last page
+    fn get_rest_records<T>(&self, api: &str) -> MetricsResult<Vec<T>>
+    where
+        T: DeserializeOwned + Debug,
+    {
+        let pages = OntapPages {
+            client: &self.client,
+            config: &self.config,
+            api,
+        };
+        fetch_all(&pages, REST_MAX_PAGES, REST_MAX_RECORDS).map_err(|e| e.with_context("netapp"))
+    }
+
+    pub fn get_qtrees(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let qtrees: Vec<Qtree> = self.get_rest_records(
+            "/api/storage/qtrees?fields=svm,volume,id,name,security_style,export_policy&max_records=1000",
+        )?;
+        debug!("netapp qtrees: {:#?}", qtrees);
+
+        let mut points: Vec<TsPoint> = qtrees
+            .iter()
+            .flat_map(|q| q.into_point(Some("netapp_qtree"), true))
+            .collect();
+        // Set all the timestamps to be identical
+        for p in &mut points {
+            p.timestamp = Some(t);
+        }
+
+        Ok(points)
+    }
+
+    /// EMS events at or above min_severity logged after the event at
+    /// since_index.  The cluster does the filtering.  Points are stamped
+    /// with the time of their event, or t when it's missing.  Along with
+    /// the points this returns the highest index seen, or since_index if
+    /// there were no new events, to pass as since_index next time
+    pub fn get_ems_events(
+        &self,
+        min_severity: Severity,
+        since_index: Option<u64>,
+        t: DateTime<Utc>,
+    ) -> MetricsResult<(Vec<TsPoint>, u64)> {
+        let mut api = format!(
+            "/api/support/ems/events?fields=index,time,node,message,log_message&message.severity={}&max_records=1000",
+            ems_severities(min_severity).join("|")
+        );
+        if let Some(index) = since_index {
+            api.push_str(&format!("&index=>{}", index));
+        }
+        let events: Vec<EmsEvent> = self.get_rest_records(&api)?;
+        debug!("netapp ems events: {:#?}", events);
+
+        let last_index = events
+            .iter()
+            .map(|e| e.index)
+            .chain(since_index)
+            .max()
+            .unwrap_or(0);
+        let mut points: Vec<TsPoint> = events
+            .iter()
+            .flat_map(|e| e.into_point(Some("netapp_ems_event"), true))
+            .collect();
+        for p in &mut points {
+            p.timestamp.get_or_insert(t);
+        }
+
+        Ok((points, last_index))
+    }
+
+    pub fn get_quota_reports(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let reports: Vec<QuotaReport> = self.get_rest_records(
+            "/api/storage/quota/reports?fields=svm,volume,index,type,qtree,users,group,space,files&max_records=1000",
+        )?;
+        debug!("netapp quota reports: {:#?}", reports);
+
+        let mut points: Vec<TsPoint> = reports
+            .iter()
+            .flat_map(|r| r.into_point(Some("netapp_quota"), true))
+            .collect();
+        // Set all the timestamps to be identical
+        for p in &mut points {
+            p.timestamp = Some(t);
+        }
+
+        Ok(points)
+    }
+
+    pub fn get_nodes(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let nodes: Vec<ClusterNode> = self.get_rest_records(
+            "/api/cluster/nodes?fields=state,health,uptime,controller.over_temperature,battery,model,serial_number&max_records=1000",
+        )?;
+        debug!("netapp nodes: {:#?}", nodes);
+
+        let mut points: Vec<TsPoint> = nodes
+            .iter()
+            .flat_map(|n| n.into_point(Some("netapp_node"), true))
+            .collect();
+        // Set all the timestamps to be identical
+        for p in &mut points {
+            p.timestamp = Some(t);
+        }
+
+        Ok(points)
+    }
+
+    /// A netapp_shelf point per shelf and a netapp_shelf_fru point per FRU
+    pub fn get_shelves(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let shelves: Vec<Shelf> = self
+            .get_rest_records("/api/storage/shelves?fields=state,drawers,frus&max_records=1000")?;
+        debug!("netapp shelves: {:#?}", shelves);
+
+        let mut points: Vec<TsPoint> = shelves
+            .iter()
+            .flat_map(|s| s.into_point(Some("netapp_shelf"), true))
+            .collect();
+        // Set all the timestamps to be identical
+        for p in &mut points {
+            p.timestamp = Some(t);
+        }
+
+        Ok(points)
+    }
+}
+
+#[test]
+fn test_get_ems_events() {
+    use chrono::TimeZone;
+    use std::fs::File;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+
+    let read = |name: &str| {
+        let mut s = String::new();
+        File::open(format!("tests/netapp/{}", name))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        s
+    };
+    let (first, second) = (read("ems_events_page1.json"), read("ems_events_page2.json"));
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let port = crate::mock_https_server(move |req| {
+        recorded.lock().unwrap().push(req.path.clone());
+        let body = if req.path.contains("start.index=1044") {
+            second.clone()
+        } else if req.path.contains("&index=%3E1047") {
+            r#"{"records": [{"node": {"name": "cluster1-01"}, "index": 1052, "time": "2021-03-02T10:30:00-05:00", "message": {"severity": "error", "name": "raid.disk.missing"}, "log_message": "raid.disk.missing: Disk 1.0.15 is missing from the system."}], "num_records": 1}"#.to_string()
+        } else if req.path.contains("&index=%3E1052") {
+            r#"{"records": [], "num_records": 0}"#.to_string()
+        } else {
+            first.clone()
+        };
+        (200, String::new(), body.into_bytes())
+    });
+    let netapp = mock_netapp(port);
+    let t = Utc.timestamp_opt(1_614_700_000, 0).unwrap();
+
+    // Both pages of the first call
+    let (points, cursor) = netapp.get_ems_events(Severity::Error, None, t).unwrap();
+    assert_eq!(cursor, 1047);
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[0].measurement, "netapp_ems_event");
+    assert_eq!(
+        points[0].timestamp,
+        Some(Utc.with_ymd_and_hms(2021, 3, 2, 14, 14, 7).unwrap())
+    );
+    assert_eq!(points[0].tags["severity"], TsValue::String("error".into()));
+    assert_eq!(
+        points[0].tags["message_name"],
+        TsValue::String("raid.disk.missing".into())
+    );
+    assert_eq!(
+        points[1].tags["node"],
+        TsValue::String("cluster1-02".into())
+    );
+    assert_eq!(points[2].fields["index"], TsValue::Long(1047));
+    assert!(matches!(
+        &points[2].fields["log_message"],
+        TsValue::String(m) if m.starts_with("monitor.shelf.fault")
+    ));
+
+    // Picking up from the cursor only returns newer events
+    let (points, cursor) = netapp
+        .get_ems_events(Severity::Error, Some(cursor), t)
+        .unwrap();
+    assert_eq!(cursor, 1052);
+    assert_eq!(points.len(), 1);
+    let (points, cursor) = netapp
+        .get_ems_events(Severity::Error, Some(cursor), t)
+        .unwrap();
+    assert_eq!(cursor, 1052);
+    assert!(points.is_empty());
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 4);
+    // The severity filter is left to the cluster
+    assert!(requests
+        .iter()
+        .all(|p| p.contains("message.severity=emergency|alert|error")));
+    assert!(!requests[0].contains("&index="));
+}
+
+#[test]
+fn test_get_nodes_and_shelves() {
+    use chrono::TimeZone;
+    use std::fs::File;
+    use std::io::Read;
+
+    let read = |name: &str| {
+        let mut s = String::new();
+        File::open(format!("tests/netapp/{}", name))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        s
+    };
+    let (nodes, shelves) = (read("nodes.json"), read("shelves.json"));
+    let port = crate::mock_https_server(move |req| {
+        let body = if req.path.starts_with("/api/cluster/nodes?") {
+            nodes.clone()
+        } else if req
+            .path
+            .starts_with("/api/storage/shelves?fields=state,drawers,frus")
+        {
+            shelves.clone()
+        } else {
+            return (404, String::new(), vec![]);
+        };
+        (200, String::new(), body.into_bytes())
+    });
+    let netapp = mock_netapp(port);
+    let t = Utc.timestamp_opt(1_614_700_000, 0).unwrap();
+
+    let points = netapp.get_nodes(t).unwrap();
+    assert_eq!(points.len(), 3);
+    assert!(points.iter().all(|p| p.timestamp == Some(t)));
+    let health: Vec<TsValue> = points.iter().map(|p| p.fields["health"].clone()).collect();
+    assert_eq!(
+        health,
+        vec![TsValue::Long(0), TsValue::Long(2), TsValue::Long(1)]
+    );
+
+    let points = netapp.get_shelves(t).unwrap();
+    assert_eq!(points.len(), 10);
+    assert_eq!(
+        points
+            .iter()
+            .filter(|p| p.measurement == "netapp_shelf_fru")
+            .count(),
+        8
+    );
+    assert!(points.iter().all(|p| p.timestamp == Some(t)));
 }
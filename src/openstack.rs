@@ -17,10 +17,12 @@
 */
 use std::{collections::HashMap, fmt, fmt::Debug, str::FromStr};
 
+use crate::collector::Collector;
 use crate::error::{MetricsResult, StorageError};
 use crate::ir::{TsPoint, TsValue};
-use crate::IntoPoint;
+use crate::{read_text, IntoPoint};
 
+use chrono::{DateTime, Utc};
 use log::debug;
 use reqwest::{header::HeaderName, header::HeaderValue, StatusCode};
 use serde::de::DeserializeOwned;
@@ -42,6 +44,9 @@ pub struct OpenstackConfig {
     /// der encoded
     pub certificate: Option<String>,
     pub region: String,
+    /// Largest response body to read, in bytes.  Defaults to
+    /// DEFAULT_MAX_RESPONSE_BYTES
+    pub max_response_bytes: Option<u64>,
 }
 
 pub struct Openstack {
@@ -322,6 +327,94 @@ impl IntoPoint for Volumes {
     }
 }
 
+/// Hypervisor and service ids became uuids in compute microversion 2.53
+#[derive(Clone, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ComputeId {
+    Legacy(u64),
+    Uuid(String),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HypervisorService {
+    pub host: String,
+    pub id: ComputeId,
+    pub disabled_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug, IntoPoint)]
+pub struct Hypervisor {
+    pub id: ComputeId,
+    pub hypervisor_hostname: String,
+    pub hypervisor_type: String,
+    pub state: String,
+    pub status: String,
+    pub vcpus: u64,
+    pub vcpus_used: u64,
+    pub memory_mb: u64,
+    pub memory_mb_used: u64,
+    pub local_gb: u64,
+    pub local_gb_used: u64,
+    pub running_vms: u64,
+    pub service: HypervisorService,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Hypervisors {
+    pub hypervisors: Vec<Hypervisor>,
+}
+
+impl IntoPoint for Hypervisors {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        self.hypervisors
+            .iter()
+            .flat_map(|h| h.into_point(name, is_time_series))
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Debug, IntoPoint)]
+pub struct Aggregate {
+    pub id: u64,
+    pub name: String,
+    pub availability_zone: Option<String>,
+    pub hosts: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Aggregates {
+    pub aggregates: Vec<Aggregate>,
+}
+
+impl IntoPoint for Aggregates {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        self.aggregates
+            .iter()
+            .flat_map(|a| a.into_point(name, is_time_series))
+            .collect()
+    }
+}
+
+// Ask nova for uuid hypervisor ids.  Clouds that predate it answer 406
+const COMPUTE_MICROVERSION: &str = "compute 2.53";
+
+impl Collector for Openstack {
+    fn backend(&self) -> &str {
+        "openstack"
+    }
+
+    fn array_id(&self) -> &str {
+        &self.config.endpoint
+    }
+
+    /// Call get_api_token first
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut points = self.get_hypervisors(t)?;
+        points.extend(self.get_aggregates(t)?);
+        Ok(points)
+    }
+}
+
 impl Openstack {
     pub fn new(client: &reqwest::blocking::Client, config: OpenstackConfig) -> Self {
         Openstack {
@@ -336,6 +429,31 @@ impl Openstack {
     }
 
     fn get<T>(&self, api: &str) -> MetricsResult<T>
+    where
+        T: DeserializeOwned + Debug,
+    {
+        self.get_with_headers(api, &[])
+    }
+
+    // GET a compute api with the microversion we understand, falling
+    // back to the base version when nova doesn't support it
+    fn get_compute<T>(&self, api: &str) -> MetricsResult<T>
+    where
+        T: DeserializeOwned + Debug,
+    {
+        match self.get_with_headers(api, &[("OpenStack-API-Version", COMPUTE_MICROVERSION)]) {
+            Err(e) if e.status() == Some(StatusCode::NOT_ACCEPTABLE) => {
+                debug!(
+                    "{} not supported for {}, retrying without it",
+                    COMPUTE_MICROVERSION, api
+                );
+                self.get(api)
+            }
+            res => res,
+        }
+    }
+
+    fn get_with_headers<T>(&self, api: &str, headers: &[(&str, &str)]) -> MetricsResult<T>
     where
         T: DeserializeOwned + Debug,
     {
@@ -346,18 +464,17 @@ impl Openstack {
 
         // This could be more efficient by deserializing immediately but when errors
         // occur it can be really difficult to debug.
-        let res: Result<String, reqwest::Error> = loop {
-            match self
-                .client
-                .get(&url)
-                .header(
-                    HeaderName::from_str("X-Auth-Token")?,
-                    HeaderValue::from_str(&self.config.password)?,
-                )
-                .send()
-            {
+        let res: MetricsResult<String> = loop {
+            let mut req = self.client.get(&url).header(
+                HeaderName::from_str("X-Auth-Token")?,
+                HeaderValue::from_str(&self.config.password)?,
+            );
+            for (name, value) in headers {
+                req = req.header(HeaderName::from_str(name)?, HeaderValue::from_str(value)?);
+            }
+            match req.send() {
                 Ok(status) => match status.error_for_status() {
-                    Ok(s) => break s.text(),
+                    Ok(s) => break read_text(s, self.config.max_response_bytes),
                     Err(e) => match e.status() {
                         Some(reqwest::StatusCode::REQUEST_TIMEOUT) => {}
                         Some(reqwest::StatusCode::GATEWAY_TIMEOUT) => {}
@@ -472,6 +589,26 @@ impl Openstack {
         Ok(volumes.into_point(Some("openstack_volume"), true))
     }
 
+    /// vcpu, memory and local disk usage of every hypervisor
+    pub fn get_hypervisors(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let hypervisors: Hypervisors = self.get_compute("v2.1/os-hypervisors/detail")?;
+        Ok(hypervisors
+            .into_point(Some("openstack_hypervisor"), true)
+            .into_iter()
+            .map(|p| p.set_time(t))
+            .collect())
+    }
+
+    /// Host aggregates and the hosts in them
+    pub fn get_aggregates(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let aggregates: Aggregates = self.get_compute("v2.1/os-aggregates")?;
+        Ok(aggregates
+            .into_point(Some("openstack_aggregate"), true)
+            .into_iter()
+            .map(|p| p.set_time(t))
+            .collect())
+    }
+
     pub fn get_user(&self, user_id: &str) -> MetricsResult<User> {
         let user: UserRoot = self.get(&format!("/v3/users/{}", user_id))?;
         Ok(user.user)
@@ -515,3 +652,132 @@ fn test_get_openstack_user() {
     let i: UserRoot = serde_json::from_str(&buff).unwrap();
     println!("result: {:#?}", i);
 }
+
+#[cfg(test)]
+// The sorted tag and field names of each point
+fn point_schema(points: &[TsPoint]) -> Vec<(Vec<String>, Vec<String>)> {
+    points
+        .iter()
+        .map(|p| {
+            let mut tags: Vec<String> = p.tags.keys().map(|k| k.to_string()).collect();
+            let mut fields: Vec<String> = p.fields.keys().map(|k| k.to_string()).collect();
+            tags.sort();
+            fields.sort();
+            (tags, fields)
+        })
+        .collect()
+}
+
+#[test]
+fn test_hypervisor_microversions() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let load = |path: &str| -> Hypervisors {
+        let mut f = File::open(path).unwrap();
+        let mut buff = String::new();
+        f.read_to_string(&mut buff).unwrap();
+        serde_json::from_str(&buff).unwrap()
+    };
+    let current = load("tests/openstack/hypervisors_detail.json");
+    let legacy = load("tests/openstack/hypervisors_detail_legacy.json");
+    assert_eq!(
+        current.hypervisors[0].id,
+        ComputeId::Uuid("b1e43b5f-eec1-44e0-9f10-7b4945c0226d".into())
+    );
+    assert_eq!(legacy.hypervisors[0].id, ComputeId::Legacy(1));
+    assert_eq!(legacy.hypervisors[1].service.id, ComputeId::Legacy(7));
+
+    let current = current.into_point(Some("openstack_hypervisor"), false);
+    let legacy = legacy.into_point(Some("openstack_hypervisor"), false);
+    assert_eq!(current, legacy);
+    assert_eq!(point_schema(&current), point_schema(&legacy));
+    assert_eq!(
+        current[0].tags["hypervisor_hostname"],
+        TsValue::String("compute-a01.example.net".into())
+    );
+    assert_eq!(current[0].fields["vcpus_used"], TsValue::Long(56));
+    assert_eq!(current[0].fields["memory_mb_used"], TsValue::Long(273920));
+    assert_eq!(current[0].fields["local_gb"], TsValue::Long(1787));
+    assert_eq!(current[1].tags["state"], TsValue::String("down".into()));
+}
+
+#[test]
+fn test_aggregate_microversions() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let load = |path: &str| -> Aggregates {
+        let mut f = File::open(path).unwrap();
+        let mut buff = String::new();
+        f.read_to_string(&mut buff).unwrap();
+        serde_json::from_str(&buff).unwrap()
+    };
+    let current = load("tests/openstack/aggregates.json").into_point(None, false);
+    let legacy = load("tests/openstack/aggregates_legacy.json").into_point(None, false);
+    assert_eq!(current, legacy);
+    assert_eq!(current[0].tags["name"], TsValue::String("ssd-hosts".into()));
+    assert_eq!(
+        current[0].tags["hosts"],
+        TsValue::StringVec(vec!["compute-a01".into(), "compute-a02".into()])
+    );
+    // No availability zone
+    assert!(!current[1].tags.contains_key("availability_zone"));
+}
+
+#[test]
+fn test_compute_microversion_fallback() {
+    use chrono::TimeZone;
+    use std::fs::File;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+
+    let mut legacy = vec![];
+    File::open("tests/openstack/hypervisors_detail_legacy.json")
+        .unwrap()
+        .read_to_end(&mut legacy)
+        .unwrap();
+    let versions: Arc<Mutex<Vec<Option<String>>>> = Arc::new(Mutex::new(vec![]));
+    let seen = versions.clone();
+    // An older nova that rejects the microversion
+    let port = crate::mock_https_server(move |req| {
+        let version = req
+            .head
+            .lines()
+            .find(|l| l.to_lowercase().starts_with("openstack-api-version:"))
+            .map(|l| l.split_once(':').unwrap().1.trim().to_string());
+        seen.lock().unwrap().push(version.clone());
+        assert_eq!(req.path, "/v2.1/os-hypervisors/detail");
+        match version {
+            Some(_) => (406, String::new(), b"{}".to_vec()),
+            None => (200, String::new(), legacy.clone()),
+        }
+    });
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let openstack = Openstack::new(
+        &client,
+        OpenstackConfig {
+            endpoint: "127.0.0.1".into(),
+            port: Some(port),
+            user: "admin".into(),
+            password: "token".into(),
+            domain: "default".into(),
+            project_name: "admin".into(),
+            certificate: None,
+            region: "test".into(),
+            max_response_bytes: None,
+        },
+    );
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let points = openstack.get_hypervisors(t).unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].measurement, "openstack_hypervisor");
+    assert_eq!(points[0].timestamp, Some(t));
+    assert_eq!(
+        *versions.lock().unwrap(),
+        vec![Some("compute 2.53".to_string()), None]
+    );
+}
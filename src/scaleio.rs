@@ -23,22 +23,42 @@
 *
 * SPDX-License-Identifier: Apache-2.0
 */
+use crate::collector::Collector;
+use crate::credentials::{with_credential, CredentialsProvider, StaticCredentials};
+use crate::decode_json;
 use crate::deserialize_string_or_int;
-use crate::error::{MetricsResult, StorageError};
+use crate::error::{redact_url, MetricsResult, StorageError};
+use crate::ir::filter::FieldFilter;
 use crate::ir::{TsPoint, TsValue};
-use crate::IntoPoint;
-
-use std::collections::HashMap;
+use crate::{
+    read_bytes, read_limited, read_text, require_config_value, validate_endpoint, with_retry,
+    ChildPoint, ClientBuilderOptions, IntoPoint, LogPrefix, RetryPolicy,
+    DEFAULT_MAX_RESPONSE_BYTES,
+};
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 use std::fmt::Debug;
+#[cfg(test)]
+use std::io::Read;
 use std::net::IpAddr;
 use std::str;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use chrono::offset::Utc;
-use chrono::DateTime;
-use log::{debug, trace};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone};
+use flate2::read::GzDecoder;
+use log::{debug, error, info, trace, warn};
 use nom::IResult;
-use reqwest::header::CONTENT_TYPE;
-use serde::de::DeserializeOwned;
+use rayon::prelude::*;
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE};
+use serde::de::{Deserialize, DeserializeOwned};
+use serde::ser::Serialize;
+use serde::Deserializer;
+#[cfg(test)]
+use serde_json::json;
 
 #[derive(Clone, Deserialize, Debug)]
 pub struct ScaleioConfig {
@@ -56,11 +76,236 @@ pub struct ScaleioConfig {
     pub bandwidth_limit: Option<u64>,
     /// iops limit for new volumes in this cluster
     pub iops_limit: Option<u64>,
+    /// Properties to request for drive statistics.  Defaults to
+    /// DRIVE_STATS_PROPERTIES
+    pub drive_stats_properties: Option<Vec<StatsProperty>>,
+    /// Properties to request for sdc statistics.  Defaults to
+    /// SDC_STATS_PROPERTIES
+    pub sdc_stats_properties: Option<Vec<StatsProperty>>,
+    /// Properties to request for storage pool statistics.  Defaults to
+    /// POOL_STATS_PROPERTIES, or POOL_STATS_PROPERTIES_V2 on 2.x clusters
+    pub pool_stats_properties: Option<Vec<StatsProperty>>,
+    /// Properties to request for volume statistics.  Defaults to
+    /// VOLUME_STATS_PROPERTIES
+    pub volume_stats_properties: Option<Vec<StatsProperty>>,
+    /// Only emit point fields whose names match one of these globs, ie
+    /// `*_bwc`.  Defaults to every field
+    pub emit_fields_allow: Option<Vec<String>>,
+    /// Never emit point fields whose names match one of these globs
+    pub emit_fields_deny: Option<Vec<String>>,
+    /// Optional user for the provisioning actions, ie create_volume and
+    /// map_volumes, so collection can run with a reporting only role.
+    /// Defaults to user.  `Scaleio::with_management_credentials` takes it
+    /// from a CredentialsProvider instead
+    pub management_user: Option<String>,
+    pub management_password: Option<String>,
+    /// Skip certificate verification for clients built by
+    /// `Scaleio::from_config`
+    pub accept_invalid_certs: Option<bool>,
+    /// Check the gateway certificate names the endpoint host.  Turn this
+    /// off when the gateway is reached by IP but trusted through
+    /// `certificate`.  Defaults to true
+    #[serde(default = "default_verify_hostname")]
+    pub verify_hostname: bool,
+    /// How many SDS's `get_all_sds_statistics` queries at once.  Defaults
+    /// to 1
+    pub sds_stats_concurrency: Option<usize>,
+    /// Largest response body to read, in bytes, before failing the request.
+    /// Applies to the inflated body of a gzip response too.  Defaults to
+    /// `DEFAULT_MAX_RESPONSE_BYTES`
+    pub max_response_bytes: Option<u64>,
+    /// Log the fields of the statistics responses the structs don't know,
+    /// see `UnknownFields`.  Defaults to false
+    pub log_unknown_fields: Option<bool>,
+}
+
+fn default_verify_hostname() -> bool {
+    true
+}
+
+impl Default for ScaleioConfig {
+    fn default() -> Self {
+        ScaleioConfig {
+            endpoint: String::new(),
+            user: String::new(),
+            password: String::new(),
+            certificate: None,
+            region: String::new(),
+            bandwidth_limit: None,
+            iops_limit: None,
+            drive_stats_properties: None,
+            sdc_stats_properties: None,
+            pool_stats_properties: None,
+            volume_stats_properties: None,
+            emit_fields_allow: None,
+            emit_fields_deny: None,
+            management_user: None,
+            management_password: None,
+            accept_invalid_certs: None,
+            verify_hostname: default_verify_hostname(),
+            sds_stats_concurrency: None,
+            max_response_bytes: None,
+            log_unknown_fields: None,
+        }
+    }
+}
+
+impl ScaleioConfig {
+    pub fn log_prefix(&self) -> LogPrefix<'_> {
+        LogPrefix {
+            endpoint: &self.endpoint,
+            region: &self.region,
+        }
+    }
+
+    /// Check the endpoint is a host or host:port and the credentials are
+    /// set.  Called by `Scaleio::new` before logging in
+    pub fn validate(&self) -> MetricsResult<()> {
+        validate_endpoint(&self.endpoint)
+            .and_then(|_| require_config_value("user", &self.user))
+            .and_then(|_| require_config_value("password", &self.password))
+            .and_then(
+                |_| match (&self.management_user, &self.management_password) {
+                    (Some(user), Some(password)) => require_config_value("management_user", user)
+                        .and_then(|_| require_config_value("management_password", password)),
+                    (None, None) => Ok(()),
+                    _ => Err(StorageError::new(
+                        "management_user and management_password must be set together".into(),
+                    )),
+                },
+            )
+            .and_then(|_| self.client_options().validate())
+            .map_err(|e| e.with_context("scaleio config"))
+    }
+
+    /// The tls settings of this config for building a client
+    pub fn client_options(&self) -> ClientBuilderOptions {
+        ClientBuilderOptions {
+            accept_invalid_certs: self.accept_invalid_certs.unwrap_or(false),
+            root_certificate: self.certificate.clone(),
+            verify_hostname: self.verify_hostname,
+            ..Default::default()
+        }
+    }
+
+    /// The filter built from emit_fields_allow and emit_fields_deny
+    pub fn field_filter(&self) -> FieldFilter {
+        FieldFilter::new(
+            self.emit_fields_allow.clone(),
+            self.emit_fields_deny.clone(),
+        )
+    }
+}
+
+#[test]
+fn test_scaleio_config_validate() {
+    let config = || ScaleioConfig {
+        endpoint: "10.0.0.1:443".into(),
+        user: "admin".into(),
+        password: "secret".into(),
+        region: "test".into(),
+        ..Default::default()
+    };
+    assert!(config().validate().is_ok());
+
+    let mut c = config();
+    c.endpoint = String::new();
+    let err = c.validate().unwrap_err().to_string();
+    assert!(err.contains("endpoint must not be empty"), "{}", err);
+    assert!(err.contains("scaleio config"), "{}", err);
+
+    let mut c = config();
+    c.endpoint = "https://10.0.0.1/api".into();
+    assert!(c.validate().unwrap_err().to_string().contains("scheme"));
+
+    let mut c = config();
+    c.user = String::new();
+    let err = c.validate().unwrap_err().to_string();
+    assert!(err.contains("user must not be empty"), "{}", err);
+
+    let mut c = config();
+    c.password = " ".into();
+    let err = c.validate().unwrap_err().to_string();
+    assert!(err.contains("password must not be empty"), "{}", err);
+
+    let mut c = config();
+    c.management_user = Some("operator".into());
+    let err = c.validate().unwrap_err().to_string();
+    assert!(err.contains("must be set together"), "{}", err);
+    c.management_password = Some("opsecret".into());
+    assert!(c.validate().is_ok());
+
+    // Skipping hostname checks needs a root certificate or an explicit
+    // accept_invalid_certs
+    for (certificate, accept_invalid_certs, valid) in &[
+        (None, None, false),
+        (None, Some(false), false),
+        (None, Some(true), true),
+        (Some("tests/vnx/mock_certificate.der"), None, true),
+    ] {
+        let mut c = config();
+        c.verify_hostname = false;
+        c.certificate = certificate.map(String::from);
+        c.accept_invalid_certs = *accept_invalid_certs;
+        assert_eq!(c.validate().is_ok(), *valid, "{:?}", c);
+    }
+    let mut c = config();
+    c.verify_hostname = false;
+    let err = c.validate().unwrap_err().to_string();
+    assert!(err.contains("verify_hostname"), "{}", err);
+    assert!(err.contains("scaleio config"), "{}", err);
+
+    // Rejected before any request is made
+    let client = reqwest::blocking::Client::new();
+    let mut c = config();
+    c.endpoint = "10.0.0.1:0".into();
+    let err = Scaleio::new(&client, c).err().unwrap().to_string();
+    assert!(err.contains("invalid port"), "{}", err);
 }
 
+/// Measurements that used to be written under another name, keyed by
+/// the old name.  SystemStatistics defaulted to scaleio_sys_stat while
+/// get_system_stats wrote scaleio_sys_stats, so history can be under
+/// either.  Use this to rewrite queries against the old names
+pub fn migrate_measurement_names() -> HashMap<&'static str, &'static str> {
+    let mut names = HashMap::new();
+    names.insert("scaleio_sys_stat", SystemStatistics::MEASUREMENT);
+    names
+}
+
+/// Fields that used to be written under another name, keyed by the old
+/// name.  The certificate expiry was briefly written as both
+/// certificate_days_until_expiry and certificate_days_remaining, only the
+/// latter is written now
+pub fn migrate_field_names() -> HashMap<&'static str, &'static str> {
+    let mut names = HashMap::new();
+    names.insert(
+        "certificate_days_until_expiry",
+        "certificate_days_remaining",
+    );
+    names
+}
+
+/// Clones share the api token obtained at login
+#[derive(Clone)]
 pub struct Scaleio {
     client: reqwest::blocking::Client,
     config: ScaleioConfig,
+    credentials: Arc<dyn CredentialsProvider>,
+    dry_run: bool,
+    // Logs in the user for provisioning actions, if it differs from the
+    // main one
+    management_credentials: Option<Arc<dyn CredentialsProvider>>,
+    // The management user and its api token
+    management_token: Option<(String, String)>,
+    // For querySelectedStatistics requests
+    stats_retry: RetryPolicy,
+    // Ask for gzip compressed responses
+    gzip: bool,
+    // Shared by clones so the totals cover a whole collection
+    transfers: Arc<TransferTotals>,
+    // Queries the SDS statistics, None to query them one at a time
+    sds_pool: Option<Arc<rayon::ThreadPool>>,
 }
 
 #[test]
@@ -96,7 +341,7 @@ pub struct SystemConfig {
 }
 
 // BWC=Bandwidth Calculation
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Default, Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BWC {
     pub total_weight_in_kb: u64,
@@ -115,11 +360,10 @@ impl BWC {
             .checked_div(self.num_seconds)
             .unwrap_or(0)
     }
-    // Calculate IO Size or Latency: totalWeightIn* / numOccured
-    fn iosize_or_latency(self) -> u64 {
-        self.total_weight_in_kb
-            .checked_div(self.num_occured)
-            .unwrap_or(0)
+    // Calculate IO Size or Latency: totalWeightIn* / numOccured.  None
+    // when nothing occured rather than a 0 that looks like a measurement
+    fn iosize_or_latency(&self) -> Option<u64> {
+        self.total_weight_in_kb.checked_div(self.num_occured)
     }
     // Calculate the average kb/s from the fields
     fn average(&self) -> u64 {
@@ -131,7 +375,241 @@ impl BWC {
     }
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[test]
+fn test_bwc_latency() {
+    #[derive(IntoPoint)]
+    struct Meters {
+        #[point(latency)]
+        read_latency: BWC,
+        #[point(latency)]
+        write_latency: Option<BWC>,
+    }
+    let bwc = |total_weight_in_kb, num_occured| BWC {
+        total_weight_in_kb,
+        num_occured,
+        num_seconds: 5,
+    };
+
+    let busy = Meters {
+        read_latency: bwc(600_000, 1200),
+        write_latency: Some(bwc(240_000, 300)),
+    }
+    .into_point(None, true);
+    let fields = &busy[0].fields;
+    assert_eq!(fields["read_latency"], TsValue::Long(100));
+    assert_eq!(fields["read_latency_microsec"], TsValue::Long(500));
+    assert_eq!(fields["read_latency_num_occured"], TsValue::Long(1200));
+    assert_eq!(fields["write_latency"], TsValue::Long(160));
+    assert_eq!(fields["write_latency_microsec"], TsValue::Long(800));
+
+    // Without any io there's no latency to report, only the raw counters
+    let idle = Meters {
+        read_latency: bwc(0, 0),
+        write_latency: Some(bwc(0, 0)),
+    }
+    .into_point(None, true);
+    let fields = &idle[0].fields;
+    assert_eq!(bwc(0, 0).iosize_or_latency(), None);
+    assert!(!fields.contains_key("read_latency"));
+    assert!(!fields.contains_key("read_latency_microsec"));
+    assert_eq!(fields["read_latency_num_occured"], TsValue::Long(0));
+    assert!(!fields.contains_key("write_latency"));
+    assert!(!fields.contains_key("write_latency_microsec"));
+}
+
+#[test]
+fn test_into_point_supported_types() {
+    use std::borrow::Cow;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    // Every type the derive knows, bare and optional.  Add new types to
+    // both halves and to the expected fields or tags below
+    #[derive(IntoPoint)]
+    struct Everything {
+        bwc: BWC,
+        string: String,
+        host: HostAddress,
+        duration: Duration,
+        signed_short: i16,
+        integer: i32,
+        signed_long: i64,
+        uuid: Uuid,
+        byte: u8,
+        short: u16,
+        long: u64,
+        float: f64,
+        boolean: bool,
+        strings: Vec<String>,
+        longs: Vec<u64>,
+        uuids: Vec<Uuid>,
+        opt_bwc: Option<BWC>,
+        opt_string: Option<String>,
+        opt_host: Option<HostAddress>,
+        opt_duration: Option<Duration>,
+        opt_signed_short: Option<i16>,
+        opt_integer: Option<i32>,
+        opt_signed_long: Option<i64>,
+        opt_uuid: Option<Uuid>,
+        opt_byte: Option<u8>,
+        opt_short: Option<u16>,
+        opt_long: Option<u64>,
+        opt_float: Option<f64>,
+        opt_boolean: Option<bool>,
+        opt_strings: Option<Vec<String>>,
+        opt_longs: Option<Vec<u64>>,
+        opt_uuids: Option<Vec<Uuid>>,
+        unset: Option<u64>,
+        empty_string: String,
+    }
+
+    let bwc = || BWC {
+        total_weight_in_kb: 40,
+        num_occured: 4,
+        num_seconds: 2,
+    };
+    let host = || HostAddress::Ip("10.0.0.1".parse().unwrap());
+    let id = Uuid::parse_str("6c4d4f6a-7e6b-4a62-9f1e-2a3b4c5d6e7f").unwrap();
+    let e = Everything {
+        bwc: bwc(),
+        string: "s".into(),
+        host: host(),
+        duration: Duration::from_secs(3),
+        signed_short: -2,
+        integer: -3,
+        signed_long: -4,
+        uuid: id,
+        byte: 5,
+        short: 6,
+        long: 7,
+        float: 8.5,
+        boolean: true,
+        strings: vec!["a".into()],
+        longs: vec![9],
+        uuids: vec![id],
+        opt_bwc: Some(bwc()),
+        opt_string: Some("s".into()),
+        opt_host: Some(host()),
+        opt_duration: Some(Duration::from_secs(3)),
+        opt_signed_short: Some(-2),
+        opt_integer: Some(-3),
+        opt_signed_long: Some(-4),
+        opt_uuid: Some(id),
+        opt_byte: Some(5),
+        opt_short: Some(6),
+        opt_long: Some(7),
+        opt_float: Some(8.5),
+        opt_boolean: Some(true),
+        opt_strings: Some(vec!["a".into()]),
+        opt_longs: Some(vec![9]),
+        opt_uuids: Some(vec![id]),
+        unset: None,
+        empty_string: String::new(),
+    };
+    let p = &e.into_point(Some("everything"), true)[0];
+
+    let expect = |values: Vec<(&'static str, TsValue)>| {
+        values
+            .into_iter()
+            .map(|(k, v)| (Cow::Borrowed(k), v))
+            .collect::<HashMap<Cow<'static, str>, TsValue>>()
+    };
+    let uuid = || TsValue::String(id.to_string());
+    assert_eq!(
+        p.tags,
+        expect(vec![
+            ("string", TsValue::String("s".into())),
+            ("host", TsValue::String("10.0.0.1".into())),
+            ("strings", TsValue::StringVec(vec!["a".into()])),
+            ("longs", TsValue::LongVec(vec![9])),
+            ("uuids", TsValue::StringVec(vec![id.to_string()])),
+            ("opt_string", TsValue::String("s".into())),
+            ("opt_host", TsValue::String("10.0.0.1".into())),
+        ])
+    );
+    assert_eq!(
+        p.fields,
+        expect(vec![
+            ("bwc", TsValue::Long(bwc().average())),
+            ("bwc_total_weight_in_kb", TsValue::Long(40)),
+            ("bwc_num_seconds", TsValue::Long(2)),
+            ("bwc_num_occured", TsValue::Long(4)),
+            ("duration", TsValue::Duration(Duration::from_secs(3))),
+            ("signed_short", TsValue::Integer(-2)),
+            ("integer", TsValue::Integer(-3)),
+            ("signed_long", TsValue::SignedLong(-4)),
+            ("uuid", uuid()),
+            ("byte", TsValue::Byte(5)),
+            ("short", TsValue::Short(6)),
+            ("long", TsValue::Long(7)),
+            ("float", TsValue::Float(8.5)),
+            ("boolean", TsValue::Boolean(true)),
+            // Optional BWCs only carry the average
+            ("opt_bwc", TsValue::Long(bwc().average())),
+            ("opt_duration", TsValue::Duration(Duration::from_secs(3))),
+            ("opt_signed_short", TsValue::Integer(-2)),
+            ("opt_integer", TsValue::Integer(-3)),
+            ("opt_signed_long", TsValue::SignedLong(-4)),
+            ("opt_uuid", uuid()),
+            ("opt_byte", TsValue::Byte(5)),
+            ("opt_short", TsValue::Short(6)),
+            ("opt_long", TsValue::Long(7)),
+            ("opt_float", TsValue::Float(8.5)),
+            ("opt_boolean", TsValue::Boolean(true)),
+            // Optional vecs are fields
+            ("opt_strings", TsValue::StringVec(vec!["a".into()])),
+            ("opt_longs", TsValue::LongVec(vec![9])),
+            ("opt_uuids", TsValue::StringVec(vec![id.to_string()])),
+        ])
+    );
+}
+
+#[test]
+fn test_into_point_serde_names() {
+    #[derive(Deserialize, IntoPoint)]
+    #[serde(rename_all = "camelCase")]
+    #[point(serde_names)]
+    struct ApiVolume {
+        volume_name: String,
+        size_in_kb: u64,
+        thin_capacity_in_kb: Option<u64>,
+        #[serde(rename = "IOPS")]
+        iops: f64,
+        user_data_read_bwc: BWC,
+    }
+
+    #[derive(Deserialize, IntoPoint)]
+    #[serde(rename_all = "camelCase")]
+    struct RustVolume {
+        volume_name: String,
+        size_in_kb: u64,
+    }
+
+    let json = r#"{
+        "volumeName": "vol1",
+        "sizeInKb": 1024,
+        "thinCapacityInKb": 512,
+        "IOPS": 2.5,
+        "userDataReadBwc": {"totalWeightInKb": 10, "numOccured": 5, "numSeconds": 1}
+    }"#;
+    let v: ApiVolume = serde_json::from_str(json).unwrap();
+    let p = &v.into_point(Some("volume"), true)[0];
+    assert_eq!(p.tags["volumeName"], TsValue::String("vol1".into()));
+    assert_eq!(p.fields["sizeInKb"], TsValue::Long(1024));
+    assert_eq!(p.fields["thinCapacityInKb"], TsValue::Long(512));
+    assert_eq!(p.fields["IOPS"], TsValue::Float(2.5));
+    assert!(p.fields.contains_key("userDataReadBwc"));
+    assert!(p.fields.contains_key("userDataReadBwc_num_occured"));
+    assert!(!p.fields.contains_key("size_in_kb"));
+
+    // Without the option the rust names are kept
+    let v: RustVolume = serde_json::from_str(json).unwrap();
+    let p: &TsPoint = &v.into_point(Some("volume"), true)[0];
+    assert_eq!(p.tags["volume_name"], TsValue::String("vol1".into()));
+    assert_eq!(p.fields["size_in_kb"], TsValue::Long(1024));
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CertificateInfo {
     subject: String,
@@ -143,10 +621,268 @@ pub struct CertificateInfo {
     valid_to_asn1_format: String,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// Parse an asn1 UTCTime (YYMMDDHHMMSSZ) or GeneralizedTime
+/// (YYYYMMDDHHMMSSZ) timestamp
+fn parse_asn1_time(s: &str) -> Option<DateTime<Utc>> {
+    let digits = s.trim().strip_suffix('Z')?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| digits[range].parse::<u32>().ok();
+    let (year, rest) = match digits.len() {
+        14 => (num(0..4)? as i32, 4),
+        // RFC 5280 puts two digit years 50 and up in the 1900s
+        12 => match num(0..2)? as i32 {
+            yy if yy >= 50 => (1900 + yy, 2),
+            yy => (2000 + yy, 2),
+        },
+        _ => return None,
+    };
+    let date = NaiveDate::from_ymd_opt(year, num(rest..rest + 2)?, num(rest + 2..rest + 4)?)?;
+    let time = date.and_hms_opt(
+        num(rest + 4..rest + 6)?,
+        num(rest + 6..rest + 8)?,
+        num(rest + 8..rest + 10)?,
+    )?;
+    Some(Utc.from_utc_datetime(&time))
+}
+
+/// Parse the human readable openssl date format, `Jun  8 12:00:00 2020 GMT`
+fn parse_readable_time(s: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(s.trim(), "%b %e %H:%M:%S %Y GMT")
+        .ok()
+        .map(|n| Utc.from_utc_datetime(&n))
+}
+
+#[test]
+fn test_parse_asn1_time() {
+    assert_eq!(
+        parse_asn1_time("20200608120000Z"),
+        Some(Utc.with_ymd_and_hms(2020, 6, 8, 12, 0, 0).unwrap())
+    );
+    assert_eq!(
+        parse_asn1_time("491231235959Z"),
+        Some(Utc.with_ymd_and_hms(2049, 12, 31, 23, 59, 59).unwrap())
+    );
+    assert_eq!(
+        parse_asn1_time("991112191722Z"),
+        Some(Utc.with_ymd_and_hms(1999, 11, 12, 19, 17, 22).unwrap())
+    );
+    assert_eq!(parse_asn1_time("20200608120000"), None);
+    assert_eq!(parse_asn1_time("20201308120000Z"), None);
+    assert_eq!(parse_asn1_time("2020-06-08Z"), None);
+    assert_eq!(parse_asn1_time(""), None);
+
+    assert_eq!(
+        parse_readable_time("Jun  8 12:00:00 2020 GMT"),
+        Some(Utc.with_ymd_and_hms(2020, 6, 8, 12, 0, 0).unwrap())
+    );
+    assert_eq!(
+        parse_readable_time("Nov 12 19:17:22 2019 GMT"),
+        Some(Utc.with_ymd_and_hms(2019, 11, 12, 19, 17, 22).unwrap())
+    );
+    assert_eq!(parse_readable_time("N/A"), None);
+}
+
+impl CertificateInfo {
+    pub const MEASUREMENT: &str = "scaleio_certificate";
+
+    /// When this certificate expires.  The asn1 formatted date is preferred
+    /// with the human readable one as a fallback
+    pub fn expires(&self) -> Option<DateTime<Utc>> {
+        let expires = parse_asn1_time(&self.valid_to_asn1_format)
+            .or_else(|| parse_readable_time(&self.valid_to));
+        if expires.is_none() {
+            debug!(
+                "Unable to parse certificate expiry {} / {}",
+                self.valid_to_asn1_format, self.valid_to
+            );
+        }
+        expires
+    }
+
+    /// Whole days remaining until this certificate expires.  This goes
+    /// negative once the certificate has expired
+    pub fn days_until_expiry(&self, now: DateTime<Utc>) -> Option<i64> {
+        self.expires().map(|expires| (expires - now).num_days())
+    }
+}
+
+impl ChildPoint for CertificateInfo {
+    fn sub_point(&self, p: &mut TsPoint) {
+        p.add_field("certificate_subject", TsValue::String(self.subject.clone()));
+        p.add_field("certificate_issuer", TsValue::String(self.issuer.clone()));
+        p.add_field(
+            "certificate_validfrom",
+            TsValue::String(self.valid_from.clone()),
+        );
+        p.add_field(
+            "certificate_validto",
+            TsValue::String(self.valid_to.clone()),
+        );
+        p.add_field(
+            "certificate_thumbprint",
+            TsValue::String(self.thumbprint.clone()),
+        );
+        p.add_field(
+            "certificate_validfrom_asn",
+            TsValue::String(self.valid_from_asn1_format.clone()),
+        );
+        p.add_field(
+            "certificate_validto_asn",
+            TsValue::String(self.valid_to_asn1_format.clone()),
+        );
+        // Days are counted from when the point was collected
+        let now = p.timestamp.unwrap_or_else(Utc::now);
+        match self.days_until_expiry(now) {
+            Some(days) => {
+                p.add_field("certificate_days_remaining", TsValue::SignedLong(days));
+                p.add_field("certificate_parse_error", TsValue::Boolean(false));
+            }
+            None => {
+                p.add_field("certificate_parse_error", TsValue::Boolean(true));
+            }
+        }
+    }
+}
+
+impl IntoPoint for CertificateInfo {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or(Self::MEASUREMENT), is_time_series);
+        self.sub_point(&mut p);
+        vec![p]
+    }
+}
+
+#[test]
+fn test_certificate_expiry() {
+    use chrono::Duration;
+
+    let now = Utc::now();
+    let cert = |expires: DateTime<Utc>| CertificateInfo {
+        subject: "/GN=MDM/CN=sds1".into(),
+        issuer: "/GN=MDM/CN=mdm1".into(),
+        valid_from: "Nov 12 19:17:22 2019 GMT".into(),
+        valid_to: expires.format("%b %e %H:%M:%S %Y GMT").to_string(),
+        thumbprint: "AA:BB:CC".into(),
+        valid_from_asn1_format: "191112191722Z".into(),
+        valid_to_asn1_format: expires.format("%y%m%d%H%M%SZ").to_string(),
+    };
+
+    // Expires in a few days
+    let near = cert(now + Duration::days(5) + Duration::hours(1));
+    assert_eq!(near.days_until_expiry(now), Some(5));
+    let points = near.into_point(None, true);
+    assert_eq!(points[0].measurement, "scaleio_certificate");
+    assert_eq!(
+        points[0].fields.get("certificate_days_remaining"),
+        Some(&TsValue::SignedLong(5))
+    );
+
+    // Already expired
+    let expired = cert(now - Duration::days(3) - Duration::hours(1));
+    assert_eq!(expired.days_until_expiry(now), Some(-3));
+
+    // Falls back to the human readable date
+    let mut readable = cert(now + Duration::days(30) + Duration::hours(1));
+    readable.valid_to_asn1_format = String::new();
+    assert_eq!(readable.days_until_expiry(now), Some(30));
+
+    // Unparseable dates skip the field
+    let mut garbage = cert(now);
+    garbage.valid_to = "sometime".into();
+    garbage.valid_to_asn1_format = "soon".into();
+    assert_eq!(garbage.days_until_expiry(now), None);
+    let points = garbage.into_point(None, true);
+    assert!(!points[0].fields.contains_key("certificate_days_remaining"));
+}
+
+#[test]
+fn test_certificate_days_remaining() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let load = |name: &str| -> CertificateInfo {
+        let mut f = File::open(format!("tests/scaleio/{}", name)).unwrap();
+        let mut buff = String::new();
+        f.read_to_string(&mut buff).unwrap();
+        serde_json::from_str(&buff).unwrap()
+    };
+    let collected = Utc.with_ymd_and_hms(2020, 6, 1, 0, 0, 0).unwrap();
+    let point = |cert: &CertificateInfo| {
+        let mut p = TsPoint::new("scaleio_sds", true);
+        p.timestamp = Some(collected);
+        cert.sub_point(&mut p);
+        p
+    };
+
+    let p = point(&load("certificate_expired.json"));
+    assert_eq!(
+        p.fields.get("certificate_days_remaining"),
+        Some(&TsValue::SignedLong(-30))
+    );
+    assert_eq!(
+        p.fields.get("certificate_parse_error"),
+        Some(&TsValue::Boolean(false))
+    );
+
+    let p = point(&load("certificate_expiring.json"));
+    assert_eq!(
+        p.fields.get("certificate_days_remaining"),
+        Some(&TsValue::SignedLong(7))
+    );
+
+    // The raw strings are still reported alongside the parse error
+    let p = point(&load("certificate_malformed.json"));
+    assert!(!p.fields.contains_key("certificate_days_remaining"));
+    assert_eq!(
+        p.fields.get("certificate_parse_error"),
+        Some(&TsValue::Boolean(true))
+    );
+    assert_eq!(
+        p.fields.get("certificate_validto_asn"),
+        Some(&TsValue::String("2020-06-08".into()))
+    );
+
+    // SdsObject counts from the collection time it's given
+    let mut f = File::open("tests/scaleio/sdsObject.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+    let mut sds: serde_json::Value = serde_json::from_str(&buff).unwrap();
+    sds["certificateInfo"] = serde_json::to_value(load("certificate_expiring.json")).unwrap();
+    let sds: SdsObject = serde_json::from_value(sds).unwrap();
+    let points = sds.into_point_at(None, collected);
+    assert_eq!(points[0].timestamp, Some(collected));
+    assert_eq!(
+        points[0].fields.get("certificate_days_remaining"),
+        Some(&TsValue::SignedLong(7))
+    );
+    assert!(!points[0]
+        .fields
+        .contains_key("certificate_days_until_expiry"));
+
+    // And so does get_sds_objects, whatever the time of the request
+    let body = serde_json::to_vec(&vec![sds]).unwrap();
+    let (port, _) = mock_scaleio_api(false, move |_: &str, _: &str| body.clone());
+    let points = mock_scaleio(port).get_sds_objects(collected).unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].timestamp, Some(collected));
+    assert_eq!(
+        points[0].fields.get("certificate_days_remaining"),
+        Some(&TsValue::SignedLong(7))
+    );
+    assert_eq!(
+        migrate_field_names().get("certificate_days_until_expiry"),
+        Some(&"certificate_days_remaining")
+    );
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
 #[serde(untagged, rename_all = "camelCase")]
 pub enum ThinCapacityAllocatedInKb {
+    #[serde(rename_all = "camelCase")]
     Km { thin_capacity_allocated_in_km: u64 },
+    #[serde(rename_all = "camelCase")]
     Kb { thin_capacity_allocated_in_kb: u64 },
 }
 
@@ -163,7 +899,47 @@ impl ThinCapacityAllocatedInKb {
     }
 }
 
-#[derive(Clone, Deserialize, Debug, IntoPoint)]
+/// Adapter for `#[point(flatten_with)]` that emits the thin capacity
+/// allocated as thin_capacity_allocated_in_kb regardless of which key
+/// the server used
+pub fn thin_capacity_allocated_point(
+    thin_capacity: &Option<ThinCapacityAllocatedInKb>,
+    p: &mut TsPoint,
+) {
+    if let Some(ref thin_capacity) = thin_capacity {
+        p.add_field(
+            "thin_capacity_allocated_in_kb",
+            TsValue::Long(thin_capacity.clone().get_thin_capacity_allocated()),
+        );
+    }
+}
+
+#[test]
+fn test_oscillating_counter_window_thin_capacity() {
+    let km: OscillatingCounterWindow =
+        serde_json::from_str(r#"{"threshold": 1, "thinCapacityAllocatedInKm": 1024}"#).unwrap();
+    let points = km.into_point(Some("scaleio_window"), true);
+    assert_eq!(
+        points[0].fields.get("thin_capacity_allocated_in_kb"),
+        Some(&TsValue::Long(1024))
+    );
+
+    let kb: OscillatingCounterWindow =
+        serde_json::from_str(r#"{"threshold": 1, "thinCapacityAllocatedInKb": 2048}"#).unwrap();
+    let points = kb.into_point(Some("scaleio_window"), true);
+    assert_eq!(
+        points[0].fields.get("thin_capacity_allocated_in_kb"),
+        Some(&TsValue::Long(2048))
+    );
+
+    let neither: OscillatingCounterWindow = serde_json::from_str(r#"{"threshold": 1}"#).unwrap();
+    let points = neither.into_point(Some("scaleio_window"), true);
+    assert!(!points[0]
+        .fields
+        .contains_key("thin_capacity_allocated_in_kb"));
+}
+
+#[derive(Clone, Deserialize, Debug, IntoPoint, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OscillatingCounterWindow {
     pub threshold: Option<i64>,
@@ -219,6 +995,7 @@ pub struct OscillatingCounterWindow {
     pub background_scan_compare_count: Option<u64>,
     pub background_scanned_in_mb: Option<u64>,
     #[serde(flatten)]
+    #[point(flatten_with = "thin_capacity_allocated_point")]
     pub thin_capacity_allocated_in_km: Option<ThinCapacityAllocatedInKb>, //This value is optional
     pub rm_pending_allocated_in_kb: Option<u64>,
     pub semi_protected_vac_in_kb: Option<u64>,
@@ -263,7 +1040,7 @@ pub struct OscillatingCounterWindow {
     pub rfcache_write_pending: Option<u64>,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FailureCounter {
     pub short_window: Window,
@@ -271,6 +1048,144 @@ pub struct FailureCounter {
     pub long_window: Option<Window>,
 }
 
+impl FailureCounter {
+    fn windows(&self) -> [(&'static str, Option<&Window>); 3] {
+        [
+            ("short_window", Some(&self.short_window)),
+            ("medium_window", self.medium_window.as_ref()),
+            ("long_window", self.long_window.as_ref()),
+        ]
+    }
+
+    /// Add the threshold and window size of each window as
+    /// `{prefix}_{window}_threshold` and `{prefix}_{window}_window_size_in_sec`
+    pub fn add_fields(&self, prefix: &str, p: &mut TsPoint) {
+        for (name, window) in self.windows().iter() {
+            if let Some(window) = window {
+                window.add_fields(&format!("{}_{}", prefix, name), p);
+            }
+        }
+    }
+}
+
+impl ChildPoint for FailureCounter {
+    fn sub_point(&self, p: &mut TsPoint) {
+        for (name, window) in self.windows().iter() {
+            if let Some(window) = window {
+                window.add_fields(name, p);
+            }
+        }
+    }
+}
+
+impl IntoPoint for FailureCounter {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("scaleio_failure_counter"), is_time_series);
+        self.sub_point(&mut p);
+        vec![p]
+    }
+}
+
+#[test]
+fn test_failure_counter_fields() {
+    let counter: FailureCounter = serde_json::from_str(
+        r#"{
+            "shortWindow": {"windowSizeInSec": 60, "threshold": 300},
+            "mediumWindow": {"windowSizeInSec": 3600, "threshold": 500},
+            "longWindow": {"windowSizeInSec": 86400, "threshold": 700}
+        }"#,
+    )
+    .unwrap();
+    let mut p = TsPoint::new("scaleio_system", true);
+    counter.add_fields("sdc_mdm_disc", &mut p);
+    let mut fields: Vec<&str> = p.field_keys().collect();
+    fields.sort();
+    assert_eq!(
+        fields,
+        vec![
+            "sdc_mdm_disc_long_window_threshold",
+            "sdc_mdm_disc_long_window_window_size_in_sec",
+            "sdc_mdm_disc_medium_window_threshold",
+            "sdc_mdm_disc_medium_window_window_size_in_sec",
+            "sdc_mdm_disc_short_window_threshold",
+            "sdc_mdm_disc_short_window_window_size_in_sec",
+        ]
+    );
+    assert_eq!(
+        p.fields["sdc_mdm_disc_medium_window_threshold"],
+        TsValue::Long(500)
+    );
+
+    let counter: FailureCounter =
+        serde_json::from_str(r#"{"shortWindow": {"windowSizeInSec": 60, "threshold": 300}}"#)
+            .unwrap();
+    let points = counter.into_point(None, true);
+    let mut fields: Vec<&str> = points[0].field_keys().collect();
+    fields.sort();
+    assert_eq!(
+        fields,
+        vec!["short_window_threshold", "short_window_window_size_in_sec"]
+    );
+
+    let points = counter.short_window.into_point(Some("window"), true);
+    assert_eq!(points[0].fields["threshold"], TsValue::Long(300));
+    assert_eq!(points[0].fields["window_size_in_sec"], TsValue::Long(60));
+}
+
+/// Adapter for `#[point(extend_with)]` that emits the oscillating failure
+/// counter parameters of the system
+pub fn system_failure_counters_point(system: &System, p: &mut TsPoint) {
+    let counters = [
+        (
+            "sdc_mdm_network_disconnections",
+            &system.sdc_mdm_network_disconnections_counter_parameters,
+        ),
+        (
+            "sdc_sds_network_disconnections",
+            &system.sdc_sds_network_disconnections_counter_parameters,
+        ),
+        (
+            "sdc_memory_allocation_failures",
+            &system.sdc_memory_allocation_failures_counter_parameters,
+        ),
+        (
+            "sdc_socket_allocation_failures",
+            &system.sdc_socket_allocation_failures_counter_parameters,
+        ),
+        (
+            "sdc_long_operations",
+            &system.sdc_long_operations_counter_parameters,
+        ),
+    ];
+    for (prefix, counter) in counters.iter() {
+        counter.add_fields(prefix, p);
+    }
+}
+
+/// Adapter for `#[point(extend_with)]` that adds the failure counters and
+/// the mdm cluster addresses to the system point
+pub fn extend_system_point(system: &System, p: &mut TsPoint) {
+    system_failure_counters_point(system, p);
+    system_mdm_cluster_point(&system.mdm_cluster, p);
+}
+
+/// Tags the system point with the virtual IPs of the mdm cluster and the
+/// management IPs of its mdms and tie breakers so it can be found by
+/// address
+pub fn system_mdm_cluster_point(cluster: &MdmCluster, p: &mut TsPoint) {
+    if let Some(virtual_ips) = &cluster.virtual_ip {
+        p.add_tag("virtual_ips", TsValue::StringVec(virtual_ips.clone()));
+    }
+    p.add_tag(
+        "mdm_management_ips",
+        TsValue::StringVec(cluster.mdm_management_ips()),
+    );
+    p.add_tag(
+        "tie_breaker_management_ips",
+        TsValue::StringVec(cluster.tie_breaker_management_ips()),
+    );
+}
+
 #[derive(Clone, Deserialize, Debug)] // v3 look for OscillatingCounter
 #[serde(rename_all = "camelCase")]
 pub struct Successfulio {
@@ -279,13 +1194,137 @@ pub struct Successfulio {
     pub long_window: Option<OscillatingCounterWindow>,
 }
 
+impl ChildPoint for Successfulio {
+    fn sub_point(&self, p: &mut TsPoint) {
+        let windows = [
+            ("short_window", &self.short_window),
+            ("medium_window", &self.medium_window),
+            ("long_window", &self.long_window),
+        ];
+        for (prefix, window) in windows.iter() {
+            if let Some(ref window) = window {
+                let fields = [
+                    (
+                        "avg_read_latency_in_microsec",
+                        window.avg_read_latency_in_microsec,
+                    ),
+                    (
+                        "avg_write_latency_in_microsec",
+                        window.avg_write_latency_in_microsec,
+                    ),
+                    ("fixed_read_error_count", window.fixed_read_error_count),
+                ];
+                for (name, value) in fields.iter() {
+                    if let Some(value) = value {
+                        p.add_field(format!("{}_{}", prefix, name), TsValue::Long(*value));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Adapter for `#[point(flatten_with)]` that emits the per window read/write
+/// latency and fixed read error counts of a device
+pub fn successful_ios_point(successful_ios: &Option<Successfulio>, p: &mut TsPoint) {
+    if let Some(ref successful_ios) = successful_ios {
+        successful_ios.sub_point(p);
+    }
+}
+
 #[derive(Clone, Deserialize, Debug)]
 pub struct Link {
     pub rel: String,
     pub href: String,
 }
 
-#[derive(Deserialize, Debug)]
+/// An active alert and the object it was raised against
+#[derive(Clone, Deserialize, Debug, IntoPoint)]
+#[serde(rename_all = "camelCase")]
+#[point(extend_with = "alert_point")]
+pub struct Alert {
+    pub id: String,
+    pub alert_type: String,
+    pub severity: String,
+    pub affected_object: AffectedObject,
+    pub start_time: Option<DateTime<Utc>>,
+    pub last_observed: Option<DateTime<Utc>>,
+    pub uuid: Option<String>,
+    pub name: Option<String>,
+    pub links: Vec<Link>,
+}
+
+impl Alert {
+    pub const MEASUREMENT: &str = "scaleio_alert";
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedObject {
+    #[serde(rename = "type")]
+    pub object_type: String,
+    pub object_id: String,
+}
+
+pub fn alert_point(alert: &Alert, p: &mut TsPoint) {
+    p.add_tag(
+        "object_type",
+        TsValue::String(alert.affected_object.object_type.clone()),
+    );
+    p.add_tag(
+        "object_id",
+        TsValue::String(alert.affected_object.object_id.clone()),
+    );
+    p.add_field("active", TsValue::Boolean(true));
+}
+
+#[test]
+fn test_alerts() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f = File::open("tests/scaleio/alerts.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+    let body = buff.into_bytes();
+
+    let (port, requests) = mock_scaleio_api(false, move |_, _| body.clone());
+    let scaleio = mock_scaleio(port);
+    let t = Utc.with_ymd_and_hms(2020, 6, 8, 13, 0, 0).unwrap();
+    let points = scaleio.get_alerts(t).unwrap();
+    assert_eq!(requests.lock().unwrap()[0].1, "/api/types/Alert/instances");
+    println!("result: {:#?}", points);
+    assert_eq!(points.len(), 3);
+    assert!(points.iter().all(|p| p.measurement == Alert::MEASUREMENT));
+
+    let p = &points[0];
+    assert_eq!(p.tags["severity"], TsValue::String("ALERT_HIGH".into()));
+    assert_eq!(
+        p.tags["alert_type"],
+        TsValue::String("SDS_DISCONNECTED".into())
+    );
+    assert_eq!(p.tags["object_type"], TsValue::String("Sds".into()));
+    assert_eq!(
+        p.tags["object_id"],
+        TsValue::String("t51s1sanumb3r006".into())
+    );
+    assert_eq!(p.fields["active"], TsValue::Boolean(true));
+    assert_eq!(
+        p.timestamp,
+        Some(Utc.with_ymd_and_hms(2020, 6, 8, 12, 0, 0).unwrap())
+    );
+    assert_eq!(
+        points[1].timestamp,
+        Some(
+            Utc.with_ymd_and_hms(2020, 6, 7, 8, 15, 30).unwrap()
+                + chrono::Duration::milliseconds(250)
+        )
+    );
+    // Alerts without a start time are stamped with the collection time
+    assert_eq!(points[2].timestamp, Some(t));
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
 pub enum AuthenticationError {
     None,
     General,
@@ -315,7 +1354,7 @@ impl ToString for AuthenticationError {
     }
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Serialize)]
 pub enum DeviceState {
     DeviceInit,
     DeviceRecovery,
@@ -372,33 +1411,82 @@ pub struct DriveId {
     storage_pool_id: String, // storage pool id
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct DeviceStatistics {
-    avg_write_size_in_bytes: u64,             // in v3
-    active_moving_in_fwd_rebuild_jobs: u64,   // in v3
-    active_moving_in_rebalance_jobs: u64,     // in v3
-    active_moving_out_bck_rebuild_jobs: u64,  // in v3
-    active_moving_out_fwd_rebuild_jobs: u64,  // in v3
-    active_moving_rebalance_jobs: u64,        // in v3
-    active_moving_in_norm_rebuild_jobs: u64,  // in v3
-    active_moving_in_bck_rebuild_jobs: u64,   // in v3
-    active_moving_out_norm_rebuild_jobs: u64, // in v3
-    avg_read_latency_in_microsec: u64,        // in v3
-    avg_write_latency_in_microsec: u64,       // in v3
-    avg_read_size_in_bytes: u64,              // in v3
+// Type and field names log_unknown_fields has already logged
+static LOGGED_UNKNOWN_FIELDS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// A response struct whose unknown fields can be logged.  A new field is
+/// usually a counter a firmware release added that's worth collecting.
+/// Finding them means reading the response as json first, so it's only
+/// done with `ScaleioConfig::log_unknown_fields` set.
+pub trait UnknownFields: Serialize {
+    const TYPE_NAME: &'static str;
+
+    /// Log each field of response, the json self was read from, that self
+    /// doesn't have.  A field is only logged the first time this process
+    /// sees it, so a collector polling every minute doesn't repeat itself.
+    /// Returns the fields logged by this call
+    fn log_unknown_fields(&self, response: &serde_json::Value) -> Vec<String> {
+        // The keys self serializes to are the ones it read
+        let known = match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(known)) => known,
+            _ => return Vec::new(),
+        };
+        let response = match response.as_object() {
+            Some(response) => response,
+            None => return Vec::new(),
+        };
+        let mut logged = match LOGGED_UNKNOWN_FIELDS.lock() {
+            Ok(logged) => logged,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut fields: Vec<String> = response
+            .keys()
+            .filter(|field| !known.contains_key(*field))
+            .filter(|field| logged.insert(format!("{}.{}", Self::TYPE_NAME, field)))
+            .cloned()
+            .collect();
+        fields.sort();
+        for field in &fields {
+            info!(
+                "scaleio {} has an unknown field {}: {}",
+                Self::TYPE_NAME,
+                field,
+                response[field]
+            );
+        }
+        fields
+    }
+}
+
+// Counters are optional so one a firmware release drops is left off the
+// point instead of failing the statistics of every device
+#[derive(Default, Deserialize, Debug, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DeviceStatistics {
+    avg_write_size_in_bytes: Option<u64>,             // in v3
+    active_moving_in_fwd_rebuild_jobs: Option<u64>,   // in v3
+    active_moving_in_rebalance_jobs: Option<u64>,     // in v3
+    active_moving_out_bck_rebuild_jobs: Option<u64>,  // in v3
+    active_moving_out_fwd_rebuild_jobs: Option<u64>,  // in v3
+    active_moving_rebalance_jobs: Option<u64>,        // in v3
+    active_moving_in_norm_rebuild_jobs: Option<u64>,  // in v3
+    active_moving_in_bck_rebuild_jobs: Option<u64>,   // in v3
+    active_moving_out_norm_rebuild_jobs: Option<u64>, // in v3
+    avg_read_latency_in_microsec: Option<u64>,        // in v3
+    avg_write_latency_in_microsec: Option<u64>,       // in v3
+    avg_read_size_in_bytes: Option<u64>,              // in v3
     #[serde(rename = "BackgroundScanCompareCount")]
     background_scan_compare_count: Option<u64>, // in v3
     #[serde(rename = "BackgroundScannedInMB")]
-    background_scanned_in_mb: u64, // in v3
-    bck_rebuild_write_bwc: BWC,               // in v3
-    bck_rebuild_read_bwc: BWC,                // in v3
-    capacity_in_use_in_kb: u64,               // in v3
-    capacity_in_use_no_overhead_in_kb: Option<u64>, // NEW V3
-    capacity_limit_in_kb: Option<u64>,        // NEW V3
+    background_scanned_in_mb: Option<u64>, // in v3
+    bck_rebuild_write_bwc: Option<BWC>,               // in v3
+    bck_rebuild_read_bwc: Option<BWC>,                // in v3
+    capacity_in_use_in_kb: Option<u64>,               // in v3
+    capacity_in_use_no_overhead_in_kb: Option<u64>,   // NEW V3
+    capacity_limit_in_kb: Option<u64>,                // NEW V3
     changelog_destage_completion_percent: Option<u64>, // NEW V3
     checksum_calculation_completion_percent: Option<u64>, // NEW V3
-    checksum_capacity_in_kb: Option<u64>,     // NEW v3
+    checksum_capacity_in_kb: Option<u64>,             // NEW v3
     checksum_migration_completion_percent: Option<u64>, // NEW V3
     #[serde(flatten)]
     pub compressed_data_compression_ratio: CompressedDataCompressionRatio, // NEW V3
@@ -407,213 +1495,243 @@ pub struct DeviceStatistics {
     #[serde(rename = "currentChecksumMigrationSizeInKB")]
     current_checksum_migration_size_in_kb: Option<u64>, // NEW V3
     current_checksum_protected_combs_num: Option<u64>, // NEW V3
-    current_fgl_migration_size_in_kb: Option<u64>, // NEW V3
-    degraded_healthy_vac_in_kb: u64,          // in v3
-    degraded_failed_vac_in_kb: u64,           // in v3
-    failed_vac_in_kb: u64,                    // in v3
-    fgl_uncompressed_data_size_in_kb: Option<u64>, // NEW V3
-    fgl_compressed_data_size_in_kb: Option<u64>, // NEW V3
-    fgl_spares_in_kb: Option<u64>,            // NEW V3
-    fgl_migration_completion_percent: Option<u64>, // NEW V3
-    fgl_user_data_capacity_in_kb: Option<u64>, // NEW V3
-    fixed_read_error_count: Option<u64>,      //in v3
-    fwd_rebuild_read_bwc: BWC,                // in v3
-    fwd_rebuild_write_bwc: BWC,               // in v3
-    in_maintenance_vac_in_kb: u64,            // in v3
-    in_use_vac_in_kb: u64,                    // in v3
-    log_written_blocks_in_kb: Option<u64>,    // NEW v3
-    max_capacity_in_kb: Option<u64>,          // NEW v3
-    max_user_data_capacity_in_kb: Option<u64>, // NEW V3
-    metadata_overhead_in_kb: Option<u64>,     // NEW V3
-    mg_user_ddata_ccapacity_in_kb: Option<u64>, // NEW V3
-    net_capacity_in_use_in_kb: Option<u64>,   // NEW V3
+    current_fgl_migration_size_in_kb: Option<u64>,    // NEW V3
+    degraded_healthy_vac_in_kb: Option<u64>,          // in v3
+    degraded_failed_vac_in_kb: Option<u64>,           // in v3
+    failed_vac_in_kb: Option<u64>,                    // in v3
+    fgl_uncompressed_data_size_in_kb: Option<u64>,    // NEW V3
+    fgl_compressed_data_size_in_kb: Option<u64>,      // NEW V3
+    fgl_spares_in_kb: Option<u64>,                    // NEW V3
+    fgl_migration_completion_percent: Option<u64>,    // NEW V3
+    fgl_user_data_capacity_in_kb: Option<u64>,        // NEW V3
+    fixed_read_error_count: Option<u64>,              //in v3
+    fwd_rebuild_read_bwc: Option<BWC>,                // in v3
+    fwd_rebuild_write_bwc: Option<BWC>,               // in v3
+    in_maintenance_vac_in_kb: Option<u64>,            // in v3
+    in_use_vac_in_kb: Option<u64>,                    // in v3
+    log_written_blocks_in_kb: Option<u64>,            // NEW v3
+    max_capacity_in_kb: Option<u64>,                  // NEW v3
+    max_user_data_capacity_in_kb: Option<u64>,        // NEW V3
+    metadata_overhead_in_kb: Option<u64>,             // NEW V3
+    mg_user_ddata_ccapacity_in_kb: Option<u64>,       // NEW V3
+    net_capacity_in_use_in_kb: Option<u64>,           // NEW V3
     net_capacity_in_use_no_overhead_in_kb: Option<u64>, // NEW V3
-    net_fgl_compressed_data_size_in_kb: Option<u64>, // NEW V3
-    net_fgl_spares_in_kb: Option<u64>,        // NEW V3
+    net_fgl_compressed_data_size_in_kb: Option<u64>,  // NEW V3
+    net_fgl_spares_in_kb: Option<u64>,                // NEW V3
     net_fgl_uncompressed_data_size_in_kb: Option<u64>, // NEW V3
-    net_fgl_user_data_capacity_in_kb: Option<u64>, // NEW V3
-    net_max_user_data_capacity_in_kb: Option<u64>, // NEW V3
-    net_metadata_overhead_in_kb: Option<u64>, // NEW V3
-    net_mg_user_data_capacity_in_kb: Option<u64>, // NEW V3
-    net_provisioned_addresses_in_kb: Option<u64>, // NEW V3
-    net_snapshot_capacity_in_kb: Option<u64>, // NEW V3
+    net_fgl_user_data_capacity_in_kb: Option<u64>,    // NEW V3
+    net_max_user_data_capacity_in_kb: Option<u64>,    // NEW V3
+    net_metadata_overhead_in_kb: Option<u64>,         // NEW V3
+    net_mg_user_data_capacity_in_kb: Option<u64>,     // NEW V3
+    net_provisioned_addresses_in_kb: Option<u64>,     // NEW V3
+    net_snapshot_capacity_in_kb: Option<u64>,         // NEW V3
     net_trimmed_user_data_capacity_in_kb: Option<u64>, // NEW V3
-    net_unused_capacity_in_kb: Option<u64>,   // NEW V3
-    net_user_data_capacity_in_kb: Option<u64>, // NEW V3
+    net_unused_capacity_in_kb: Option<u64>,           // NEW V3
+    net_user_data_capacity_in_kb: Option<u64>,        // NEW V3
     net_user_data_capacity_no_trim_in_kb: Option<u64>, // NEW V3
-    norm_rebuild_read_bwc: BWC,               // in v3
-    norm_rebuild_write_bwc: BWC,              // in v3
+    norm_rebuild_read_bwc: Option<BWC>,               // in v3
+    norm_rebuild_write_bwc: Option<BWC>,              // in v3
     num_changelog_records_left_to_destage: Option<u64>, // NEW V3
-    pending_moving_in_bck_rebuild_jobs: u64,  // in v3
-    pending_moving_out_bck_rebuild_jobs: u64, // in v3
-    pending_moving_in_norm_rebuild_jobs: u64, // in v3
-    pending_moving_rebalance_jobs: u64,       // in v3
-    pending_moving_out_normrebuild_jobs: u64, // in v3
-    pending_moving_in_rebalance_jobs: u64,    // in v3
-    pending_moving_in_fwd_rebuild_jobs: u64,  // in v3
-    pending_moving_out_fwd_rebuild_jobs: u64, // in v3
-    primary_read_from_rmcache_bwc: BWC,       // in v3
-    primary_read_from_dev_bwc: BWC,           // in v3
-    primary_read_bwc: BWC,                    // in v3
-    primary_vac_in_kb: u64,                   // in v3
-    protected_vac_in_kb: u64,                 // in v3
-    primary_write_bwc: BWC,                   // in v3
-    rebalance_read_bwc: BWC,                  // in v3
-    rebalance_write_bwc: BWC,                 // in v3
-    rfcache_avg_read_time: u64,               // in v3
-    rfcache_io_errors: u64,                   // in v3
-    rfcache_reads_skipped_internal_error: u64, // in v3
-    rfcache_source_device_writes: u64,        // in v3
-    rfcache_reads_skipped_low_resources: u64, // in v3
-    rfcache_reads_skipped_max_io_size: u64,   // in v3
-    rfcache_reads_skipped_aligned_size_too_large: u64, // in v3
-    rfcache_writes_skipped_internal_error: u64, // in v3
-    rfcache_writes_skipped_stuck_io: u64,     // in v3
-    rfcache_writes_skipped_cache_miss: u64,   // in v3
-    rfcache_writes_skipped_heavy_load: u64,   // in v3
-    rfcache_write_miss: u64,                  // in v3
-    rfcache_writes_skipped_low_resources: u64, // in v3
-    rfcache_reads_from_cache: u64,            // in v3
-    rfcache_ios_outstanding: u64,             // in v3
-    rfcache_skipped_unlined_write: u64,       // in v3
-    rfcache_writes_received: u64,             // in v3
-    rfcache_write_pending: u64,               // in v3
-    rfcache_writes_skipped_max_io_size: u64,  // in v3
-    rfcache_reads_skipped_stuck_io: u64,      // in v3
-    rfcache_reads_skipped: u64,               // in v3
-    rfcache_reads_received: u64,              // in v3
-    rfcache_ios_skipped: u64,                 // in v3
-    rfcache_read_miss: u64,                   // in v3
-    rfcache_reads_skipped_lock_ios: u64,      // in v3
-    rfache_read_hit: u64,                     // in v3
-    rfcache_avg_write_time: u64,              // in v3
-    rfcache_source_device_reads: u64,         // in v3
-    rfcache_reads_skipped_heavy_load: u64,    // in v3
-    rfcache_reads_pending: u64,               // in v3
-    rfcache_fd_reads_recieved: Option<u64>,   // NEW V3
-    rfcache_fd_writes_recieved: Option<u64>,  // NEW V3
-    rfcache_fd_inlight_reads: Option<u64>,    // NEW V3
-    rfcache_fd_inlight_writes: Option<u64>,   // NEW V3
+    pending_moving_in_bck_rebuild_jobs: Option<u64>,  // in v3
+    pending_moving_out_bck_rebuild_jobs: Option<u64>, // in v3
+    pending_moving_in_norm_rebuild_jobs: Option<u64>, // in v3
+    pending_moving_rebalance_jobs: Option<u64>,       // in v3
+    pending_moving_out_normrebuild_jobs: Option<u64>, // in v3
+    pending_moving_in_rebalance_jobs: Option<u64>,    // in v3
+    pending_moving_in_fwd_rebuild_jobs: Option<u64>,  // in v3
+    pending_moving_out_fwd_rebuild_jobs: Option<u64>, // in v3
+    primary_read_from_rmcache_bwc: Option<BWC>,       // in v3
+    primary_read_from_dev_bwc: Option<BWC>,           // in v3
+    primary_read_bwc: Option<BWC>,                    // in v3
+    primary_vac_in_kb: Option<u64>,                   // in v3
+    protected_vac_in_kb: Option<u64>,                 // in v3
+    primary_write_bwc: Option<BWC>,                   // in v3
+    rebalance_read_bwc: Option<BWC>,                  // in v3
+    rebalance_write_bwc: Option<BWC>,                 // in v3
+    rfcache_avg_read_time: Option<u64>,               // in v3
+    rfcache_io_errors: Option<u64>,                   // in v3
+    rfcache_reads_skipped_internal_error: Option<u64>, // in v3
+    rfcache_source_device_writes: Option<u64>,        // in v3
+    rfcache_reads_skipped_low_resources: Option<u64>, // in v3
+    rfcache_reads_skipped_max_io_size: Option<u64>,   // in v3
+    rfcache_reads_skipped_aligned_size_too_large: Option<u64>, // in v3
+    rfcache_writes_skipped_internal_error: Option<u64>, // in v3
+    rfcache_writes_skipped_stuck_io: Option<u64>,     // in v3
+    rfcache_writes_skipped_cache_miss: Option<u64>,   // in v3
+    rfcache_writes_skipped_heavy_load: Option<u64>,   // in v3
+    rfcache_write_miss: Option<u64>,                  // in v3
+    rfcache_writes_skipped_low_resources: Option<u64>, // in v3
+    rfcache_reads_from_cache: Option<u64>,            // in v3
+    rfcache_ios_outstanding: Option<u64>,             // in v3
+    rfcache_skipped_unlined_write: Option<u64>,       // in v3
+    rfcache_writes_received: Option<u64>,             // in v3
+    rfcache_write_pending: Option<u64>,               // in v3
+    rfcache_writes_skipped_max_io_size: Option<u64>,  // in v3
+    rfcache_reads_skipped_stuck_io: Option<u64>,      // in v3
+    rfcache_reads_skipped: Option<u64>,               // in v3
+    rfcache_reads_received: Option<u64>,              // in v3
+    rfcache_ios_skipped: Option<u64>,                 // in v3
+    rfcache_read_miss: Option<u64>,                   // in v3
+    rfcache_reads_skipped_lock_ios: Option<u64>,      // in v3
+    rfache_read_hit: Option<u64>,                     // in v3
+    rfcache_avg_write_time: Option<u64>,              // in v3
+    rfcache_source_device_reads: Option<u64>,         // in v3
+    rfcache_reads_skipped_heavy_load: Option<u64>,    // in v3
+    rfcache_reads_pending: Option<u64>,               // in v3
+    rfcache_fd_reads_recieved: Option<u64>,           // NEW V3
+    rfcache_fd_writes_recieved: Option<u64>,          // NEW V3
+    rfcache_fd_inlight_reads: Option<u64>,            // NEW V3
+    rfcache_fd_inlight_writes: Option<u64>,           // NEW V3
     rfcache_fd_read_time_greater500_millis: Option<u64>, // NEW V3
-    rfcache_fd_read_time_greater1_sec: Option<u64>, // NEW V3
-    rfcache_fd_read_time_greater5_sec: Option<u64>, // NEW V3
-    rfcache_fd_read_time_greater1_min: Option<u64>, // NEW V3
+    rfcache_fd_read_time_greater1_sec: Option<u64>,   // NEW V3
+    rfcache_fd_read_time_greater5_sec: Option<u64>,   // NEW V3
+    rfcache_fd_read_time_greater1_min: Option<u64>,   // NEW V3
     rfcache_fd_write_time_greater500_millis: Option<u64>, // NEW V3
-    rfcache_fd_write_time_greater1_sec: Option<u64>, // NEW V3
-    rfcache_fd_write_time_greater5_sec: Option<u64>, // NEW V3
-    rfcache_fd_write_time_greater1_min: Option<u64>, // NEW V3
-    rfcache_fd_avg_read_time: Option<u64>,    // NEW V3
-    rfcache_fd_avg_write_time: Option<u64>,   // NEW V3
-    rfcache_fd_io_errors: Option<u64>,        // NEW V3
-    rfcache_fd_cache_overloaded: Option<u64>, // NEW V3
-    rfcache_fd_monitor_error_stuck_io: Option<u64>, // NEW V3
-    rm_pending_allocated_in_kb: u64,          // in v3
-    rm_pending_thick_in_kb: Option<u64>,      // NEW v3
-    secondary_read_from_dev_bwc: BWC,         // in v3
-    secondary_vac_in_kb: u64,                 // in v3
-    secondary_read_bwc: BWC,                  // in v3
-    secondary_read_from_rmcache_bwc: BWC,     // in v3
-    secondary_write_bwc: BWC,                 // in v3
-    semi_protected_vac_in_kb: u64,            // in v3
+    rfcache_fd_write_time_greater1_sec: Option<u64>,  // NEW V3
+    rfcache_fd_write_time_greater5_sec: Option<u64>,  // NEW V3
+    rfcache_fd_write_time_greater1_min: Option<u64>,  // NEW V3
+    rfcache_fd_avg_read_time: Option<u64>,            // NEW V3
+    rfcache_fd_avg_write_time: Option<u64>,           // NEW V3
+    rfcache_fd_io_errors: Option<u64>,                // NEW V3
+    rfcache_fd_cache_overloaded: Option<u64>,         // NEW V3
+    rfcache_fd_monitor_error_stuck_io: Option<u64>,   // NEW V3
+    rm_pending_allocated_in_kb: Option<u64>,          // in v3
+    rm_pending_thick_in_kb: Option<u64>,              // NEW v3
+    secondary_read_from_dev_bwc: Option<BWC>,         // in v3
+    secondary_vac_in_kb: Option<u64>,                 // in v3
+    secondary_read_bwc: Option<BWC>,                  // in v3
+    secondary_read_from_rmcache_bwc: Option<BWC>,     // in v3
+    secondary_write_bwc: Option<BWC>,                 // in v3
+    semi_protected_vac_in_kb: Option<u64>,            // in v3
     snap_capacity_in_use_occupied_in_kb: Option<u64>, //deprecated in v3.5.1.4, will eventually return 0 and then be removed
     snap_capacity_in_use_in_kb: Option<u64>, //deprecated in v3.5.1.4, will eventually return 0 and then be removed
     snapshot_capacity_in_kb: Option<u64>,    // NEW V3
     temp_capacity_vac_in_kb: Option<u64>,    // NEW v3
-    thick_capacity_in_use_in_kb: u64,        // in v3
+    thick_capacity_in_use_in_kb: Option<u64>, // in v3
     thin_capacity_in_use_in_kb: Option<u64>, // deprecated, use netThinUserDataCapacityInKb * 2
     net_thin_user_data_capacity_in_kb: Option<u64>, // use this value * 2 as thin_capacityInuseinKb
-    thin_capacity_allocated_in_km: u64,      // in v3
+    thin_capacity_allocated_in_km: Option<u64>, // in v3
     total_changelog_records_to_destage: Option<u64>, // NEW V3
     #[serde(rename = "totalChecksumMigrationSizeInKB")]
     total_checksum_migration_size_in_kb: Option<u64>, // NEW V3
     total_checksum_protected_combs_num: Option<u64>, // NEW V3
     total_fgl_migration_size_in_kb: Option<u64>, // NEW V3
-    total_read_bwc: BWC,                     // in v3
-    total_write_bwc: BWC,                    // in v3
+    total_read_bwc: Option<BWC>,             // in v3
+    total_write_bwc: Option<BWC>,            // in v3
     trimmed_user_data_capacity_in_kb: Option<u64>, // NEW V3
-    unused_capacity_in_kb: u64,
-    unreachable_unused_capacity_in_kb: u64,        // in v3
-    user_data_capacity_in_kb: Option<u64>,         // NEW V3
-    user_data_capacity_no_trim_in_kb: Option<u64>, // NEW V3
-    vol_migration_read_bwc: Option<BWC>,           // NEW V3
-    vol_migration_write_bwc: Option<BWC>,          // NEW V3
+    unused_capacity_in_kb: Option<u64>,
+    unreachable_unused_capacity_in_kb: Option<u64>, // in v3
+    user_data_capacity_in_kb: Option<u64>,          // NEW V3
+    user_data_capacity_no_trim_in_kb: Option<u64>,  // NEW V3
+    vol_migration_read_bwc: Option<BWC>,            // NEW V3
+    vol_migration_write_bwc: Option<BWC>,           // NEW V3
+}
+
+impl DeviceStatistics {
+    pub const MEASUREMENT: &str = "scaleio_drive_stat";
+}
+
+impl UnknownFields for DeviceStatistics {
+    const TYPE_NAME: &'static str = "DeviceStatistics";
 }
 
 impl IntoPoint for DeviceStatistics {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
-        let mut p = TsPoint::new(name.unwrap_or("scaleio_drive_stat"), is_time_series);
-        p.add_field(
-            "avg_write_size_in_bytes",
-            TsValue::Long(self.avg_write_size_in_bytes),
-        );
-        p.add_field(
-            "avg_read_latency_in_microsec",
-            TsValue::Long(self.avg_read_latency_in_microsec),
-        );
-        p.add_field(
-            "avg_write_latency_in_microsec",
-            TsValue::Long(self.avg_write_latency_in_microsec),
-        );
-        p.add_field(
-            "avg_read_size_in_bytes",
-            TsValue::Long(self.avg_read_size_in_bytes),
-        );
-        p.add_field(
-            "capacity_in_use_in_kb",
-            TsValue::Long(self.capacity_in_use_in_kb),
-        );
-        p.add_field(
-            "degraded_healthy_vac_in_kb",
-            TsValue::Long(self.degraded_healthy_vac_in_kb),
-        );
-        p.add_field(
-            "degraded_failed_vac_in_kb",
-            TsValue::Long(self.degraded_failed_vac_in_kb),
-        );
+        let mut p =
+            TsPoint::with_capacity(name.unwrap_or(Self::MEASUREMENT), is_time_series, 56, 0);
+        if let Some(avg_write_size_in_bytes) = self.avg_write_size_in_bytes {
+            p.add_field(
+                "avg_write_size_in_bytes",
+                TsValue::Long(avg_write_size_in_bytes),
+            );
+        }
+        if let Some(avg_read_latency_in_microsec) = self.avg_read_latency_in_microsec {
+            p.add_field(
+                "avg_read_latency_in_microsec",
+                TsValue::Long(avg_read_latency_in_microsec),
+            );
+        }
+        if let Some(avg_write_latency_in_microsec) = self.avg_write_latency_in_microsec {
+            p.add_field(
+                "avg_write_latency_in_microsec",
+                TsValue::Long(avg_write_latency_in_microsec),
+            );
+        }
+        if let Some(avg_read_size_in_bytes) = self.avg_read_size_in_bytes {
+            p.add_field(
+                "avg_read_size_in_bytes",
+                TsValue::Long(avg_read_size_in_bytes),
+            );
+        }
+        if let Some(capacity_in_use_in_kb) = self.capacity_in_use_in_kb {
+            p.add_field(
+                "capacity_in_use_in_kb",
+                TsValue::Long(capacity_in_use_in_kb),
+            );
+        }
+        if let Some(degraded_healthy_vac_in_kb) = self.degraded_healthy_vac_in_kb {
+            p.add_field(
+                "degraded_healthy_vac_in_kb",
+                TsValue::Long(degraded_healthy_vac_in_kb),
+            );
+        }
+        if let Some(degraded_failed_vac_in_kb) = self.degraded_failed_vac_in_kb {
+            p.add_field(
+                "degraded_failed_vac_in_kb",
+                TsValue::Long(degraded_failed_vac_in_kb),
+            );
+        }
         // not in use
-        p.add_field(
-            "primary_read_bwc",
-            TsValue::Long(self.primary_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "primary_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.primary_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "primary_read_bwc_num_seconds",
-            TsValue::Long(self.primary_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "primary_read_bwc_num_occured",
-            TsValue::Long(self.primary_read_bwc.num_occured),
-        );
-        p.add_field("primary_vac_in_kb", TsValue::Long(self.primary_vac_in_kb));
-        p.add_field(
-            "protected_vac_in_kb",
-            TsValue::Long(self.protected_vac_in_kb),
-        );
+        if let Some(primary_read_bwc) = &self.primary_read_bwc {
+            p.add_field(
+                "primary_read_bwc",
+                TsValue::Long(primary_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "primary_read_bwc_total_weight_in_kb",
+                TsValue::Long(primary_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "primary_read_bwc_num_seconds",
+                TsValue::Long(primary_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "primary_read_bwc_num_occured",
+                TsValue::Long(primary_read_bwc.num_occured),
+            );
+        }
+        if let Some(primary_vac_in_kb) = self.primary_vac_in_kb {
+            p.add_field("primary_vac_in_kb", TsValue::Long(primary_vac_in_kb));
+        }
+        if let Some(protected_vac_in_kb) = self.protected_vac_in_kb {
+            p.add_field("protected_vac_in_kb", TsValue::Long(protected_vac_in_kb));
+        }
         //Not in use
-        p.add_field(
-            "primary_write_bwc",
-            TsValue::Long(self.primary_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "primary_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.primary_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "primary_write_bwc_num_seconds",
-            TsValue::Long(self.primary_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "primary_write_bwc_num_occured",
-            TsValue::Long(self.primary_write_bwc.num_occured),
-        );
+        if let Some(primary_write_bwc) = &self.primary_write_bwc {
+            p.add_field(
+                "primary_write_bwc",
+                TsValue::Long(primary_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "primary_write_bwc_total_weight_in_kb",
+                TsValue::Long(primary_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "primary_write_bwc_num_seconds",
+                TsValue::Long(primary_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "primary_write_bwc_num_occured",
+                TsValue::Long(primary_write_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "thick_capacity_in_use_in_kb",
-            TsValue::Long(self.thick_capacity_in_use_in_kb),
-        );
+        if let Some(thick_capacity_in_use_in_kb) = self.thick_capacity_in_use_in_kb {
+            p.add_field(
+                "thick_capacity_in_use_in_kb",
+                TsValue::Long(thick_capacity_in_use_in_kb),
+            );
+        }
 
         if let Some(thin_capacity_in_use_in_kb) = self.thin_capacity_in_use_in_kb {
             p.add_field(
@@ -630,169 +1748,190 @@ impl IntoPoint for DeviceStatistics {
             }
         }
 
-        p.add_field(
-            "thin_capacity_allocated_in_km",
-            TsValue::Long(self.thin_capacity_allocated_in_km),
-        );
+        if let Some(thin_capacity_allocated_in_km) = self.thin_capacity_allocated_in_km {
+            p.add_field(
+                "thin_capacity_allocated_in_km",
+                TsValue::Long(thin_capacity_allocated_in_km),
+            );
+        }
 
-        p.add_field(
-            "total_read_bwc",
-            TsValue::Long(self.total_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "total_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.total_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "total_read_bwc_num_seconds",
-            TsValue::Long(self.total_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "total_read_bwc_num_occured",
-            TsValue::Long(self.total_read_bwc.num_occured),
-        );
+        if let Some(total_read_bwc) = &self.total_read_bwc {
+            p.add_field(
+                "total_read_bwc",
+                TsValue::Long(total_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "total_read_bwc_total_weight_in_kb",
+                TsValue::Long(total_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "total_read_bwc_num_seconds",
+                TsValue::Long(total_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "total_read_bwc_num_occured",
+                TsValue::Long(total_read_bwc.num_occured),
+            );
+        }
 
         //not used
-        p.add_field(
-            "total_write_bwc",
-            TsValue::Long(self.total_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "total_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.total_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "total_write_bwc_num_seconds",
-            TsValue::Long(self.total_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "total_write_bwc_num_occured",
-            TsValue::Long(self.total_write_bwc.num_occured),
-        );
+        if let Some(total_write_bwc) = &self.total_write_bwc {
+            p.add_field(
+                "total_write_bwc",
+                TsValue::Long(total_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "total_write_bwc_total_weight_in_kb",
+                TsValue::Long(total_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "total_write_bwc_num_seconds",
+                TsValue::Long(total_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "total_write_bwc_num_occured",
+                TsValue::Long(total_write_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "unused_capacity_in_kb",
-            TsValue::Long(self.unused_capacity_in_kb),
-        );
-        p.add_field(
-            "unreachable_unused_capacity_in_kb",
-            TsValue::Long(self.unreachable_unused_capacity_in_kb),
-        );
+        if let Some(unused_capacity_in_kb) = self.unused_capacity_in_kb {
+            p.add_field(
+                "unused_capacity_in_kb",
+                TsValue::Long(unused_capacity_in_kb),
+            );
+        }
+        if let Some(unreachable_unused_capacity_in_kb) = self.unreachable_unused_capacity_in_kb {
+            p.add_field(
+                "unreachable_unused_capacity_in_kb",
+                TsValue::Long(unreachable_unused_capacity_in_kb),
+            );
+        }
         //not used
-        p.add_field(
-            "rebalance_read_bwc",
-            TsValue::Long(self.rebalance_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "rebalance_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.rebalance_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "rebalance_read_bwc_num_seconds",
-            TsValue::Long(self.rebalance_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "rebalance_read_bwc_num_occured",
-            TsValue::Long(self.rebalance_read_bwc.num_occured),
-        );
+        if let Some(rebalance_read_bwc) = &self.rebalance_read_bwc {
+            p.add_field(
+                "rebalance_read_bwc",
+                TsValue::Long(rebalance_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "rebalance_read_bwc_total_weight_in_kb",
+                TsValue::Long(rebalance_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "rebalance_read_bwc_num_seconds",
+                TsValue::Long(rebalance_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "rebalance_read_bwc_num_occured",
+                TsValue::Long(rebalance_read_bwc.num_occured),
+            );
+        }
         // not used
-        p.add_field(
-            "rebalance_write_bwc",
-            TsValue::Long(self.rebalance_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "rebalance_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.rebalance_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "rebalance_write_bwc_num_seconds",
-            TsValue::Long(self.rebalance_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "rebalance_write_bwc_num_occured",
-            TsValue::Long(self.rebalance_write_bwc.num_occured),
-        );
+        if let Some(rebalance_write_bwc) = &self.rebalance_write_bwc {
+            p.add_field(
+                "rebalance_write_bwc",
+                TsValue::Long(rebalance_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "rebalance_write_bwc_total_weight_in_kb",
+                TsValue::Long(rebalance_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "rebalance_write_bwc_num_seconds",
+                TsValue::Long(rebalance_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "rebalance_write_bwc_num_occured",
+                TsValue::Long(rebalance_write_bwc.num_occured),
+            );
+        }
 
         // not used
-        p.add_field(
-            "secondary_read_from_dev_bwc",
-            TsValue::Long(self.secondary_read_from_dev_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_read_from_dev_bwc_total_weight_in_kb",
-            TsValue::Long(self.secondary_read_from_dev_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_read_from_dev_bwc_num_seconds",
-            TsValue::Long(self.secondary_read_from_dev_bwc.num_seconds),
-        );
-        p.add_field(
-            "secondary_read_from_dev_bwc_num_occured",
-            TsValue::Long(self.secondary_read_from_dev_bwc.num_occured),
-        );
+        if let Some(secondary_read_from_dev_bwc) = &self.secondary_read_from_dev_bwc {
+            p.add_field(
+                "secondary_read_from_dev_bwc",
+                TsValue::Long(secondary_read_from_dev_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_read_from_dev_bwc_total_weight_in_kb",
+                TsValue::Long(secondary_read_from_dev_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_read_from_dev_bwc_num_seconds",
+                TsValue::Long(secondary_read_from_dev_bwc.num_seconds),
+            );
+            p.add_field(
+                "secondary_read_from_dev_bwc_num_occured",
+                TsValue::Long(secondary_read_from_dev_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "secondary_vac_in_kb",
-            TsValue::Long(self.secondary_vac_in_kb),
-        );
+        if let Some(secondary_vac_in_kb) = self.secondary_vac_in_kb {
+            p.add_field("secondary_vac_in_kb", TsValue::Long(secondary_vac_in_kb));
+        }
         //not used
-        p.add_field(
-            "secondary_read_bwc",
-            TsValue::Long(self.secondary_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.secondary_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_read_bwc_num_seconds",
-            TsValue::Long(self.secondary_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "secondary_read_bwc_num_occured",
-            TsValue::Long(self.secondary_read_bwc.num_occured),
-        );
+        if let Some(secondary_read_bwc) = &self.secondary_read_bwc {
+            p.add_field(
+                "secondary_read_bwc",
+                TsValue::Long(secondary_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_read_bwc_total_weight_in_kb",
+                TsValue::Long(secondary_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_read_bwc_num_seconds",
+                TsValue::Long(secondary_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "secondary_read_bwc_num_occured",
+                TsValue::Long(secondary_read_bwc.num_occured),
+            );
+        }
 
         //not used
-        p.add_field(
-            "secondary_read_from_rmcache_bwc",
-            TsValue::Long(self.secondary_read_from_rmcache_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_read_from_rmcache_bwc_total_weight_in_kb",
-            TsValue::Long(self.secondary_read_from_rmcache_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_read_from_rmcache_bwc_num_seconds",
-            TsValue::Long(self.secondary_read_from_rmcache_bwc.num_seconds),
-        );
-        p.add_field(
-            "secondary_read_from_rmcache_bwc_num_occured",
-            TsValue::Long(self.secondary_read_from_rmcache_bwc.num_occured),
-        );
+        if let Some(secondary_read_from_rmcache_bwc) = &self.secondary_read_from_rmcache_bwc {
+            p.add_field(
+                "secondary_read_from_rmcache_bwc",
+                TsValue::Long(secondary_read_from_rmcache_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_read_from_rmcache_bwc_total_weight_in_kb",
+                TsValue::Long(secondary_read_from_rmcache_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_read_from_rmcache_bwc_num_seconds",
+                TsValue::Long(secondary_read_from_rmcache_bwc.num_seconds),
+            );
+            p.add_field(
+                "secondary_read_from_rmcache_bwc_num_occured",
+                TsValue::Long(secondary_read_from_rmcache_bwc.num_occured),
+            );
+        }
         //not used
-        p.add_field(
-            "secondary_write_bwc",
-            TsValue::Long(self.secondary_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.secondary_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_write_bwc_num_seconds",
-            TsValue::Long(self.secondary_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "secondary_write_bwc_num_occured",
-            TsValue::Long(self.secondary_write_bwc.num_occured),
-        );
+        if let Some(secondary_write_bwc) = &self.secondary_write_bwc {
+            p.add_field(
+                "secondary_write_bwc",
+                TsValue::Long(secondary_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_write_bwc_total_weight_in_kb",
+                TsValue::Long(secondary_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_write_bwc_num_seconds",
+                TsValue::Long(secondary_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "secondary_write_bwc_num_occured",
+                TsValue::Long(secondary_write_bwc.num_occured),
+            );
+        }
 
         vec![p]
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Serialize)]
 pub enum DrlMode {
     Volatile,
     NonVolatile,
@@ -807,7 +1946,7 @@ impl ToString for DrlMode {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Serialize)]
 pub enum IpRole {
     #[serde(rename = "sdsOnly")]
     SdsOnly,
@@ -817,7 +1956,7 @@ pub enum IpRole {
     All,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Serialize)]
 pub enum MembershipState {
     JoinPending,
     Joined,
@@ -834,7 +1973,7 @@ impl ToString for MembershipState {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Serialize)]
 pub enum MaintenanceState {
     NoMaintenance,
     SetMaintenanceInProgress,
@@ -853,7 +1992,20 @@ impl ToString for MaintenanceState {
     }
 }
 
-#[derive(Deserialize, Debug)]
+impl MaintenanceState {
+    /// The state as a number so it can be graphed and alerted on.  0 no
+    /// maintenance, 1 entering, 2 in and 3 exiting maintenance
+    pub fn code(&self) -> u64 {
+        match *self {
+            MaintenanceState::NoMaintenance => 0,
+            MaintenanceState::SetMaintenanceInProgress => 1,
+            MaintenanceState::InMaintenance => 2,
+            MaintenanceState::ExitMaintenanceInProgress => 3,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
 pub enum MdmConnectionState {
     Connected,
     Disconnected,
@@ -868,7 +2020,7 @@ impl ToString for MdmConnectionState {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Serialize)]
 // This is the RmcacheMemoryAllocationState in V3
 pub enum MemoryAllocationState {
     RmcacheMemoryAllocationStateInvalid,
@@ -892,10 +2044,11 @@ impl ToString for MemoryAllocationState {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Default, Deserialize, Debug, Serialize)]
 pub enum PerfProfile {
     Compact, // new to V3
     Custom,
+    #[default]
     Default,
     HighPerformance,
 }
@@ -929,6 +2082,96 @@ fn test_instances() {
 
     let i: Vec<Instance> = serde_json::from_str(&buff).unwrap();
     println!("result: {:#?}", i);
+    let points: Vec<TsPoint> = i
+        .iter()
+        .flat_map(|instance| instance.into_point(Some(Instance::MEASUREMENT), true))
+        .collect();
+    assert!(changed_device_paths(&points).is_empty());
+    for (instance, point) in i.iter().zip(points.iter()) {
+        assert_eq!(
+            point.tags.get("error_state"),
+            instance
+                .error_state
+                .as_ref()
+                .map(|s| TsValue::String(s.clone()))
+                .as_ref()
+        );
+    }
+}
+
+#[test]
+fn test_instance_device_path_changed() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f = File::open("tests/scaleio/instances_path_changed.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+
+    let i: Vec<Instance> = serde_json::from_str(&buff).unwrap();
+    let points: Vec<TsPoint> = i
+        .iter()
+        .flat_map(|instance| instance.into_point(Some(Instance::MEASUREMENT), true))
+        .collect();
+    assert_eq!(
+        changed_device_paths(&points),
+        vec![(
+            "t51s1sanumb3r006".to_string(),
+            "/dev/sdc".to_string(),
+            "/dev/sdm".to_string()
+        )]
+    );
+    assert_eq!(
+        points[1].fields.get("device_path_changed"),
+        Some(&TsValue::Boolean(true))
+    );
+    assert_eq!(
+        points[0].fields.get("device_path_changed"),
+        Some(&TsValue::Boolean(false))
+    );
+    assert!(!points[0].fields.contains_key("device_path_changed_from"));
+    let mut fresh = i[1].clone();
+    fresh.device_original_path_name = String::new();
+    assert!(!fresh.device_path_changed());
+}
+
+#[test]
+fn test_instance_long_ios_windows() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f = File::open("tests/scaleio/instances_long_ios.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+
+    let i: Vec<Instance> = serde_json::from_str(&buff).unwrap();
+    let points: Vec<TsPoint> = i
+        .iter()
+        .flat_map(|instance| instance.into_point(Some(Instance::MEASUREMENT), true))
+        .collect();
+    let windowed: Vec<&TsPoint> = points
+        .iter()
+        .filter(|p| {
+            p.fields
+                .contains_key("short_window_avg_read_latency_in_microsec")
+        })
+        .collect();
+    assert_eq!(windowed.len(), 1);
+    assert_eq!(
+        windowed[0]
+            .fields
+            .get("short_window_avg_read_latency_in_microsec"),
+        Some(&TsValue::Long(1850))
+    );
+    assert_eq!(
+        windowed[0]
+            .fields
+            .get("short_window_fixed_read_error_count"),
+        Some(&TsValue::Long(3))
+    );
+    assert!(!windowed[0]
+        .fields
+        .contains_key("medium_window_avg_read_latency_in_microsec"));
 }
 
 #[test]
@@ -969,30 +2212,130 @@ pub struct DeviceSelectedStatisticsResponse {
     pub device: HashMap<String, HashMap<String, u64>>,
 }
 
+/// Storage pool statistics keyed by pool id.  A pool whose entry can't be
+/// parsed is logged and counted in pools_skipped rather than failing the
+/// whole response.  The count is reported in the
+/// scaleio_pool_stats_summary point
 #[derive(Deserialize, Debug)]
+#[serde(from = "RawClusterSelectedStatisticsResponse")]
 pub struct ClusterSelectedStatisticsResponse {
-    #[serde(rename = "StoragePool")]
     pub storage_pool: HashMap<String, StoragePoolInfo>,
+    pub pools_skipped: u64,
+}
+
+#[derive(Deserialize)]
+struct RawClusterSelectedStatisticsResponse {
+    #[serde(rename = "StoragePool")]
+    storage_pool: HashMap<String, serde_json::Value>,
+}
+
+impl From<RawClusterSelectedStatisticsResponse> for ClusterSelectedStatisticsResponse {
+    fn from(raw: RawClusterSelectedStatisticsResponse) -> Self {
+        let mut storage_pool = HashMap::new();
+        let mut pools_skipped = 0;
+        for (pool_id, value) in raw.storage_pool {
+            match serde_json::from_value::<StoragePoolInfo>(value) {
+                Ok(info) => {
+                    storage_pool.insert(pool_id, info);
+                }
+                Err(e) => {
+                    warn!("Skipping statistics of storage pool {}: {}", pool_id, e);
+                    pools_skipped += 1;
+                }
+            }
+        }
+        ClusterSelectedStatisticsResponse {
+            storage_pool,
+            pools_skipped,
+        }
+    }
+}
+
+impl ClusterSelectedStatisticsResponse {
+    pub const MEASUREMENT: &str = "scaleio_pool_stats";
+    pub const SUMMARY_MEASUREMENT: &str = "scaleio_pool_stats_summary";
+}
+
+impl IntoPoint for ClusterSelectedStatisticsResponse {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut points: Vec<TsPoint> = Vec::new();
+        for (pool_id, info) in self.storage_pool.iter() {
+            for mut p in info.into_point(Some(name.unwrap_or(Self::MEASUREMENT)), is_time_series) {
+                p.add_tag("storage_pool_id", TsValue::String(pool_id.to_string()));
+                points.push(p);
+            }
+        }
+        let mut summary = TsPoint::new(Self::SUMMARY_MEASUREMENT, is_time_series);
+        summary.add_field("pool_count", TsValue::Long(self.storage_pool.len() as u64));
+        summary.add_field("pools_skipped", TsValue::Long(self.pools_skipped));
+        points.push(summary);
+        points
+    }
 }
 
+/// Any of these can be left out for a pool that is being migrated
 #[derive(Deserialize, Debug, IntoPoint)]
 #[serde(rename_all = "camelCase")]
 pub struct StoragePoolInfo {
     // StoragePoolStatistics (selected ones)
-    pub num_of_devices: u64,
-    pub num_of_volumes: u64,
-    pub primary_read_bwc: BWC,
-    pub primary_write_bwc: BWC,
-    pub secondary_write_bwc: BWC,
-    pub secondary_read_bwc: BWC,
-    pub capacity_limit_in_kb: u64,
-    pub thick_capacity_in_use_in_kb: u64,
+    pub num_of_devices: Option<u64>,
+    pub num_of_volumes: Option<u64>,
+    pub primary_read_bwc: Option<BWC>,
+    pub primary_write_bwc: Option<BWC>,
+    pub secondary_write_bwc: Option<BWC>,
+    pub secondary_read_bwc: Option<BWC>,
+    pub capacity_limit_in_kb: Option<u64>,
+    pub thick_capacity_in_use_in_kb: Option<u64>,
     // this attribute will be deleted in future versions of PowerFlex/SIO
     pub thin_capacity_in_use_in_kb: Option<u64>, // deprecated, use net_thin_user_data_capacity_in_kb * 2
     pub net_thin_user_data_capacity_in_kb: Option<u64>,
-    pub thin_capacity_allocated_in_km: u64,
-    pub total_write_bwc: BWC,
-    pub total_read_bwc: BWC,
+    pub thin_capacity_allocated_in_km: Option<u64>,
+    pub total_write_bwc: Option<BWC>,
+    pub total_read_bwc: Option<BWC>,
+}
+
+#[test]
+fn test_degraded_pool_stats() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f =
+        File::open("tests/scaleio/clusterSelectedStatisticsResponse_degraded.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+
+    let i: ClusterSelectedStatisticsResponse = serde_json::from_str(&buff).unwrap();
+    println!("result: {:#?}", i);
+    assert_eq!(i.pools_skipped, 1);
+    assert_eq!(i.storage_pool.len(), 2);
+    assert!(!i.storage_pool.contains_key("c5ef15cb00000002"));
+
+    let (summary, mut points): (Vec<TsPoint>, Vec<TsPoint>) = i
+        .into_point(Some("scaleio_pool_stats"), true)
+        .into_iter()
+        .partition(|p| p.measurement == ClusterSelectedStatisticsResponse::SUMMARY_MEASUREMENT);
+    assert_eq!(summary.len(), 1);
+    assert_eq!(summary[0].fields["pool_count"], TsValue::Long(2));
+    assert_eq!(summary[0].fields["pools_skipped"], TsValue::Long(1));
+    points.sort_by_key(|p| p.tags["storage_pool_id"].to_string());
+    assert_eq!(points.len(), 2);
+    let healthy = &points[0];
+    assert_eq!(
+        healthy.tags["storage_pool_id"],
+        TsValue::String("c5ef15cb00000000".into())
+    );
+    assert_eq!(healthy.fields["num_of_devices"], TsValue::Long(216));
+    assert!(healthy.fields.contains_key("primary_read_bwc"));
+    // The migrating pool only reports what the gateway sent
+    let migrating = &points[1];
+    assert_eq!(
+        migrating.fields["capacity_limit_in_kb"],
+        TsValue::Long(421785391104)
+    );
+    assert!(!migrating.fields.contains_key("primary_read_bwc"));
+    assert!(!migrating
+        .fields
+        .contains_key("thin_capacity_allocated_in_km"));
 }
 
 #[derive(Deserialize, Debug)]
@@ -1001,12 +2344,16 @@ pub struct SdcSelectedStatisticsResponse {
     pub sdc: HashMap<String, SdcStatsInfo>,
 }
 
+impl SdcSelectedStatisticsResponse {
+    pub const MEASUREMENT: &str = "scaleio_sdc_stats";
+}
+
 impl IntoPoint for SdcSelectedStatisticsResponse {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
         let mut all_sdc_stats: Vec<TsPoint> = Vec::new();
         for (key, value) in self.sdc.iter() {
             let point: Vec<TsPoint> = value
-                .into_point(name, is_time_series)
+                .into_point(Some(name.unwrap_or(Self::MEASUREMENT)), is_time_series)
                 .iter_mut()
                 .map(|p| {
                     p.add_tag("sdc_id", TsValue::String(key.to_string()));
@@ -1024,16 +2371,118 @@ impl IntoPoint for SdcSelectedStatisticsResponse {
 pub struct SdcStatsInfo {
     pub user_data_read_bwc: BWC,
     pub user_data_write_bwc: BWC,
-    pub user_data_trim_bwc: Option<BWC>,          // in v2 and v3
-    pub user_data_sdc_read_latency: Option<BWC>,  // NEW V3
+    pub user_data_trim_bwc: Option<BWC>, // in v2 and v3
+    #[point(latency)]
+    pub user_data_sdc_read_latency: Option<BWC>, // NEW V3
+    #[point(latency)]
     pub user_data_sdc_write_latency: Option<BWC>, // NEW V3
-    pub user_data_sdc_trim_latency: Option<BWC>,  // NEW V3
+    #[point(latency)]
+    pub user_data_sdc_trim_latency: Option<BWC>, // NEW V3
     pub volume_ids: Vec<String>,
     pub num_of_mapped_volumes: u64,
 }
 
+#[test]
+fn test_volume_selected_stats() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f = File::open("tests/scaleio/volumeSelectedStatisticsResponse.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+
+    let i: VolumeSelectedStatisticsResponse = serde_json::from_str(&buff).unwrap();
+    println!("result: {:#?}", i);
+    let points = i.into_point(Some("scaleio_volume_stats"), true);
+    println!("points: {:#?}", points);
+    assert_eq!(points.len(), 2);
+
+    let busy = points
+        .iter()
+        .find(|p| p.tags["volume_id"] == TsValue::String("a1b2c3d400000001".into()))
+        .unwrap();
+    assert_eq!(busy.measurement, "scaleio_volume_stats");
+    assert_eq!(busy.fields["num_of_mapped_sdcs"], TsValue::Long(2));
+    assert_eq!(
+        busy.fields["user_data_read_bwc_total_weight_in_kb"],
+        TsValue::Long(48000)
+    );
+    // Latency is totalWeight / numOccured / numSeconds
+    assert_eq!(
+        busy.fields["user_data_sdc_write_latency"],
+        TsValue::Long(160)
+    );
+    // and in microseconds totalWeight / numOccured
+    assert_eq!(
+        busy.fields["user_data_sdc_write_latency_microsec"],
+        TsValue::Long(800)
+    );
+    assert_eq!(
+        busy.fields["user_data_sdc_read_latency_microsec"],
+        TsValue::Long(500)
+    );
+    // No trims were sampled, so there's no latency rather than a 0
+    assert!(!busy.fields.contains_key("user_data_sdc_trim_latency"));
+    assert!(!busy
+        .fields
+        .contains_key("user_data_sdc_trim_latency_microsec"));
+
+    // Older releases don't report trim or latency meters
+    let idle = points
+        .iter()
+        .find(|p| p.tags["volume_id"] == TsValue::String("a1b2c3d500000002".into()))
+        .unwrap();
+    assert!(!idle.fields.contains_key("user_data_sdc_read_latency"));
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VolumeSelectedStatisticsResponse {
+    #[serde(rename = "Volume")]
+    pub volume: HashMap<String, VolumeStatistics>,
+}
+
+impl VolumeSelectedStatisticsResponse {
+    pub const MEASUREMENT: &str = "scaleio_volume_stats";
+}
+
+impl IntoPoint for VolumeSelectedStatisticsResponse {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut all_volume_stats: Vec<TsPoint> = Vec::new();
+        for (key, value) in self.volume.iter() {
+            let point: Vec<TsPoint> = value
+                .into_point(Some(name.unwrap_or(Self::MEASUREMENT)), is_time_series)
+                .into_iter()
+                .map(|mut p| {
+                    p.add_tag("volume_id", TsValue::String(key.to_string()));
+                    p
+                })
+                .collect();
+            all_volume_stats.extend(point);
+        }
+        all_volume_stats
+    }
+}
+
+#[derive(Deserialize, Debug, IntoPoint)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeStatistics {
+    pub num_of_mapped_sdcs: u64,
+    pub num_of_child_volumes: Option<u64>,
+    pub num_of_descendant_volumes: Option<u64>,
+    pub user_data_read_bwc: BWC,
+    pub user_data_write_bwc: BWC,
+    pub user_data_trim_bwc: Option<BWC>, // in v2 and v3
+    #[point(latency)]
+    pub user_data_sdc_read_latency: Option<BWC>, // NEW V3
+    #[point(latency)]
+    pub user_data_sdc_write_latency: Option<BWC>, // NEW V3
+    #[point(latency)]
+    pub user_data_sdc_trim_latency: Option<BWC>, // NEW V3
+}
+
 #[derive(Clone, Deserialize, Debug, IntoPoint)]
 #[serde(rename_all = "camelCase")]
+#[point(extend_with = "device_path_point")]
 pub struct Instance {
     // Device Object
     pub device_current_path_name: String,  // in v3
@@ -1044,6 +2493,7 @@ pub struct Instance {
     pub capacity_limit_in_kb: Option<u64>, // in v3
     pub max_capacity_in_kb: u64,           // in v3
     pub storage_pool_id: Option<String>,   // in v3 ** required, however can still be null
+    #[point(flatten_with = "successful_ios_point")]
     pub long_successful_ios: Option<Successfulio>,
     pub error_state: Option<String>, // in v3 (note this could be an enum)
     pub name: Option<String>,        // in v3
@@ -1056,7 +2506,56 @@ pub struct Instance {
                                           // SDS object....
 }
 
-#[derive(Debug, Deserialize)]
+impl Instance {
+    pub const MEASUREMENT: &str = "scaleio_drive";
+}
+
+impl Instance {
+    /// The OS renamed the block device since it was added, usually after a
+    /// reboot or the HBAs being reordered.  A device without an original
+    /// path is new and hasn't been renamed
+    pub fn device_path_changed(&self) -> bool {
+        !self.device_original_path_name.is_empty()
+            && self.device_current_path_name != self.device_original_path_name
+    }
+}
+
+pub fn device_path_point(instance: &Instance, p: &mut TsPoint) {
+    let changed = instance.device_path_changed();
+    p.add_field("device_path_changed", TsValue::Boolean(changed));
+    if changed {
+        p.add_field(
+            "device_path_changed_from",
+            TsValue::String(instance.device_original_path_name.clone()),
+        );
+    }
+}
+
+/// The (sds_id, original path, current path) of every drive point whose
+/// device path changed
+pub fn changed_device_paths(points: &[TsPoint]) -> Vec<(String, String, String)> {
+    let tag = |p: &TsPoint, name: &str| match p.tags.get(name) {
+        Some(TsValue::String(s)) => s.clone(),
+        _ => String::new(),
+    };
+    points
+        .iter()
+        .filter(|p| p.fields.get("device_path_changed") == Some(&TsValue::Boolean(true)))
+        .map(|p| {
+            let original = match p.fields.get("device_path_changed_from") {
+                Some(TsValue::String(s)) => s.clone(),
+                _ => tag(p, "device_original_path_name"),
+            };
+            (
+                tag(p, "sds_id"),
+                original,
+                tag(p, "device_current_path_name"),
+            )
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MdmCluster {
     pub master: TieBreaker,            //
@@ -1070,16 +2569,44 @@ pub struct MdmCluster {
     pub cluster_state: String,         //
     pub name: String,                  //
     pub id: String,                    //
+    #[serde(alias = "virtualIps")]
     pub virtual_ip: Option<Vec<String>>, // NEW V3
 }
 
+// Sorted and without duplicates so the tag doesn't change with the order
+// the gateway lists nodes in
+fn management_ips<'a, I>(nodes: I) -> Vec<String>
+where
+    I: Iterator<Item = &'a TieBreaker>,
+{
+    let mut ips: Vec<String> = nodes.flat_map(|n| n.management_ips.clone()).collect();
+    ips.sort();
+    ips.dedup();
+    ips
+}
+
+impl MdmCluster {
+    /// Management IPs of the master, slave and standby mdms
+    pub fn mdm_management_ips(&self) -> Vec<String> {
+        management_ips(
+            std::iter::once(&self.master)
+                .chain(self.slaves.iter())
+                .chain(self.standby_mdms.iter().flatten()),
+        )
+    }
+
+    pub fn tie_breaker_management_ips(&self) -> Vec<String> {
+        management_ips(self.tie_breakers.iter())
+    }
+}
+
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SelectedStatisticsRequest {
     pub selected_statistics_list: Vec<StatsRequest>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Clone, Serialize, Debug)]
 pub enum StatsRequestType {
     System,
     ProtectionDomain,
@@ -1093,7 +2620,7 @@ pub enum StatsRequestType {
     RfcacheDevice,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub enum RebuildIoPriority {
     #[serde(rename = "unlimited")]
     Unlimited,
@@ -1105,20 +2632,32 @@ pub enum RebuildIoPriority {
     DynamicBwThrottling,
 }
 
-#[derive(Debug, Deserialize)]
+impl fmt::Display for RebuildIoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            RebuildIoPriority::Unlimited => "unlimited",
+            RebuildIoPriority::LimitNumOfConcurrentIos => "limitNumOfConcurrentIos",
+            RebuildIoPriority::FavorAppIos => "favorAppIos",
+            RebuildIoPriority::DynamicBwThrottling => "dynamicBwThrottling",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum BackgroundScannerMode {
     Disabled,
     DeviceOnly,
     DataComparison,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum CacheWriteHandlingMode {
     Passthrough,
     Cached,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Clone, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct StatsRequest {
     #[serde(rename = "type")]
@@ -1130,13 +2669,337 @@ pub struct StatsRequest {
     pub properties: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// A querySelectedStatistics property name, ie "avgReadLatencyInMicrosec"
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct StatsProperty(pub String);
+
+impl From<&str> for StatsProperty {
+    fn from(s: &str) -> Self {
+        StatsProperty(s.to_string())
+    }
+}
+
+pub const DRIVE_STATS_PROPERTIES: &[&str] = &[
+    "fixedReadErrorCount",
+    "avgReadSizeInBytes",
+    "avgWriteSizeInBytes",
+    "avgReadLatencyInMicrosec",
+    "avgWriteLatencyInMicrosec",
+];
+
+pub const SDC_STATS_PROPERTIES: &[&str] = &[
+    "userDataReadBwc",
+    "userDataWriteBwc",
+    "volumeIds",
+    "numOfMappedVolumes",
+];
+
+pub const POOL_STATS_PROPERTIES: &[&str] = &[
+    "numOfDevices",
+    "numOfVolumes",
+    "capacityLimitInKb",
+    "thickCapacityInUseInKb",
+    "netThinUserDataCapacityInKb", // thinCapacityInUseInKb is deprecated
+    "primaryReadBwc",
+    "primaryWriteBwc",
+    "secondaryReadBwc",
+    "secondaryWriteBwc",
+    "totalReadBwc",
+    "totalWriteBwc",
+    "thinCapacityAllocatedInKm",
+];
+
+// 2.x clusters don't have netThinUserDataCapacityInKb
+pub const POOL_STATS_PROPERTIES_V2: &[&str] = &[
+    "numOfDevices",
+    "numOfVolumes",
+    "capacityLimitInKb",
+    "thickCapacityInUseInKb",
+    "thinCapacityInUseInKb",
+    "primaryReadBwc",
+    "primaryWriteBwc",
+    "secondaryReadBwc",
+    "secondaryWriteBwc",
+    "totalReadBwc",
+    "totalWriteBwc",
+    "thinCapacityAllocatedInKm",
+];
+
+pub const VOLUME_STATS_PROPERTIES: &[&str] = &[
+    "numOfMappedSdcs",
+    "numOfChildVolumes",
+    "numOfDescendantVolumes",
+    "userDataReadBwc",
+    "userDataWriteBwc",
+    "userDataTrimBwc",
+    "userDataSdcReadLatency",
+    "userDataSdcWriteLatency",
+    "userDataSdcTrimLatency",
+];
+
+impl SelectedStatisticsRequest {
+    /// Request statistics for every object of req_type.  The configured
+    /// properties are used if given, otherwise the defaults
+    pub fn new(
+        req_type: StatsRequestType,
+        properties: &Option<Vec<StatsProperty>>,
+        defaults: &[&str],
+    ) -> Self {
+        let properties = match properties {
+            Some(props) => props.iter().map(|p| p.0.clone()).collect(),
+            None => defaults.iter().map(|p| p.to_string()).collect(),
+        };
+        SelectedStatisticsRequest {
+            selected_statistics_list: vec![StatsRequest {
+                req_type,
+                all_ids: vec![],
+                properties,
+            }],
+        }
+    }
+}
+
+impl SelectedStatisticsRequest {
+    /// Halve a request, by its list of requests when it has more than one
+    /// and otherwise by the properties of the one.  None if there's only a
+    /// single property left
+    pub fn split(&self) -> Option<(Self, Self)> {
+        let list = &self.selected_statistics_list;
+        if list.len() > 1 {
+            let (a, b) = list.split_at(list.len() / 2);
+            return Some((
+                SelectedStatisticsRequest {
+                    selected_statistics_list: a.to_vec(),
+                },
+                SelectedStatisticsRequest {
+                    selected_statistics_list: b.to_vec(),
+                },
+            ));
+        }
+        let req = list.first()?;
+        if req.properties.len() < 2 {
+            return None;
+        }
+        let (a, b) = req.properties.split_at(req.properties.len() / 2);
+        let half = |properties: &[String]| SelectedStatisticsRequest {
+            selected_statistics_list: vec![StatsRequest {
+                req_type: req.req_type.clone(),
+                all_ids: req.all_ids.clone(),
+                properties: properties.to_vec(),
+            }],
+        };
+        Some((half(a), half(b)))
+    }
+}
+
+// Fold the querySelectedStatistics response b into a.  Responses are
+// objects of type, then object id, then property so they're merged key
+// by key
+fn merge_statistics(a: &mut serde_json::Value, b: serde_json::Value) {
+    match (a, b) {
+        (serde_json::Value::Object(a), serde_json::Value::Object(b)) => {
+            for (key, value) in b {
+                match a.get_mut(&key) {
+                    Some(existing) => merge_statistics(existing, value),
+                    None => {
+                        a.insert(key, value);
+                    }
+                }
+            }
+        }
+        (a, b) => *a = b,
+    }
+}
+
+#[test]
+fn test_stats_request_split() {
+    let req = SelectedStatisticsRequest::new(
+        StatsRequestType::Volume,
+        &None,
+        &["userDataReadBwc", "userDataWriteBwc", "numOfMappedSdcs"],
+    );
+    let (a, b) = req.split().unwrap();
+    assert_eq!(
+        a.selected_statistics_list[0].properties,
+        vec!["userDataReadBwc"]
+    );
+    assert_eq!(
+        b.selected_statistics_list[0].properties,
+        vec!["userDataWriteBwc", "numOfMappedSdcs"]
+    );
+    assert!(a.split().is_none());
+
+    let mut merged = serde_json::json!({"Volume": {"v1": {"userDataReadBwc": 1}}});
+    merge_statistics(
+        &mut merged,
+        serde_json::json!({"Volume": {"v1": {"numOfMappedSdcs": 2}, "v2": {"numOfMappedSdcs": 3}}}),
+    );
+    assert_eq!(
+        merged,
+        serde_json::json!({"Volume": {
+            "v1": {"userDataReadBwc": 1, "numOfMappedSdcs": 2},
+            "v2": {"numOfMappedSdcs": 3},
+        }})
+    );
+}
+
+#[test]
+fn test_stats_request_properties() {
+    let custom: Option<Vec<StatsProperty>> = Some(vec![
+        "avgReadLatencyInMicrosec".into(),
+        "fixedReadErrorCount".into(),
+        "numOfIgnoredReadErrors".into(),
+    ]);
+    let req =
+        SelectedStatisticsRequest::new(StatsRequestType::Device, &custom, DRIVE_STATS_PROPERTIES);
+    let json = serde_json::to_value(&req).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "selectedStatisticsList": [{
+                "type": "Device",
+                "allIds": [],
+                "properties": [
+                    "avgReadLatencyInMicrosec",
+                    "fixedReadErrorCount",
+                    "numOfIgnoredReadErrors"
+                ]
+            }]
+        })
+    );
+
+    // Nothing configured falls back to the defaults
+    let req = SelectedStatisticsRequest::new(StatsRequestType::Sdc, &None, SDC_STATS_PROPERTIES);
+    assert_eq!(
+        req.selected_statistics_list[0].properties,
+        SDC_STATS_PROPERTIES
+    );
+
+    // Configs without any properties still deserialize
+    let config: ScaleioConfig = serde_json::from_str(
+        r#"{"endpoint": "localhost", "user": "admin", "password": "",
+            "region": "test", "pool_stats_properties": ["numOfDevices"]}"#,
+    )
+    .unwrap();
+    assert!(config.drive_stats_properties.is_none());
+    assert_eq!(
+        config.pool_stats_properties,
+        Some(vec![StatsProperty("numOfDevices".into())])
+    );
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct IpObject {
     pub ip: IpAddr,
     pub role: IpRole,
 }
 
-#[derive(Debug, Deserialize, IntoPoint)]
+/// An address reported for an sdc.  Values that look like an IP address
+/// must parse as one, anything else (a hostname) is kept as reported
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostAddress {
+    Ip(IpAddr),
+    Name(String),
+}
+
+impl str::FromStr for HostAddress {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> MetricsResult<Self> {
+        if let Ok(ip) = s.parse::<IpAddr>() {
+            return Ok(HostAddress::Ip(ip));
+        }
+        let looks_v4 = s.contains('.') && s.chars().all(|c| c.is_ascii_digit() || c == '.');
+        let looks_v6 = s.contains(':')
+            && s.chars()
+                .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.');
+        if looks_v4 || looks_v6 {
+            return Err(StorageError::new(format!("invalid ip address {:?}", s)));
+        }
+        Ok(HostAddress::Name(s.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for HostAddress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for HostAddress {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// IPs are written in their normalized form
+impl fmt::Display for HostAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HostAddress::Ip(ip) => write!(f, "{}", ip),
+            HostAddress::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[test]
+fn test_host_address() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let parse = |s: &str| serde_json::from_str::<HostAddress>(&format!("{:?}", s));
+    assert_eq!(
+        parse("10.1.2.3").unwrap(),
+        HostAddress::Ip("10.1.2.3".parse().unwrap())
+    );
+    assert_eq!(
+        parse("2001:0db8:0:0::0001").unwrap().to_string(),
+        "2001:db8::1"
+    );
+    assert_eq!(
+        parse("sdc-01.example.com").unwrap(),
+        HostAddress::Name("sdc-01.example.com".into())
+    );
+    assert!(parse("10.1.2.256").is_err());
+    assert!(parse("10.1.2").is_err());
+    assert!(parse("fe80::1::2").is_err());
+
+    let mapping: SdcMappingInfo = serde_json::from_str(
+        r#"{"sdcId": "a1b2", "sdcIp": "2001:db8:0:0:0:0:0:1", "limitIops": 0, "limitBwInMbps": 0}"#,
+    )
+    .unwrap();
+    let points = mapping.into_point(None, true);
+    assert_eq!(
+        points[0].tags.get("sdc_ip"),
+        Some(&TsValue::String("2001:db8::1".into()))
+    );
+    assert!(serde_json::from_str::<SdcMappingInfo>(
+        r#"{"sdcId": "a1b2", "sdcIp": "10.0.0.300", "limitIops": 0, "limitBwInMbps": 0}"#,
+    )
+    .is_err());
+
+    let mut f = File::open("tests/scaleio/sdc_info.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+    let sdcs: Vec<Sdc> = serde_json::from_str(&buff).unwrap();
+    let points = sdcs[0].into_point(None, true);
+    assert_eq!(
+        points[0].tags.get("sdc_ip"),
+        Some(&TsValue::String("192.168.1.1".into()))
+    );
+}
+
+#[derive(Clone, Debug, Deserialize, IntoPoint, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ScsiInitiatorMappingInfo {
     pub scsi_initiator_id: String,
@@ -1145,20 +3008,28 @@ pub struct ScsiInitiatorMappingInfo {
     pub lun: String,
 }
 
-#[derive(Debug, Deserialize)]
+impl ScsiInitiatorMappingInfo {
+    pub const MEASUREMENT: &str = "scaleio_volume_scsi";
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SdcMappingInfo {
     pub sdc_id: String,
-    pub sdc_ip: String,
+    pub sdc_ip: HostAddress,
     pub limit_iops: u64,
     pub limit_bw_in_mbps: u64,
 }
 
+impl SdcMappingInfo {
+    pub const MEASUREMENT: &str = "scaleio_volume_sdc";
+}
+
 impl IntoPoint for SdcMappingInfo {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
-        let mut p = TsPoint::new(name.unwrap_or("scaleio_volume_sdc"), is_time_series);
+        let mut p = TsPoint::new(name.unwrap_or(Self::MEASUREMENT), is_time_series);
         p.add_tag("sdc_id", TsValue::String(self.sdc_id.clone()));
-        p.add_tag("sdc_ip", TsValue::String(self.sdc_ip.clone()));
+        p.add_tag("sdc_ip", TsValue::String(self.sdc_ip.to_string()));
         p.add_field("limit_iops", TsValue::Long(self.limit_iops));
         p.add_field("limit_bw_in_mbps", TsValue::Long(self.limit_bw_in_mbps));
 
@@ -1166,7 +3037,7 @@ impl IntoPoint for SdcMappingInfo {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SdsVolume {
     // Volume Object
@@ -1186,56 +3057,55 @@ pub struct SdsVolume {
     pub use_rmcache: Option<bool>,
 }
 
+impl SdsVolume {
+    pub const MEASUREMENT: &str = "scaleio_volume";
+}
+
 impl IntoPoint for SdsVolume {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
         let mut points: Vec<TsPoint> = Vec::new();
-        let mut p = TsPoint::new(name.unwrap_or("scaleio_volume"), is_time_series);
+        let mut p = TsPoint::new(name.unwrap_or(Self::MEASUREMENT), is_time_series);
         p.add_tag("id", TsValue::String(self.id.clone()));
         if let Some(ref name) = self.name {
             p.add_tag("name", TsValue::String(name.clone()));
         }
         p.add_field("size_in_kb", TsValue::Long(self.size_in_kb));
-        if let Some(ref is_obfuscated) = self.is_obfuscated {
-            p.add_field("is_obfuscated", TsValue::Boolean(is_obfuscated.clone()));
+        if let Some(is_obfuscated) = self.is_obfuscated {
+            p.add_field("is_obfuscated", TsValue::Boolean(is_obfuscated));
         }
         p.add_field("creation_time", TsValue::Long(self.creation_time));
         p.add_tag("volume_type", TsValue::String(self.volume_type.clone()));
         if let Some(ref group_id) = self.consistency_group_id {
             p.add_tag("consistency_group_id", TsValue::String(group_id.clone()));
         }
-        if let Some(ref mapping_to_all_sdcs_enabled) = self.mapping_to_all_sdcs_enabled {
+        if let Some(mapping_to_all_sdcs_enabled) = self.mapping_to_all_sdcs_enabled {
             p.add_field(
                 "mapping_to_all_sdcs_enabled",
-                TsValue::Boolean(mapping_to_all_sdcs_enabled.clone()),
+                TsValue::Boolean(mapping_to_all_sdcs_enabled),
             );
         }
 
         // This is a 1:Many relationship so we're going to denormalize that here
-        // and store the sdc_info is a separate table with the volume id so we can
-        // find it later
-        if let Some(ref mapped_sdc_info) = self.mapped_sdc_info {
-            for sdc_map in mapped_sdc_info {
-                sdc_map
-                    .into_point(Some("scaleio_volume_sdc"), is_time_series)
-                    .into_iter()
-                    .for_each(|mut point| {
-                        // Add the volume id so we can look this up later
-                        point.add_tag("volume", TsValue::String(self.id.clone()));
-                        points.push(point);
-                    });
-            }
-        }
-
-        if let Some(ref mapped_scsi_list) = self.mapped_scsi_initiator_info_list {
-            for scsi_map in mapped_scsi_list {
-                scsi_map
-                    .into_point(Some("scaleio_volume_scsi"), is_time_series)
-                    .into_iter()
-                    .for_each(|mut point| {
-                        point.add_tag("volume", TsValue::String(self.id.clone()));
-                        points.push(point);
-                    });
-            }
+        // and store the sdc and scsi mappings in separate tables with the
+        // volume id so we can find it later.  The mapping points are tagged
+        // volume, never id, so they can't be mistaken for the volume itself
+        let mapping_points = self
+            .mapped_sdc_info
+            .iter()
+            .flatten()
+            .flat_map(|m| m.into_point(Some(SdcMappingInfo::MEASUREMENT), is_time_series))
+            .chain(
+                self.mapped_scsi_initiator_info_list
+                    .iter()
+                    .flatten()
+                    .flat_map(|m| {
+                        m.into_point(Some(ScsiInitiatorMappingInfo::MEASUREMENT), is_time_series)
+                    }),
+            );
+        for mut point in mapping_points {
+            debug_assert!(!point.tags.contains_key("volume"));
+            point.add_tag("volume", TsValue::String(self.id.clone()));
+            points.push(point);
         }
 
         if let Some(ref ancestor) = self.ancestor_volume_id {
@@ -1255,6 +3125,197 @@ impl IntoPoint for SdsVolume {
     }
 }
 
+/// Where a vtree is in a storage pool migration.  Only PowerFlex 3.5 and
+/// later report this
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VTreeMigrationInfo {
+    pub source_storage_pool_id: Option<String>,
+    pub destination_storage_pool_id: Option<String>,
+    pub migration_status: String,
+    pub migration_pause_reason: Option<String>,
+    pub migration_queue_position: Option<u64>,
+    pub percent_done: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VTree {
+    pub id: String,
+    pub name: Option<String>,
+    pub storage_pool_id: String,
+    pub base_volume_id: Option<String>,
+    pub vtree_migration_info: Option<VTreeMigrationInfo>,
+}
+
+impl VTree {
+    /// The migration this vtree is part of, if any
+    pub fn migration(&self) -> Option<VTreeMigration> {
+        let info = self.vtree_migration_info.as_ref()?;
+        if info.migration_status == "NotInMigration" {
+            return None;
+        }
+        Some(VTreeMigration {
+            vtree_id: self.id.clone(),
+            source_pool_id: info
+                .source_storage_pool_id
+                .clone()
+                .unwrap_or_else(|| self.storage_pool_id.clone()),
+            destination_pool_id: info.destination_storage_pool_id.clone(),
+            status: info.migration_status.clone(),
+            queue_position: info.migration_queue_position,
+            percent_done: info.percent_done,
+        })
+    }
+}
+
+/// A vtree moving between storage pools
+#[derive(Clone, Debug, PartialEq)]
+pub struct VTreeMigration {
+    pub vtree_id: String,
+    pub source_pool_id: String,
+    pub destination_pool_id: Option<String>,
+    pub status: String,
+    pub queue_position: Option<u64>,
+    pub percent_done: Option<f64>,
+}
+
+impl VTreeMigration {
+    pub const MEASUREMENT: &str = "scaleio_vtree_migration";
+    pub const SUMMARY_MEASUREMENT: &str = "scaleio_vtree_migration_summary";
+
+    /// Waiting for its turn rather than moving data
+    pub fn is_queued(&self) -> bool {
+        self.status == "PendingMigration"
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.status == "Paused" || self.status.ends_with("Pausing")
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.is_queued() && !self.is_paused()
+    }
+}
+
+impl IntoPoint for VTreeMigration {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or(Self::MEASUREMENT), is_time_series);
+        p.add_tag("vtree_id", TsValue::String(self.vtree_id.clone()));
+        p.add_tag(
+            "source_pool_id",
+            TsValue::String(self.source_pool_id.clone()),
+        );
+        if let Some(ref pool) = self.destination_pool_id {
+            p.add_tag("destination_pool_id", TsValue::String(pool.clone()));
+        }
+        p.add_field("status", TsValue::String(self.status.clone()));
+        if let Some(position) = self.queue_position {
+            p.add_field("queue_position", TsValue::Long(position));
+        }
+        if let Some(percent) = self.percent_done {
+            p.add_field("percent_done", TsValue::Float(percent));
+        }
+
+        vec![p]
+    }
+}
+
+#[test]
+fn test_vtree_migrations() {
+    use std::fs::File;
+
+    let mut buff = String::new();
+    File::open("tests/scaleio/vtree_instances.json")
+        .unwrap()
+        .read_to_string(&mut buff)
+        .unwrap();
+    let vtrees: Vec<VTree> = serde_json::from_str(&buff).unwrap();
+    assert_eq!(vtrees.len(), 4);
+    let migrations: Vec<VTreeMigration> = vtrees.iter().filter_map(|v| v.migration()).collect();
+    assert_eq!(migrations.len(), 3);
+    assert_eq!(migrations[0].vtree_id, "b4e8a9d200000001");
+    assert_eq!(migrations[0].source_pool_id, "8d1cba1b00000000");
+    assert_eq!(
+        migrations[0].destination_pool_id.as_deref(),
+        Some("8d1cba1c00000001")
+    );
+    assert!(migrations[0].is_active());
+    assert!(migrations[2].is_queued());
+
+    let points = migrations[0].into_point(None, true);
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].measurement, VTreeMigration::MEASUREMENT);
+    assert_eq!(points[0].fields["percent_done"], TsValue::Float(42.5));
+    assert_eq!(points[0].fields["queue_position"], TsValue::Long(0));
+    assert_eq!(
+        points[0].tags["destination_pool_id"],
+        TsValue::String("8d1cba1c00000001".into())
+    );
+    let points = migrations[1].into_point(None, true);
+    assert_eq!(points[0].fields["percent_done"], TsValue::Float(87.25));
+}
+
+#[test]
+fn test_sds_volume_mapping_tags() {
+    let vol: SdsVolume = serde_json::from_value(json!({
+        "id": "5a3b1c7e00000001",
+        "name": "vol1",
+        "sizeInKb": 8388608,
+        "creationTime": 1_577_836_800,
+        "volumeType": "ThinProvisioned",
+        "mappedSdcInfo": [
+            {"sdcId": "c1a2b3c400000000", "sdcIp": "10.1.1.10", "limitIops": 0, "limitBwInMbps": 0},
+            {"sdcId": "c1a2b3c400000001", "sdcIp": "10.1.1.11", "limitIops": 500, "limitBwInMbps": 100}
+        ],
+        "mappedScsiInitiatorInfoList": [
+            {"scsiInitiatorId": "e0f1a2b300000000", "scsiInitiatorName": "host1",
+             "scsiInitiatorIqn": "iqn.1994-05.com.redhat:host1", "lun": "3"}
+        ],
+        "vtreeId": "b4e8a9d200000001",
+        "storagePoolId": "8d1cba1b00000000"
+    }))
+    .unwrap();
+    let points = vol.into_point(None, true);
+    assert_eq!(points.len(), 4);
+
+    // The volume point comes last and keeps its own id
+    let volume = points.last().unwrap();
+    assert_eq!(volume.measurement, SdsVolume::MEASUREMENT);
+    assert_eq!(volume.tags["id"], TsValue::String(vol.id.clone()));
+    assert!(!volume.tags.contains_key("volume"));
+
+    let sdc_ids: Vec<&TsValue> = points
+        .iter()
+        .filter(|p| p.measurement == SdcMappingInfo::MEASUREMENT)
+        .map(|p| &p.tags["sdc_id"])
+        .collect();
+    assert_eq!(
+        sdc_ids,
+        vec![
+            &TsValue::String("c1a2b3c400000000".into()),
+            &TsValue::String("c1a2b3c400000001".into())
+        ]
+    );
+    let scsi = points
+        .iter()
+        .find(|p| p.measurement == ScsiInitiatorMappingInfo::MEASUREMENT)
+        .unwrap();
+    assert_eq!(
+        scsi.tags["scsi_initiator_id"],
+        TsValue::String("e0f1a2b300000000".into())
+    );
+    for point in &points[..3] {
+        assert_eq!(point.tags["volume"], TsValue::String(vol.id.clone()));
+        assert!(!point.tags.contains_key("id"));
+        // No mapping tag shares a name with a volume tag
+        assert!(point
+            .tags
+            .keys()
+            .all(|k| k == "volume" || !volume.tags.contains_key(k)));
+    }
+}
+
 #[test]
 fn test_sds_object() {
     use std::fs::File;
@@ -1281,7 +3342,73 @@ fn test_sds_statistics() {
     println!("result: {:#?}", i);
 }
 
-#[derive(Debug, Deserialize, IntoPoint)]
+#[test]
+fn test_network_latency_meters() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f = File::open("tests/scaleio/networkLatencyMeters.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+
+    let meters: Vec<NetworkLatencyMeter> = serde_json::from_str(&buff).unwrap();
+    println!("result: {:#?}", meters);
+    let points: Vec<TsPoint> = meters
+        .iter()
+        .flat_map(|meter| meter.into_point(None, true))
+        .collect();
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[0].measurement, "scaleio_sds_network_latency");
+    assert_eq!(
+        points[0].tags.get("peer_sds_id"),
+        Some(&TsValue::String("6c9b2a8c00000001".into()))
+    );
+    assert_eq!(
+        points[1].fields.get("avg_latency_in_microsec"),
+        Some(&TsValue::Long(2317))
+    );
+    // A peer without any samples has no meaningful latency
+    assert!(!points[2].fields.contains_key("avg_latency_in_microsec"));
+    assert_eq!(
+        points[2].fields.get("num_of_samples"),
+        Some(&TsValue::Long(0))
+    );
+}
+
+/// Latency between an SDS and one of its peers as measured by the
+/// SDS network test
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkLatencyMeter {
+    pub peer_sds_id: String,
+    pub avg_latency_in_microsec: u64,
+    pub num_of_samples: u64,
+}
+
+impl NetworkLatencyMeter {
+    pub const MEASUREMENT: &str = "scaleio_sds_network_latency";
+    /// Counts of the sds that were and weren't queried
+    pub const SUMMARY_MEASUREMENT: &str = "scaleio_sds_network_latency_summary";
+}
+
+impl IntoPoint for NetworkLatencyMeter {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or(Self::MEASUREMENT), is_time_series);
+        p.add_tag("peer_sds_id", TsValue::String(self.peer_sds_id.clone()));
+        // Without samples the average is meaningless
+        if self.num_of_samples > 0 {
+            p.add_field(
+                "avg_latency_in_microsec",
+                TsValue::Long(self.avg_latency_in_microsec),
+            );
+        }
+        p.add_field("num_of_samples", TsValue::Long(self.num_of_samples));
+
+        vec![p]
+    }
+}
+
+#[derive(Debug, Deserialize, IntoPoint, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SdsStatistics {
     active_moving_in_bck_rebuild_jobs: u64,
@@ -1454,7 +3581,16 @@ pub struct SdsStatistics {
     unused_capacity_in_kb: u64,
 }
 
-#[derive(Debug, Deserialize)]
+impl SdsStatistics {
+    pub const MEASUREMENT: &str = "scaleio_sds_stat";
+    pub const SUMMARY_MEASUREMENT: &str = "scaleio_sds_stat_summary";
+}
+
+impl UnknownFields for SdsStatistics {
+    const TYPE_NAME: &'static str = "SdsStatistics";
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SdsObject {
     pub ip_list: Vec<IpObject>,
@@ -1491,9 +3627,382 @@ pub struct SdsObject {
     pub links: Vec<HashMap<String, String>>,
 }
 
-impl IntoPoint for SdsObject {
-    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
-        let mut p = TsPoint::new(name.unwrap_or("scaleio_sds"), is_time_series);
+impl SdsObject {
+    pub const MEASUREMENT: &str = "scaleio_sds";
+    pub const MAINTENANCE_SUMMARY_MEASUREMENT: &str = "scaleio_maintenance_summary";
+}
+
+/// Summarize the maintenance state of the cluster from the points of
+/// get_sds_objects so dashboards and alert silencing can key off a single
+/// series.  Counts the nodes in each state and lists the names of those
+/// entering, in or exiting maintenance.  The summary takes the latest
+/// timestamp of the sds points
+pub fn maintenance_report(sds_points: &[TsPoint]) -> TsPoint {
+    let mut counts = [0u64; 4];
+    let mut names: Vec<String> = Vec::new();
+    let mut summary = TsPoint::new(SdsObject::MAINTENANCE_SUMMARY_MEASUREMENT, true);
+    let latest = sds_points.iter().filter_map(|p| p.timestamp).max();
+    if latest.is_some() {
+        summary.timestamp = latest;
+    }
+    for p in sds_points {
+        let code = match p.fields.get("maintenance_state_code") {
+            Some(TsValue::Long(code)) if *code < 4 => *code as usize,
+            _ => continue,
+        };
+        counts[code] += 1;
+        if code != 0 {
+            if let Some(name) = p.tags.get("name") {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    summary.add_field("no_maintenance", TsValue::Long(counts[0]));
+    summary.add_field("entering_maintenance", TsValue::Long(counts[1]));
+    summary.add_field("in_maintenance", TsValue::Long(counts[2]));
+    summary.add_field("exiting_maintenance", TsValue::Long(counts[3]));
+    summary.add_field("maintenance_nodes", TsValue::StringVec(names));
+    summary
+}
+
+#[test]
+fn test_maintenance_report() {
+    use std::fs::File;
+
+    let mut buff = String::new();
+    File::open("tests/scaleio/sdsObject.json")
+        .unwrap()
+        .read_to_string(&mut buff)
+        .unwrap();
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let states = [
+        ("sds-a", "NoMaintenance"),
+        ("sds-b", "InMaintenance"),
+        ("sds-c", "NoMaintenance"),
+        ("sds-d", "SetMaintenanceInProgress"),
+        ("sds-e", "InMaintenance"),
+        ("sds-f", "ExitMaintenanceInProgress"),
+    ];
+    let mut points: Vec<TsPoint> = Vec::new();
+    for (name, state) in &states {
+        let mut v: serde_json::Value = serde_json::from_str(&buff).unwrap();
+        v["name"] = json!(name);
+        v["maintenanceState"] = json!(state);
+        let sds: SdsObject = serde_json::from_value(v).unwrap();
+        points.extend(sds.into_point(None, true).into_iter().map(|mut p| {
+            p.timestamp = Some(t);
+            p
+        }));
+    }
+    assert_eq!(points[1].fields["maintenance_state_code"], TsValue::Long(2));
+    assert_eq!(
+        points[1].fields["maintenance_state"],
+        TsValue::String("Inmaintenance".into())
+    );
+
+    let summary = maintenance_report(&points);
+    assert_eq!(summary.measurement, "scaleio_maintenance_summary");
+    assert_eq!(summary.timestamp, Some(t));
+    assert_eq!(summary.fields["no_maintenance"], TsValue::Long(2));
+    assert_eq!(summary.fields["entering_maintenance"], TsValue::Long(1));
+    assert_eq!(summary.fields["in_maintenance"], TsValue::Long(2));
+    assert_eq!(summary.fields["exiting_maintenance"], TsValue::Long(1));
+    assert_eq!(
+        summary.fields["maintenance_nodes"],
+        TsValue::StringVec(vec![
+            "sds-b".into(),
+            "sds-d".into(),
+            "sds-e".into(),
+            "sds-f".into()
+        ])
+    );
+
+    // The sds statistics carry the maintenance counterparts
+    let mut buff = String::new();
+    File::open("tests/scaleio/sds_statistics.json")
+        .unwrap()
+        .read_to_string(&mut buff)
+        .unwrap();
+    let stats: SdsStatistics = serde_json::from_str(&buff).unwrap();
+    let fields = &stats.into_point(None, true)[0].fields;
+    assert_eq!(fields["maintenance_mode_state"], TsValue::Long(1));
+    assert!(fields.contains_key("in_maintenance_vac_in_kb"));
+}
+
+/// One point per distinct sds or sdc software version
+pub const VERSION_INVENTORY_MEASUREMENT: &str = "scaleio_version_inventory";
+/// One point per component flagging a mix of versions
+pub const VERSION_SUMMARY_MEASUREMENT: &str = "scaleio_version_summary";
+
+// Version strings compared after trimming and collapsing whitespace.  None
+// for a component that didn't report one
+fn normalize_version(version: &str) -> Option<String> {
+    let normalized = version.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if normalized.is_empty() {
+        None
+    } else {
+        Some(normalized)
+    }
+}
+
+// The inventory and summary points of one component
+fn component_versions<'a, I>(component: &str, versions: I, t: DateTime<Utc>) -> Vec<TsPoint>
+where
+    I: Iterator<Item = Option<&'a str>>,
+{
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    let mut unreported = 0;
+    for version in versions {
+        match version.and_then(normalize_version) {
+            Some(version) => *counts.entry(version).or_insert(0) += 1,
+            None => unreported += 1,
+        }
+    }
+    let mut points: Vec<TsPoint> = counts
+        .iter()
+        .map(|(version, count)| {
+            let mut p = TsPoint::new(VERSION_INVENTORY_MEASUREMENT, true).set_time(t);
+            p.add_tag("component", TsValue::String(component.to_string()));
+            p.add_tag("version", TsValue::String(version.clone()));
+            p.add_field("count", TsValue::Long(*count));
+            p
+        })
+        .collect();
+    let mut summary = TsPoint::new(VERSION_SUMMARY_MEASUREMENT, true).set_time(t);
+    summary.add_tag("component", TsValue::String(component.to_string()));
+    summary.add_field("distinct_versions", TsValue::Long(counts.len() as u64));
+    summary.add_field("version_mismatch", TsValue::Boolean(counts.len() > 1));
+    summary.add_field("unreported", TsValue::Long(unreported));
+    points.push(summary);
+    points
+}
+
+/// Count the sds and sdc software versions so an upgrade left half done,
+/// ie one sdc still on an old kernel module, stands out.  Gives a
+/// scaleio_version_inventory point per component and version with its
+/// count, and a scaleio_version_summary point per component whose
+/// version_mismatch is set when it runs more than one version.  Components
+/// that don't report a version are only counted as unreported
+pub fn version_report(sds: &[SdsObject], sdcs: &[Sdc], t: DateTime<Utc>) -> Vec<TsPoint> {
+    let mut points = component_versions(
+        "sds",
+        sds.iter().map(|s| Some(s.software_version_info.as_str())),
+        t,
+    );
+    points.extend(component_versions(
+        "sdc",
+        sdcs.iter().map(|s| s.version_info.as_deref()),
+        t,
+    ));
+    points
+}
+
+#[test]
+fn test_version_report() {
+    use std::fs::File;
+
+    let read = |path: &str| {
+        let mut buff = String::new();
+        File::open(path).unwrap().read_to_string(&mut buff).unwrap();
+        buff
+    };
+    let sds: Vec<SdsObject> =
+        serde_json::from_str(&read("tests/scaleio/sds_mixed_versions.json")).unwrap();
+    let sdcs: Vec<Sdc> =
+        serde_json::from_str(&read("tests/scaleio/sdc_mixed_versions.json")).unwrap();
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let points = version_report(&sds, &sdcs, t);
+    assert!(points.iter().all(|p| p.timestamp == Some(t)));
+
+    let count = |component: &str, version: &str| {
+        points
+            .iter()
+            .find(|p| {
+                p.measurement == VERSION_INVENTORY_MEASUREMENT
+                    && p.tags["component"] == TsValue::String(component.into())
+                    && p.tags["version"] == TsValue::String(version.into())
+            })
+            .map(|p| p.fields["count"].clone())
+    };
+    let summary = |component: &str| {
+        points
+            .iter()
+            .find(|p| {
+                p.measurement == VERSION_SUMMARY_MEASUREMENT
+                    && p.tags["component"] == TsValue::String(component.into())
+            })
+            .unwrap()
+            .fields
+            .clone()
+    };
+    // " R2_0.13000.0 " is the same version once trimmed
+    assert_eq!(count("sds", "R2_0.13000.0"), Some(TsValue::Long(2)));
+    assert_eq!(count("sds", "R2_5.10000.0"), Some(TsValue::Long(1)));
+    assert_eq!(count("sdc", "R2_0.13000.0"), Some(TsValue::Long(3)));
+    assert_eq!(count("sdc", "R2_0.12000.0"), Some(TsValue::Long(1)));
+    assert_eq!(points.len(), 6);
+    let sdc = summary("sdc");
+    assert_eq!(sdc["version_mismatch"], TsValue::Boolean(true));
+    assert_eq!(sdc["distinct_versions"], TsValue::Long(2));
+    assert_eq!(sdc["unreported"], TsValue::Long(1));
+    assert_eq!(summary("sds")["version_mismatch"], TsValue::Boolean(true));
+
+    // Once everything is upgraded there's nothing to flag
+    let points = version_report(&sds[..2], &sdcs[..2], t);
+    assert_eq!(points.len(), 4);
+    assert!(points
+        .iter()
+        .filter(|p| p.measurement == VERSION_SUMMARY_MEASUREMENT)
+        .all(|p| p.fields["version_mismatch"] == TsValue::Boolean(false)));
+    assert_eq!(
+        normalize_version("  R2_0\t 13000.0 \n"),
+        Some("R2_0 13000.0".into())
+    );
+    assert_eq!(normalize_version("   "), None);
+
+    // The Scaleio method fetches both inventories
+    let (sds, sdcs) = (
+        read("tests/scaleio/sds_mixed_versions.json"),
+        read("tests/scaleio/sdc_mixed_versions.json"),
+    );
+    let (port, requests) = mock_scaleio_api(false, move |_: &str, path: &str| {
+        if path.ends_with("types/Sds/instances") {
+            sds.clone().into_bytes()
+        } else {
+            sdcs.clone().into_bytes()
+        }
+    });
+    let points = mock_scaleio(port).get_version_report(t).unwrap();
+    assert_eq!(points.len(), 6);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_get_vtree_migrations() {
+    use std::fs::File;
+
+    let mut vtrees = Vec::new();
+    File::open("tests/scaleio/vtree_instances.json")
+        .unwrap()
+        .read_to_end(&mut vtrees)
+        .unwrap();
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+
+    let (port, _) = mock_scaleio_api(false, move |_: &str, _: &str| vtrees.clone());
+    let points = mock_scaleio(port).get_vtree_migrations(t).unwrap();
+    assert_eq!(points.len(), 4);
+    assert!(points.iter().all(|p| p.timestamp == Some(t)));
+    let percent_done: Vec<&TsValue> = points
+        .iter()
+        .filter(|p| p.measurement == VTreeMigration::MEASUREMENT)
+        .map(|p| &p.fields["percent_done"])
+        .collect();
+    assert_eq!(
+        percent_done,
+        vec![
+            &TsValue::Float(42.5),
+            &TsValue::Float(87.25),
+            &TsValue::Float(0.0)
+        ]
+    );
+    let summary = points
+        .iter()
+        .find(|p| p.measurement == VTreeMigration::SUMMARY_MEASUREMENT)
+        .unwrap();
+    assert_eq!(summary.fields["active"], TsValue::Long(2));
+    assert_eq!(summary.fields["queued"], TsValue::Long(1));
+    assert_eq!(summary.fields["paused"], TsValue::Long(0));
+
+    // Nothing migrating is still a summary, of zeros
+    let (port, _) = mock_scaleio_api(false, |_: &str, _: &str| b"[]".to_vec());
+    let points = mock_scaleio(port).get_vtree_migrations(t).unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].measurement, VTreeMigration::SUMMARY_MEASUREMENT);
+    assert_eq!(points[0].timestamp, Some(t));
+    for field in &["active", "queued", "paused"] {
+        assert_eq!(points[0].fields[*field], TsValue::Long(0));
+    }
+}
+
+#[test]
+fn test_get_all_sds_statistics() {
+    use std::fs::File;
+
+    let read = |path: &str| {
+        let mut buff = Vec::new();
+        File::open(path).unwrap().read_to_end(&mut buff).unwrap();
+        buff
+    };
+    let stats = read("tests/scaleio/sds_statistics.json");
+    // Copies of an SDS with ids the mock api answers for or fails
+    let object: serde_json::Value =
+        serde_json::from_slice(&read("tests/scaleio/sdsObject.json")).unwrap();
+    let sds: Vec<serde_json::Value> = ["good0001", "bad00001", "good0002", "bad00002"]
+        .iter()
+        .map(|id| {
+            let mut sds = object.clone();
+            sds["id"] = json!(id);
+            sds
+        })
+        .collect();
+    let sds = serde_json::to_vec(&sds).unwrap();
+    let (port, requests) = mock_scaleio_api(false, move |_: &str, path: &str| {
+        if path.ends_with("types/Sds/instances") {
+            sds.clone()
+        } else if path.contains("Sds::good") {
+            stats.clone()
+        } else {
+            b"not json".to_vec()
+        }
+    });
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let sds_ids = |points: &[TsPoint]| {
+        points
+            .iter()
+            .filter_map(|p| match p.tags.get("sds_id") {
+                Some(TsValue::String(id)) => Some(id.clone()),
+                _ => None,
+            })
+            .collect::<Vec<String>>()
+    };
+    let summary = |points: &[TsPoint]| {
+        let summary = points
+            .iter()
+            .find(|p| p.measurement == SdsStatistics::SUMMARY_MEASUREMENT)
+            .unwrap();
+        (
+            summary.fields["sds_count"].clone(),
+            summary.fields["failures"].clone(),
+        )
+    };
+
+    let mut scaleio = mock_scaleio(port);
+    let points = scaleio.get_all_sds_statistics(t).unwrap();
+    assert_eq!(sds_ids(&points), vec!["good0001", "good0002"]);
+    assert_eq!(summary(&points), (TsValue::Long(4), TsValue::Long(2)));
+    assert!(points.iter().all(|p| p.timestamp == Some(t)));
+    assert_eq!(requests.lock().unwrap().len(), 5);
+
+    // The same points in the same order when queried concurrently
+    scaleio.config.sds_stats_concurrency = Some(3);
+    scaleio.sds_pool = sds_stats_pool(&scaleio.config);
+    assert!(scaleio.sds_pool.is_some());
+    assert_eq!(scaleio.get_all_sds_statistics(t).unwrap(), points);
+}
+
+impl SdsObject {
+    /// Like into_point but stamped with the collection time, so the
+    /// certificate expiry is counted from t rather than the wall clock
+    pub fn into_point_at(&self, name: Option<&str>, t: DateTime<Utc>) -> Vec<TsPoint> {
+        vec![self.point(name, true, Some(t))]
+    }
+
+    fn point(&self, name: Option<&str>, is_time_series: bool, t: Option<DateTime<Utc>>) -> TsPoint {
+        let mut p = TsPoint::new(name.unwrap_or(Self::MEASUREMENT), is_time_series);
+        if t.is_some() {
+            p.timestamp = t;
+        }
         p.add_field(
             "ip_list",
             TsValue::StringVec(
@@ -1540,6 +4049,10 @@ impl IntoPoint for SdsObject {
             "maintenance_state",
             TsValue::String(self.maintenance_state.to_string()),
         );
+        p.add_field(
+            "maintenance_state_code",
+            TsValue::Long(self.maintenance_state.code()),
+        );
         //if let Some(counter) = self.sds_decoupled {
         //p.add_field("sds_decoupled", self.sds_decoupled: Option<OscillatingCounterWindow>);
         //}
@@ -1576,28 +4089,7 @@ impl IntoPoint for SdsObject {
         );
 
         if let Some(ref info) = self.certificate_info {
-            p.add_field("certificate_subject", TsValue::String(info.subject.clone()));
-            p.add_field("certificate_issuer", TsValue::String(info.issuer.clone()));
-            p.add_field(
-                "certificate_validfrom",
-                TsValue::String(info.valid_from.clone()),
-            );
-            p.add_field(
-                "certificate_validto",
-                TsValue::String(info.valid_to.clone()),
-            );
-            p.add_field(
-                "certificate_thumbprint",
-                TsValue::String(info.thumbprint.clone()),
-            );
-            p.add_field(
-                "certificate_validfrom_asn",
-                TsValue::String(info.valid_from_asn1_format.clone()),
-            );
-            p.add_field(
-                "certificate_validto_asn",
-                TsValue::String(info.valid_to_asn1_format.clone()),
-            );
+            info.sub_point(&mut p);
         }
 
         if let Some(ref err) = self.authentication_error {
@@ -1612,10 +4104,95 @@ impl IntoPoint for SdsObject {
         p.add_field("port", TsValue::Short(self.port));
         p.add_tag("id", TsValue::String(self.id.clone()));
 
-        vec![p]
+        p
+    }
+}
+
+impl IntoPoint for SdsObject {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        vec![self.point(name, is_time_series, None)]
     }
 }
 
+/// Adds the io priority policies of a pool as fields, so changing one
+/// doesn't start a new series, and tags it with its cache and scanner
+/// modes.  The derive doesn't know how to convert any of them
+pub fn pool_policy_point(pool: &PoolInstanceResponse, p: &mut TsPoint) {
+    p.add_field(
+        "rebuild_io_priority_policy",
+        TsValue::String(pool.rebuild_io_priority_policy.to_string()),
+    );
+    p.add_field(
+        "rebalance_io_priority_policy",
+        TsValue::String(pool.rebalance_io_priority_policy.to_string()),
+    );
+    p.add_tag(
+        "background_scanner_mode",
+        TsValue::String(format!("{:?}", pool.background_scanner_mode)),
+    );
+    p.add_tag(
+        "rmcache_write_handling_mode",
+        TsValue::String(format!("{:?}", pool.rmcache_write_handling_mode)),
+    );
+}
+
+#[test]
+fn test_get_storage_pools() {
+    use chrono::TimeZone;
+    use std::fs::File;
+
+    let mut pools = vec![];
+    File::open("tests/scaleio/storage_pools.json")
+        .unwrap()
+        .read_to_end(&mut pools)
+        .unwrap();
+    let (port, requests) = mock_scaleio_api(false, move |_: &str, _: &str| pools.clone());
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let points = mock_scaleio(port).get_storage_pools(t).unwrap();
+    assert_eq!(
+        requests.lock().unwrap()[0].1,
+        "/api/types/StoragePool/instances"
+    );
+    assert_eq!(points.len(), 3);
+    assert!(points
+        .iter()
+        .all(|p| p.measurement == "scaleio_pool" && p.timestamp == Some(t)));
+
+    let ids: Vec<String> = points.iter().map(|p| p.tags["id"].to_string()).collect();
+    assert_eq!(
+        ids,
+        vec!["5ceba28700000002", "5ceba28800000003", "5ceba28900000004"]
+    );
+    assert_eq!(
+        points[0].tags["protection_domain_id"],
+        TsValue::String("7a91b2ad00000002".into())
+    );
+    assert_eq!(
+        points[0].fields["rebuild_io_priority_policy"],
+        TsValue::String("unlimited".into())
+    );
+    assert!(!points[0].tags.contains_key("rebuild_io_priority_policy"));
+    // The throttling params only show up once a policy sets them
+    assert!(!points[0]
+        .fields
+        .contains_key("rebuild_io_priority_num_of_concurrent_ios_per_device"));
+    let throttled = &points[1];
+    assert_eq!(
+        throttled.fields["rebalance_io_priority_policy"],
+        TsValue::String("favorAppIos".into())
+    );
+    assert_eq!(
+        throttled.fields["rebalance_io_priority_bw_limit_per_device_in_kbps"],
+        TsValue::Long(10240)
+    );
+    assert_eq!(
+        throttled.tags["background_scanner_mode"],
+        TsValue::String("DataComparison".into())
+    );
+    assert_eq!(throttled.fields["spare_percentage"], TsValue::Byte(10));
+    assert_eq!(points[2].fields["rebuild_enabled"], TsValue::Boolean(false));
+}
+
 #[test]
 fn test_pool_response() {
     use std::fs::File;
@@ -1630,8 +4207,9 @@ fn test_pool_response() {
     println!("result: {:#?}", i);
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, IntoPoint, Serialize)]
 #[serde(rename_all = "camelCase")]
+#[point(extend_with = "pool_policy_point")]
 pub struct PoolInstanceResponse {
     pub rebuild_io_priority_policy: RebuildIoPriority,
     pub rebalance_io_priority_policy: RebuildIoPriority,
@@ -1665,6 +4243,64 @@ pub struct PoolInstanceResponse {
     pub links: Vec<HashMap<String, String>>,
 }
 
+impl PoolInstanceResponse {
+    pub const MEASUREMENT: &str = "scaleio_pool";
+
+    /// The params of the pool's rebuild io priority policy
+    pub fn rebuild_throttle_params(&self) -> ThrottleParams {
+        ThrottleParams {
+            num_of_concurrent_ios_per_device: self
+                .rebuild_io_priority_num_of_concurrent_ios_per_device,
+            bw_limit_per_device_in_kbps: self.rebuild_io_priority_bw_limit_per_device_in_kbps,
+            app_iops_per_device_threshold: self.rebuild_io_priority_app_iops_per_device_threshold,
+            app_bw_per_device_threshold_in_kbps: self
+                .rebuild_io_priority_app_bw_per_device_threshold_in_kbps,
+            quiet_period_in_msec: self.rebuild_io_priority_quiet_period_in_msec,
+        }
+    }
+
+    /// The params of the pool's rebalance io priority policy
+    pub fn rebalance_throttle_params(&self) -> ThrottleParams {
+        ThrottleParams {
+            num_of_concurrent_ios_per_device: self
+                .rebalance_io_priority_num_of_concurrent_ios_per_device,
+            bw_limit_per_device_in_kbps: self.rebalance_io_priority_bw_limit_per_device_in_kbps,
+            app_iops_per_device_threshold: self.rebalance_io_priority_app_iops_per_device_threshold,
+            app_bw_per_device_threshold_in_kbps: self
+                .rebalance_io_priority_app_bw_per_device_threshold_in_kbps,
+            quiet_period_in_msec: self.rebalance_io_priority_quiet_period_in_msec,
+        }
+    }
+
+    /// The rebuild settings of the pool that differ from policy and
+    /// params.  Empty once a set_rebuild_policy has been applied
+    pub fn rebuild_policy_differences(
+        &self,
+        policy: RebuildIoPriority,
+        params: &ThrottleParams,
+    ) -> Vec<String> {
+        params.differences(
+            policy,
+            self.rebuild_io_priority_policy,
+            &self.rebuild_throttle_params(),
+        )
+    }
+
+    /// The rebalance settings of the pool that differ from policy and
+    /// params.  Empty once a set_rebalance_policy has been applied
+    pub fn rebalance_policy_differences(
+        &self,
+        policy: RebuildIoPriority,
+        params: &ThrottleParams,
+    ) -> Vec<String> {
+        params.differences(
+            policy,
+            self.rebalance_io_priority_policy,
+            &self.rebalance_throttle_params(),
+        )
+    }
+}
+
 #[test]
 fn test_sdc_objects() {
     use std::fs::File;
@@ -1678,7 +4314,7 @@ fn test_sdc_objects() {
     println!("result: {:#?}", i);
 }
 
-#[derive(Clone, Deserialize, Debug, IntoPoint)]
+#[derive(Clone, Deserialize, Debug, IntoPoint, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Sdc {
     pub sdc_approved: bool,
@@ -1686,7 +4322,7 @@ pub struct Sdc {
     pub memory_allocation_failure: Option<OscillatingCounterWindow>,
     pub socket_allocation_failure: Option<OscillatingCounterWindow>,
     pub sdc_guid: String,
-    pub sdc_ip: String,
+    pub sdc_ip: HostAddress,
     pub perf_profile: String,
     pub version_info: Option<String>,
     pub system_id: String,
@@ -1695,6 +4331,10 @@ pub struct Sdc {
     pub links: Vec<HashMap<String, String>>,
 }
 
+impl Sdc {
+    pub const MEASUREMENT: &str = "scaleio_sdc";
+}
+
 #[test]
 fn test_system_response() {
     use std::fs::File;
@@ -1707,6 +4347,14 @@ fn test_system_response() {
 
     let i: Vec<System> = serde_json::from_str(&buff).unwrap();
     println!("result: {:#?}", i);
+    // Sorted across the master and slaves
+    assert_eq!(
+        i[0].mdm_cluster.mdm_management_ips(),
+        vec!["192.168.1.100", "192.168.1.36", "192.168.1.68"]
+    );
+    assert!(!i[0].into_point(None, true)[0]
+        .tags
+        .contains_key("virtual_ips"));
 
     let mut f = File::open("tests/scaleio/system_v3.json").unwrap();
     let mut buff = String::new();
@@ -1715,45 +4363,123 @@ fn test_system_response() {
 
     let i: Vec<System> = serde_json::from_str(&buff).unwrap();
     println!("result: {:#?}", i);
+
+    let points = i[0].into_point(Some("scaleio_system"), true);
+    let tags = &points[0].tags;
+    assert!(!tags.contains_key("virtual_ips"));
+    assert_eq!(
+        tags["mdm_management_ips"],
+        TsValue::StringVec(vec!["5.5.5.5".into()])
+    );
+    assert_eq!(
+        tags["tie_breaker_management_ips"],
+        TsValue::StringVec(vec!["5.5.5.5".into()])
+    );
+    let fields = &points[0].fields;
+    let counter = "sdc_sds_network_disconnections";
+    assert_eq!(
+        fields[format!("{}_short_window_threshold", counter).as_str()],
+        TsValue::Long(300)
+    );
+    assert_eq!(
+        fields[format!("{}_medium_window_window_size_in_sec", counter).as_str()],
+        TsValue::Long(3600)
+    );
+    assert_eq!(
+        fields[format!("{}_long_window_threshold", counter).as_str()],
+        TsValue::Long(700)
+    );
+    for counter in &[
+        "sdc_mdm_network_disconnections",
+        "sdc_memory_allocation_failures",
+        "sdc_socket_allocation_failures",
+        "sdc_long_operations",
+    ] {
+        assert!(fields.contains_key(format!("{}_short_window_threshold", counter).as_str()));
+    }
 }
 
-#[derive(Debug, Deserialize, IntoPoint)]
-#[serde(rename_all = "camelCase")]
-pub struct System {
-    pub system_version_name: String,                    // in v3
-    pub capacity_alert_high_threshold_percent: u16,     // in V3
-    pub capacity_alert_critical_threshold_percent: u16, // in v3
-    pub remote_read_only_limit_state: bool,             // in v3
-    pub upgrade_state: String,                          // in v3
-    pub mdm_management_port: u16,                       // in v3
-    pub sdc_mdm_network_disconnections_counter_parameters: FailureCounter, // in v3
+#[test]
+fn test_system_virtual_ips() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f = File::open("tests/scaleio/system_virtual_ips.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+
+    let i: Vec<System> = serde_json::from_str(&buff).unwrap();
+    let points = i[0].into_point(Some("scaleio_system"), true);
+    assert_eq!(
+        points[0].tags["virtual_ips"],
+        TsValue::StringVec(vec!["10.20.30.40".into()])
+    );
+}
+
+// Only the identity, version and mdm cluster are required, the settings
+// come and go between releases.  The failure counters stay required as
+// zeroed thresholds would look like real configuration
+#[derive(Clone, Debug, Deserialize, IntoPoint, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[point(extend_with = "extend_system_point")]
+pub struct System {
+    pub system_version_name: String, // in v3
+    #[serde(default)]
+    pub capacity_alert_high_threshold_percent: u16, // in V3
+    #[serde(default)]
+    pub capacity_alert_critical_threshold_percent: u16, // in v3
+    #[serde(default)]
+    pub remote_read_only_limit_state: bool, // in v3
+    #[serde(default)]
+    pub upgrade_state: String, // in v3
+    #[serde(default)]
+    pub mdm_management_port: u16, // in v3
+    pub sdc_mdm_network_disconnections_counter_parameters: FailureCounter, // in v3
     pub sdc_sds_network_disconnections_counter_parameters: FailureCounter, // in v3
     pub sdc_memory_allocation_failures_counter_parameters: FailureCounter, // in v3
     pub sdc_socket_allocation_failures_counter_parameters: FailureCounter, // in v3
     pub sdc_long_operations_counter_parameters: FailureCounter, // in v3
-    pub cli_password_allowed: bool,                     // in v3
+    #[serde(default)]
+    pub cli_password_allowed: bool, // in v3
+    #[serde(default)]
     pub management_client_secure_communication_enabled: bool, // in v3
-    pub tls_version: String,                            // in v3
-    pub show_guid: bool,                                // in v3
-    pub authentication_method: String,                  // in v3
-    pub mdm_cluster: MdmCluster,                        // in v3
-    pub perf_profile: PerfProfile,                      // in v3
-    pub install_id: String,                             // in v3
-    pub days_installed: u64,                            // in V3
-    #[serde(deserialize_with = "deserialize_string_or_int")]
+    #[serde(default)]
+    pub tls_version: String, // in v3
+    #[serde(default)]
+    pub show_guid: bool, // in v3
+    #[serde(default)]
+    pub authentication_method: String, // in v3
+    pub mdm_cluster: MdmCluster,     // in v3
+    #[serde(default)]
+    pub perf_profile: PerfProfile, // in v3
+    #[serde(default)]
+    pub install_id: String, // in v3
+    #[serde(default)]
+    pub days_installed: u64, // in V3
+    #[serde(default, deserialize_with = "deserialize_string_or_int")]
     pub max_capacity_in_gb: i64, // in v3
-    pub capacity_time_left_in_days: String,             // in v3
-    pub enterprise_features_enabled: bool,              // in v3
-    pub is_initial_license: bool,                       // in v3
-    pub default_is_volume_obfuscated: Option<bool>,     // not in V3
-    pub restricted_sdc_mode_enabled: bool,              // in v3
-    pub swid: String,                                   // in v3
-    pub name: String,                                   // in V3
-    pub id: String,                                     // in V3
+    #[serde(default)]
+    pub capacity_time_left_in_days: String, // in v3
+    #[serde(default)]
+    pub enterprise_features_enabled: bool, // in v3
+    #[serde(default)]
+    pub is_initial_license: bool, // in v3
+    pub default_is_volume_obfuscated: Option<bool>, // not in V3
+    #[serde(default)]
+    pub restricted_sdc_mode_enabled: bool, // in v3
+    #[serde(default)]
+    pub swid: String, // in v3
+    pub name: String,                // in V3
+    pub id: String,                  // in V3
+    #[serde(default)]
     pub links: Vec<HashMap<String, String>>,
 }
 
-#[derive(Debug, Deserialize)]
+impl System {
+    pub const MEASUREMENT: &str = "scaleio_system";
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(untagged, rename_all = "camelCase")]
 pub enum CompressedDataCompressionRatio {
     Null {
@@ -1764,231 +4490,249 @@ pub enum CompressedDataCompressionRatio {
     },
 }
 
-#[derive(Debug, Deserialize)]
+impl Default for CompressedDataCompressionRatio {
+    fn default() -> Self {
+        CompressedDataCompressionRatio::Ratio {
+            compressed_data_compression_ratio: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(untagged, rename_all = "camelCase")]
 pub enum CompressionRatio {
     Null { compressed_ratio: String }, // for "NaN" cases
     Ratio { compressed_ratio: Option<f64> },
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+impl Default for CompressionRatio {
+    fn default() -> Self {
+        CompressionRatio::Ratio {
+            compressed_ratio: None,
+        }
+    }
+}
+
+// Counters are optional so one a firmware release drops is left off the
+// point instead of failing the whole response
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
 pub struct SystemStatistics {
-    pub capacity_limit_in_kb: u64,
-    pub max_capacity_in_kb: u64,
-    pub capacity_in_use_in_kb: u64,
-    pub thick_capacity_in_use_in_kb: u64,
+    pub capacity_limit_in_kb: Option<u64>,
+    pub max_capacity_in_kb: Option<u64>,
+    pub capacity_in_use_in_kb: Option<u64>,
+    pub thick_capacity_in_use_in_kb: Option<u64>,
     pub thin_capacity_in_use_in_kb: Option<u64>, //deprecated, use net_thin_user_data_capacity_in_kb * 2 instead
     pub net_thin_user_data_capacity_in_kb: Option<u64>,
     pub snap_capacity_in_use_in_kb: Option<u64>, //deprecated in v3.5.1.4, will eventually return 0 and then be removed
-    pub unreachable_unused_capacity_in_kb: u64,
-    pub unused_capacity_in_kb: u64,
+    pub unreachable_unused_capacity_in_kb: Option<u64>,
+    pub unused_capacity_in_kb: Option<u64>,
     pub snap_capacity_in_use_occupied_in_kb: Option<u64>, //deprecated in v3.5.1.4, will eventually return 0 and then be removed
-    pub thin_capacity_allocated_in_kb: u64,
-    pub rm_pending_allocated_in_kb: u64,
+    pub thin_capacity_allocated_in_kb: Option<u64>,
+    pub rm_pending_allocated_in_kb: Option<u64>,
     pub rm_pending_thick_in_kb: Option<u64>,
     pub checksum_capacity_in_kb: Option<u64>,
-    pub spare_capacity_in_kb: u64,
-    pub capacity_available_for_volume_allocation_in_kb: u64,
+    pub spare_capacity_in_kb: Option<u64>,
+    pub capacity_available_for_volume_allocation_in_kb: Option<u64>,
     pub volume_allocation_limit_in_kb: Option<u64>,
-    pub protected_capacity_in_kb: u64,
-    pub degraded_healthy_capacity_in_kb: u64,
-    pub degraded_failed_capacity_in_kb: u64,
-    pub failed_capacity_in_kb: u64,
-    pub semi_protected_capacity_in_kb: u64,
-    pub in_maintenance_capacity_in_kb: u64,
+    pub protected_capacity_in_kb: Option<u64>,
+    pub degraded_healthy_capacity_in_kb: Option<u64>,
+    pub degraded_failed_capacity_in_kb: Option<u64>,
+    pub failed_capacity_in_kb: Option<u64>,
+    pub semi_protected_capacity_in_kb: Option<u64>,
+    pub in_maintenance_capacity_in_kb: Option<u64>,
     pub temp_capacity_in_kb: Option<u64>,
-    pub protected_vac_in_kb: u64,
-    pub degraded_healthy_vac_in_kb: u64,
-    pub degraded_failed_vac_in_kb: u64,
-    pub failed_vac_in_kb: u64,
-    pub semi_protected_vac_in_kb: u64,
-    pub in_maintenance_vac_in_kb: u64,
+    pub protected_vac_in_kb: Option<u64>,
+    pub degraded_healthy_vac_in_kb: Option<u64>,
+    pub degraded_failed_vac_in_kb: Option<u64>,
+    pub failed_vac_in_kb: Option<u64>,
+    pub semi_protected_vac_in_kb: Option<u64>,
+    pub in_maintenance_vac_in_kb: Option<u64>,
     pub temp_capacity_vac_in_kb: Option<u64>,
-    pub moving_capacity_in_kb: u64,
-    pub active_moving_capacity_in_kb: u64,
-    pub pending_moving_capacity_in_kb: u64,
-    pub fwd_rebuild_capacity_in_kb: u64,
-    pub active_fwd_rebuild_capacity_in_kb: u64,
-    pub pending_fwd_rebuild_capacity_in_kb: u64,
-    pub bck_rebuild_capacity_in_kb: u64,
-    pub active_bck_rebuild_capacity_in_kb: u64,
-    pub pending_bck_rebuild_capacity_in_kb: u64,
-    pub rebalance_capacity_in_kb: u64,
-    pub active_rebalance_capacity_in_kb: u64,
-    pub pending_rebalance_capacity_in_kb: u64,
-    pub at_rest_capacity_in_kb: u64,
-    pub norm_rebuild_capacity_in_kb: u64,
-    pub active_norm_rebuild_capacity_in_kb: u64,
-    pub pending_norm_rebuild_capacity_in_kb: u64,
-    pub active_moving_in_fwd_rebuild_jobs: u64,
-    pub active_moving_in_bck_rebuild_jobs: u64,
-    pub active_moving_in_rebalance_jobs: u64,
-    pub active_moving_out_fwd_rebuild_jobs: u64,
-    pub active_moving_out_bck_rebuild_jobs: u64,
-    pub active_moving_rebalance_jobs: u64,
-    pub pending_moving_in_fwd_rebuild_jobs: u64,
-    pub pending_moving_in_bck_rebuild_jobs: u64,
-    pub pending_moving_in_rebalance_jobs: u64,
-    pub pending_moving_out_fwd_rebuild_jobs: u64,
-    pub pending_moving_out_bck_rebuild_jobs: u64,
-    pub pending_moving_rebalance_jobs: u64,
-    pub active_moving_in_norm_rebuild_jobs: u64,
-    pub active_moving_out_norm_rebuild_jobs: u64,
-    pub pending_moving_in_norm_rebuild_jobs: u64,
-    pub pending_moving_out_normrebuild_jobs: u64,
-    pub in_use_vac_in_kb: u64,
-    pub primary_vac_in_kb: u64,
-    pub secondary_vac_in_kb: u64,
-    pub rebuild_wait_send_q_length: u64,
-    pub rebalance_wait_send_q_length: u64,
-    pub rebuild_per_receive_job_net_throttling_in_kbps: u64,
-    pub rebalance_per_receive_job_net_throttling_in_kbps: u64,
+    pub moving_capacity_in_kb: Option<u64>,
+    pub active_moving_capacity_in_kb: Option<u64>,
+    pub pending_moving_capacity_in_kb: Option<u64>,
+    pub fwd_rebuild_capacity_in_kb: Option<u64>,
+    pub active_fwd_rebuild_capacity_in_kb: Option<u64>,
+    pub pending_fwd_rebuild_capacity_in_kb: Option<u64>,
+    pub bck_rebuild_capacity_in_kb: Option<u64>,
+    pub active_bck_rebuild_capacity_in_kb: Option<u64>,
+    pub pending_bck_rebuild_capacity_in_kb: Option<u64>,
+    pub rebalance_capacity_in_kb: Option<u64>,
+    pub active_rebalance_capacity_in_kb: Option<u64>,
+    pub pending_rebalance_capacity_in_kb: Option<u64>,
+    pub at_rest_capacity_in_kb: Option<u64>,
+    pub norm_rebuild_capacity_in_kb: Option<u64>,
+    pub active_norm_rebuild_capacity_in_kb: Option<u64>,
+    pub pending_norm_rebuild_capacity_in_kb: Option<u64>,
+    pub active_moving_in_fwd_rebuild_jobs: Option<u64>,
+    pub active_moving_in_bck_rebuild_jobs: Option<u64>,
+    pub active_moving_in_rebalance_jobs: Option<u64>,
+    pub active_moving_out_fwd_rebuild_jobs: Option<u64>,
+    pub active_moving_out_bck_rebuild_jobs: Option<u64>,
+    pub active_moving_rebalance_jobs: Option<u64>,
+    pub pending_moving_in_fwd_rebuild_jobs: Option<u64>,
+    pub pending_moving_in_bck_rebuild_jobs: Option<u64>,
+    pub pending_moving_in_rebalance_jobs: Option<u64>,
+    pub pending_moving_out_fwd_rebuild_jobs: Option<u64>,
+    pub pending_moving_out_bck_rebuild_jobs: Option<u64>,
+    pub pending_moving_rebalance_jobs: Option<u64>,
+    pub active_moving_in_norm_rebuild_jobs: Option<u64>,
+    pub active_moving_out_norm_rebuild_jobs: Option<u64>,
+    pub pending_moving_in_norm_rebuild_jobs: Option<u64>,
+    pub pending_moving_out_normrebuild_jobs: Option<u64>,
+    pub in_use_vac_in_kb: Option<u64>,
+    pub primary_vac_in_kb: Option<u64>,
+    pub secondary_vac_in_kb: Option<u64>,
+    pub rebuild_wait_send_q_length: Option<u64>,
+    pub rebalance_wait_send_q_length: Option<u64>,
+    pub rebuild_per_receive_job_net_throttling_in_kbps: Option<u64>,
+    pub rebalance_per_receive_job_net_throttling_in_kbps: Option<u64>,
     pub fixed_read_error_count: Option<u64>, // Optional Statistic
     #[serde(rename = "BackgroundScanCompareCount")]
     pub background_scan_compare_count: Option<u64>,
     #[serde(rename = "BackgroundScannedInMB")]
-    pub background_scanned_in_mb: u64,
-    pub primary_read_bwc: BWC,
-    pub primary_read_from_dev_bwc: BWC,
-    pub primary_write_bwc: BWC,
-    pub secondary_read_bwc: BWC,
-    pub secondary_read_from_dev_bwc: BWC,
-    pub secondary_write_bwc: BWC,
-    pub fwd_rebuild_read_bwc: BWC,
-    pub fwd_rebuild_write_bwc: BWC,
-    pub bck_rebuild_read_bwc: BWC,
-    pub bck_rebuild_write_bwc: BWC,
-    pub rebalance_read_bwc: BWC,
-    pub rebalance_write_bwc: BWC,
-    pub total_read_bwc: BWC,
-    pub total_write_bwc: BWC,
-    pub primary_read_from_rmcache_bwc: BWC,
-    pub secondary_read_from_rmcache_bwc: BWC,
-    pub norm_rebuild_read_bwc: BWC,
-    pub norm_rebuild_write_bwc: BWC,
+    pub background_scanned_in_mb: Option<u64>,
+    pub primary_read_bwc: Option<BWC>,
+    pub primary_read_from_dev_bwc: Option<BWC>,
+    pub primary_write_bwc: Option<BWC>,
+    pub secondary_read_bwc: Option<BWC>,
+    pub secondary_read_from_dev_bwc: Option<BWC>,
+    pub secondary_write_bwc: Option<BWC>,
+    pub fwd_rebuild_read_bwc: Option<BWC>,
+    pub fwd_rebuild_write_bwc: Option<BWC>,
+    pub bck_rebuild_read_bwc: Option<BWC>,
+    pub bck_rebuild_write_bwc: Option<BWC>,
+    pub rebalance_read_bwc: Option<BWC>,
+    pub rebalance_write_bwc: Option<BWC>,
+    pub total_read_bwc: Option<BWC>,
+    pub total_write_bwc: Option<BWC>,
+    pub primary_read_from_rmcache_bwc: Option<BWC>,
+    pub secondary_read_from_rmcache_bwc: Option<BWC>,
+    pub norm_rebuild_read_bwc: Option<BWC>,
+    pub norm_rebuild_write_bwc: Option<BWC>,
     pub vol_migration_read_bwc: Option<BWC>,
     pub vol_migration_write_bwc: Option<BWC>,
-    pub user_data_read_bwc: BWC,
-    pub user_data_write_bwc: BWC,
+    pub user_data_read_bwc: Option<BWC>,
+    pub user_data_write_bwc: Option<BWC>,
     pub user_data_trim_bwc: Option<BWC>,
     pub user_data_sdc_read_latency: Option<BWC>, // THIS IS A BWC
     pub user_data_sdc_write_latency: Option<BWC>,
     pub user_data_sdc_trim_latency: Option<BWC>,
-    pub rmcache_size_in_kb: u64,
-    pub rmcache_size_in_use_in_kb: u64,
-    pub rmcache_entry_eviction_size_count_in_kb: u64,
-    pub rmcache_big_block_eviction_size_count_in_kb: u64,
-    pub rmcache_curr_num_of4kb_entries: u64,
-    pub rmcache_curr_num_of8kb_entries: u64,
-    pub rmcache_curr_num_of16kb_entries: u64,
-    pub rmcache_curr_num_of32kb_entries: u64,
-    pub rmcache_curr_num_of64kb_entries: u64,
-    pub rmcache_curr_num_of128kb_entries: u64,
-    pub rmcache_entry_eviction_count: u64,
-    pub rmcache_big_block_eviction_count: u64,
-    pub rmcache_no_eviction_count: u64,
-    pub rmcache_skip_count_large_io: u64,
-    pub rmcache_skip_count_unaligned4kb_io: u64,
-    pub rmcache_skip_count_cache_all_busy: u64,
-    pub num_of_unmapped_volumes: u64,
-    pub num_of_mapped_to_all_volumes: u64,
-    pub num_of_thick_base_volumes: u64,
-    pub num_of_thin_base_volumes: u64,
-    pub num_of_snapshots: u64,
-    pub num_of_volumes_in_deletion: u64,
-    pub num_of_devices: u64,
-    pub num_of_sds: u64,
-    pub num_of_storage_pools: u64,
-    pub num_of_volumes: u64,
-    pub num_of_vtrees: u64,
+    pub rmcache_size_in_kb: Option<u64>,
+    pub rmcache_size_in_use_in_kb: Option<u64>,
+    pub rmcache_entry_eviction_size_count_in_kb: Option<u64>,
+    pub rmcache_big_block_eviction_size_count_in_kb: Option<u64>,
+    pub rmcache_curr_num_of4kb_entries: Option<u64>,
+    pub rmcache_curr_num_of8kb_entries: Option<u64>,
+    pub rmcache_curr_num_of16kb_entries: Option<u64>,
+    pub rmcache_curr_num_of32kb_entries: Option<u64>,
+    pub rmcache_curr_num_of64kb_entries: Option<u64>,
+    pub rmcache_curr_num_of128kb_entries: Option<u64>,
+    pub rmcache_entry_eviction_count: Option<u64>,
+    pub rmcache_big_block_eviction_count: Option<u64>,
+    pub rmcache_no_eviction_count: Option<u64>,
+    pub rmcache_skip_count_large_io: Option<u64>,
+    pub rmcache_skip_count_unaligned4kb_io: Option<u64>,
+    pub rmcache_skip_count_cache_all_busy: Option<u64>,
+    pub num_of_unmapped_volumes: Option<u64>,
+    pub num_of_mapped_to_all_volumes: Option<u64>,
+    pub num_of_thick_base_volumes: Option<u64>,
+    pub num_of_thin_base_volumes: Option<u64>,
+    pub num_of_snapshots: Option<u64>,
+    pub num_of_volumes_in_deletion: Option<u64>,
+    pub num_of_devices: Option<u64>,
+    pub num_of_sds: Option<u64>,
+    pub num_of_storage_pools: Option<u64>,
+    pub num_of_volumes: Option<u64>,
+    pub num_of_vtrees: Option<u64>,
     pub protection_domain_ids: Vec<String>,
-    pub num_of_protection_domains: u64,
+    pub num_of_protection_domains: Option<u64>,
     pub sdc_ids: Vec<String>,
-    pub num_of_sdc: u64,
-    pub num_of_fault_sets: u64,
-    pub num_of_rfcache_devices: u64,
+    pub num_of_sdc: Option<u64>,
+    pub num_of_fault_sets: Option<u64>,
+    pub num_of_rfcache_devices: Option<u64>,
     pub num_of_acceleration_pools: Option<u64>,
     pub sp_sds_ids: Option<Vec<String>>,
     pub num_of_vtree_migrations_in_system: Option<u64>,
     pub compression_ratio: Option<f64>,
     pub snap_policy_ids: Option<Vec<String>>,
     pub num_of_snap_policies: Option<u64>,
-    pub rfcache_reads_received: u64,
-    pub rfcache_writes_received: u64,
-    pub rfcache_avg_read_time: u64,
-    pub rfcache_avg_write_time: u64,
-    pub rfcache_source_device_reads: u64,
-    pub rfcache_source_device_writes: u64,
-    pub rfache_read_hit: u64,
-    pub rfcache_read_miss: u64,
-    pub rfache_write_hit: u64,
-    pub rfcache_write_miss: u64,
-    pub rfcache_ios_skipped: u64,
-    pub rfcache_reads_skipped: u64,
-    pub rfcache_reads_skipped_aligned_size_too_large: u64,
-    pub rfcache_reads_skipped_max_io_size: u64,
-    pub rfcache_reads_skipped_heavy_load: u64,
-    pub rfcache_reads_skipped_stuck_io: u64,
-    pub rfcache_reads_skipped_low_resources: u64,
-    pub rfcache_reads_skipped_internal_error: u64,
-    pub rfcache_reads_skipped_lock_ios: u64,
-    pub rfcache_writes_skipped_max_io_size: u64,
-    pub rfcache_writes_skipped_heavy_load: u64,
-    pub rfcache_writes_skipped_stuck_io: u64,
-    pub rfcache_writes_skipped_low_resources: u64,
-    pub rfcache_writes_skipped_internal_error: u64,
-    pub rfcache_writes_skipped_cache_miss: u64,
-    pub rfcache_io_errors: u64,
-    pub rfcache_reads_from_cache: u64,
-    pub rfcache_ios_outstanding: u64,
-    pub rfcache_reads_pending: u64,
-    pub rfcache_write_pending: u64,
-    pub rfcache_fd_reads_received: u64,
-    pub rfcache_fd_writes_received: u64,
-    pub rfcache_fd_inlight_reads: u64,
-    pub rfcache_fd_inlight_writes: u64,
-    pub rfcache_fd_read_time_greater500_millis: u64,
-    pub rfcache_fd_read_time_greater1_sec: u64,
-    pub rfcache_fd_read_time_greater5_sec: u64,
-    pub rfcache_fd_read_time_greater1_min: u64,
-    pub rfcache_fd_write_time_greater500_millis: u64,
-    pub rfcache_fd_write_time_greater1_sec: u64,
-    pub rfcache_fd_write_time_greater5_sec: u64,
-    pub rfcache_fd_write_time_greater1_min: u64,
-    pub rfcache_fd_avg_read_time: u64,
-    pub rfcache_fd_avg_write_time: u64,
-    pub rfcache_fd_io_errors: u64,
-    pub rfcache_fd_cache_overloaded: u64,
-    pub rfcache_fd_monitor_error_stuck_io: u64,
-    pub rfcache_pool_num_src_devs: u64,
-    pub rfcache_pool_num_cache_devs: u64,
-    pub rfcache_pool_size: u64,
-    pub rfcache_pool_read_hit: u64,
-    pub rfcache_pool_read_miss: u64,
-    pub rfcache_pool_write_hit: u64,
-    pub rfcache_pool_write_miss: u64,
-    pub rfcache_pool_cache_pages: u64,
-    pub rfcache_pool_pages_inuse: u64,
-    pub rfcache_pool_evictions: u64,
-    pub rfcache_pool_in_low_memory_condition: u64,
-    pub rfcache_pool_io_time_greater1_min: u64,
-    pub rfcache_pool_lock_time_greater1_sec: u64,
-    pub rfcache_pool_suspended_ios: u64,
-    pub rfcache_pool_low_resources_initiated_passthrough_mode: u64,
-    pub rfcache_poo_ios_outstanding: u64,
-    pub rfcache_pool_reads_pending: u64,
-    pub rfcache_pool_write_pending: u64,
-    pub rfcache_pool_suspended_pequests_redundant_searchs: u64,
-    pub rfcache_pool_read_pending_g1_sec: u64,
-    pub rfcache_pool_read_pending_g10_millis: u64,
-    pub rfcache_pool_read_pending_g1_millis: u64,
-    pub rfcache_pool_read_pending_g500_micro: u64,
-    pub rfcache_pool_write_pending_g1_sec: u64,
-    pub rfcache_pool_write_pending_g10_millis: u64,
-    pub rfcache_pool_write_pending_g1_millis: u64,
-    pub rfcache_pool_write_pending_g500_micro: u64,
-    pub rfcache_pool_source_id_mismatch: u64,
+    pub rfcache_reads_received: Option<u64>,
+    pub rfcache_writes_received: Option<u64>,
+    pub rfcache_avg_read_time: Option<u64>,
+    pub rfcache_avg_write_time: Option<u64>,
+    pub rfcache_source_device_reads: Option<u64>,
+    pub rfcache_source_device_writes: Option<u64>,
+    pub rfache_read_hit: Option<u64>,
+    pub rfcache_read_miss: Option<u64>,
+    pub rfache_write_hit: Option<u64>,
+    pub rfcache_write_miss: Option<u64>,
+    pub rfcache_ios_skipped: Option<u64>,
+    pub rfcache_reads_skipped: Option<u64>,
+    pub rfcache_reads_skipped_aligned_size_too_large: Option<u64>,
+    pub rfcache_reads_skipped_max_io_size: Option<u64>,
+    pub rfcache_reads_skipped_heavy_load: Option<u64>,
+    pub rfcache_reads_skipped_stuck_io: Option<u64>,
+    pub rfcache_reads_skipped_low_resources: Option<u64>,
+    pub rfcache_reads_skipped_internal_error: Option<u64>,
+    pub rfcache_reads_skipped_lock_ios: Option<u64>,
+    pub rfcache_writes_skipped_max_io_size: Option<u64>,
+    pub rfcache_writes_skipped_heavy_load: Option<u64>,
+    pub rfcache_writes_skipped_stuck_io: Option<u64>,
+    pub rfcache_writes_skipped_low_resources: Option<u64>,
+    pub rfcache_writes_skipped_internal_error: Option<u64>,
+    pub rfcache_writes_skipped_cache_miss: Option<u64>,
+    pub rfcache_io_errors: Option<u64>,
+    pub rfcache_reads_from_cache: Option<u64>,
+    pub rfcache_ios_outstanding: Option<u64>,
+    pub rfcache_reads_pending: Option<u64>,
+    pub rfcache_write_pending: Option<u64>,
+    pub rfcache_fd_reads_received: Option<u64>,
+    pub rfcache_fd_writes_received: Option<u64>,
+    pub rfcache_fd_inlight_reads: Option<u64>,
+    pub rfcache_fd_inlight_writes: Option<u64>,
+    pub rfcache_fd_read_time_greater500_millis: Option<u64>,
+    pub rfcache_fd_read_time_greater1_sec: Option<u64>,
+    pub rfcache_fd_read_time_greater5_sec: Option<u64>,
+    pub rfcache_fd_read_time_greater1_min: Option<u64>,
+    pub rfcache_fd_write_time_greater500_millis: Option<u64>,
+    pub rfcache_fd_write_time_greater1_sec: Option<u64>,
+    pub rfcache_fd_write_time_greater5_sec: Option<u64>,
+    pub rfcache_fd_write_time_greater1_min: Option<u64>,
+    pub rfcache_fd_avg_read_time: Option<u64>,
+    pub rfcache_fd_avg_write_time: Option<u64>,
+    pub rfcache_fd_io_errors: Option<u64>,
+    pub rfcache_fd_cache_overloaded: Option<u64>,
+    pub rfcache_fd_monitor_error_stuck_io: Option<u64>,
+    pub rfcache_pool_num_src_devs: Option<u64>,
+    pub rfcache_pool_num_cache_devs: Option<u64>,
+    pub rfcache_pool_size: Option<u64>,
+    pub rfcache_pool_read_hit: Option<u64>,
+    pub rfcache_pool_read_miss: Option<u64>,
+    pub rfcache_pool_write_hit: Option<u64>,
+    pub rfcache_pool_write_miss: Option<u64>,
+    pub rfcache_pool_cache_pages: Option<u64>,
+    pub rfcache_pool_pages_inuse: Option<u64>,
+    pub rfcache_pool_evictions: Option<u64>,
+    pub rfcache_pool_in_low_memory_condition: Option<u64>,
+    pub rfcache_pool_io_time_greater1_min: Option<u64>,
+    pub rfcache_pool_lock_time_greater1_sec: Option<u64>,
+    pub rfcache_pool_suspended_ios: Option<u64>,
+    pub rfcache_pool_low_resources_initiated_passthrough_mode: Option<u64>,
+    pub rfcache_poo_ios_outstanding: Option<u64>,
+    pub rfcache_pool_reads_pending: Option<u64>,
+    pub rfcache_pool_write_pending: Option<u64>,
+    pub rfcache_pool_suspended_pequests_redundant_searchs: Option<u64>,
+    pub rfcache_pool_read_pending_g1_sec: Option<u64>,
+    pub rfcache_pool_read_pending_g10_millis: Option<u64>,
+    pub rfcache_pool_read_pending_g1_millis: Option<u64>,
+    pub rfcache_pool_read_pending_g500_micro: Option<u64>,
+    pub rfcache_pool_write_pending_g1_sec: Option<u64>,
+    pub rfcache_pool_write_pending_g10_millis: Option<u64>,
+    pub rfcache_pool_write_pending_g1_millis: Option<u64>,
+    pub rfcache_pool_write_pending_g500_micro: Option<u64>,
+    pub rfcache_pool_source_id_mismatch: Option<u64>,
     pub vtree_migration_wait_send_q_length: Option<u64>,
     pub vtree_migration_per_receive_job_net_throttling_in_kbps: Option<u64>,
     pub log_written_blocks_in_kb: Option<u64>,
@@ -2036,23 +4780,38 @@ pub struct SystemStatistics {
     pub num_cmatrix_policy_changes: Option<u64>,
 }
 
+impl SystemStatistics {
+    pub const MEASUREMENT: &str = "scaleio_sys_stats";
+}
+
+impl UnknownFields for SystemStatistics {
+    const TYPE_NAME: &'static str = "SystemStatistics";
+}
+
 impl IntoPoint for SystemStatistics {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
         let mut points = Vec::new();
-        let mut p = TsPoint::new(name.unwrap_or("scaleio_sys_stat"), is_time_series);
-        p.add_field(
-            "capacity_limit_in_kb",
-            TsValue::Long(self.capacity_limit_in_kb),
-        );
-        p.add_field("max_capacity_in_kb", TsValue::Long(self.max_capacity_in_kb));
-        p.add_field(
-            "capacity_in_use_in_kb",
-            TsValue::Long(self.capacity_in_use_in_kb),
-        );
-        p.add_field(
-            "thick capacity_in_use_in_kb",
-            TsValue::Long(self.thick_capacity_in_use_in_kb),
-        );
+        // A 3.x system fills in about 105 fields
+        let mut p =
+            TsPoint::with_capacity(name.unwrap_or(Self::MEASUREMENT), is_time_series, 112, 0);
+        if let Some(capacity_limit_in_kb) = self.capacity_limit_in_kb {
+            p.add_field("capacity_limit_in_kb", TsValue::Long(capacity_limit_in_kb));
+        }
+        if let Some(max_capacity_in_kb) = self.max_capacity_in_kb {
+            p.add_field("max_capacity_in_kb", TsValue::Long(max_capacity_in_kb));
+        }
+        if let Some(capacity_in_use_in_kb) = self.capacity_in_use_in_kb {
+            p.add_field(
+                "capacity_in_use_in_kb",
+                TsValue::Long(capacity_in_use_in_kb),
+            );
+        }
+        if let Some(thick_capacity_in_use_in_kb) = self.thick_capacity_in_use_in_kb {
+            p.add_field(
+                "thick capacity_in_use_in_kb",
+                TsValue::Long(thick_capacity_in_use_in_kb),
+            );
+        }
         if let Some(thin_capacity_in_use_in_kb) = self.thin_capacity_in_use_in_kb {
             p.add_field(
                 "thin_capacity_in_use_in_kb",
@@ -2074,28 +4833,33 @@ impl IntoPoint for SystemStatistics {
             );
         }
 
-        p.add_field(
-            "unreachable_unused_capacity_in_kb",
-            TsValue::Long(self.unreachable_unused_capacity_in_kb),
-        );
-        p.add_field(
-            "unused_capacity_in_kb",
-            TsValue::Long(self.unused_capacity_in_kb),
-        );
+        if let Some(unreachable_unused_capacity_in_kb) = self.unreachable_unused_capacity_in_kb {
+            p.add_field(
+                "unreachable_unused_capacity_in_kb",
+                TsValue::Long(unreachable_unused_capacity_in_kb),
+            );
+        }
+        if let Some(unused_capacity_in_kb) = self.unused_capacity_in_kb {
+            p.add_field(
+                "unused_capacity_in_kb",
+                TsValue::Long(unused_capacity_in_kb),
+            );
+        }
         if let Some(snap_capacity_in_use_in_kb) = self.snap_capacity_in_use_in_kb {
             p.add_field(
                 "snap_capacity_in_use_in_kb",
                 TsValue::Long(snap_capacity_in_use_in_kb),
             );
         }
-        p.add_field(
-            "thin_capacity_allocated_in_kb",
-            TsValue::Long(self.thin_capacity_allocated_in_kb),
-        );
-        p.add_field(
-            "spare_capacity_in_kb",
-            TsValue::Long(self.spare_capacity_in_kb),
-        );
+        if let Some(thin_capacity_allocated_in_kb) = self.thin_capacity_allocated_in_kb {
+            p.add_field(
+                "thin_capacity_allocated_in_kb",
+                TsValue::Long(thin_capacity_allocated_in_kb),
+            );
+        }
+        if let Some(spare_capacity_in_kb) = self.spare_capacity_in_kb {
+            p.add_field("spare_capacity_in_kb", TsValue::Long(spare_capacity_in_kb));
+        }
         if let Some(fixed_read_error_count) = self.fixed_read_error_count {
             p.add_field(
                 "fixed_read_error_count",
@@ -2103,35 +4867,54 @@ impl IntoPoint for SystemStatistics {
             );
         }
 
-        p.add_field(
-            "num_of_unmapped_volumes",
-            TsValue::Long(self.num_of_unmapped_volumes),
-        );
-        p.add_field(
-            "num_of_mapped_to_all_volumes",
-            TsValue::Long(self.num_of_mapped_to_all_volumes),
-        );
-        p.add_field(
-            "num_of_thick_base_volumes",
-            TsValue::Long(self.num_of_thick_base_volumes),
-        );
-        p.add_field(
-            "num_of_thin_base_volumes",
-            TsValue::Long(self.num_of_thin_base_volumes),
-        );
-        p.add_field("num_of_snapshots", TsValue::Long(self.num_of_snapshots));
-        p.add_field(
-            "num_of_volumes_in_deletion",
-            TsValue::Long(self.num_of_volumes_in_deletion),
-        );
-        p.add_field("num_of_devices", TsValue::Long(self.num_of_devices));
-        p.add_field("num_of_sds", TsValue::Long(self.num_of_sds));
-        p.add_field(
-            "num_of_storage_pools",
-            TsValue::Long(self.num_of_storage_pools),
-        );
-        p.add_field("num_of_volumes", TsValue::Long(self.num_of_volumes));
-        p.add_field("num_of_sdc", TsValue::Long(self.num_of_sdc));
+        if let Some(num_of_unmapped_volumes) = self.num_of_unmapped_volumes {
+            p.add_field(
+                "num_of_unmapped_volumes",
+                TsValue::Long(num_of_unmapped_volumes),
+            );
+        }
+        if let Some(num_of_mapped_to_all_volumes) = self.num_of_mapped_to_all_volumes {
+            p.add_field(
+                "num_of_mapped_to_all_volumes",
+                TsValue::Long(num_of_mapped_to_all_volumes),
+            );
+        }
+        if let Some(num_of_thick_base_volumes) = self.num_of_thick_base_volumes {
+            p.add_field(
+                "num_of_thick_base_volumes",
+                TsValue::Long(num_of_thick_base_volumes),
+            );
+        }
+        if let Some(num_of_thin_base_volumes) = self.num_of_thin_base_volumes {
+            p.add_field(
+                "num_of_thin_base_volumes",
+                TsValue::Long(num_of_thin_base_volumes),
+            );
+        }
+        if let Some(num_of_snapshots) = self.num_of_snapshots {
+            p.add_field("num_of_snapshots", TsValue::Long(num_of_snapshots));
+        }
+        if let Some(num_of_volumes_in_deletion) = self.num_of_volumes_in_deletion {
+            p.add_field(
+                "num_of_volumes_in_deletion",
+                TsValue::Long(num_of_volumes_in_deletion),
+            );
+        }
+        if let Some(num_of_devices) = self.num_of_devices {
+            p.add_field("num_of_devices", TsValue::Long(num_of_devices));
+        }
+        if let Some(num_of_sds) = self.num_of_sds {
+            p.add_field("num_of_sds", TsValue::Long(num_of_sds));
+        }
+        if let Some(num_of_storage_pools) = self.num_of_storage_pools {
+            p.add_field("num_of_storage_pools", TsValue::Long(num_of_storage_pools));
+        }
+        if let Some(num_of_volumes) = self.num_of_volumes {
+            p.add_field("num_of_volumes", TsValue::Long(num_of_volumes));
+        }
+        if let Some(num_of_sdc) = self.num_of_sdc {
+            p.add_field("num_of_sdc", TsValue::Long(num_of_sdc));
+        }
         if let Some(compression_ratio) = self.compression_ratio {
             p.add_field("compression_ratio", TsValue::Float(compression_ratio));
         }
@@ -2162,239 +4945,275 @@ impl IntoPoint for SystemStatistics {
                 TsValue::Long(num_sd_sdc_disconnections),
             );
         }
-        p.add_field(
-            "primary_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.primary_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "primary_read_bwc_num_seconds",
-            TsValue::Long(self.primary_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "primary_read_bwc_num_occured",
-            TsValue::Long(self.primary_read_bwc.num_occured),
-        );
+        if let Some(primary_read_bwc) = &self.primary_read_bwc {
+            p.add_field(
+                "primary_read_bwc_total_weight_in_kb",
+                TsValue::Long(primary_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "primary_read_bwc_num_seconds",
+                TsValue::Long(primary_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "primary_read_bwc_num_occured",
+                TsValue::Long(primary_read_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "primary_read_from_dev_bwc_total_weight_in_kb",
-            TsValue::Long(self.primary_read_from_dev_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "primary_read_from_dev_bwc_num_seconds",
-            TsValue::Long(self.primary_read_from_dev_bwc.num_seconds),
-        );
-        p.add_field(
-            "primary_read_from_dev_bwc_num_occured",
-            TsValue::Long(self.primary_read_from_dev_bwc.num_occured),
-        );
+        if let Some(primary_read_from_dev_bwc) = &self.primary_read_from_dev_bwc {
+            p.add_field(
+                "primary_read_from_dev_bwc_total_weight_in_kb",
+                TsValue::Long(primary_read_from_dev_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "primary_read_from_dev_bwc_num_seconds",
+                TsValue::Long(primary_read_from_dev_bwc.num_seconds),
+            );
+            p.add_field(
+                "primary_read_from_dev_bwc_num_occured",
+                TsValue::Long(primary_read_from_dev_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "primary_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.primary_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "primary_write_bwc_num_seconds",
-            TsValue::Long(self.primary_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "primary_write_bwc_num_occured",
-            TsValue::Long(self.primary_write_bwc.num_occured),
-        );
+        if let Some(primary_write_bwc) = &self.primary_write_bwc {
+            p.add_field(
+                "primary_write_bwc_total_weight_in_kb",
+                TsValue::Long(primary_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "primary_write_bwc_num_seconds",
+                TsValue::Long(primary_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "primary_write_bwc_num_occured",
+                TsValue::Long(primary_write_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "secondary_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.secondary_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_read_bwc_num_seconds",
-            TsValue::Long(self.secondary_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "secondary_read_bwc_num_occured",
-            TsValue::Long(self.secondary_read_bwc.num_occured),
-        );
+        if let Some(secondary_read_bwc) = &self.secondary_read_bwc {
+            p.add_field(
+                "secondary_read_bwc_total_weight_in_kb",
+                TsValue::Long(secondary_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_read_bwc_num_seconds",
+                TsValue::Long(secondary_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "secondary_read_bwc_num_occured",
+                TsValue::Long(secondary_read_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "secondary_read_from_dev_bwc_total_weight_in_kb",
-            TsValue::Long(self.secondary_read_from_dev_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_read_from_dev_bwc_num_seconds",
-            TsValue::Long(self.secondary_read_from_dev_bwc.num_seconds),
-        );
-        p.add_field(
-            "secondary_read_from_dev_bwc_num_occured",
-            TsValue::Long(self.secondary_read_from_dev_bwc.num_occured),
-        );
+        if let Some(secondary_read_from_dev_bwc) = &self.secondary_read_from_dev_bwc {
+            p.add_field(
+                "secondary_read_from_dev_bwc_total_weight_in_kb",
+                TsValue::Long(secondary_read_from_dev_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_read_from_dev_bwc_num_seconds",
+                TsValue::Long(secondary_read_from_dev_bwc.num_seconds),
+            );
+            p.add_field(
+                "secondary_read_from_dev_bwc_num_occured",
+                TsValue::Long(secondary_read_from_dev_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "secondary_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.secondary_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_write_bwc_num_seconds",
-            TsValue::Long(self.secondary_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "secondary_write_bwc_num_occured",
-            TsValue::Long(self.secondary_write_bwc.num_occured),
-        );
+        if let Some(secondary_write_bwc) = &self.secondary_write_bwc {
+            p.add_field(
+                "secondary_write_bwc_total_weight_in_kb",
+                TsValue::Long(secondary_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_write_bwc_num_seconds",
+                TsValue::Long(secondary_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "secondary_write_bwc_num_occured",
+                TsValue::Long(secondary_write_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "fwd_rebuild_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.fwd_rebuild_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "fwd_rebuild_read_bwc_num_seconds",
-            TsValue::Long(self.fwd_rebuild_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "fwd_rebuild_read_bwc_num_occured",
-            TsValue::Long(self.fwd_rebuild_read_bwc.num_occured),
-        );
+        if let Some(fwd_rebuild_read_bwc) = &self.fwd_rebuild_read_bwc {
+            p.add_field(
+                "fwd_rebuild_read_bwc_total_weight_in_kb",
+                TsValue::Long(fwd_rebuild_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "fwd_rebuild_read_bwc_num_seconds",
+                TsValue::Long(fwd_rebuild_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "fwd_rebuild_read_bwc_num_occured",
+                TsValue::Long(fwd_rebuild_read_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "fwd_rebuild_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.fwd_rebuild_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "fwd_rebuild_write_bwc_num_seconds",
-            TsValue::Long(self.fwd_rebuild_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "fwd_rebuild_write_bwc_num_occured",
-            TsValue::Long(self.fwd_rebuild_write_bwc.num_occured),
-        );
+        if let Some(fwd_rebuild_write_bwc) = &self.fwd_rebuild_write_bwc {
+            p.add_field(
+                "fwd_rebuild_write_bwc_total_weight_in_kb",
+                TsValue::Long(fwd_rebuild_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "fwd_rebuild_write_bwc_num_seconds",
+                TsValue::Long(fwd_rebuild_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "fwd_rebuild_write_bwc_num_occured",
+                TsValue::Long(fwd_rebuild_write_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "bck_rebuild_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.bck_rebuild_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "bck_rebuild_read_bwc_num_seconds",
-            TsValue::Long(self.bck_rebuild_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "bck_rebuild_read_bwc_num_occured",
-            TsValue::Long(self.bck_rebuild_read_bwc.num_occured),
-        );
+        if let Some(bck_rebuild_read_bwc) = &self.bck_rebuild_read_bwc {
+            p.add_field(
+                "bck_rebuild_read_bwc_total_weight_in_kb",
+                TsValue::Long(bck_rebuild_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "bck_rebuild_read_bwc_num_seconds",
+                TsValue::Long(bck_rebuild_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "bck_rebuild_read_bwc_num_occured",
+                TsValue::Long(bck_rebuild_read_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "bck_rebuild_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.bck_rebuild_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "bck_rebuild_write_bwc_num_seconds",
-            TsValue::Long(self.bck_rebuild_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "bck_rebuild_write_bwc_num_occured",
-            TsValue::Long(self.bck_rebuild_write_bwc.num_occured),
-        );
+        if let Some(bck_rebuild_write_bwc) = &self.bck_rebuild_write_bwc {
+            p.add_field(
+                "bck_rebuild_write_bwc_total_weight_in_kb",
+                TsValue::Long(bck_rebuild_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "bck_rebuild_write_bwc_num_seconds",
+                TsValue::Long(bck_rebuild_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "bck_rebuild_write_bwc_num_occured",
+                TsValue::Long(bck_rebuild_write_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "rebalance_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.rebalance_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "rebalance_read_bwc_num_seconds",
-            TsValue::Long(self.rebalance_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "rebalance_read_bwc_num_occured",
-            TsValue::Long(self.rebalance_read_bwc.num_occured),
-        );
+        if let Some(rebalance_read_bwc) = &self.rebalance_read_bwc {
+            p.add_field(
+                "rebalance_read_bwc_total_weight_in_kb",
+                TsValue::Long(rebalance_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "rebalance_read_bwc_num_seconds",
+                TsValue::Long(rebalance_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "rebalance_read_bwc_num_occured",
+                TsValue::Long(rebalance_read_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "rebalance_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.rebalance_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "rebalance_write_bwc_num_seconds",
-            TsValue::Long(self.rebalance_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "rebalance_write_bwc_num_occured",
-            TsValue::Long(self.rebalance_write_bwc.num_occured),
-        );
+        if let Some(rebalance_write_bwc) = &self.rebalance_write_bwc {
+            p.add_field(
+                "rebalance_write_bwc_total_weight_in_kb",
+                TsValue::Long(rebalance_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "rebalance_write_bwc_num_seconds",
+                TsValue::Long(rebalance_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "rebalance_write_bwc_num_occured",
+                TsValue::Long(rebalance_write_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "total_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.total_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "total_read_bwc_num_seconds",
-            TsValue::Long(self.total_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "total_read_bwc_num_occured",
-            TsValue::Long(self.total_read_bwc.num_occured),
-        );
+        if let Some(total_read_bwc) = &self.total_read_bwc {
+            p.add_field(
+                "total_read_bwc_total_weight_in_kb",
+                TsValue::Long(total_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "total_read_bwc_num_seconds",
+                TsValue::Long(total_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "total_read_bwc_num_occured",
+                TsValue::Long(total_read_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "total_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.total_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "total_write_bwc_num_seconds",
-            TsValue::Long(self.total_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "total_write_bwc_num_occured",
-            TsValue::Long(self.total_write_bwc.num_occured),
-        );
+        if let Some(total_write_bwc) = &self.total_write_bwc {
+            p.add_field(
+                "total_write_bwc_total_weight_in_kb",
+                TsValue::Long(total_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "total_write_bwc_num_seconds",
+                TsValue::Long(total_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "total_write_bwc_num_occured",
+                TsValue::Long(total_write_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "primary_read_from_rmcache_bwc_total_weight_in_kb",
-            TsValue::Long(self.primary_read_from_rmcache_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "primary_read_from_rmcache_bwc_num_seconds",
-            TsValue::Long(self.primary_read_from_rmcache_bwc.num_seconds),
-        );
-        p.add_field(
-            "primary_read_from_rmcache_bwc_num_occured",
-            TsValue::Long(self.primary_read_from_rmcache_bwc.num_occured),
-        );
+        if let Some(primary_read_from_rmcache_bwc) = &self.primary_read_from_rmcache_bwc {
+            p.add_field(
+                "primary_read_from_rmcache_bwc_total_weight_in_kb",
+                TsValue::Long(primary_read_from_rmcache_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "primary_read_from_rmcache_bwc_num_seconds",
+                TsValue::Long(primary_read_from_rmcache_bwc.num_seconds),
+            );
+            p.add_field(
+                "primary_read_from_rmcache_bwc_num_occured",
+                TsValue::Long(primary_read_from_rmcache_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "secondary_read_from_rmcache_bwc_total_weight_in_kb",
-            TsValue::Long(self.secondary_read_from_rmcache_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "secondary_read_from_rmcache_bwc_num_seconds",
-            TsValue::Long(self.secondary_read_from_rmcache_bwc.num_seconds),
-        );
-        p.add_field(
-            "secondary_read_from_rmcache_bwc_num_seconds",
-            TsValue::Long(self.secondary_read_from_rmcache_bwc.num_occured),
-        );
+        if let Some(secondary_read_from_rmcache_bwc) = &self.secondary_read_from_rmcache_bwc {
+            p.add_field(
+                "secondary_read_from_rmcache_bwc_total_weight_in_kb",
+                TsValue::Long(secondary_read_from_rmcache_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "secondary_read_from_rmcache_bwc_num_seconds",
+                TsValue::Long(secondary_read_from_rmcache_bwc.num_seconds),
+            );
+            p.add_field(
+                "secondary_read_from_rmcache_bwc_num_seconds",
+                TsValue::Long(secondary_read_from_rmcache_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "norm_rebuild_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.norm_rebuild_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "norm_rebuild_read_bwc_num_seconds",
-            TsValue::Long(self.norm_rebuild_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "norm_rebuild_read_bwc_num_occured",
-            TsValue::Long(self.norm_rebuild_read_bwc.num_occured),
-        );
+        if let Some(norm_rebuild_read_bwc) = &self.norm_rebuild_read_bwc {
+            p.add_field(
+                "norm_rebuild_read_bwc_total_weight_in_kb",
+                TsValue::Long(norm_rebuild_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "norm_rebuild_read_bwc_num_seconds",
+                TsValue::Long(norm_rebuild_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "norm_rebuild_read_bwc_num_occured",
+                TsValue::Long(norm_rebuild_read_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "norm_rebuild_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.norm_rebuild_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "norm_rebuild_write_bwc_num_seconds",
-            TsValue::Long(self.norm_rebuild_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "norm_rebuild_write_bwc_num_occured",
-            TsValue::Long(self.norm_rebuild_write_bwc.num_occured),
-        );
+        if let Some(norm_rebuild_write_bwc) = &self.norm_rebuild_write_bwc {
+            p.add_field(
+                "norm_rebuild_write_bwc_total_weight_in_kb",
+                TsValue::Long(norm_rebuild_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "norm_rebuild_write_bwc_num_seconds",
+                TsValue::Long(norm_rebuild_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "norm_rebuild_write_bwc_num_occured",
+                TsValue::Long(norm_rebuild_write_bwc.num_occured),
+            );
+        }
 
         if let Some(vol_migration_read_bwc) = &self.vol_migration_read_bwc {
             p.add_field(
@@ -2426,31 +5245,35 @@ impl IntoPoint for SystemStatistics {
             );
         }
 
-        p.add_field(
-            "user_data_read_bwc_total_weight_in_kb",
-            TsValue::Long(self.user_data_read_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "user_data_read_bwc_num_seconds",
-            TsValue::Long(self.user_data_read_bwc.num_seconds),
-        );
-        p.add_field(
-            "user_data_read_bwc_num_occured",
-            TsValue::Long(self.user_data_read_bwc.num_occured),
-        );
+        if let Some(user_data_read_bwc) = &self.user_data_read_bwc {
+            p.add_field(
+                "user_data_read_bwc_total_weight_in_kb",
+                TsValue::Long(user_data_read_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "user_data_read_bwc_num_seconds",
+                TsValue::Long(user_data_read_bwc.num_seconds),
+            );
+            p.add_field(
+                "user_data_read_bwc_num_occured",
+                TsValue::Long(user_data_read_bwc.num_occured),
+            );
+        }
 
-        p.add_field(
-            "user_data_write_bwc_total_weight_in_kb",
-            TsValue::Long(self.user_data_write_bwc.total_weight_in_kb),
-        );
-        p.add_field(
-            "user_data_write_bwc_num_seconds",
-            TsValue::Long(self.user_data_write_bwc.num_seconds),
-        );
-        p.add_field(
-            "user_data_write_bwc_num_occured",
-            TsValue::Long(self.user_data_write_bwc.num_occured),
-        );
+        if let Some(user_data_write_bwc) = &self.user_data_write_bwc {
+            p.add_field(
+                "user_data_write_bwc_total_weight_in_kb",
+                TsValue::Long(user_data_write_bwc.total_weight_in_kb),
+            );
+            p.add_field(
+                "user_data_write_bwc_num_seconds",
+                TsValue::Long(user_data_write_bwc.num_seconds),
+            );
+            p.add_field(
+                "user_data_write_bwc_num_occured",
+                TsValue::Long(user_data_write_bwc.num_occured),
+            );
+        }
 
         if let Some(user_data_trim_bwc) = &self.user_data_trim_bwc {
             p.add_field(
@@ -2480,6 +5303,12 @@ impl IntoPoint for SystemStatistics {
                 "user_data_sdc_read_latency_num_occured",
                 TsValue::Long(user_data_sdc_read_latency.num_occured),
             );
+            if let Some(latency) = user_data_sdc_read_latency.iosize_or_latency() {
+                p.add_field(
+                    "user_data_sdc_read_latency_microsec",
+                    TsValue::Long(latency),
+                );
+            }
         }
 
         if let Some(user_data_sdc_write_latency) = &self.user_data_sdc_write_latency {
@@ -2495,6 +5324,12 @@ impl IntoPoint for SystemStatistics {
                 "user_data_sdc_write_latency_num_occured",
                 TsValue::Long(user_data_sdc_write_latency.num_occured),
             );
+            if let Some(latency) = user_data_sdc_write_latency.iosize_or_latency() {
+                p.add_field(
+                    "user_data_sdc_write_latency_microsec",
+                    TsValue::Long(latency),
+                );
+            }
         }
 
         if let Some(user_data_sdc_trim_latency) = &self.user_data_sdc_trim_latency {
@@ -2510,6 +5345,12 @@ impl IntoPoint for SystemStatistics {
                 "user_data_sdc_trim_latency_num_occured",
                 TsValue::Long(user_data_sdc_trim_latency.num_occured),
             );
+            if let Some(latency) = user_data_sdc_trim_latency.iosize_or_latency() {
+                p.add_field(
+                    "user_data_sdc_trim_latency_microsec",
+                    TsValue::Long(latency),
+                );
+            }
         }
 
         points.push(p);
@@ -2517,6 +5358,120 @@ impl IntoPoint for SystemStatistics {
     }
 }
 
+#[test]
+fn test_missing_fields_tolerated() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let read = |path: &str| {
+        let mut buff = String::new();
+        File::open(path).unwrap().read_to_string(&mut buff).unwrap();
+        buff
+    };
+    // Each fixture drops fields that used to be required and adds one the
+    // structs don't know
+    let stats: SystemStatistics =
+        serde_json::from_str(&read("tests/scaleio/system_statistics_trimmed.json")).unwrap();
+    assert_eq!(stats.capacity_limit_in_kb, None);
+    assert!(stats.primary_read_bwc.is_none());
+    let fields = &stats.into_point(None, true)[0].fields;
+    assert!(!fields.is_empty());
+    // A dropped counter is left off rather than reported as 0
+    assert!(!fields.contains_key("capacity_limit_in_kb"));
+    assert!(!fields.contains_key("primary_read_bwc_num_occured"));
+
+    let device: DeviceStatistics =
+        serde_json::from_str(&read("tests/scaleio/device_statistics_trimmed.json")).unwrap();
+    let fields = &device.into_point(None, true)[0].fields;
+    assert!(!fields.contains_key("avg_read_latency_in_microsec"));
+    assert!(fields.contains_key("avg_write_size_in_bytes"));
+
+    let systems: Vec<System> =
+        serde_json::from_str(&read("tests/scaleio/system_trimmed.json")).unwrap();
+    assert_eq!(systems[0].tls_version, "");
+    assert_eq!(systems[0].max_capacity_in_gb, 0);
+    let tags = &systems[0].into_point(None, true)[0].tags;
+    assert!(!tags.contains_key("tls_version"));
+    assert!(tags.contains_key("mdm_management_ips"));
+
+    // The identity of a system is still required
+    let mut system: serde_json::Value =
+        serde_json::from_str(&read("tests/scaleio/system_trimmed.json")).unwrap();
+    system[0].as_object_mut().unwrap().remove("id");
+    assert!(serde_json::from_value::<Vec<System>>(system).is_err());
+}
+
+#[test]
+fn test_unknown_fields_logged() {
+    use std::fs::File;
+    use std::io::Read;
+
+    crate::capture_logs();
+    let mut buff = String::new();
+    File::open("tests/scaleio/device_statistics_trimmed.json")
+        .unwrap()
+        .read_to_string(&mut buff)
+        .unwrap();
+    // Named so no other test logs it first
+    let mut response: serde_json::Value = serde_json::from_str(&buff).unwrap();
+    response["unknownFieldsLoggedTestInKb"] = json!(7);
+    let device = DeviceStatistics::deserialize(&response).unwrap();
+    let logged = device.log_unknown_fields(&response);
+    assert!(logged.contains(&"newFirmwareCounterInKb".to_string()));
+    assert!(logged.contains(&"unknownFieldsLoggedTestInKb".to_string()));
+    // Known fields aren't reported
+    assert!(!logged.contains(&"avgWriteSizeInBytes".to_string()));
+    assert_eq!(
+        crate::captured_logs(
+            "scaleio DeviceStatistics has an unknown field unknownFieldsLoggedTestInKb: 7"
+        )
+        .len(),
+        1
+    );
+    // Only logged the first time
+    assert!(device.log_unknown_fields(&response).is_empty());
+    assert_eq!(
+        crate::captured_logs("DeviceStatistics has an unknown field unknownFieldsLoggedTestInKb")
+            .len(),
+        1
+    );
+    // The same name on another struct is a different field
+    let response = json!({ "unknownFieldsLoggedTestInKb": 7 });
+    let system = SystemStatistics::deserialize(&response).unwrap();
+    assert_eq!(
+        system.log_unknown_fields(&response),
+        vec!["unknownFieldsLoggedTestInKb".to_string()]
+    );
+    assert_eq!(
+        crate::captured_logs("SystemStatistics has an unknown field unknownFieldsLoggedTestInKb")
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_unknown_fields_config() {
+    crate::capture_logs();
+    let body = br#"{"unknownFieldsConfigTestInKb": 7}"#.to_vec();
+    let port = mock_scaleio_server(body, false);
+
+    // Off by default, the response isn't looked at
+    let s = mock_scaleio(port);
+    s.get_stats::<SystemStatistics>("instances/System::1/relationships/Statistics")
+        .unwrap();
+    assert!(crate::captured_logs("unknownFieldsConfigTestInKb").is_empty());
+
+    let mut s = mock_scaleio(port);
+    s.config.log_unknown_fields = Some(true);
+    s.get_stats::<SystemStatistics>("instances/System::1/relationships/Statistics")
+        .unwrap();
+    assert_eq!(
+        crate::captured_logs("SystemStatistics has an unknown field unknownFieldsConfigTestInKb")
+            .len(),
+        1
+    );
+}
+
 #[test]
 fn test_system_stats() {
     use std::fs::File;
@@ -2541,7 +5496,7 @@ fn test_system_stats() {
     println!("result: {:#?}", i);
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TieBreaker {
     pub openssl_version: String,
@@ -2556,55 +5511,793 @@ pub struct TieBreaker {
     pub port: u16,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Window {
     threshold: u64,
     window_size_in_sec: u64,
 }
 
-fn get<T>(client: &reqwest::blocking::Client, config: &ScaleioConfig, api: &str) -> MetricsResult<T>
-where
-    T: DeserializeOwned + Debug,
-{
-    let res: Result<T, reqwest::Error> = client
-        .get(&format!("https://{}/api/{}", config.endpoint, api))
-        .basic_auth(config.user.clone(), Some(config.password.clone()))
-        .send()?
-        .error_for_status()?
-        .json();
-    debug!("deserialized: {:?}", res);
-    Ok(res?)
+impl Window {
+    /// Add `{prefix}_threshold` and `{prefix}_window_size_in_sec`
+    pub fn add_fields(&self, prefix: &str, p: &mut TsPoint) {
+        p.add_field(
+            format!("{}_threshold", prefix),
+            TsValue::Long(self.threshold),
+        );
+        p.add_field(
+            format!("{}_window_size_in_sec", prefix),
+            TsValue::Long(self.window_size_in_sec),
+        );
+    }
 }
 
-// Connect to the metadata server and request a new api token
-pub fn get_api_token(
-    client: &reqwest::blocking::Client,
-    config: &ScaleioConfig,
-) -> MetricsResult<String> {
-    let token = client
-        .get(&format!("https://{}/api/login", config.endpoint))
-        .basic_auth(config.user.clone(), Some(config.password.clone()))
-        .send()?
-        .error_for_status()?;
-    let t = token.text()?;
-    trace!("api token: {}", t);
-
-    match api_token(t.as_bytes()) {
-        IResult::Done(_, o) => Ok(o.into()),
-        IResult::Incomplete(_) => Err(StorageError::new(format!(
-            "Unable to parse api token {} from server",
-            t
-        ))),
-        IResult::Error(e) => Err(StorageError::new(e.to_string())),
+impl IntoPoint for Window {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("scaleio_window"), is_time_series);
+        p.add_field("threshold", TsValue::Long(self.threshold));
+        p.add_field("window_size_in_sec", TsValue::Long(self.window_size_in_sec));
+        vec![p]
     }
 }
 
-#[test]
-fn test_api_token_parser() {
-    let raw_token = "\"YXV0b21hdGlvbjoxNTE1MTk4NjYzNDg0OjJiOWFhODhiYzliY2Y5O\
-                     WU3OTc1OGVjMmM0MzgyZGE0\"";
-    let expected = "YXV0b21hdGlvbjoxNTE1MTk4NjYzNDg0OjJiOWFhODhi\
+// Running totals of the responses a Scaleio and its clones received
+#[derive(Debug, Default)]
+struct TransferTotals {
+    wire_bytes: AtomicU64,
+    body_bytes: AtomicU64,
+}
+
+/// Response size as received over the wire and after decompression
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferSize {
+    pub wire_bytes: u64,
+    pub body_bytes: u64,
+}
+
+impl TransferSize {
+    /// How many times smaller the response was on the wire.  1.0 for
+    /// uncompressed responses
+    pub fn compression_ratio(&self) -> f64 {
+        if self.wire_bytes == 0 {
+            return 1.0;
+        }
+        self.body_bytes as f64 / self.wire_bytes as f64
+    }
+}
+
+impl fmt::Display for TransferSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes on the wire, {} decoded ({:.1}x)",
+            self.wire_bytes,
+            self.body_bytes,
+            self.compression_ratio()
+        )
+    }
+}
+
+// Read a response body, inflating it if the server gzipped it.  The
+// client belongs to the caller so reqwest may have already inflated the
+// body and dropped the Content-Encoding header.  Servers that ignore
+// Accept-Encoding just send the plain body.
+fn read_body(
+    resp: reqwest::blocking::Response,
+    limit: Option<u64>,
+) -> MetricsResult<(String, TransferSize)> {
+    let gzipped = resp
+        .headers()
+        .get(CONTENT_ENCODING)
+        .map(|v| v.as_bytes().eq_ignore_ascii_case(b"gzip"))
+        .unwrap_or(false);
+    // The limit applies to the inflated body too so a small gzip bomb
+    // can't get around it
+    let raw = read_bytes(resp, limit)?;
+    let wire_bytes = raw.len() as u64;
+    let body = if gzipped {
+        let limit = limit.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+        String::from_utf8_lossy(&read_limited(GzDecoder::new(&raw[..]), limit)?).into_owned()
+    } else {
+        String::from_utf8_lossy(&raw).into_owned()
+    };
+    let size = TransferSize {
+        wire_bytes,
+        body_bytes: body.len() as u64,
+    };
+    Ok((body, size))
+}
+
+// GET /api/{api} and deserialize the response, along with its size.
+// With gzip the response is asked for compressed
+fn get_sized<T>(
+    client: &reqwest::blocking::Client,
+    config: &ScaleioConfig,
+    api: &str,
+    gzip: bool,
+) -> MetricsResult<(T, TransferSize)>
+where
+    T: DeserializeOwned + Debug,
+{
+    let url = format!("https://{}/api/{}", config.endpoint, api);
+    let ctx = |e: StorageError| {
+        e.with_context(format!("GET {}", redact_url(&url)))
+            .with_context("scaleio")
+    };
+    let mut req = client
+        .get(&url)
+        .basic_auth(config.user.clone(), Some(config.password.clone()));
+    if gzip {
+        req = req.header(ACCEPT_ENCODING, "gzip");
+    }
+    let resp = req
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| ctx(StorageError::from(e.without_url())))?;
+    let (res, size) = read_body(resp, config.max_response_bytes).map_err(ctx)?;
+    debug!("{} GET {}: {}", config.log_prefix(), redact_url(&url), size);
+    let res: MetricsResult<T> = decode_json("GET", &url, &res);
+    debug!("{} deserialized: {:?}", config.log_prefix(), res);
+    res.map(|res| (res, size))
+        .map_err(|e| e.with_context("scaleio"))
+}
+
+// Turn the response to a request into an iterator of points.  A failed
+// request is the only item
+fn points_iter<T, I, F>(
+    res: MetricsResult<T>,
+    points: F,
+) -> impl Iterator<Item = MetricsResult<TsPoint>>
+where
+    I: Iterator<Item = TsPoint>,
+    F: FnOnce(T) -> I,
+{
+    let (ok, err) = match res {
+        Ok(res) => (Some(points(res)), None),
+        Err(e) => (None, Some(e)),
+    };
+    err.into_iter()
+        .map(Err)
+        .chain(ok.into_iter().flatten().map(Ok))
+}
+
+// POST a json body to the api and return the raw response text and its
+// size.  With gzip the response is asked for compressed
+fn post_sized<U>(
+    client: &reqwest::blocking::Client,
+    config: &ScaleioConfig,
+    api: &str,
+    body: &U,
+    gzip: bool,
+) -> MetricsResult<(String, TransferSize)>
+where
+    U: Serialize + ?Sized,
+{
+    let url = format!("https://{}/api/{}", config.endpoint, api);
+    let ctx = |e: StorageError| {
+        e.with_context(format!("POST {}", redact_url(&url)))
+            .with_context("scaleio")
+    };
+    let mut req = client
+        .post(&url)
+        .header(CONTENT_TYPE, "application/json")
+        .basic_auth(&config.user, Some(&config.password))
+        .json(body);
+    if gzip {
+        req = req.header(ACCEPT_ENCODING, "gzip");
+    }
+    let resp = req
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| ctx(StorageError::from(e.without_url())))?;
+    let (res, size) = read_body(resp, config.max_response_bytes).map_err(ctx)?;
+    debug!(
+        "{} POST {}: {}",
+        config.log_prefix(),
+        redact_url(&url),
+        size
+    );
+    Ok((res, size))
+}
+
+#[cfg(test)]
+// Method, path and body of each request a mock server received
+type RecordedRequests = std::sync::Arc<std::sync::Mutex<Vec<(String, String, String)>>>;
+
+#[cfg(test)]
+// Spawn a tls server on localhost that answers every request with body.
+// If gzip is set and the client asks for it the body is gzipped
+fn mock_scaleio_server(body: Vec<u8>, gzip: bool) -> u16 {
+    mock_scaleio_api(gzip, move |_, _| body.clone()).0
+}
+
+#[cfg(test)]
+// A Scaleio talking to a mock server on port
+fn mock_scaleio(port: u16) -> Scaleio {
+    Scaleio {
+        client: reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap(),
+        config: mock_scaleio_config(port),
+        credentials: Arc::new(StaticCredentials::new("admin", "token")),
+        dry_run: false,
+        management_credentials: None,
+        management_token: None,
+        stats_retry: RetryPolicy::default(),
+        gzip: true,
+        transfers: Arc::default(),
+        sds_pool: None,
+    }
+}
+
+#[cfg(test)]
+// The config mock_scaleio uses, for tests that need to change it
+fn mock_scaleio_config(port: u16) -> ScaleioConfig {
+    ScaleioConfig {
+        endpoint: format!("127.0.0.1:{}", port),
+        user: "admin".into(),
+        password: "token".into(),
+        region: "test".into(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+// Spawn a tls server on localhost.  respond is given the method and path
+// of each request and returns the body to answer with.  If gzip is set and
+// the client asks for it the body is gzipped
+fn mock_scaleio_api<F>(gzip: bool, respond: F) -> (u16, RecordedRequests)
+where
+    F: Fn(&str, &str) -> Vec<u8> + Send + 'static,
+{
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    let requests: RecordedRequests = Arc::new(Mutex::new(vec![]));
+    let recorded = requests.clone();
+    let port = crate::mock_https_server(move |req| {
+        let body = respond(&req.method, &req.path);
+        recorded
+            .lock()
+            .unwrap()
+            .push((req.method.clone(), req.path.clone(), req.body.clone()));
+        if gzip && req.head.to_lowercase().contains("accept-encoding: gzip") {
+            let mut e = GzEncoder::new(Vec::new(), Compression::default());
+            e.write_all(&body).unwrap();
+            (
+                200,
+                "Content-Encoding: gzip\r\n".into(),
+                e.finish().unwrap(),
+            )
+        } else {
+            (200, String::new(), body)
+        }
+    });
+    (port, requests)
+}
+
+#[test]
+fn test_scaleio_clone() {
+    let port = mock_scaleio_server(b"\"3.5\"".to_vec(), false);
+    let scaleio = mock_scaleio(port);
+    let cloned = scaleio.clone();
+    drop(scaleio);
+    assert_eq!(cloned.get_version().unwrap(), "\"3.5\"");
+}
+
+#[test]
+fn test_credential_rotation() {
+    use crate::credentials::{CallbackCredentials, Credential};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    // Only the rotated password, admin:new, gets a token.  Everything else
+    // needs the token
+    let logins = Arc::new(Mutex::new(vec![]));
+    let recorded = logins.clone();
+    let port = crate::mock_https_server(move |req| {
+        let auth = req
+            .head
+            .lines()
+            .find(|l| l.to_lowercase().starts_with("authorization:"))
+            .unwrap_or("")
+            .to_string();
+        if req.path == "/api/login" {
+            recorded.lock().unwrap().push(auth.clone());
+            if auth.ends_with("Basic YWRtaW46bmV3") {
+                return (200, String::new(), b"\"c2Vzc2lvbg\"".to_vec());
+            }
+            return (401, String::new(), vec![]);
+        }
+        // admin:c2Vzc2lvbg
+        if auth.ends_with("Basic YWRtaW46YzJWemMybHZiZw==") {
+            (200, String::new(), b"\"3.5\"".to_vec())
+        } else {
+            (401, String::new(), vec![])
+        }
+    });
+    // The secrets store rotated the password right after the first fetch
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetched = fetches.clone();
+    let provider = CallbackCredentials::new(move |backend: &str, _: &str| {
+        assert_eq!(backend, "scaleio");
+        match fetched.fetch_add(1, Ordering::SeqCst) {
+            0 => Ok(Credential::new("admin", "old")),
+            _ => Ok(Credential::new("admin", "new")),
+        }
+    });
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let config = ScaleioConfig {
+        endpoint: format!("127.0.0.1:{}", port),
+        user: String::new(),
+        password: String::new(),
+        region: "test".into(),
+        ..Default::default()
+    };
+    let mut scaleio = Scaleio::with_credentials(&client, config, Arc::new(provider)).unwrap();
+    assert_eq!(logins.lock().unwrap().len(), 2);
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    assert_eq!(scaleio.get_version().unwrap(), "\"3.5\"");
+
+    // A refresh asks the provider again
+    scaleio.refresh_token().unwrap();
+    assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    assert_eq!(logins.lock().unwrap().len(), 3);
+    assert_eq!(scaleio.get_version().unwrap(), "\"3.5\"");
+}
+
+#[test]
+fn test_stats_retry() {
+    use std::fs::File;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    let mut stats = vec![];
+    File::open("tests/scaleio/querySelectedStatistics.json")
+        .unwrap()
+        .read_to_end(&mut stats)
+        .unwrap();
+    let retry = || RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(1),
+    };
+
+    // A busy mdm fails twice before answering
+    let requests = Arc::new(AtomicUsize::new(0));
+    let served = requests.clone();
+    let port = crate::mock_https_server(move |_| match served.fetch_add(1, Ordering::SeqCst) {
+        0 | 1 => (500, String::new(), b"{}".to_vec()),
+        _ => (200, String::new(), stats.clone()),
+    });
+    let scaleio = mock_scaleio(port).with_stats_retry(retry());
+    let res = scaleio.get_drive_stats().unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        res.device["aeac502460250233"]["avgReadLatencyInMicrosec"],
+        164
+    );
+
+    // Client errors aren't retried
+    let requests = Arc::new(AtomicUsize::new(0));
+    let served = requests.clone();
+    let port = crate::mock_https_server(move |_| {
+        served.fetch_add(1, Ordering::SeqCst);
+        (400, String::new(), b"{}".to_vec())
+    });
+    let scaleio = mock_scaleio(port).with_stats_retry(retry());
+    let err = scaleio.get_drive_stats().unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::BAD_REQUEST));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // Only requests for fewer than 4 properties get through, so the
+    // request is split once its tries run out
+    let requests = Arc::new(AtomicUsize::new(0));
+    let served = requests.clone();
+    let port = crate::mock_https_server(move |req| {
+        served.fetch_add(1, Ordering::SeqCst);
+        let body: serde_json::Value = serde_json::from_str(&req.body).unwrap();
+        let properties = body["selectedStatisticsList"][0]["properties"]
+            .as_array()
+            .unwrap()
+            .clone();
+        if properties.len() > 3 {
+            return (503, String::new(), b"{}".to_vec());
+        }
+        let stats: serde_json::Map<String, serde_json::Value> = properties
+            .iter()
+            .map(|p| (p.as_str().unwrap().to_string(), json!(1)))
+            .collect();
+        (
+            200,
+            String::new(),
+            json!({"Device": {"d1": stats}}).to_string().into_bytes(),
+        )
+    });
+    let scaleio = mock_scaleio(port).with_stats_retry(retry());
+    let res = scaleio.get_drive_stats().unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 5);
+    let mut properties: Vec<&str> = res.device["d1"].keys().map(|k| k.as_str()).collect();
+    properties.sort_unstable();
+    let mut expected = DRIVE_STATS_PROPERTIES.to_vec();
+    expected.sort_unstable();
+    assert_eq!(properties, expected);
+}
+
+#[test]
+fn test_ping() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let logins = Arc::new(AtomicUsize::new(0));
+    let counted = logins.clone();
+    let port = crate::mock_https_server(move |req| {
+        let auth = req
+            .head
+            .lines()
+            .find(|l| l.to_lowercase().starts_with("authorization:"))
+            .unwrap_or("")
+            .to_string();
+        match req.path.as_str() {
+            // admin:secret, hands out dG9rMQ and then dG9rMg
+            "/api/login" if auth.ends_with("Basic YWRtaW46c2VjcmV0") => {
+                let token = match counted.fetch_add(1, Ordering::SeqCst) {
+                    0 => b"\"dG9rMQ\"".to_vec(),
+                    _ => b"\"dG9rMg\"".to_vec(),
+                };
+                (200, String::new(), token)
+            }
+            // Only the second token is still valid
+            "/api/version" if auth.ends_with("Basic YWRtaW46ZEc5ck1n") => {
+                (200, String::new(), b"\"3.5\"".to_vec())
+            }
+            _ => (401, String::new(), vec![]),
+        }
+    });
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let mut config = mock_scaleio(port).config;
+    config.password = "secret".into();
+    let mut scaleio = Scaleio::new(&client, config).unwrap();
+    assert_eq!(logins.load(Ordering::SeqCst), 1);
+
+    // The expired token is refreshed
+    scaleio.ping().unwrap();
+    assert_eq!(logins.load(Ordering::SeqCst), 2);
+    // and then used as is
+    scaleio.ping().unwrap();
+    assert_eq!(logins.load(Ordering::SeqCst), 2);
+
+    // A dead cluster fails without a login attempt
+    let dead_port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut dead = mock_scaleio(dead_port);
+    let err = dead.ping().unwrap_err();
+    assert_eq!(err.context(), &["scaleio ping".to_string()]);
+    assert!(!err.is_unauthorized());
+}
+
+#[test]
+fn test_points_iter() {
+    use std::fs::File;
+
+    let read = |path: &str| {
+        let mut f = File::open(path).unwrap();
+        let mut buff = vec![];
+        f.read_to_end(&mut buff).unwrap();
+        buff
+    };
+    let volumes = read("tests/scaleio/volume_instances.json");
+    let stats = read("tests/scaleio/device_statistics.json");
+    let (port, _) = mock_scaleio_api(false, move |_, path: &str| {
+        if path.contains("types/Volume/instances") {
+            volumes.clone()
+        } else if path.contains("Device::d3b6ff6b00000000") {
+            stats.clone()
+        } else {
+            b"not json".to_vec()
+        }
+    });
+    let scaleio = mock_scaleio(port);
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+
+    let points = scaleio.get_volumes(t).unwrap();
+    // Two volumes and the two sdcs the first one is mapped to
+    assert_eq!(points.len(), 4);
+    let streamed: Vec<TsPoint> = scaleio
+        .get_volumes_iter(t)
+        .collect::<MetricsResult<_>>()
+        .unwrap();
+    assert_eq!(streamed, points);
+
+    let ids = DriveId {
+        id: "d3b6ff6b00000000".into(),
+        sds_id: "6f5c5bfb00000000".into(),
+        storage_pool_id: "c5ef15cb00000000".into(),
+    };
+    let points = scaleio.get_drive_statistics(t, &ids).unwrap();
+    assert!(!points.is_empty());
+    let streamed: Vec<TsPoint> = scaleio
+        .get_drive_statistics_iter(t, &ids)
+        .collect::<MetricsResult<_>>()
+        .unwrap();
+    assert_eq!(streamed, points);
+
+    // Nothing to ask for without a storage pool
+    let unassigned = DriveId {
+        storage_pool_id: "NaN".into(),
+        ..ids.clone()
+    };
+    assert_eq!(scaleio.get_drive_statistics_iter(t, &unassigned).count(), 0);
+
+    // A failed request is the only item
+    let broken = DriveId {
+        id: "broken".into(),
+        ..ids
+    };
+    let mut res = scaleio.get_drive_statistics_iter(t, &broken);
+    assert!(res.next().unwrap().is_err());
+    assert!(res.next().is_none());
+}
+
+#[test]
+fn test_measurement_names() {
+    use std::fs::File;
+
+    type Call = fn(&Scaleio, DateTime<Utc>) -> MetricsResult<Vec<TsPoint>>;
+    let table: Vec<(&str, Call, Vec<&str>)> = vec![
+        (
+            "instances.json",
+            |s, t| s.get_drive_instances(t),
+            vec![Instance::MEASUREMENT],
+        ),
+        (
+            "sds_statistics.json",
+            |s, t| s.get_sds_statistics(t, "4fdf0e0700000000"),
+            vec![SdsStatistics::MEASUREMENT],
+        ),
+        (
+            "networkLatencyMeters.json",
+            |s, t| s.get_sds_network_latency("4fdf0e0700000000", t),
+            vec![NetworkLatencyMeter::MEASUREMENT],
+        ),
+        (
+            "sdcSelectedStatisticsResponse.json",
+            |s, _| s.get_sdc_stats(),
+            vec![SdcSelectedStatisticsResponse::MEASUREMENT],
+        ),
+        (
+            "volumeSelectedStatisticsResponse.json",
+            |s, t| s.get_volume_statistics(t),
+            vec![VolumeSelectedStatisticsResponse::MEASUREMENT],
+        ),
+        (
+            "sdc_info.json",
+            |s, t| s.get_sdc_objects("7f5d8fc72b3b8b3f", t),
+            vec![Sdc::MEASUREMENT],
+        ),
+        (
+            "system_statistics.json",
+            |s, t| s.get_system_stats("7f5d8fc72b3b8b3f", t),
+            vec![SystemStatistics::MEASUREMENT],
+        ),
+        (
+            "volume_instances.json",
+            |s, t| s.get_volumes(t),
+            vec![SdsVolume::MEASUREMENT, SdcMappingInfo::MEASUREMENT],
+        ),
+    ];
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let scaleio = |body: Vec<u8>| {
+        let (port, _) = mock_scaleio_api(false, move |_, _| body.clone());
+        mock_scaleio(port)
+    };
+    let read = |name: &str| {
+        let mut buff = vec![];
+        File::open(format!("tests/scaleio/{}", name))
+            .unwrap()
+            .read_to_end(&mut buff)
+            .unwrap();
+        buff
+    };
+
+    let measurements = |points: Vec<TsPoint>| {
+        let mut names: Vec<String> = points.into_iter().map(|p| p.measurement).collect();
+        names.sort();
+        names.dedup();
+        names
+    };
+    for (fixture, call, expected) in table {
+        let points = call(&scaleio(read(fixture)), t).unwrap();
+        assert_eq!(measurements(points), expected, "{}", fixture);
+    }
+
+    let drive = DriveId {
+        id: "d3b6ff6b00000000".into(),
+        sds_id: "4fdf0e0700000000".into(),
+        storage_pool_id: "b6b0b24600000000".into(),
+    };
+    let points = scaleio(read("device_statistics.json"))
+        .get_drive_statistics(t, &drive)
+        .unwrap();
+    assert_eq!(measurements(points), vec![DeviceStatistics::MEASUREMENT]);
+    // The sds listing is a single object in the fixture
+    let mut sds = b"[".to_vec();
+    sds.extend(read("sdsObject.json"));
+    sds.extend(b"]");
+    let points = scaleio(sds).get_sds_objects(t).unwrap();
+    assert_eq!(measurements(points), vec![SdsObject::MEASUREMENT]);
+
+    // Points built without a name land in the same place
+    let mut buff = String::new();
+    File::open("tests/scaleio/system_statistics.json")
+        .unwrap()
+        .read_to_string(&mut buff)
+        .unwrap();
+    let stats: SystemStatistics = serde_json::from_str(&buff).unwrap();
+    assert_eq!(
+        stats.into_point(None, true)[0].measurement,
+        SystemStatistics::MEASUREMENT
+    );
+    assert_eq!(
+        migrate_measurement_names().get("scaleio_sys_stat"),
+        Some(&"scaleio_sys_stats")
+    );
+}
+
+#[test]
+fn test_emit_fields() {
+    use std::fs::File;
+
+    let mut body = vec![];
+    File::open("tests/scaleio/sds_statistics.json")
+        .unwrap()
+        .read_to_end(&mut body)
+        .unwrap();
+    let (port, _) = mock_scaleio_api(false, move |_, _| body.clone());
+    let fields = |allow: Option<Vec<&str>>, deny: Option<Vec<&str>>| {
+        let globs = |g: Option<Vec<&str>>| g.map(|g| g.iter().map(|s| s.to_string()).collect());
+        let scaleio = Scaleio {
+            config: ScaleioConfig {
+                emit_fields_allow: globs(allow),
+                emit_fields_deny: globs(deny),
+                ..mock_scaleio_config(port)
+            },
+            ..mock_scaleio(port)
+        };
+        let points = scaleio
+            .get_sds_statistics(Utc::now(), "4fdf0e0700000000")
+            .unwrap();
+        assert_eq!(
+            points[0].tags["sds_id"],
+            TsValue::String("4fdf0e0700000000".into())
+        );
+        let mut names: Vec<String> = points[0].field_keys().map(|k| k.to_string()).collect();
+        names.sort();
+        names
+    };
+
+    let everything = fields(None, None);
+    assert!(everything.len() > 100);
+    assert_eq!(
+        fields(Some(vec!["total_*_bwc"]), None),
+        vec!["total_read_bwc", "total_write_bwc"]
+    );
+    let denied = fields(None, Some(vec!["rfcache_*", "*_bwc*"]));
+    assert!(!denied.is_empty());
+    assert!(denied
+        .iter()
+        .all(|f| !f.starts_with("rfcache_") && !f.contains("_bwc")));
+    assert!(denied.contains(&"capacity_limit_in_kb".to_string()));
+    assert_eq!(
+        fields(Some(vec!["rmcache??kb_entry_count"]), None),
+        vec![
+            "rmcache16kb_entry_count",
+            "rmcache32kb_entry_count",
+            "rmcache64kb_entry_count"
+        ]
+    );
+}
+
+#[test]
+fn test_log_endpoint() {
+    crate::capture_logs();
+    let port = mock_scaleio_server(b"{}".to_vec(), false);
+    let _: serde_json::Value = mock_scaleio(port).get("types/System/instances").unwrap();
+    let logs = crate::captured_logs(&format!("[test/127.0.0.1:{}] GET", port));
+    assert_eq!(logs.len(), 1);
+}
+
+#[test]
+fn test_gzip_responses() {
+    use std::fs::File;
+
+    let mut f = File::open("tests/scaleio/system_statistics_v3.json").unwrap();
+    let mut plain = String::new();
+    f.read_to_string(&mut plain).unwrap();
+
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let gzip_port = mock_scaleio_server(plain.clone().into_bytes(), true);
+    let plain_port = mock_scaleio_server(plain.clone().into_bytes(), false);
+
+    let resp = client
+        .get(format!("https://127.0.0.1:{}/api/stats", gzip_port))
+        .header(ACCEPT_ENCODING, "gzip")
+        .send()
+        .unwrap();
+    let (body, size) = read_body(resp, None).unwrap();
+    assert_eq!(body, plain);
+    assert_eq!(size.body_bytes, plain.len() as u64);
+    assert!(size.compression_ratio() > 1.0);
+
+    // A server that ignores Accept-Encoding
+    let resp = client
+        .get(format!("https://127.0.0.1:{}/api/stats", plain_port))
+        .header(ACCEPT_ENCODING, "gzip")
+        .send()
+        .unwrap();
+    let (body, size) = read_body(resp, None).unwrap();
+    assert_eq!(body, plain);
+    assert_eq!(size.compression_ratio(), 1.0);
+
+    // Both deserialize the same through the api helpers, and the sizes
+    // are totalled for the caller
+    let scaleio = mock_scaleio(gzip_port);
+    let gzipped: serde_json::Value = scaleio.get("stats").unwrap();
+    let ungzipped: serde_json::Value = mock_scaleio(plain_port).get("stats").unwrap();
+    assert_eq!(gzipped, ungzipped);
+    let _: SystemStatistics = scaleio.clone().get("stats").unwrap();
+    let size = scaleio.transfer_size();
+    assert_eq!(size.body_bytes, 2 * plain.len() as u64);
+    assert!(size.compression_ratio() > 1.0);
+
+    // Turning gzip off leaves the response uncompressed
+    let scaleio = mock_scaleio(gzip_port).with_gzip(false);
+    let _: serde_json::Value = scaleio.get("stats").unwrap();
+    assert_eq!(scaleio.transfer_size().compression_ratio(), 1.0);
+}
+
+// Connect to the metadata server and request a new api token
+pub fn get_api_token(
+    client: &reqwest::blocking::Client,
+    config: &ScaleioConfig,
+) -> MetricsResult<String> {
+    let token = client
+        .get(&format!("https://{}/api/login", config.endpoint))
+        .basic_auth(config.user.clone(), Some(config.password.clone()))
+        .send()?
+        .error_for_status()?;
+    let t = read_text(token, config.max_response_bytes)?;
+    trace!("{} api token: {}", config.log_prefix(), t);
+
+    match api_token(t.as_bytes()) {
+        IResult::Done(_, o) => Ok(o.into()),
+        IResult::Incomplete(_) => Err(StorageError::new(format!(
+            "Unable to parse api token {} from server",
+            t
+        ))),
+        IResult::Error(e) => Err(StorageError::new(e.to_string())),
+    }
+}
+
+#[test]
+fn test_api_token_parser() {
+    let raw_token = "\"YXV0b21hdGlvbjoxNTE1MTk4NjYzNDg0OjJiOWFhODhiYzliY2Y5O\
+                     WU3OTc1OGVjMmM0MzgyZGE0\"";
+    let expected = "YXV0b21hdGlvbjoxNTE1MTk4NjYzNDg0OjJiOWFhODhi\
                     YzliY2Y5OWU3OTc1OGVjMmM0MzgyZGE0";
     let res = api_token(raw_token.as_bytes());
     println!("parsed api_token: {:?}", res);
@@ -2624,65 +6317,400 @@ named!(
     ))
 );
 
+#[test]
+fn test_parse_version() {
+    assert_eq!(parse_version("\"2.6\"").unwrap(), (2, 6));
+    assert_eq!(parse_version("3.5").unwrap(), (3, 5));
+    assert_eq!(parse_version(" \"3.10\"\n").unwrap(), (3, 10));
+    assert_eq!(parse_version("10.0").unwrap(), (10, 0));
+    assert_eq!(parse_version("3").unwrap(), (3, 0));
+    assert!(parse_version("\"\"").is_err());
+
+    assert!(parse_version("2.6").unwrap() < (3, 0));
+    assert!(parse_version("3.5").unwrap() >= (3, 0));
+    assert!(parse_version("3.10").unwrap() > parse_version("3.5").unwrap());
+    assert!(parse_version("10.0").unwrap() >= (3, 0));
+}
+
+// Parse the version string returned by the gateway, ie "3.5", into a
+// (major, minor) tuple that compares numerically.  Any patch or build
+// components are ignored
+fn parse_version(version: &str) -> MetricsResult<(u32, u32)> {
+    let trimmed = version.trim().trim_matches('"');
+    let mut parts = trimmed.split('.');
+    let major = match parts.next() {
+        Some(m) if !m.is_empty() => m.parse::<u32>()?,
+        _ => {
+            return Err(StorageError::new(format!(
+                "Unable to parse scaleio version {}",
+                version
+            )))
+        }
+    };
+    let minor = match parts.next() {
+        Some(m) => m.parse::<u32>()?,
+        None => 0,
+    };
+    Ok((major, minor))
+}
+
+// The pool that queries sds_stats_concurrency SDS's at a time, built once
+// so a collection doesn't start new threads
+fn sds_stats_pool(config: &ScaleioConfig) -> Option<Arc<rayon::ThreadPool>> {
+    match config.sds_stats_concurrency {
+        Some(threads) if threads > 1 => {
+            match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+                Ok(pool) => Some(Arc::new(pool)),
+                Err(e) => {
+                    error!(
+                        "{} Unable to start {} sds statistics threads, querying them one at a time: {}",
+                        config.log_prefix(),
+                        threads,
+                        e
+                    );
+                    None
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
 impl Scaleio {
-    pub fn new(
+    /// Log in with the user and password from config
+    pub fn new(client: &reqwest::blocking::Client, config: ScaleioConfig) -> MetricsResult<Self> {
+        config.validate()?;
+        let credentials = StaticCredentials::new(config.user.as_str(), config.password.as_str());
+        Scaleio::with_credentials(client, config, Arc::new(credentials))
+    }
+
+    /// Build a client from the tls settings of config, see
+    /// `ScaleioConfig::client_options`, and log in like `new`
+    pub fn from_config(config: ScaleioConfig) -> MetricsResult<Self> {
+        config.validate()?;
+        let client = config
+            .client_options()
+            .build()
+            .map_err(|e| e.with_context("scaleio client"))?;
+        Scaleio::new(&client, config)
+    }
+
+    /// Log in with a credential from credentials instead of the user and
+    /// password in config.  The provider is asked again whenever the token
+    /// is refreshed.  The management user, if any, still comes from config
+    pub fn with_credentials(
+        client: &reqwest::blocking::Client,
+        config: ScaleioConfig,
+        credentials: Arc<dyn CredentialsProvider>,
+    ) -> MetricsResult<Self> {
+        let management = match (&config.management_user, &config.management_password) {
+            (Some(user), password) => Some(Arc::new(StaticCredentials::new(
+                user.as_str(),
+                password.clone().unwrap_or_default(),
+            )) as Arc<dyn CredentialsProvider>),
+            (None, _) => None,
+        };
+        Scaleio::login(client, config, credentials, management)
+    }
+
+    /// Like `with_credentials` but the user for provisioning actions also
+    /// comes from a provider, asked with a backend of "scaleio_management".
+    /// management_user and management_password in config are ignored
+    pub fn with_management_credentials(
+        client: &reqwest::blocking::Client,
+        config: ScaleioConfig,
+        credentials: Arc<dyn CredentialsProvider>,
+        management_credentials: Arc<dyn CredentialsProvider>,
+    ) -> MetricsResult<Self> {
+        Scaleio::login(client, config, credentials, Some(management_credentials))
+    }
+
+    fn login(
         client: &reqwest::blocking::Client,
-        mut config: ScaleioConfig,
+        config: ScaleioConfig,
+        credentials: Arc<dyn CredentialsProvider>,
+        management_credentials: Option<Arc<dyn CredentialsProvider>>,
     ) -> MetricsResult<Self> {
-        let token = get_api_token(client, &config)?;
-        config.password = token;
-        Ok(Scaleio {
+        validate_endpoint(&config.endpoint).map_err(|e| e.with_context("scaleio config"))?;
+        let mut scaleio = Scaleio {
             client: client.clone(),
             config,
-        })
+            credentials,
+            dry_run: false,
+            management_credentials,
+            management_token: None,
+            stats_retry: RetryPolicy::default(),
+            gzip: true,
+            transfers: Arc::default(),
+            sds_pool: None,
+        };
+        scaleio.sds_pool = sds_stats_pool(&scaleio.config);
+        scaleio.refresh_token()?;
+        Ok(scaleio)
+    }
+
+    /// Log in again for a new api token with the current credential from
+    /// the provider.  Clones made before this keep the old token
+    pub fn refresh_token(&mut self) -> MetricsResult<()> {
+        let client = &self.client;
+        let config = &self.config;
+        let (user, token) =
+            with_credential(&*self.credentials, "scaleio", &config.endpoint, |c| {
+                let mut login = config.clone();
+                login.user = c.user.clone();
+                login.password = c.password.clone();
+                get_api_token(client, &login).map(|token| (c.user.clone(), token))
+            })?;
+        self.config.user = user;
+        self.config.password = token;
+        if let Some(provider) = &self.management_credentials {
+            let config = &self.config;
+            let token =
+                with_credential(&**provider, "scaleio_management", &config.endpoint, |c| {
+                    let mut login = config.clone();
+                    login.user = c.user.clone();
+                    login.password = c.password.clone();
+                    get_api_token(client, &login)
+                        .map(|token| (c.user.clone(), token))
+                        .map_err(|e| {
+                            e.with_context(format!("scaleio management login as {}", c.user))
+                        })
+                })?;
+            self.management_token = Some(token);
+        }
+        Ok(())
+    }
+
+    // The config provisioning actions are sent with, the management user
+    // and its token when there is one
+    fn management_config(&self) -> Cow<'_, ScaleioConfig> {
+        match &self.management_token {
+            Some((user, token)) => {
+                let mut config = self.config.clone();
+                config.user = user.clone();
+                config.password = token.clone();
+                Cow::Owned(config)
+            }
+            None => Cow::Borrowed(&self.config),
+        }
+    }
+
+    /// Retry querySelectedStatistics requests that fail with a server
+    /// error following policy instead of the default 3 tries
+    pub fn with_stats_retry(mut self, policy: RetryPolicy) -> Self {
+        self.stats_retry = policy;
+        self
+    }
+
+    // POST a querySelectedStatistics request.  Server errors, which a busy
+    // mdm answers large requests with, are retried with backoff.  If the
+    // retries run out the request is split in two, each half retried on
+    // its own, and the responses merged.  Halves aren't split again
+    fn query_selected_statistics<T>(&self, req: &SelectedStatisticsRequest) -> MetricsResult<T>
+    where
+        T: DeserializeOwned + Debug,
+    {
+        let api = "instances/querySelectedStatistics";
+        let query = |req: &SelectedStatisticsRequest| {
+            with_retry(&self.stats_retry, "scaleio querySelectedStatistics", || {
+                self.post::<serde_json::Value, _>(api, req)
+            })
+        };
+        let res = match query(req) {
+            Err(e) if e.is_server_error() => match req.split() {
+                Some((a, b)) => {
+                    warn!(
+                        "{} querySelectedStatistics keeps failing, splitting it in two: {}",
+                        self.config.log_prefix(),
+                        e
+                    );
+                    let mut res = query(&a)?;
+                    merge_statistics(&mut res, query(&b)?);
+                    res
+                }
+                None => return Err(e),
+            },
+            res => res?,
+        };
+        let url = format!("https://{}/api/{}", self.config.endpoint, api);
+        decode_json("POST", &url, &res.to_string()).map_err(|e| e.with_context("scaleio"))
+    }
+
+    /// In dry run mode the provisioning actions only log the body they
+    /// would send.  Reads are still made
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Responses are requested gzip compressed unless this is turned off
+    pub fn with_gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    /// Total size of the responses received by this Scaleio and its clones
+    pub fn transfer_size(&self) -> TransferSize {
+        TransferSize {
+            wire_bytes: self.transfers.wire_bytes.load(Ordering::Relaxed),
+            body_bytes: self.transfers.body_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record_transfer(&self, size: TransferSize) {
+        self.transfers
+            .wire_bytes
+            .fetch_add(size.wire_bytes, Ordering::Relaxed);
+        self.transfers
+            .body_bytes
+            .fetch_add(size.body_bytes, Ordering::Relaxed);
+    }
+
+    fn get<T>(&self, api: &str) -> MetricsResult<T>
+    where
+        T: DeserializeOwned + Debug,
+    {
+        let (res, size) = get_sized(&self.client, &self.config, api, self.gzip)?;
+        self.record_transfer(size);
+        Ok(res)
+    }
+
+    // GET a statistics response.  With log_unknown_fields it's read as
+    // json first so the fields T doesn't know can be logged
+    fn get_stats<T>(&self, api: &str) -> MetricsResult<T>
+    where
+        T: DeserializeOwned + Debug + UnknownFields,
+    {
+        if !self.config.log_unknown_fields.unwrap_or(false) {
+            return self.get(api);
+        }
+        let response: serde_json::Value = self.get(api)?;
+        let stats = T::deserialize(&response).map_err(|e| {
+            StorageError::from(e)
+                .with_context(format!("decoding {}", T::TYPE_NAME))
+                .with_context("scaleio")
+        })?;
+        stats.log_unknown_fields(&response);
+        Ok(stats)
+    }
+
+    fn post<T, U>(&self, api: &str, body: &U) -> MetricsResult<T>
+    where
+        T: DeserializeOwned + Debug,
+        U: Serialize + ?Sized,
+    {
+        let (res, size) = post_sized(&self.client, &self.config, api, body, self.gzip)?;
+        self.record_transfer(size);
+        trace!("{} server returned: {}", self.config.log_prefix(), res);
+        let url = format!("https://{}/api/{}", self.config.endpoint, api);
+        decode_json("POST", &url, &res).map_err(|e| e.with_context("scaleio"))
+    }
+
+    // Post a provisioning action to /api/{api}.  None for a dry run or an
+    // empty response, most actions only answer with a status
+    fn post_action<T, R>(&self, api: &str, body: &T) -> MetricsResult<Option<R>>
+    where
+        T: Serialize + ?Sized,
+        R: DeserializeOwned + Debug,
+    {
+        if self.dry_run {
+            info!(
+                "{} Dry run, not sending POST /api/{} {}",
+                self.config.log_prefix(),
+                api,
+                serde_json::to_string(body)?
+            );
+            return Ok(None);
+        }
+        let (res, size) = post_sized(
+            &self.client,
+            &self.management_config(),
+            api,
+            body,
+            self.gzip,
+        )?;
+        self.record_transfer(size);
+        trace!("{} server returned: {}", self.config.log_prefix(), res);
+        if res.trim().is_empty() {
+            return Ok(None);
+        }
+        let url = format!("https://{}/api/{}", self.config.endpoint, api);
+        decode_json("POST", &url, &res)
+            .map(Some)
+            .map_err(|e| e.with_context("scaleio"))
     }
     // Get the basic cluster configuration
     pub fn get_configuration(&self) -> MetricsResult<SystemConfig> {
         // Ask scaleio for the system configuration information
-        let sys_config = get::<SystemConfig>(&self.client, &self.config, "Configuration")?;
+        let sys_config = self.get::<SystemConfig>("Configuration")?;
         Ok(sys_config)
     }
 
     // Dump all drive information.  Call get_sds_object afterwards to turn the sdsId into
     // more useful information
     pub fn get_drive_instances(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
-        let instances = get::<Vec<Instance>>(&self.client, &self.config, "types/Device/instances")
+        let filter = self.config.field_filter();
+        let instances = self
+            .get::<Vec<Instance>>("types/Device/instances")
             .map(|instance| {
                 let points: Vec<TsPoint> = instance
                     .iter()
-                    .flat_map(|instance| instance.into_point(Some("scaleio_drive"), true))
+                    .flat_map(|instance| instance.into_point(Some(Instance::MEASUREMENT), true))
                     .map(|mut point| {
                         point.timestamp = Some(t);
                         point
                     })
+                    .filter_map(|point| filter.filter_point(point))
                     .collect();
                 points
             })?;
         Ok(instances)
     }
 
-    pub fn get_drive_ids(&self) -> MetricsResult<Vec<DriveId>> {
-        let instance_ids = get::<Vec<Instance>>(
-            &self.client,
-            &self.config,
-            "types/Device/instances",
-        )
-        .map(|instances| {
-            let ids = instances
-                .iter()
-                .map(|instance| DriveId {
-                    id: instance.id.clone(),
-                    sds_id: instance.sds_id.clone(),
-                    storage_pool_id: instance.storage_pool_id.clone().unwrap_or(String::new()),
-                })
-                .collect::<Vec<DriveId>>();
-            ids
-        })?;
+    /// The currently active alerts.  Points are stamped with the time the
+    /// alert started, or `t` when the array doesn't say
+    pub fn get_alerts(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let alerts = self.get::<Vec<Alert>>("types/Alert/instances")?;
+        let filter = self.config.field_filter();
+        Ok(alerts
+            .iter()
+            .flat_map(|alert| {
+                let timestamp = alert.start_time.unwrap_or(t);
+                alert
+                    .into_point(Some(Alert::MEASUREMENT), true)
+                    .into_iter()
+                    .map(move |mut point| {
+                        point.timestamp = Some(timestamp);
+                        point
+                    })
+            })
+            .filter_map(|point| filter.filter_point(point))
+            .collect())
+    }
+
+    pub fn get_drive_ids(&self) -> MetricsResult<Vec<DriveId>> {
+        let instance_ids =
+            self.get::<Vec<Instance>>("types/Device/instances")
+                .map(|instances| {
+                    let ids = instances
+                        .iter()
+                        .map(|instance| DriveId {
+                            id: instance.id.clone(),
+                            sds_id: instance.sds_id.clone(),
+                            storage_pool_id: instance
+                                .storage_pool_id
+                                .clone()
+                                .unwrap_or(String::new()),
+                        })
+                        .collect::<Vec<DriveId>>();
+                    ids
+                })?;
         Ok(instance_ids)
     }
 
     pub fn get_sds_ids(&self) -> MetricsResult<Vec<String>> {
-        let sds_ids = get::<Vec<SdsObject>>(&self.client, &self.config, "types/Sds/instances")
+        let sds_ids = self
+            .get::<Vec<SdsObject>>("types/Sds/instances")
             .map(|sds_objects| {
                 let ids = sds_objects
                     .iter()
@@ -2699,46 +6727,183 @@ impl Scaleio {
         t: DateTime<Utc>,
         sds_id: &str,
     ) -> MetricsResult<Vec<TsPoint>> {
-        let instance_statistics = get::<SdsStatistics>(
-            &self.client,
-            &self.config,
-            &format!("instances/Sds::{}/relationships/Statistics", sds_id),
-        )
-        .map(|instance| {
-            let points: Vec<TsPoint> = instance
-                .into_point(Some("scaleio_sds_stat"), true)
-                .iter_mut()
-                .map(|point| {
-                    point.timestamp = Some(t);
-                    point.add_tag("sds_id", TsValue::String(sds_id.to_string()));
-                    point.clone()
-                })
-                .collect();
-            points
-        })?;
+        let filter = self.config.field_filter();
+        let instance_statistics = self
+            .get_stats::<SdsStatistics>(&format!(
+                "instances/Sds::{}/relationships/Statistics",
+                sds_id
+            ))
+            .map(|instance| {
+                let points: Vec<TsPoint> = instance
+                    .into_point(Some(SdsStatistics::MEASUREMENT), true)
+                    .iter_mut()
+                    .map(|point| {
+                        point.timestamp = Some(t);
+                        point.add_tag("sds_id", TsValue::String(sds_id.to_string()));
+                        point.clone()
+                    })
+                    .filter_map(|point| filter.filter_point(point))
+                    .collect();
+                points
+            })?;
 
         Ok(instance_statistics)
     }
 
+    /// Query the statistics of every SDS in the cluster,
+    /// sds_stats_concurrency of them at a time.  SDS's that fail to respond
+    /// are logged and skipped.  The number of failures is reported in the
+    /// scaleio_sds_stat_summary point
+    pub fn get_all_sds_statistics(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let sds_ids = self.get_sds_ids()?;
+        let query = |sds_id: &String| match self.get_sds_statistics(t, sds_id) {
+            Ok(points) => Some(points),
+            Err(e) => {
+                error!(
+                    "{} Unable to query statistics for sds {}: {}",
+                    self.config.log_prefix(),
+                    sds_id,
+                    e
+                );
+                None
+            }
+        };
+        let results: Vec<Option<Vec<TsPoint>>> = match self.sds_pool {
+            Some(ref pool) => pool.install(|| sds_ids.par_iter().map(query).collect()),
+            None => sds_ids.iter().map(query).collect(),
+        };
+        let failures = results.iter().filter(|r| r.is_none()).count();
+        let mut points: Vec<TsPoint> = results.into_iter().flatten().flatten().collect();
+        let mut summary = TsPoint::new(SdsStatistics::SUMMARY_MEASUREMENT, true);
+        summary.timestamp = Some(t);
+        summary.add_field("sds_count", TsValue::Long(sds_ids.len() as u64));
+        summary.add_field("failures", TsValue::Long(failures as u64));
+        points.extend(self.config.field_filter().filter_point(summary));
+
+        Ok(points)
+    }
+
+    /// Vtrees being migrated between storage pools, plus a
+    /// scaleio_vtree_migration_summary point counting them.  The summary
+    /// is always sent, with zero counts when nothing is migrating, so a
+    /// finished migration doesn't leave its last counts standing
+    pub fn get_vtree_migrations(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let vtrees = self.get::<Vec<VTree>>("types/VTree/instances")?;
+        let migrations: Vec<VTreeMigration> = vtrees
+            .iter()
+            .filter_map(|vtree| vtree.migration())
+            .collect();
+        let mut points: Vec<TsPoint> = migrations
+            .iter()
+            .flat_map(|m| m.into_point(Some(VTreeMigration::MEASUREMENT), true))
+            .collect();
+        let count = |f: fn(&VTreeMigration) -> bool| {
+            TsValue::Long(migrations.iter().filter(|m| f(m)).count() as u64)
+        };
+        let mut summary = TsPoint::new(VTreeMigration::SUMMARY_MEASUREMENT, true);
+        summary.add_field("active", count(VTreeMigration::is_active));
+        summary.add_field("queued", count(VTreeMigration::is_queued));
+        summary.add_field("paused", count(VTreeMigration::is_paused));
+        points.push(summary);
+
+        let filter = self.config.field_filter();
+        Ok(points
+            .into_iter()
+            .map(|mut point| {
+                point.timestamp = Some(t);
+                point
+            })
+            .filter_map(|point| filter.filter_point(point))
+            .collect())
+    }
+
+    /// Query the network latency meters an SDS keeps for each of its peers
+    pub fn get_sds_network_latency(
+        &self,
+        sds_id: &str,
+        t: DateTime<Utc>,
+    ) -> MetricsResult<Vec<TsPoint>> {
+        let meters: Vec<NetworkLatencyMeter> = self.post(
+            &format!("instances/Sds::{}/action/queryNetworkLatencyMeters", sds_id),
+            &HashMap::<String, String>::new(),
+        )?;
+        let filter = self.config.field_filter();
+        let points = meters
+            .iter()
+            .flat_map(|meter| meter.into_point(Some(NetworkLatencyMeter::MEASUREMENT), true))
+            .map(|mut point| {
+                point.timestamp = Some(t);
+                point.add_tag("sds_id", TsValue::String(sds_id.to_string()));
+                point
+            })
+            .filter_map(|point| filter.filter_point(point))
+            .collect();
+
+        Ok(points)
+    }
+
+    /// Query the network latency meters for every SDS in the cluster.
+    /// SDS's that fail to respond are logged and skipped.  The number of
+    /// failures is reported in the scaleio_sds_network_latency_summary point
+    pub fn get_all_sds_network_latency(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let sds_ids = self.get_sds_ids()?;
+        let mut points: Vec<TsPoint> = Vec::new();
+        let mut failures: u64 = 0;
+        for sds_id in &sds_ids {
+            match self.get_sds_network_latency(sds_id, t) {
+                Ok(mut p) => points.append(&mut p),
+                Err(e) => {
+                    error!(
+                        "{} Unable to query network latency for sds {}: {}",
+                        self.config.log_prefix(),
+                        sds_id,
+                        e
+                    );
+                    failures += 1;
+                }
+            }
+        }
+        let mut summary = TsPoint::new(NetworkLatencyMeter::SUMMARY_MEASUREMENT, true);
+        summary.timestamp = Some(t);
+        summary.add_field("sds_count", TsValue::Long(sds_ids.len() as u64));
+        summary.add_field("failures", TsValue::Long(failures));
+        points.extend(self.config.field_filter().filter_point(summary));
+
+        Ok(points)
+    }
+
     //ids is (device_id, sds_id, storage_pool_id)
     pub fn get_drive_statistics(
         &self,
         t: DateTime<Utc>,
         ids: &DriveId,
     ) -> MetricsResult<Vec<TsPoint>> {
-        if ids.storage_pool_id == "NaN".to_string() || ids.storage_pool_id.is_empty() {
-            return Ok(vec![]);
-        }
-        let instance_statistics = get::<DeviceStatistics>(
-            &self.client,
-            &self.config,
-            &format!("instances/Device::{}/relationships/Statistics", ids.id),
-        )
-        .map(|instance| {
-            let points: Vec<TsPoint> = instance
-                .into_point(Some("scaleio_drive_stat"), true)
+        self.get_drive_statistics_iter(t, ids).collect()
+    }
+
+    /// The points of get_drive_statistics as an iterator
+    pub fn get_drive_statistics_iter(
+        &self,
+        t: DateTime<Utc>,
+        ids: &DriveId,
+    ) -> impl Iterator<Item = MetricsResult<TsPoint>> {
+        let instance_statistics =
+            if ids.storage_pool_id == "NaN".to_string() || ids.storage_pool_id.is_empty() {
+                Ok(None)
+            } else {
+                self.get_stats::<DeviceStatistics>(&format!(
+                    "instances/Device::{}/relationships/Statistics",
+                    ids.id
+                ))
+                .map(Some)
+            };
+        let ids = ids.clone();
+        let filter = self.config.field_filter();
+        points_iter(instance_statistics, move |instance| {
+            instance
                 .into_iter()
-                .map(|mut point| {
+                .flat_map(|instance| instance.into_point(Some(DeviceStatistics::MEASUREMENT), true))
+                .map(move |mut point| {
                     point.timestamp = Some(t);
                     point.add_tag("device_id", TsValue::String(ids.id.to_string()));
                     point.add_tag("sds_id", TsValue::String(ids.sds_id.to_string()));
@@ -2748,45 +6913,23 @@ impl Scaleio {
                     );
                     point
                 })
-                .collect();
-            points
-        })?;
-
-        Ok(instance_statistics)
+                .filter_map(move |point| filter.filter_point(point))
+        })
     }
 
     // Get all the drive stats.  This hashmap is referenced by sdsId.
     pub fn get_drive_stats(&self) -> MetricsResult<DeviceSelectedStatisticsResponse> {
-        let stats_req = SelectedStatisticsRequest {
-            selected_statistics_list: vec![StatsRequest {
-                req_type: StatsRequestType::Device,
-                all_ids: vec![],
-                properties: vec![
-                    // TODO: Change this into an enum
-                    "fixedReadErrorCount".into(),
-                    "avgReadSizeInBytes".into(),
-                    "avgWriteSizeInBytes".into(),
-                    "avgReadLatencyInMicrosec".into(),
-                    "avgWriteLatencyInMicrosec".into(),
-                ],
-            }],
-        };
+        let stats_req = SelectedStatisticsRequest::new(
+            StatsRequestType::Device,
+            &self.config.drive_stats_properties,
+            DRIVE_STATS_PROPERTIES,
+        );
 
         // Contact scaleio metadata server and parse the results
         // back into json.  If the call isn't an http success result
         // then return an error
-        let resp = self
-            .client
-            .post(&format!(
-                "https://{}/api/instances/querySelectedStatistics",
-                self.config.endpoint
-            ))
-            .header(CONTENT_TYPE, "application/json")
-            .basic_auth(&self.config.user, Some(&self.config.password))
-            .json(&stats_req)
-            .send()?
-            .error_for_status()?;
-        let json_resp: DeviceSelectedStatisticsResponse = resp.json()?;
+        let json_resp: DeviceSelectedStatisticsResponse =
+            self.query_selected_statistics(&stats_req)?;
         Ok(json_resp)
     }
 
@@ -2797,114 +6940,88 @@ impl Scaleio {
             .get(&format!("https://{}/api/instances", self.config.endpoint,))
             .basic_auth(&self.config.user, Some(&self.config.password))
             .send()?
-            .error_for_status()?
-            .text()?;
+            .error_for_status()
+            .map_err(StorageError::from)
+            .and_then(|r| read_text(r, self.config.max_response_bytes))?;
         println!("instances: {}", instances);
 
         Ok(())
     }
 
+    /// The configuration of every storage pool, stamped with t
+    pub fn get_storage_pools(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let filter = self.config.field_filter();
+        let pools = self.get::<Vec<PoolInstanceResponse>>("types/StoragePool/instances")?;
+        Ok(pools
+            .iter()
+            .flat_map(|pool| pool.into_point(Some(PoolInstanceResponse::MEASUREMENT), true))
+            .map(|point| point.set_time(t))
+            .filter_map(|point| filter.filter_point(point))
+            .collect())
+    }
+
     pub fn get_pool_info(&self, pool_id: &str) -> MetricsResult<PoolInstanceResponse> {
-        let pool_info = get::<PoolInstanceResponse>(
-            &self.client,
-            &self.config,
-            &format!("instances/StoragePool::{}", pool_id),
-        )?;
+        let pool_info =
+            self.get::<PoolInstanceResponse>(&format!("instances/StoragePool::{}", pool_id))?;
         Ok(pool_info)
     }
 
     pub fn get_pool_stats(&self) -> MetricsResult<ClusterSelectedStatisticsResponse> {
-        let version = self.get_version()?;
-        let stats_req: SelectedStatisticsRequest;
-        if version >= "3.0".to_string() {
-            stats_req = SelectedStatisticsRequest {
-                selected_statistics_list: vec![StatsRequest {
-                    req_type: StatsRequestType::StoragePool,
-                    all_ids: vec![],
-                    properties: vec![
-                        "numOfDevices".into(),
-                        "numOfVolumes".into(),
-                        "capacityLimitInKb".into(),
-                        "thickCapacityInUseInKb".into(),
-                        "netThinUserDataCapacityInKb".into(), // thinCapacityInUseInKb is deprecated"thinCapacityInUseInKb".into(),
-                        "primaryReadBwc".into(),
-                        "primaryWriteBwc".into(),
-                        "secondaryReadBwc".into(),
-                        "secondaryWriteBwc".into(),
-                        "totalReadBwc".into(),
-                        "totalWriteBwc".into(),
-                        "thinCapacityAllocatedInKm".into(),
-                    ],
-                }],
-            };
+        let defaults = if parse_version(&self.get_version()?)? >= (3, 0) {
+            POOL_STATS_PROPERTIES
         } else {
-            stats_req = SelectedStatisticsRequest {
-                selected_statistics_list: vec![StatsRequest {
-                    req_type: StatsRequestType::StoragePool,
-                    all_ids: vec![],
-                    properties: vec![
-                        "numOfDevices".into(),
-                        "numOfVolumes".into(),
-                        "capacityLimitInKb".into(),
-                        "thickCapacityInUseInKb".into(),
-                        "thinCapacityInUseInKb".into(),
-                        "primaryReadBwc".into(),
-                        "primaryWriteBwc".into(),
-                        "secondaryReadBwc".into(),
-                        "secondaryWriteBwc".into(),
-                        "totalReadBwc".into(),
-                        "totalWriteBwc".into(),
-                        "thinCapacityAllocatedInKm".into(),
-                    ],
-                }],
-            };
-        }
+            POOL_STATS_PROPERTIES_V2
+        };
+        let stats_req = SelectedStatisticsRequest::new(
+            StatsRequestType::StoragePool,
+            &self.config.pool_stats_properties,
+            defaults,
+        );
 
         // Contact scaleio metadata server and parse the results
         // back into json.  If the call isn't an http success result
         // then return an error
-        let resp = self
-            .client
-            .post(&format!(
-                "https://{}/api/instances/querySelectedStatistics",
-                self.config.endpoint
-            ))
-            .header(CONTENT_TYPE, "application/json")
-            .basic_auth(&self.config.user, Some(&self.config.password))
-            .json(&stats_req)
-            .send()?
-            .error_for_status()?;
-        let json_resp: ClusterSelectedStatisticsResponse = resp.json()?;
+        let json_resp: ClusterSelectedStatisticsResponse =
+            self.query_selected_statistics(&stats_req)?;
         Ok(json_resp)
     }
 
     pub fn get_sdc_stats(&self) -> MetricsResult<Vec<TsPoint>> {
-        let stats_req = SelectedStatisticsRequest {
-            selected_statistics_list: vec![StatsRequest {
-                req_type: StatsRequestType::Sdc,
-                all_ids: vec![],
-                properties: vec![
-                    "userDataReadBwc".into(),
-                    "userDataWriteBwc".into(),
-                    "volumeIds".into(),
-                    "numOfMappedVolumes".into(),
-                ],
-            }],
-        };
-        let resp = self
-            .client
-            .post(&format!(
-                "https://{}/api/instances/querySelectedStatistics",
-                self.config.endpoint
-            ))
-            .header(CONTENT_TYPE, "application/json")
-            .basic_auth(&self.config.user, Some(&self.config.password))
-            .json(&stats_req)
-            .send()?
-            .error_for_status()?;
-        debug!("deserialized: {:?}", resp);
-        let json_resp: SdcSelectedStatisticsResponse = resp.json()?;
-        Ok(json_resp.into_point(Some("scaleio_sdc_stats"), true))
+        let stats_req = SelectedStatisticsRequest::new(
+            StatsRequestType::Sdc,
+            &self.config.sdc_stats_properties,
+            SDC_STATS_PROPERTIES,
+        );
+        let json_resp: SdcSelectedStatisticsResponse =
+            self.query_selected_statistics(&stats_req)?;
+        let filter = self.config.field_filter();
+        Ok(json_resp
+            .into_point(Some(SdcSelectedStatisticsResponse::MEASUREMENT), true)
+            .into_iter()
+            .filter_map(|point| filter.filter_point(point))
+            .collect())
+    }
+
+    /// Per volume io statistics.  Useful for finding noisy neighbors
+    pub fn get_volume_statistics(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let stats_req = SelectedStatisticsRequest::new(
+            StatsRequestType::Volume,
+            &self.config.volume_stats_properties,
+            VOLUME_STATS_PROPERTIES,
+        );
+        let json_resp: VolumeSelectedStatisticsResponse =
+            self.query_selected_statistics(&stats_req)?;
+        let filter = self.config.field_filter();
+        let points: Vec<TsPoint> = json_resp
+            .into_point(Some(VolumeSelectedStatisticsResponse::MEASUREMENT), true)
+            .into_iter()
+            .map(|mut point| {
+                point.timestamp = Some(t);
+                point
+            })
+            .filter_map(|point| filter.filter_point(point))
+            .collect();
+        Ok(points)
     }
 
     pub fn get_sdc_objects(
@@ -2912,46 +7029,43 @@ impl Scaleio {
         system_id: &str,
         t: DateTime<Utc>,
     ) -> MetricsResult<Vec<TsPoint>> {
-        let sdc_info = get::<Vec<Sdc>>(
-            &self.client,
-            &self.config,
-            &format!("instances/System::{}/relationships/Sdc", system_id),
-        )
-        .map(|sdc_objects| {
-            let points: Vec<TsPoint> = sdc_objects
-                .iter()
-                .flat_map(|sdc| sdc.into_point(Some("scaleio_sdc"), true))
-                .map(|mut point| {
-                    point.timestamp = Some(t);
-                    point
-                })
-                .collect();
-            points
-        })?;
+        let filter = self.config.field_filter();
+        let sdc_info = self
+            .get::<Vec<Sdc>>(&format!(
+                "instances/System::{}/relationships/Sdc",
+                system_id
+            ))
+            .map(|sdc_objects| {
+                let points: Vec<TsPoint> = sdc_objects
+                    .iter()
+                    .flat_map(|sdc| sdc.into_point(Some(Sdc::MEASUREMENT), true))
+                    .map(|mut point| {
+                        point.timestamp = Some(t);
+                        point
+                    })
+                    .filter_map(|point| filter.filter_point(point))
+                    .collect();
+                points
+            })?;
         Ok(sdc_info)
     }
 
     // Use this to gather more information about the sds device like
     // ip address, state, storage server attached to, etc
     pub fn get_sds_object(&self, sds_id: &str) -> MetricsResult<SdsObject> {
-        let sds_object = get::<SdsObject>(
-            &self.client,
-            &self.config,
-            &format!("instances/Sds::{}", sds_id),
-        )?;
+        let sds_object = self.get::<SdsObject>(&format!("instances/Sds::{}", sds_id))?;
         Ok(sds_object)
     }
 
     pub fn get_sds_objects(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
-        let sds_info = get::<Vec<SdsObject>>(&self.client, &self.config, "types/Sds/instances")
+        let filter = self.config.field_filter();
+        let sds_info = self
+            .get::<Vec<SdsObject>>("types/Sds/instances")
             .map(|sds_objects| {
                 let points: Vec<TsPoint> = sds_objects
                     .iter()
-                    .flat_map(|sds| sds.into_point(Some("scaleio_sds"), true))
-                    .map(|mut point| {
-                        point.timestamp = Some(t);
-                        point
-                    })
+                    .flat_map(|sds| sds.into_point_at(Some(SdsObject::MEASUREMENT), t))
+                    .filter_map(|point| filter.filter_point(point))
                     .collect();
                 points
             })?;
@@ -2963,271 +7077,1870 @@ impl Scaleio {
         system_id: &str,
         t: DateTime<Utc>,
     ) -> MetricsResult<Vec<TsPoint>> {
-        let systemstats = get::<SystemStatistics>(
-            &self.client,
-            &self.config,
-            &format!("instances/System::{}/relationships/Statistics", system_id),
-        )
-        .map(|system_stats| {
-            let points: Vec<TsPoint> = system_stats
-                .into_point(Some("scaleio_sys_stats"), true)
-                .into_iter()
-                .map(|mut point| {
-                    point.timestamp = Some(t);
-                    point.add_tag("sys_id", TsValue::String(system_id.to_string()));
-                    point
-                })
-                .collect();
-            points
-        })?;
+        let filter = self.config.field_filter();
+        let systemstats = self
+            .get_stats::<SystemStatistics>(&format!(
+                "instances/System::{}/relationships/Statistics",
+                system_id
+            ))
+            .map(|system_stats| {
+                let points: Vec<TsPoint> = system_stats
+                    .into_point(Some(SystemStatistics::MEASUREMENT), true)
+                    .into_iter()
+                    .map(|mut point| {
+                        point.timestamp = Some(t);
+                        point.add_tag("sys_id", TsValue::String(system_id.to_string()));
+                        point
+                    })
+                    .filter_map(|point| filter.filter_point(point))
+                    .collect();
+                points
+            })?;
         Ok(systemstats)
     }
 
     pub fn get_system(&self, system_id: &str) -> MetricsResult<System> {
-        let system = get::<System>(
-            &self.client,
-            &self.config,
-            &format!("instances/System::{}", system_id),
-        )?;
+        let system = self.get::<System>(&format!("instances/System::{}", system_id))?;
         Ok(system)
     }
 
+    /// Fetch the sds and sdc inventories and count their software versions,
+    /// see version_report
+    pub fn get_version_report(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let sds = self.get::<Vec<SdsObject>>("types/Sds/instances")?;
+        let sdcs = self.get::<Vec<Sdc>>("types/Sdc/instances")?;
+        Ok(version_report(&sds, &sdcs, t))
+    }
+
     pub fn get_systems(&self) -> MetricsResult<Vec<System>> {
-        let systems = get::<Vec<System>>(&self.client, &self.config, "types/System/instances")?;
+        let systems = self.get::<Vec<System>>("types/System/instances")?;
         Ok(systems)
     }
 
+    /// Fetch the systems, sds, pools, volumes and sdcs of the cluster in
+    /// one pass.  Each section is fetched on its own so an endpoint that
+    /// fails only costs its own section
+    pub fn inventory(&self) -> MetricsResult<ScaleioInventory> {
+        let inventory = ScaleioInventory {
+            systems: self.get_systems(),
+            sds: self.get("types/Sds/instances"),
+            pools: self.get("types/StoragePool/instances"),
+            volumes: self.get("types/Volume/instances"),
+            sdcs: self.get("types/Sdc/instances"),
+        };
+        for (section, err) in inventory.errors() {
+            warn!(
+                "{} inventory {} failed: {}",
+                self.config.log_prefix(),
+                section,
+                err
+            );
+        }
+        Ok(inventory)
+    }
+
+    // The points of one CollectionPlan section
+    fn collect_section(
+        &self,
+        section: PlanSection,
+        t: DateTime<Utc>,
+    ) -> MetricsResult<Vec<TsPoint>> {
+        let filter = self.config.field_filter();
+        // The selected statistics responses aren't stamped with t
+        let stamp = |points: Vec<TsPoint>| -> Vec<TsPoint> {
+            points.into_iter().map(|point| point.set_time(t)).collect()
+        };
+        let points = match section {
+            PlanSection::SystemStats => {
+                let mut points = Vec::new();
+                for system in self.get_systems()? {
+                    points.extend(self.get_system_stats(&system.id, t)?);
+                }
+                points
+            }
+            PlanSection::PoolStats => stamp(
+                self.get_pool_stats()?
+                    .into_point(Some(ClusterSelectedStatisticsResponse::MEASUREMENT), true)
+                    .into_iter()
+                    .filter_map(|point| filter.filter_point(point))
+                    .collect(),
+            ),
+            PlanSection::SdsStats => self.get_all_sds_statistics(t)?,
+            PlanSection::DriveStats => {
+                let mut points = Vec::new();
+                for ids in self.get_drive_ids()? {
+                    points.extend(self.get_drive_statistics(t, &ids)?);
+                }
+                points
+            }
+            PlanSection::SdcStats => stamp(self.get_sdc_stats()?),
+            PlanSection::Volumes => {
+                let mut points = self.get_volumes(t)?;
+                points.extend(self.get_volume_statistics(t)?);
+                points
+            }
+            PlanSection::Inventory => self.inventory()?.into_points(t),
+        };
+        Ok(points)
+    }
+
+    /// Run the sections of plan that are due at now, see
+    /// CollectionPlan::due.  A section that fails is logged and left out
+    /// of last_run so it's tried again on the next poll, the others carry
+    /// on.  Every point is stamped with now
+    pub fn collect_with_plan(
+        &self,
+        plan: &CollectionPlan,
+        now: DateTime<Utc>,
+        last_run: &mut HashMap<String, DateTime<Utc>>,
+    ) -> MetricsResult<Vec<TsPoint>> {
+        let mut points = Vec::new();
+        for section in plan.due(now, last_run) {
+            match self.collect_section(section, now) {
+                Ok(section_points) => {
+                    points.extend(section_points);
+                    last_run.insert(section.to_string(), now);
+                }
+                Err(e) => {
+                    error!(
+                        "{} collection of {} failed: {}",
+                        self.config.log_prefix(),
+                        section,
+                        e
+                    );
+                }
+            }
+        }
+        Ok(points)
+    }
+
     pub fn get_version(&self) -> MetricsResult<String> {
         let version = self
             .client
             .get(&format!("https://{}/api/version", self.config.endpoint))
             .basic_auth(&self.config.user, Some(&self.config.password))
             .send()?
-            .error_for_status()?
-            .text()?;
+            .error_for_status()
+            .map_err(StorageError::from)
+            .and_then(|r| read_text(r, self.config.max_response_bytes))?;
         Ok(version)
     }
 
+    /// A cheap liveness check to run before a collection cycle.  Asks for
+    /// the api version and if the token was rejected, usually because it
+    /// expired, logs in again and asks once more.  Takes &mut self since
+    /// the refresh replaces the token
+    pub fn ping(&mut self) -> MetricsResult<()> {
+        match self.get_version() {
+            Ok(_) => Ok(()),
+            Err(e) if e.is_unauthorized() => {
+                debug!(
+                    "{} token rejected, logging in again",
+                    self.config.log_prefix()
+                );
+                self.refresh_token()?;
+                self.get_version().map(|_| ())
+            }
+            Err(e) => Err(e),
+        }
+        .map_err(|e| e.with_context("scaleio ping"))
+    }
+
     pub fn get_volumes(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
-        let sds_vols = get::<Vec<SdsVolume>>(&self.client, &self.config, "types/Volume/instances")
-            .map(|sds_vols| {
-                let points: Vec<TsPoint> = sds_vols
-                    .iter()
-                    .flat_map(|vol| vol.into_point(Some("scaleio_volume"), true))
-                    .map(|mut point| {
-                        point.timestamp = Some(t);
-                        point
-                    })
-                    .collect();
-                points
-            })?;
-        Ok(sds_vols)
+        self.get_volumes_iter(t).collect()
+    }
+
+    /// The points of get_volumes, converted one volume at a time as the
+    /// iterator is advanced so they can be streamed to a writer
+    pub fn get_volumes_iter(
+        &self,
+        t: DateTime<Utc>,
+    ) -> impl Iterator<Item = MetricsResult<TsPoint>> {
+        let sds_vols = self.get::<Vec<SdsVolume>>("types/Volume/instances");
+        let filter = self.config.field_filter();
+        points_iter(sds_vols, move |sds_vols| {
+            sds_vols
+                .into_iter()
+                .flat_map(|vol| vol.into_point(Some(SdsVolume::MEASUREMENT), true))
+                .map(move |mut point| {
+                    point.timestamp = Some(t);
+                    point
+                })
+                .filter_map(move |point| filter.filter_point(point))
+        })
+    }
+
+    /// Returns the ids of the volumes whose name starts with
+    /// `{vol_name_prefix}_` keyed by volume name
+    pub fn get_volume_ids_by_prefix(
+        &self,
+        vol_name_prefix: &str,
+    ) -> MetricsResult<HashMap<String, String>> {
+        let prefix = format!("{}_", vol_name_prefix);
+        let volumes = self.get::<Vec<VolumeName>>("types/Volume/instances")?;
+        Ok(volumes
+            .into_iter()
+            .filter_map(|vol| match vol.name {
+                Some(name) if name.starts_with(&prefix) => Some((name, vol.id)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    // Plan the volume creation requests and drop the ones for volumes that
+    // already exist.  The ids of the existing volumes are returned alongside
+    // in plan order
+    fn plan_missing_volumes(
+        &self,
+        vol_name_prefix: &str,
+        requested_size_in_kb: u64,
+        num_of_luns: usize,
+        spare_cutoff: u8,
+    ) -> MetricsResult<(Vec<PlannedRequest>, Vec<String>)> {
+        // First, get a list of available pools
+        let storage_pools = self.get::<Vec<PoolInstanceResponse>>("types/StoragePool/instances")?;
+        let plan = create_volume_plan(
+            storage_pools,
+            vol_name_prefix,
+            requested_size_in_kb,
+            num_of_luns,
+            spare_cutoff,
+        )?;
+        if plan.len() < num_of_luns {
+            debug!(
+                "{} Cannot create volumes in {} pools, creating in {} instead",
+                self.config.log_prefix(),
+                num_of_luns,
+                plan.len()
+            );
+        }
+        let mut existing_ids = self.get_volume_ids_by_prefix(vol_name_prefix)?;
+        let mut existing: Vec<String> = Vec::new();
+        let mut missing: Vec<PlannedRequest> = Vec::new();
+        for req in plan {
+            let name = req.body["name"].as_str().unwrap_or_default().to_string();
+            match existing_ids.remove(&name) {
+                Some(id) => {
+                    debug!(
+                        "{} Volume {} already exists as {}, skipping",
+                        self.config.log_prefix(),
+                        name,
+                        id
+                    );
+                    existing.push(id);
+                }
+                None => {
+                    debug!("{} Planned {}", self.config.log_prefix(), req);
+                    missing.push(req);
+                }
+            }
+        }
+        Ok((missing, existing))
+    }
+
+    /// Work out the requests create_volume would send without sending
+    /// them.  Only the storage pool and volume listings are read from the
+    /// server.  Volumes that already exist aren't part of the plan
+    pub fn plan_create_volume(
+        &self,
+        vol_name_prefix: &str,
+        requested_size_in_kb: u64,
+        num_of_luns: usize,
+        spare_cutoff: u8,
+    ) -> MetricsResult<Vec<PlannedRequest>> {
+        let (plan, _) = self.plan_missing_volumes(
+            vol_name_prefix,
+            requested_size_in_kb,
+            num_of_luns,
+            spare_cutoff,
+        )?;
+        Ok(plan)
     }
 
     /// Creates a volume on the given endpoint using the credentials specified
     /// in the config file. Automatically selects a storage pool
     /// vol_name_prefix refers to the tracking ID/ticket ID of the request
+    ///
+    /// Volumes are named `{vol_name_prefix}_{n}` where n counts the chosen
+    /// pools from 0.  A volume with one of those names that already exists
+    /// is taken to be left over from an earlier attempt and isn't created
+    /// again, so re-running after a partial failure only creates the
+    /// missing volumes.  The existing volume isn't checked for size or
+    /// pool.  The ids of the existing volumes are returned first, followed
+    /// by the newly created ones
     pub fn create_volume(
         &self,
         vol_name_prefix: &str,
         requested_size_in_kb: u64,
         num_of_luns: usize,
-        mut spare_cutoff: u8,
+        spare_cutoff: u8,
     ) -> MetricsResult<Vec<String>> {
-        // Set minimum cut off
-        if spare_cutoff <= 10 {
-            spare_cutoff = 10
+        let (plan, existing) = self.plan_missing_volumes(
+            vol_name_prefix,
+            requested_size_in_kb,
+            num_of_luns,
+            spare_cutoff,
+        )?;
+
+        let mut volume_ids: Vec<String> = existing;
+        let intended = volume_ids.len() + plan.len();
+        for req in &plan {
+            debug!("{} Sending {}", self.config.log_prefix(), req);
+            // post a request to endpoint to create a volume. If call isn't
+            // an http success result, return an error. Return is newly created volume ID
+            match self.post_action::<_, String>(&req.api, &req.body)? {
+                Some(id) if !id.trim().is_empty() => volume_ids.push(id),
+                // A dry run has no id to hand back for the volume
+                None if self.dry_run => {}
+                _ => {
+                    return Err(StorageError::new(format!("{} returned no volume id", req))
+                        .with_context("scaleio create_volume"))
+                }
+            }
+        }
+
+        // Did we succeed in creating as many as intended?
+        if volume_ids.len() != intended {
+            debug!(
+                "{} Created only {} volumes. {} intended",
+                self.config.log_prefix(),
+                volume_ids.len(),
+                intended
+            );
+            debug!("{} Request is not met in full", self.config.log_prefix());
+            // TODO: Rollback/delete these volumes without mapping?
+        }
+        Ok(volume_ids)
+    }
+
+    /// Returns the sdcId corresponding to the given name
+    fn get_sdc_id_from_name(&self, sdc_name: &str) -> MetricsResult<String> {
+        // get a list of all sdc's, filter entry that matches sdc_name
+        // and return corresponding sdc_id
+
+        debug!(
+            "{} Retrieving SDC ID for {}",
+            self.config.log_prefix(),
+            sdc_name
+        );
+        let sdc_info = self
+            .get::<Vec<Sdc>>("api/types/Sdc/instances")
+            .map(|sdc_objects| {
+                let ids: Vec<String> = sdc_objects
+                    .iter()
+                    .filter(|sdc| match sdc.name {
+                        Some(ref name) => name == sdc_name,
+                        None => false,
+                    })
+                    .map(|sdc| sdc.id.clone())
+                    .collect::<Vec<String>>();
+                ids
+            })?;
+
+        if !sdc_info.is_empty() {
+            if let Some(id) = sdc_info.get(0) {
+                Ok(id.to_string())
+            } else {
+                Err(StorageError::new(format!(
+                    "SDC ID not found for {}",
+                    sdc_name
+                )))
+            }
+        } else {
+            Err(StorageError::new(format!(
+                "SDC ID not found for {}",
+                sdc_name
+            )))
+        }
+    }
+
+    /// Work out the requests map_volumes would send without sending them.
+    /// Only the sdc listing is read from the server
+    pub fn plan_map_volumes(
+        &self,
+        volume_ids: &[String],
+        sdc_name: &str,
+    ) -> MetricsResult<Vec<PlannedRequest>> {
+        // Get sdc_id from sdc_name
+        let sdc_id = self.get_sdc_id_from_name(sdc_name)?;
+        debug!(
+            "{} SDC ID for {} is {}",
+            self.config.log_prefix(),
+            sdc_name,
+            sdc_id
+        );
+        let plan = map_volumes_plan(
+            volume_ids,
+            &sdc_id,
+            self.config.bandwidth_limit,
+            self.config.iops_limit,
+        )?;
+        for req in &plan {
+            debug!("{} Planned {}", self.config.log_prefix(), req);
+        }
+        Ok(plan)
+    }
+
+    /// Maps all the volumes in the list to the given sdc
+    /// Also sets iops and bandwidth limits
+    pub fn map_volumes(&self, volume_ids: &[String], sdc_name: &str) -> MetricsResult<bool> {
+        for req in self.plan_map_volumes(volume_ids, sdc_name)? {
+            debug!("{} Sending {}", self.config.log_prefix(), req);
+            // Returns only http status of success or failure
+            self.post_action::<_, serde_json::Value>(&req.api, &req.body)?;
+        }
+        Ok(true)
+    }
+
+    /// Unmap a volume from an sdc
+    pub fn unmap_volume(&self, volume_id: &str, sdc_id: &str) -> MetricsResult<()> {
+        self.post_action::<_, serde_json::Value>(
+            &format!("instances/Volume::{}/action/removeMappedSdc", volume_id),
+            &RemoveMappedSdc {
+                sdc_id: sdc_id.to_string(),
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Delete a volume.  It must be unmapped from every sdc first
+    pub fn remove_volume(&self, volume_id: &str, remove_mode: RemoveMode) -> MetricsResult<()> {
+        self.post_action::<_, serde_json::Value>(
+            &format!("instances/Volume::{}/action/removeVolume", volume_id),
+            &RemoveVolume { remove_mode },
+        )?;
+        Ok(())
+    }
+
+    /// Snapshot the volumes together as one consistency group.  None in
+    /// dry run mode
+    pub fn snapshot_volumes(
+        &self,
+        snapshot_defs: Vec<SnapshotDef>,
+    ) -> MetricsResult<Option<SnapshotVolumesResponse>> {
+        let res = self.post_action(
+            "instances/System/action/snapshotVolumes",
+            &SnapshotVolumes { snapshot_defs },
+        )?;
+        if res.is_none() && !self.dry_run {
+            return Err(StorageError::new(
+                "snapshotVolumes returned an empty response".into(),
+            ));
+        }
+        Ok(res)
+    }
+
+    /// Set the rebuild io priority policy of a storage pool, e.g. to
+    /// throttle rebuilds during business hours.  params are checked
+    /// against the policy before anything is sent
+    pub fn set_rebuild_policy(
+        &self,
+        pool_id: &str,
+        policy: RebuildIoPriority,
+        params: ThrottleParams,
+    ) -> MetricsResult<()> {
+        self.set_io_priority_policy(pool_id, "setRebuildIoPriorityPolicy", policy, &params)
+    }
+
+    /// Set the rebalance io priority policy of a storage pool.  params are
+    /// checked against the policy before anything is sent
+    pub fn set_rebalance_policy(
+        &self,
+        pool_id: &str,
+        policy: RebuildIoPriority,
+        params: ThrottleParams,
+    ) -> MetricsResult<()> {
+        self.set_io_priority_policy(pool_id, "setRebalanceIoPriorityPolicy", policy, &params)
+    }
+
+    fn set_io_priority_policy(
+        &self,
+        pool_id: &str,
+        action: &str,
+        policy: RebuildIoPriority,
+        params: &ThrottleParams,
+    ) -> MetricsResult<()> {
+        let body = SetIoPriorityPolicy::new(policy, params)
+            .map_err(|e| e.with_context(format!("scaleio {}", action)))?;
+        self.post_action::<_, serde_json::Value>(
+            &format!("instances/StoragePool::{}/action/{}", pool_id, action),
+            &body,
+        )?;
+        Ok(())
+    }
+
+    /// Read the pool back and list how its rebuild settings differ from
+    /// policy and params.  Empty means the settings took
+    pub fn verify_rebuild_policy(
+        &self,
+        pool_id: &str,
+        policy: RebuildIoPriority,
+        params: &ThrottleParams,
+    ) -> MetricsResult<Vec<String>> {
+        Ok(self
+            .get_pool_info(pool_id)?
+            .rebuild_policy_differences(policy, params))
+    }
+
+    /// Read the pool back and list how its rebalance settings differ from
+    /// policy and params.  Empty means the settings took
+    pub fn verify_rebalance_policy(
+        &self,
+        pool_id: &str,
+        policy: RebuildIoPriority,
+        params: &ThrottleParams,
+    ) -> MetricsResult<Vec<String>> {
+        Ok(self
+            .get_pool_info(pool_id)?
+            .rebalance_policy_differences(policy, params))
+    }
+}
+
+impl Collector for Scaleio {
+    fn backend(&self) -> &str {
+        "scaleio"
+    }
+
+    fn array_id(&self) -> &str {
+        &self.config.endpoint
+    }
+
+    /// Every CollectionPlan section, the drive instances and the active
+    /// alerts.  As with collect_with_plan a section that fails is logged
+    /// and the others carry on
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut points = self.collect_with_plan(&CollectionPlan::all(), t, &mut HashMap::new())?;
+        points.extend(self.get_drive_instances(t)?);
+        points.extend(self.get_alerts(t)?);
+        Ok(points)
+    }
+}
+
+/// The typed objects of a scaleio cluster.  Sections that failed to fetch
+/// hold their error
+#[derive(Debug)]
+pub struct ScaleioInventory {
+    pub systems: MetricsResult<Vec<System>>,
+    pub sds: MetricsResult<Vec<SdsObject>>,
+    pub pools: MetricsResult<Vec<PoolInstanceResponse>>,
+    pub volumes: MetricsResult<Vec<SdsVolume>>,
+    pub sdcs: MetricsResult<Vec<Sdc>>,
+}
+
+impl ScaleioInventory {
+    /// The sections that failed to fetch, by name
+    pub fn errors(&self) -> Vec<(&'static str, &StorageError)> {
+        let mut errors = Vec::new();
+        if let Err(e) = &self.systems {
+            errors.push(("systems", e));
+        }
+        if let Err(e) = &self.sds {
+            errors.push(("sds", e));
+        }
+        if let Err(e) = &self.pools {
+            errors.push(("pools", e));
+        }
+        if let Err(e) = &self.volumes {
+            errors.push(("volumes", e));
+        }
+        if let Err(e) = &self.sdcs {
+            errors.push(("sdcs", e));
+        }
+        errors
+    }
+
+    /// Pool names keyed by pool id.  Empty if the pools failed to fetch
+    pub fn pool_names(&self) -> HashMap<String, String> {
+        match &self.pools {
+            Ok(pools) => pools
+                .iter()
+                .map(|p| (p.id.clone(), p.name.clone()))
+                .collect(),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    /// The points get_sds_objects, get_volumes and get_sdc_objects would
+    /// produce plus a scaleio_system point per system, all stamped with t.
+    /// Failed sections are skipped and pools have no points of their own
+    pub fn into_points(&self, t: DateTime<Utc>) -> Vec<TsPoint> {
+        fn section<T: IntoPoint>(res: &MetricsResult<Vec<T>>, name: &str) -> Vec<TsPoint> {
+            res.iter()
+                .flatten()
+                .flat_map(|item| item.into_point(Some(name), true))
+                .collect()
+        }
+        let mut points = section(&self.systems, System::MEASUREMENT);
+        points.extend(section(&self.sds, SdsObject::MEASUREMENT));
+        points.extend(section(&self.volumes, SdsVolume::MEASUREMENT));
+        points.extend(section(&self.sdcs, Sdc::MEASUREMENT));
+        for point in points.iter_mut() {
+            point.timestamp = Some(t);
+        }
+        points
+    }
+}
+
+/// A family of points collect_with_plan can run on its own schedule
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanSection {
+    SystemStats,
+    PoolStats,
+    SdsStats,
+    DriveStats,
+    SdcStats,
+    Volumes,
+    Inventory,
+}
+
+impl PlanSection {
+    pub const ALL: &'static [PlanSection] = &[
+        PlanSection::SystemStats,
+        PlanSection::PoolStats,
+        PlanSection::SdsStats,
+        PlanSection::DriveStats,
+        PlanSection::SdcStats,
+        PlanSection::Volumes,
+        PlanSection::Inventory,
+    ];
+
+    /// The name used in configs and as the last_run key
+    pub fn name(self) -> &'static str {
+        match self {
+            PlanSection::SystemStats => "system_stats",
+            PlanSection::PoolStats => "pool_stats",
+            PlanSection::SdsStats => "sds_stats",
+            PlanSection::DriveStats => "drive_stats",
+            PlanSection::SdcStats => "sdc_stats",
+            PlanSection::Volumes => "volumes",
+            PlanSection::Inventory => "inventory",
+        }
+    }
+}
+
+impl fmt::Display for PlanSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn default_section_enabled() -> bool {
+    true
+}
+
+/// When a section of a CollectionPlan runs
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SectionPlan {
+    #[serde(default = "default_section_enabled")]
+    pub enabled: bool,
+    /// Seconds between runs.  Runs on every poll when unset
+    pub interval_secs: Option<u64>,
+}
+
+/// The sections collect_with_plan runs and how often, ie sdc_stats every
+/// minute but the drive statistics of a large cluster every 15 minutes.
+/// Sections that aren't listed are never run
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct CollectionPlan {
+    pub sections: BTreeMap<PlanSection, SectionPlan>,
+}
+
+impl CollectionPlan {
+    /// Every section on every poll
+    pub fn all() -> Self {
+        PlanSection::ALL
+            .iter()
+            .fold(CollectionPlan::default(), |plan, s| {
+                plan.with_section(*s, None)
+            })
+    }
+
+    /// Add or replace a section, running every interval_secs or on every
+    /// poll if None
+    pub fn with_section(mut self, section: PlanSection, interval_secs: Option<u64>) -> Self {
+        self.sections.insert(
+            section,
+            SectionPlan {
+                enabled: true,
+                interval_secs,
+            },
+        );
+        self
+    }
+
+    /// The enabled sections that haven't run, or that last ran, per the
+    /// time last_run holds under their name, in an earlier interval.
+    /// Intervals are counted from the unix epoch so a poll that drifts a
+    /// few seconds late doesn't push the section out to the next one
+    pub fn due(
+        &self,
+        now: DateTime<Utc>,
+        last_run: &HashMap<String, DateTime<Utc>>,
+    ) -> Vec<PlanSection> {
+        self.sections
+            .iter()
+            .filter(|(_, plan)| plan.enabled)
+            .filter(
+                |(section, plan)| match (plan.interval_secs, last_run.get(section.name())) {
+                    (Some(interval), Some(last)) if interval > 0 => {
+                        let interval = interval as i64;
+                        now.timestamp().div_euclid(interval)
+                            != last.timestamp().div_euclid(interval)
+                    }
+                    _ => true,
+                },
+            )
+            .map(|(section, _)| *section)
+            .collect()
+    }
+}
+
+#[test]
+fn test_collection_plan_due() {
+    let plan: CollectionPlan = serde_json::from_value(json!({
+        "sections": {
+            "system_stats": {},
+            "drive_stats": {"interval_secs": 300},
+            "inventory": {"interval_secs": 900},
+            "volumes": {"enabled": false},
+        }
+    }))
+    .unwrap();
+    let start = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let mut last_run: HashMap<String, DateTime<Utc>> = HashMap::new();
+    let mut fired: BTreeMap<PlanSection, Vec<i64>> = BTreeMap::new();
+    // A poll every minute for half an hour, every due section succeeding
+    for minute in 0..=30 {
+        let now = start + chrono::Duration::minutes(minute);
+        for section in plan.due(now, &last_run) {
+            fired.entry(section).or_default().push(minute);
+            last_run.insert(section.to_string(), now);
+        }
+    }
+    assert_eq!(
+        fired[&PlanSection::SystemStats],
+        (0..=30).collect::<Vec<i64>>()
+    );
+    assert_eq!(
+        fired[&PlanSection::DriveStats],
+        vec![0, 5, 10, 15, 20, 25, 30]
+    );
+    assert_eq!(fired[&PlanSection::Inventory], vec![0, 15, 30]);
+    assert!(!fired.contains_key(&PlanSection::Volumes));
+    assert!(!fired.contains_key(&PlanSection::SdcStats));
+
+    assert_eq!(CollectionPlan::all().due(start, &last_run).len(), 7);
+
+    // Polls that come a few seconds late, by a different amount each time,
+    // still run a section once per interval
+    let mut last_run: HashMap<String, DateTime<Utc>> = HashMap::new();
+    let mut fired: BTreeMap<PlanSection, Vec<i64>> = BTreeMap::new();
+    for minute in 0..=30 {
+        let jitter = chrono::Duration::seconds(minute / 5 % 3 * 2);
+        let now = start + chrono::Duration::minutes(minute) + jitter;
+        for section in plan.due(now, &last_run) {
+            fired.entry(section).or_default().push(minute);
+            last_run.insert(section.to_string(), now);
+        }
+    }
+    assert_eq!(
+        fired[&PlanSection::DriveStats],
+        vec![0, 5, 10, 15, 20, 25, 30]
+    );
+    assert_eq!(fired[&PlanSection::Inventory], vec![0, 15, 30]);
+}
+
+#[test]
+fn test_collect_with_plan() {
+    use std::fs::File;
+
+    let read = |path: &str| {
+        let mut buff = Vec::new();
+        File::open(path).unwrap().read_to_end(&mut buff).unwrap();
+        buff
+    };
+    let mut sds: serde_json::Value =
+        serde_json::from_slice(&read("tests/scaleio/sdsObject.json")).unwrap();
+    sds["id"] = json!("4fdf0e0700000000");
+    let sds = serde_json::to_vec(&json!([sds])).unwrap();
+    let sds_stats = read("tests/scaleio/sds_statistics.json");
+    let sdc_stats = read("tests/scaleio/sdcSelectedStatisticsResponse.json");
+    // get_pool_stats asks for the version first, which fails
+    let (port, requests) = mock_scaleio_api(false, move |_: &str, path: &str| {
+        if path.ends_with("types/Sds/instances") {
+            sds.clone()
+        } else if path.contains("Sds::") {
+            sds_stats.clone()
+        } else if path.ends_with("querySelectedStatistics") {
+            sdc_stats.clone()
+        } else {
+            b"not a version".to_vec()
+        }
+    });
+    let scaleio = mock_scaleio(port);
+    let plan = CollectionPlan::default()
+        .with_section(PlanSection::SdcStats, None)
+        .with_section(PlanSection::SdsStats, Some(900))
+        .with_section(PlanSection::PoolStats, Some(900));
+    let measurements = |points: &[TsPoint]| {
+        let mut m: Vec<String> = points.iter().map(|p| p.measurement.to_string()).collect();
+        m.dedup();
+        m
+    };
+    let sds_requests = || {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, path, _)| path.contains("Sds::"))
+            .count()
+    };
+
+    let start = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let mut last_run: HashMap<String, DateTime<Utc>> = HashMap::new();
+    let points = scaleio
+        .collect_with_plan(&plan, start, &mut last_run)
+        .unwrap();
+    // The failed pool stats don't stop the sections after them
+    assert_eq!(
+        measurements(&points),
+        vec![
+            SdsStatistics::MEASUREMENT,
+            SdsStatistics::SUMMARY_MEASUREMENT,
+            SdcSelectedStatisticsResponse::MEASUREMENT,
+        ]
+    );
+    assert!(points.iter().all(|p| p.timestamp == Some(start)));
+    assert_eq!(last_run.get("sds_stats"), Some(&start));
+    assert_eq!(last_run.get("sdc_stats"), Some(&start));
+    assert!(!last_run.contains_key("pool_stats"));
+    assert_eq!(sds_requests(), 1);
+
+    // A minute later the sds stats aren't due but the failed pool stats
+    // are tried again
+    let now = start + chrono::Duration::minutes(1);
+    assert_eq!(
+        plan.due(now, &last_run),
+        vec![PlanSection::PoolStats, PlanSection::SdcStats]
+    );
+    let points = scaleio
+        .collect_with_plan(&plan, now, &mut last_run)
+        .unwrap();
+    assert_eq!(
+        measurements(&points),
+        vec![SdcSelectedStatisticsResponse::MEASUREMENT]
+    );
+    assert_eq!(last_run.get("sdc_stats"), Some(&now));
+    assert_eq!(last_run.get("sds_stats"), Some(&start));
+    assert_eq!(sds_requests(), 1);
+
+    let now = start + chrono::Duration::minutes(15);
+    scaleio
+        .collect_with_plan(&plan, now, &mut last_run)
+        .unwrap();
+    assert_eq!(sds_requests(), 2);
+}
+
+#[test]
+fn test_inventory() {
+    use std::fs::File;
+
+    let read = |name: &str| {
+        let mut buff = String::new();
+        File::open(format!("tests/scaleio/{}", name))
+            .unwrap()
+            .read_to_string(&mut buff)
+            .unwrap();
+        buff
+    };
+    let systems = read("system_v3.json");
+    let sds = format!("[{}]", read("sdsObject.json"));
+    let pools = format!("[{}]", read("poolInstance.json"));
+    let volumes = read("volume_instances.json");
+    let sdcs = read("sdc_info.json");
+    let scaleio = |failing: &'static str| {
+        let (systems, sds, pools, volumes, sdcs) = (
+            systems.clone(),
+            sds.clone(),
+            pools.clone(),
+            volumes.clone(),
+            sdcs.clone(),
+        );
+        let port = crate::mock_https_server(move |req| {
+            if req.path.contains(failing) {
+                return (500, String::new(), b"{}".to_vec());
+            }
+            let body = match req.path.as_str() {
+                "/api/types/System/instances" => &systems,
+                "/api/types/Sds/instances" => &sds,
+                "/api/types/StoragePool/instances" => &pools,
+                "/api/types/Volume/instances" => &volumes,
+                "/api/types/Sdc/instances" => &sdcs,
+                p => panic!("unexpected request {}", p),
+            };
+            (200, String::new(), body.as_bytes().to_vec())
+        });
+        mock_scaleio(port)
+    };
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+
+    let s = scaleio("nothing fails");
+    let inventory = s.inventory().unwrap();
+    assert!(inventory.errors().is_empty());
+    assert_eq!(inventory.systems.as_ref().unwrap().len(), 1);
+    assert_eq!(inventory.sds.as_ref().unwrap().len(), 1);
+    let pool = &inventory.pools.as_ref().unwrap()[0];
+    assert_eq!(inventory.pool_names()[&pool.id], pool.name);
+    let points = inventory.into_points(t);
+    let of = |points: &[TsPoint], measurement: &str| -> Vec<TsPoint> {
+        points
+            .iter()
+            .filter(|p| p.measurement == measurement)
+            .cloned()
+            .collect()
+    };
+    // The same points the individual calls produce
+    assert_eq!(
+        of(&points, SdsObject::MEASUREMENT),
+        s.get_sds_objects(t).unwrap()
+    );
+    let volume_points = s.get_volumes(t).unwrap();
+    assert_eq!(
+        of(&points, SdsVolume::MEASUREMENT),
+        of(&volume_points, SdsVolume::MEASUREMENT)
+    );
+    assert_eq!(of(&points, System::MEASUREMENT).len(), 1);
+    assert_eq!(
+        of(&points, Sdc::MEASUREMENT).len(),
+        inventory.sdcs.as_ref().unwrap().len()
+    );
+
+    // Cached copies deserialize back into the same objects
+    let sdcs = inventory.sdcs.as_ref().unwrap().clone();
+    let cached: Vec<Sdc> = serde_json::from_str(&serde_json::to_string(&sdcs).unwrap()).unwrap();
+    assert_eq!(
+        cached.into_iter().map(|s| s.sdc_ip).collect::<Vec<_>>(),
+        sdcs.into_iter().map(|s| s.sdc_ip).collect::<Vec<_>>()
+    );
+    let cached: Vec<SdsObject> =
+        serde_json::from_value(serde_json::to_value(inventory.sds.as_ref().unwrap()).unwrap())
+            .unwrap();
+    assert_eq!(cached[0].id, inventory.sds.as_ref().unwrap()[0].id);
+
+    // One failing endpoint only costs its own section
+    let inventory = scaleio("Volume").inventory().unwrap();
+    let errors = inventory.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, "volumes");
+    assert_eq!(
+        errors[0].1.status(),
+        Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+    assert!(inventory.systems.is_ok() && inventory.sds.is_ok() && inventory.sdcs.is_ok());
+    assert_eq!(inventory.pool_names().len(), 1);
+    let points = inventory.into_points(t);
+    assert!(of(&points, SdsVolume::MEASUREMENT).is_empty());
+    assert_eq!(of(&points, SdsObject::MEASUREMENT).len(), 1);
+}
+
+#[derive(Serialize, Debug)]
+pub enum VolumeRequestType {
+    ThinProvisioned,
+    ThickProvisioned,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeRequest {
+    pub volume_size_in_kb: String,
+    pub storage_pool_id: String,
+    pub name: String,
+    pub volume_type: VolumeRequestType,
+    pub use_rmcache: bool,
+}
+
+impl VolumeRequest {
+    fn new(volume_size_int: u64, storage_pool_id: String, name: String) -> VolumeRequest {
+        VolumeRequest {
+            volume_size_in_kb: volume_size_int.to_string(),
+            storage_pool_id,
+            name,
+            volume_type: VolumeRequestType::ThinProvisioned,
+            use_rmcache: true,
         }
+    }
+}
 
-        // First, get a list of available pools
-        let storage_pools = get::<Vec<PoolInstanceResponse>>(
-            &self.client,
-            &self.config,
-            "types/StoragePool/instances",
-        )?;
+// Just enough of a volume to match it by name
+#[derive(Deserialize, Debug)]
+struct VolumeName {
+    id: String,
+    name: Option<String>,
+}
 
-        // don't need storage_pools later on, OK to move
-        let pool_ids: Vec<String> = identify_ideal_pools(storage_pools, num_of_luns, spare_cutoff)?;
+/// A request that create_volume or map_volumes would send.  The plan_
+/// methods return these so changes can be previewed before they're made
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedRequest {
+    pub method: String,
+    /// Path under /api/
+    pub api: String,
+    pub body: serde_json::Value,
+}
 
-        // Could be more defensive and check if pool_ids is empty.
-        // identify_ideal_pools() should ideally return an error in that case.
-        // So, skip that check.
-        if pool_ids.len() < num_of_luns {
-            debug!(
-                "Cannot create volumes in {} pools, creating in {} instead",
-                num_of_luns,
-                pool_ids.len()
-            );
-        }
-        // Create each volume with sizes balanced over pools
-        let each_vol_size_in_kb = requested_size_in_kb / (pool_ids.len() as u64);
+impl PlannedRequest {
+    fn post<U: Serialize>(api: String, body: &U) -> MetricsResult<Self> {
+        Ok(PlannedRequest {
+            method: "POST".into(),
+            api,
+            body: serde_json::to_value(body)?,
+        })
+    }
+}
 
-        let mut volume_ids: Vec<String> = Vec::new();
+impl fmt::Display for PlannedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} /api/{} {}", self.method, self.api, self.body)
+    }
+}
 
-        for (vol_num, pool_id) in pool_ids.iter().enumerate() {
-            debug!(
-                "Creating volume of size {} in pool with ID {}",
-                each_vol_size_in_kb,
-                pool_id.to_string()
-            );
+// Choose the pools and build one volume creation request per pool with the
+// requested size balanced over them
+fn create_volume_plan(
+    storage_pools: Vec<PoolInstanceResponse>,
+    vol_name_prefix: &str,
+    requested_size_in_kb: u64,
+    num_of_luns: usize,
+    mut spare_cutoff: u8,
+) -> MetricsResult<Vec<PlannedRequest>> {
+    // Set minimum cut off
+    if spare_cutoff <= 10 {
+        spare_cutoff = 10
+    }
+    // Could be more defensive and check if pool_ids is empty.
+    // identify_ideal_pools() should ideally return an error in that case.
+    // So, skip that check.
+    let pool_ids: Vec<String> = identify_ideal_pools(storage_pools, num_of_luns, spare_cutoff)?;
+    // Create each volume with sizes balanced over pools
+    let each_vol_size_in_kb = requested_size_in_kb / (pool_ids.len() as u64);
+
+    pool_ids
+        .iter()
+        .enumerate()
+        .map(|(vol_num, pool_id)| {
             let vol_creation_req = VolumeRequest::new(
                 each_vol_size_in_kb,
                 pool_id.to_string(),
                 format!("{}_{}", vol_name_prefix, vol_num),
             );
-            // post a request to endpoint to create a volume. If call isn't
-            // an http success result, return an error. Return is newly created volume ID
-            let vol_creation_resp = self
-                .client
-                .post(&format!(
-                    "https://{}/api/types/Volume/instances",
-                    self.config.endpoint
-                ))
-                .header(CONTENT_TYPE, "application/json")
-                .basic_auth(&self.config.user, Some(&self.config.password))
-                .json(&vol_creation_req)
-                .send()?
-                .error_for_status()?;
-            let json_resp: String = vol_creation_resp.json()?;
-            volume_ids.push(json_resp);
-        }
+            PlannedRequest::post("types/Volume/instances".into(), &vol_creation_req)
+        })
+        .collect()
+}
 
-        // Did we succeed in creating as many as intended?
-        if volume_ids.len() != pool_ids.len() {
-            debug!(
-                "Created only {} volumes. {} intended",
-                volume_ids.len(),
-                pool_ids.len()
-            );
-            debug!("Request is not met in full");
-            // TODO: Rollback/delete these volumes without mapping?
+// Map each volume to the sdc and then set its limits.  Unset limits are
+// sent as 0, unlimited
+fn map_volumes_plan(
+    volume_ids: &[String],
+    sdc_id: &str,
+    bandwidth_limit: Option<u64>,
+    iops_limit: Option<u64>,
+) -> MetricsResult<Vec<PlannedRequest>> {
+    let mut plan: Vec<PlannedRequest> = Vec::new();
+    for vol_id in volume_ids {
+        // TODO: allow multiple mappings?
+        plan.push(PlannedRequest::post(
+            format!("instances/Volume::{}/action/addMappedSdc", vol_id),
+            &AddMappedSdc {
+                sdc_id: sdc_id.to_string(),
+            },
+        )?);
+        plan.push(PlannedRequest::post(
+            format!("instances/Volume::{}/action/setMappedSdcLimits", vol_id),
+            &SetMappedSdcLimits::new(sdc_id, bandwidth_limit, iops_limit),
+        )?);
+    }
+    Ok(plan)
+}
+
+/// Body of the Volume addMappedSdc action
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddMappedSdc {
+    pub sdc_id: String,
+}
+
+/// Body of the Volume setMappedSdcLimits action.  The gateway wants the
+/// limits as strings, 0 meaning unlimited
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetMappedSdcLimits {
+    pub sdc_id: String,
+    pub bandwidth_limit_in_kbps: String,
+    pub iops_limit: String,
+}
+
+impl SetMappedSdcLimits {
+    pub fn new(sdc_id: &str, bandwidth_limit: Option<u64>, iops_limit: Option<u64>) -> Self {
+        SetMappedSdcLimits {
+            sdc_id: sdc_id.to_string(),
+            bandwidth_limit_in_kbps: bandwidth_limit.unwrap_or(0).to_string(),
+            iops_limit: iops_limit.unwrap_or(0).to_string(),
         }
-        Ok(volume_ids)
     }
+}
 
-    /// Returns the sdcId corresponding to the given name
-    fn get_sdc_id_from_name(&self, sdc_name: &str) -> MetricsResult<String> {
-        // get a list of all sdc's, filter entry that matches sdc_name
-        // and return corresponding sdc_id
+/// Body of the Volume removeMappedSdc action
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveMappedSdc {
+    pub sdc_id: String,
+}
 
-        debug!("Retrieving SDC ID for {}", sdc_name);
-        let sdc_info = get::<Vec<Sdc>>(&self.client, &self.config, "api/types/Sdc/instances").map(
-            |sdc_objects| {
-                let ids: Vec<String> = sdc_objects
-                    .iter()
-                    .filter(|sdc| match sdc.name {
-                        Some(ref name) => name == sdc_name,
-                        None => false,
-                    })
-                    .map(|sdc| sdc.id.clone())
-                    .collect::<Vec<String>>();
-                ids
-            },
-        )?;
+/// Which volumes of a vtree removeVolume deletes
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RemoveMode {
+    OnlyMe,
+    IncludingDescendants,
+    DescendantsOnly,
+    WholeVtree,
+}
 
-        if !sdc_info.is_empty() {
-            if let Some(id) = sdc_info.get(0) {
-                Ok(id.to_string())
-            } else {
-                Err(StorageError::new(format!(
-                    "SDC ID not found for {}",
-                    sdc_name
-                )))
+/// Body of the Volume removeVolume action
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveVolume {
+    pub remove_mode: RemoveMode,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDef {
+    pub volume_id: String,
+    pub snapshot_name: String,
+}
+
+/// Body of the System snapshotVolumes action
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotVolumes {
+    pub snapshot_defs: Vec<SnapshotDef>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotVolumesResponse {
+    pub volume_id_list: Vec<String>,
+    pub snapshot_group_id: String,
+}
+
+/// The params of a rebuild or rebalance io priority policy.  Which ones a
+/// policy takes is checked by validate
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ThrottleParams {
+    pub num_of_concurrent_ios_per_device: Option<u64>,
+    pub bw_limit_per_device_in_kbps: Option<u64>,
+    pub app_iops_per_device_threshold: Option<u64>,
+    pub app_bw_per_device_threshold_in_kbps: Option<u64>,
+    pub quiet_period_in_msec: Option<u64>,
+}
+
+impl ThrottleParams {
+    // The params by their api names
+    fn named(&self) -> [(&'static str, Option<u64>); 5] {
+        [
+            (
+                "numOfConcurrentIosPerDevice",
+                self.num_of_concurrent_ios_per_device,
+            ),
+            ("bwLimitPerDeviceInKbps", self.bw_limit_per_device_in_kbps),
+            (
+                "appIopsPerDeviceThreshold",
+                self.app_iops_per_device_threshold,
+            ),
+            (
+                "appBwPerDeviceThresholdInKbps",
+                self.app_bw_per_device_threshold_in_kbps,
+            ),
+            ("quietPeriodInMsec", self.quiet_period_in_msec),
+        ]
+    }
+
+    /// Check the params are the ones the gateway wants with policy.
+    /// limitNumOfConcurrentIos needs numOfConcurrentIosPerDevice,
+    /// favorAppIos adds bwLimitPerDeviceInKbps and dynamicBwThrottling
+    /// adds a quiet period and at least one of the app thresholds.
+    /// unlimited takes no params
+    pub fn validate(&self, policy: RebuildIoPriority) -> MetricsResult<()> {
+        const CONCURRENT_IOS: &str = "numOfConcurrentIosPerDevice";
+        const BW_LIMIT: &str = "bwLimitPerDeviceInKbps";
+        const APP_IOPS: &str = "appIopsPerDeviceThreshold";
+        const APP_BW: &str = "appBwPerDeviceThresholdInKbps";
+        const QUIET_PERIOD: &str = "quietPeriodInMsec";
+        let (required, optional): (&[&str], &[&str]) = match policy {
+            RebuildIoPriority::Unlimited => (&[], &[]),
+            RebuildIoPriority::LimitNumOfConcurrentIos => (&[CONCURRENT_IOS], &[]),
+            RebuildIoPriority::FavorAppIos => (&[CONCURRENT_IOS, BW_LIMIT], &[]),
+            RebuildIoPriority::DynamicBwThrottling => (
+                &[CONCURRENT_IOS, BW_LIMIT, QUIET_PERIOD],
+                &[APP_IOPS, APP_BW],
+            ),
+        };
+        let named = self.named();
+        for (name, value) in named.iter() {
+            match value {
+                None if required.contains(name) => {
+                    return Err(StorageError::new(format!(
+                        "policy {} needs {}",
+                        policy, name
+                    )));
+                }
+                Some(0) if *name == CONCURRENT_IOS => {
+                    return Err(StorageError::new(format!("{} must be at least 1", name)));
+                }
+                Some(_) if !required.contains(name) && !optional.contains(name) => {
+                    return Err(StorageError::new(format!(
+                        "policy {} doesn't take {}",
+                        policy, name
+                    )));
+                }
+                _ => {}
             }
-        } else {
-            Err(StorageError::new(format!(
-                "SDC ID not found for {}",
-                sdc_name
-            )))
         }
+        if policy == RebuildIoPriority::DynamicBwThrottling
+            && self.app_iops_per_device_threshold.is_none()
+            && self.app_bw_per_device_threshold_in_kbps.is_none()
+        {
+            return Err(StorageError::new(format!(
+                "policy {} needs {} or {}",
+                policy, APP_IOPS, APP_BW
+            )));
+        }
+        Ok(())
     }
 
-    /// Maps all the volumes in the list to the given sdc
-    /// Also sets iops and bandwidth limits
-    pub fn map_volumes(&self, volume_ids: &[String], sdc_name: &str) -> MetricsResult<bool> {
-        // Get sdc_id from sdc_name
-        let sdc_id = self.get_sdc_id_from_name(sdc_name)?;
-        debug!("SDC ID for {} is {}", sdc_name, sdc_id);
+    /// The settings that differ between these requested params with policy
+    /// and what a pool reports, as "name: requested != actual".  Params
+    /// left None aren't compared, the pool keeps its old values for those
+    pub fn differences(
+        &self,
+        policy: RebuildIoPriority,
+        actual_policy: RebuildIoPriority,
+        actual: &ThrottleParams,
+    ) -> Vec<String> {
+        let mut differences = vec![];
+        if policy != actual_policy {
+            differences.push(format!("policy: {} != {}", policy, actual_policy));
+        }
+        for ((name, requested), (_, actual)) in self.named().iter().zip(actual.named().iter()) {
+            if let Some(requested) = requested {
+                if Some(*requested) != *actual {
+                    differences.push(match actual {
+                        Some(actual) => format!("{}: {} != {}", name, requested, actual),
+                        None => format!("{}: {} != unset", name, requested),
+                    });
+                }
+            }
+        }
+        differences
+    }
+}
 
-        for vol_id in volume_ids {
-            debug!("Mapping {} to {}", vol_id, sdc_id);
+/// Body of the StoragePool setRebuildIoPriorityPolicy and
+/// setRebalanceIoPriorityPolicy actions.  As with setMappedSdcLimits the
+/// gateway wants the numbers as strings
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetIoPriorityPolicy {
+    pub policy: RebuildIoPriority,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_of_concurrent_ios_per_device: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bw_limit_per_device_in_kbps: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_iops_per_device_threshold: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_bw_per_device_threshold_in_kbps: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quiet_period_in_msec: Option<String>,
+}
 
-            let mut sdc_map = HashMap::new();
-            sdc_map.insert("sdcId", sdc_id.clone());
+impl SetIoPriorityPolicy {
+    /// Fails if params don't fit the policy, see ThrottleParams::validate
+    pub fn new(policy: RebuildIoPriority, params: &ThrottleParams) -> MetricsResult<Self> {
+        params.validate(policy)?;
+        let string = |v: Option<u64>| v.map(|v| v.to_string());
+        Ok(SetIoPriorityPolicy {
+            policy,
+            num_of_concurrent_ios_per_device: string(params.num_of_concurrent_ios_per_device),
+            bw_limit_per_device_in_kbps: string(params.bw_limit_per_device_in_kbps),
+            app_iops_per_device_threshold: string(params.app_iops_per_device_threshold),
+            app_bw_per_device_threshold_in_kbps: string(params.app_bw_per_device_threshold_in_kbps),
+            quiet_period_in_msec: string(params.quiet_period_in_msec),
+        })
+    }
+}
 
-            // TODO: allow multiple mappings?
+#[test]
+fn test_action_bodies() {
+    fn body<T: Serialize>(action: &T) -> String {
+        serde_json::to_string(action).unwrap()
+    }
 
-            // Returns only http status of success or failure
-            let mut _resp = self
-                .client
-                .post(&format!(
-                    "https://{}/api/instances/Volume::{}/action/addMappedSdc",
-                    self.config.endpoint, vol_id
-                ))
-                .header(CONTENT_TYPE, "application/json")
-                .basic_auth(&self.config.user, Some(&self.config.password))
-                .json(&sdc_map)
-                .send()?
-                .error_for_status()?;
+    assert_eq!(
+        body(&AddMappedSdc {
+            sdc_id: "7c9a2b1c00000001".into()
+        }),
+        r#"{"sdcId":"7c9a2b1c00000001"}"#
+    );
+    assert_eq!(
+        body(&SetMappedSdcLimits::new(
+            "7c9a2b1c00000001",
+            Some(10240),
+            None
+        )),
+        r#"{"sdcId":"7c9a2b1c00000001","bandwidthLimitInKbps":"10240","iopsLimit":"0"}"#
+    );
+    assert_eq!(
+        body(&RemoveMappedSdc {
+            sdc_id: "7c9a2b1c00000001".into()
+        }),
+        r#"{"sdcId":"7c9a2b1c00000001"}"#
+    );
+    assert_eq!(
+        body(&RemoveVolume {
+            remove_mode: RemoveMode::OnlyMe
+        }),
+        r#"{"removeMode":"ONLY_ME"}"#
+    );
+    assert_eq!(
+        body(&RemoveVolume {
+            remove_mode: RemoveMode::WholeVtree
+        }),
+        r#"{"removeMode":"WHOLE_VTREE"}"#
+    );
+    assert_eq!(
+        body(&SnapshotVolumes {
+            snapshot_defs: vec![SnapshotDef {
+                volume_id: "5a1b7b1c00000001".into(),
+                snapshot_name: "CHG0001_0_snap".into(),
+            }],
+        }),
+        r#"{"snapshotDefs":[{"volumeId":"5a1b7b1c00000001","snapshotName":"CHG0001_0_snap"}]}"#
+    );
+    assert_eq!(
+        body(
+            &SetIoPriorityPolicy::new(RebuildIoPriority::Unlimited, &ThrottleParams::default())
+                .unwrap()
+        ),
+        r#"{"policy":"unlimited"}"#
+    );
+    assert_eq!(
+        body(
+            &SetIoPriorityPolicy::new(
+                RebuildIoPriority::LimitNumOfConcurrentIos,
+                &ThrottleParams {
+                    num_of_concurrent_ios_per_device: Some(1),
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+        ),
+        r#"{"policy":"limitNumOfConcurrentIos","numOfConcurrentIosPerDevice":"1"}"#
+    );
+    assert_eq!(
+        body(
+            &SetIoPriorityPolicy::new(
+                RebuildIoPriority::FavorAppIos,
+                &ThrottleParams {
+                    num_of_concurrent_ios_per_device: Some(2),
+                    bw_limit_per_device_in_kbps: Some(10240),
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+        ),
+        r#"{"policy":"favorAppIos","numOfConcurrentIosPerDevice":"2","bwLimitPerDeviceInKbps":"10240"}"#
+    );
+    assert_eq!(
+        body(
+            &SetIoPriorityPolicy::new(
+                RebuildIoPriority::DynamicBwThrottling,
+                &ThrottleParams {
+                    num_of_concurrent_ios_per_device: Some(2),
+                    bw_limit_per_device_in_kbps: Some(10240),
+                    app_iops_per_device_threshold: Some(500),
+                    app_bw_per_device_threshold_in_kbps: None,
+                    quiet_period_in_msec: Some(2000),
+                }
+            )
+            .unwrap()
+        ),
+        concat!(
+            r#"{"policy":"dynamicBwThrottling","numOfConcurrentIosPerDevice":"2","#,
+            r#""bwLimitPerDeviceInKbps":"10240","appIopsPerDeviceThreshold":"500","#,
+            r#""quietPeriodInMsec":"2000"}"#
+        )
+    );
+}
+
+#[test]
+fn test_throttle_params_validate() {
+    let concurrent = ThrottleParams {
+        num_of_concurrent_ios_per_device: Some(1),
+        ..Default::default()
+    };
+    let favor = ThrottleParams {
+        bw_limit_per_device_in_kbps: Some(10240),
+        ..concurrent.clone()
+    };
+    let dynamic = ThrottleParams {
+        quiet_period_in_msec: Some(2000),
+        app_bw_per_device_threshold_in_kbps: Some(1024),
+        ..favor.clone()
+    };
+    assert!(ThrottleParams::default()
+        .validate(RebuildIoPriority::Unlimited)
+        .is_ok());
+    assert!(concurrent
+        .validate(RebuildIoPriority::LimitNumOfConcurrentIos)
+        .is_ok());
+    assert!(favor.validate(RebuildIoPriority::FavorAppIos).is_ok());
+    assert!(dynamic
+        .validate(RebuildIoPriority::DynamicBwThrottling)
+        .is_ok());
+
+    let err = |params: &ThrottleParams, policy| params.validate(policy).unwrap_err().to_string();
+    assert!(err(
+        &ThrottleParams::default(),
+        RebuildIoPriority::LimitNumOfConcurrentIos
+    )
+    .contains("needs numOfConcurrentIosPerDevice"));
+    assert!(
+        err(&concurrent, RebuildIoPriority::FavorAppIos).contains("needs bwLimitPerDeviceInKbps")
+    );
+    assert!(err(&concurrent, RebuildIoPriority::Unlimited)
+        .contains("doesn't take numOfConcurrentIosPerDevice"));
+    assert!(err(&dynamic, RebuildIoPriority::FavorAppIos).contains("doesn't take"));
+    assert!(err(
+        &ThrottleParams {
+            num_of_concurrent_ios_per_device: Some(0),
+            ..Default::default()
+        },
+        RebuildIoPriority::LimitNumOfConcurrentIos
+    )
+    .contains("at least 1"));
+    assert!(err(
+        &ThrottleParams {
+            app_bw_per_device_threshold_in_kbps: None,
+            ..dynamic
+        },
+        RebuildIoPriority::DynamicBwThrottling
+    )
+    .contains("or appBwPerDeviceThresholdInKbps"));
+}
 
-            let mut sdc_limits = HashMap::new();
-            sdc_limits.insert("sdcId", sdc_id.clone());
+#[test]
+fn test_set_rebuild_policy() {
+    use std::fs::File;
+    use std::sync::Mutex;
 
-            if let Some(limit) = &self.config.bandwidth_limit {
-                sdc_limits.insert("bandwidthLimitInKbps", limit.to_string());
-            } else {
-                sdc_limits.insert("bandwidthLimitInKbps", "0".to_string());
-            }
-            if let Some(limit) = &self.config.iops_limit {
-                sdc_limits.insert("iopsLimit", limit.to_string());
+    let mut pool = String::new();
+    File::open("tests/scaleio/poolInstance.json")
+        .unwrap()
+        .read_to_string(&mut pool)
+        .unwrap();
+    let pool: serde_json::Value = serde_json::from_str(&pool).unwrap();
+    // The mock applies the posted settings to the pool like the gateway
+    let pool = Arc::new(Mutex::new(pool));
+    let posts = Arc::new(Mutex::new(vec![]));
+    let (state, recorded) = (pool.clone(), posts.clone());
+    let port = crate::mock_https_server(move |req| {
+        let mut pool = state.lock().unwrap();
+        if req.method == "POST" {
+            recorded
+                .lock()
+                .unwrap()
+                .push((req.path.clone(), req.body.clone()));
+            let prefix = if req.path.contains("setRebuild") {
+                "rebuildIoPriority"
             } else {
-                sdc_limits.insert("iopsLimit", "0".to_string());
+                "rebalanceIoPriority"
+            };
+            let body: serde_json::Value = serde_json::from_str(&req.body).unwrap();
+            for (k, v) in body.as_object().unwrap() {
+                if k == "policy" {
+                    pool[format!("{}Policy", prefix)] = v.clone();
+                } else {
+                    let mut name = k.clone();
+                    name[..1].make_ascii_uppercase();
+                    pool[format!("{}{}", prefix, name)] =
+                        json!(v.as_str().unwrap().parse::<u64>().unwrap());
+                }
             }
+            (200, String::new(), vec![])
+        } else {
+            (200, String::new(), serde_json::to_vec(&*pool).unwrap())
+        }
+    });
+    let scaleio = mock_scaleio(port);
 
-            debug!("Adding bandwidth limits to volume with ID {}", vol_id);
-            let mut _resp = self
-                .client
-                .post(&format!(
-                    "https://{}/api/instances/Volume::{}/action/setMappedSdcLimits",
-                    self.config.endpoint, vol_id
-                ))
-                .header(CONTENT_TYPE, "application/json")
-                .basic_auth(&self.config.user, Some(&self.config.password))
-                .json(&sdc_limits)
-                .send()?
-                .error_for_status()?;
+    let params = ThrottleParams {
+        num_of_concurrent_ios_per_device: Some(2),
+        bw_limit_per_device_in_kbps: Some(10240),
+        ..Default::default()
+    };
+    let policy = RebuildIoPriority::FavorAppIos;
+    assert_eq!(
+        scaleio
+            .verify_rebuild_policy("5ceba28700000002", policy, &params)
+            .unwrap(),
+        vec![
+            "policy: favorAppIos != unlimited",
+            "numOfConcurrentIosPerDevice: 2 != unset",
+            "bwLimitPerDeviceInKbps: 10240 != unset",
+        ]
+    );
+    scaleio
+        .set_rebuild_policy("5ceba28700000002", policy, params.clone())
+        .unwrap();
+    assert!(scaleio
+        .verify_rebuild_policy("5ceba28700000002", policy, &params)
+        .unwrap()
+        .is_empty());
+    // Rebalance was left alone
+    assert!(!scaleio
+        .verify_rebalance_policy("5ceba28700000002", policy, &params)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        *posts.lock().unwrap(),
+        vec![(
+            "/api/instances/StoragePool::5ceba28700000002/action/setRebuildIoPriorityPolicy"
+                .to_string(),
+            r#"{"policy":"favorAppIos","numOfConcurrentIosPerDevice":"2","bwLimitPerDeviceInKbps":"10240"}"#
+                .to_string()
+        )]
+    );
+
+    // A bad combination fails before anything is posted
+    let err = scaleio
+        .set_rebalance_policy(
+            "5ceba28700000002",
+            RebuildIoPriority::LimitNumOfConcurrentIos,
+            ThrottleParams::default(),
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("setRebalanceIoPriorityPolicy"));
+    assert_eq!(posts.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_management_credentials() {
+    use crate::credentials::{CallbackCredentials, Credential};
+    use std::fs::File;
+    use std::sync::Mutex;
+
+    let mut sdcs = vec![];
+    File::open("tests/scaleio/sdc_v3_info.json")
+        .unwrap()
+        .read_to_end(&mut sdcs)
+        .unwrap();
+    // Management credentials from the config, from a provider or none
+    let scaleio = |management: bool, provider: Option<Arc<dyn CredentialsProvider>>| {
+        let sdcs = sdcs.clone();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        let port = crate::mock_https_server(move |req| {
+            let auth = req
+                .head
+                .lines()
+                .find(|l| l.to_lowercase().starts_with("authorization:"))
+                .map(|l| l["authorization:".len()..].trim().to_string())
+                .unwrap_or_default();
+            let user = match auth.as_str() {
+                "Basic YWRtaW46c2VjcmV0" => "admin",
+                "Basic b3BlcmF0b3I6b3BzZWNyZXQ=" => "operator",
+                "Basic YWRtaW46cmVhZHRva2Vu" => "admin token",
+                "Basic b3BlcmF0b3I6d3JpdGV0b2tlbg==" => "operator token",
+                _ => return (401, String::new(), b"{}".to_vec()),
+            };
+            recorded
+                .lock()
+                .unwrap()
+                .push((req.method.clone(), user.to_string()));
+            let body = match (req.method.as_str(), user) {
+                ("GET", "admin") if req.path == "/api/login" => b"\"readtoken\"".to_vec(),
+                ("GET", "operator") if req.path == "/api/login" => b"\"writetoken\"".to_vec(),
+                ("GET", _) => sdcs.clone(),
+                _ => vec![],
+            };
+            (200, String::new(), body)
+        });
+        let mut config = ScaleioConfig {
+            endpoint: format!("127.0.0.1:{}", port),
+            user: "admin".into(),
+            password: "secret".into(),
+            region: "test".into(),
+            ..Default::default()
+        };
+        if management {
+            config.management_user = Some("operator".into());
+            config.management_password = Some("opsecret".into());
         }
-        Ok(true)
-    }
+        let client = reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+        let s = match provider {
+            Some(provider) => Scaleio::with_management_credentials(
+                &client,
+                config,
+                Arc::new(StaticCredentials::new("admin", "secret")),
+                provider,
+            ),
+            None => Scaleio::new(&client, config),
+        };
+        (s.unwrap(), seen)
+    };
+    let volume_ids = vec!["5a1b7b1c00000001".to_string()];
+
+    let (s, seen) = scaleio(true, None);
+    assert!(s.map_volumes(&volume_ids, "ESX-0.0.0.0").unwrap());
+    s.remove_volume("5a1b7b1c00000001", RemoveMode::OnlyMe)
+        .unwrap();
+    let seen = seen.lock().unwrap();
+    // Both users log in, reads use the main token and actions the
+    // management one
+    assert_eq!(seen[0], ("GET".to_string(), "admin".to_string()));
+    assert_eq!(seen[1], ("GET".to_string(), "operator".to_string()));
+    assert_eq!(seen[2], ("GET".to_string(), "admin token".to_string()));
+    assert_eq!(seen.len(), 6);
+    assert!(seen[3..]
+        .iter()
+        .all(|(method, user)| method == "POST" && user == "operator token"));
+
+    // Without a management user everything uses the main token
+    let (s, seen) = scaleio(false, None);
+    assert!(s.map_volumes(&volume_ids, "ESX-0.0.0.0").unwrap());
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 4);
+    assert!(seen[1..].iter().all(|(_, user)| user == "admin token"));
+
+    // A provider is asked for the management user
+    let provider = CallbackCredentials::new(|backend: &str, _: &str| {
+        assert_eq!(backend, "scaleio_management");
+        Ok(Credential::new("operator", "opsecret"))
+    });
+    let (s, seen) = scaleio(false, Some(Arc::new(provider)));
+    assert!(s.map_volumes(&volume_ids, "ESX-0.0.0.0").unwrap());
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen[1], ("GET".to_string(), "operator".to_string()));
+    assert_eq!(seen[3], ("POST".to_string(), "operator token".to_string()));
 }
 
-#[derive(Serialize, Debug)]
-pub enum VolumeRequestType {
-    ThinProvisioned,
-    ThickProvisioned,
+#[test]
+fn test_dry_run_actions() {
+    use std::fs::File;
+
+    let mut sdcs = vec![];
+    File::open("tests/scaleio/sdc_v3_info.json")
+        .unwrap()
+        .read_to_end(&mut sdcs)
+        .unwrap();
+    let (port, requests) = mock_scaleio_api(false, move |method: &str, _path: &str| {
+        assert_eq!(method, "GET", "dry run sent a POST");
+        sdcs.clone()
+    });
+    let scaleio = mock_scaleio(port).with_dry_run(true);
+
+    let volume_ids = vec!["5a1b7b1c00000001".to_string()];
+    assert!(scaleio.map_volumes(&volume_ids, "ESX-0.0.0.0").unwrap());
+    scaleio
+        .unmap_volume("5a1b7b1c00000001", "7c9a2b1c00000001")
+        .unwrap();
+    scaleio
+        .remove_volume("5a1b7b1c00000001", RemoveMode::OnlyMe)
+        .unwrap();
+    let snapshot = scaleio
+        .snapshot_volumes(vec![SnapshotDef {
+            volume_id: "5a1b7b1c00000001".into(),
+            snapshot_name: "CHG0001_0_snap".into(),
+        }])
+        .unwrap();
+    assert_eq!(snapshot, None);
+    // Only the sdc lookup of map_volumes reached the server
+    assert_eq!(requests.lock().unwrap().len(), 1);
 }
 
-#[derive(Serialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct VolumeRequest {
-    pub volume_size_in_kb: String,
-    pub storage_pool_id: String,
-    pub name: String,
-    pub volume_type: VolumeRequestType,
-    pub use_rmcache: bool,
+#[test]
+fn test_plan_matches_run() {
+    use std::fs::File;
+
+    let pool = {
+        let mut f = File::open("tests/scaleio/poolInstance.json").unwrap();
+        let mut s = String::new();
+        f.read_to_string(&mut s).unwrap();
+        serde_json::from_str::<serde_json::Value>(&s).unwrap()
+    };
+    // Two pools with room and one that's too full
+    let mut roomy = pool.clone();
+    roomy["id"] = json!("roomy");
+    roomy["sparePercentage"] = json!(50);
+    let mut full = pool.clone();
+    full["id"] = json!("full");
+    full["sparePercentage"] = json!(5);
+    let pools = serde_json::to_vec(&json!([pool, roomy, full])).unwrap();
+    let sdcs = {
+        let mut f = File::open("tests/scaleio/sdc_v3_info.json").unwrap();
+        let mut s = vec![];
+        f.read_to_end(&mut s).unwrap();
+        s
+    };
+    let respond = move |method: &str, path: &str| {
+        if method == "GET" && path.contains("StoragePool") {
+            pools.clone()
+        } else if method == "GET" && path.contains("types/Volume/instances") {
+            b"[]".to_vec()
+        } else if method == "GET" {
+            sdcs.clone()
+        } else if path.contains("types/Volume/instances") {
+            b"\"5a1b7b1c00000001\"".to_vec()
+        } else {
+            vec![]
+        }
+    };
+    let scaleio = |port: u16| Scaleio {
+        config: ScaleioConfig {
+            bandwidth_limit: Some(10240),
+            ..mock_scaleio_config(port)
+        },
+        ..mock_scaleio(port)
+    };
+    let posts = |requests: &RecordedRequests| -> Vec<PlannedRequest> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(method, _, _)| method == "POST")
+            .map(|(method, path, body)| PlannedRequest {
+                method: method.clone(),
+                api: path.trim_start_matches("/api/").to_string(),
+                body: serde_json::from_str(body).unwrap(),
+            })
+            .collect()
+    };
+
+    let (dry_port, dry_requests) = mock_scaleio_api(false, respond.clone());
+    let dry = scaleio(dry_port);
+    let create_plan = dry.plan_create_volume("CHG0001", 4096, 3, 10).unwrap();
+    let volume_ids = vec!["5a1b7b1c00000001".to_string()];
+    let map_plan = dry.plan_map_volumes(&volume_ids, "ESX-0.0.0.0").unwrap();
+    println!("create plan: {:#?}", create_plan);
+    println!("map plan: {:#?}", map_plan);
+    // Only reads are made while planning
+    assert!(posts(&dry_requests).is_empty());
+    assert_eq!(dry_requests.lock().unwrap().len(), 3);
+    // The full pool is skipped and the size split across the other two
+    assert_eq!(create_plan.len(), 2);
+    assert_eq!(create_plan[0].body["storagePoolId"], json!("roomy"));
+    assert_eq!(create_plan[0].body["volumeSizeInKb"], json!("2048"));
+    assert_eq!(map_plan.len(), 2);
+    assert_eq!(map_plan[1].body["bandwidthLimitInKbps"], json!("10240"));
+
+    let (port, requests) = mock_scaleio_api(false, respond);
+    let live = scaleio(port);
+    assert_eq!(
+        live.create_volume("CHG0001", 4096, 3, 10).unwrap(),
+        vec!["5a1b7b1c00000001", "5a1b7b1c00000001"]
+    );
+    assert!(live.map_volumes(&volume_ids, "ESX-0.0.0.0").unwrap());
+    let sent = posts(&requests);
+    assert_eq!(sent.len(), create_plan.len() + map_plan.len());
+    assert_eq!(&sent[..2], &create_plan[..]);
+    assert_eq!(&sent[2..], &map_plan[..]);
 }
 
-impl VolumeRequest {
-    fn new(volume_size_int: u64, storage_pool_id: String, name: String) -> VolumeRequest {
-        VolumeRequest {
-            volume_size_in_kb: volume_size_int.to_string(),
-            storage_pool_id,
-            name,
-            volume_type: VolumeRequestType::ThinProvisioned,
-            use_rmcache: true,
+#[test]
+fn test_create_volume_skips_existing() {
+    use std::fs::File;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let pool = {
+        let mut f = File::open("tests/scaleio/poolInstance.json").unwrap();
+        let mut s = String::new();
+        f.read_to_string(&mut s).unwrap();
+        serde_json::from_str::<serde_json::Value>(&s).unwrap()
+    };
+    let mut second = pool.clone();
+    second["id"] = json!("second");
+    let mut third = pool.clone();
+    third["id"] = json!("third");
+    let pools = serde_json::to_vec(&json!([pool, second, third])).unwrap();
+    // An earlier run got as far as the first and last volume
+    let volumes = serde_json::to_vec(&json!([
+        {"id": "5a1b7b1c00000001", "name": "CHG0002_0"},
+        {"id": "5a1b7b1c00000003", "name": "CHG0002_2"},
+        {"id": "5a1b7b1c00000009", "name": "CHG0001_1"},
+        {"id": "5a1b7b1c0000000a", "name": null},
+    ]))
+    .unwrap();
+    let empty_id = Arc::new(AtomicBool::new(false));
+    let send_empty = empty_id.clone();
+    let (port, requests) = mock_scaleio_api(false, move |method: &str, path: &str| {
+        if method == "GET" && path.contains("StoragePool") {
+            pools.clone()
+        } else if method == "GET" {
+            volumes.clone()
+        } else if send_empty.load(Ordering::SeqCst) {
+            b"\"\"".to_vec()
+        } else {
+            b"\"5a1b7b1c00000002\"".to_vec()
         }
-    }
+    });
+    let scaleio = mock_scaleio(port);
+
+    let existing = scaleio.get_volume_ids_by_prefix("CHG0002").unwrap();
+    assert_eq!(existing.len(), 2);
+    assert_eq!(existing["CHG0002_2"], "5a1b7b1c00000003");
+
+    let plan = scaleio.plan_create_volume("CHG0002", 3072, 3, 10).unwrap();
+    assert_eq!(plan.len(), 1);
+    assert_eq!(plan[0].body["name"], json!("CHG0002_1"));
+
+    requests.lock().unwrap().clear();
+    assert_eq!(
+        scaleio.create_volume("CHG0002", 3072, 3, 10).unwrap(),
+        vec!["5a1b7b1c00000001", "5a1b7b1c00000003", "5a1b7b1c00000002"]
+    );
+    let posts: Vec<serde_json::Value> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(method, _, _)| method == "POST")
+        .map(|(_, _, body)| serde_json::from_str(body).unwrap())
+        .collect();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0]["name"], json!("CHG0002_1"));
+    assert_eq!(posts[0]["volumeSizeInKb"], json!("1024"));
+
+    // A dry run only has the ids of the volumes that already exist
+    let dry_run = mock_scaleio(port).with_dry_run(true);
+    assert_eq!(
+        dry_run.create_volume("CHG0002", 3072, 3, 10).unwrap(),
+        vec!["5a1b7b1c00000001", "5a1b7b1c00000003"]
+    );
+
+    empty_id.store(true, Ordering::SeqCst);
+    let err = scaleio
+        .create_volume("CHG0002", 3072, 3, 10)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("returned no volume id"), "{}", err);
 }
+
 /// Finds the ideal pools where volumes need to be created.
 /// Returns a vector of pool_ids, will never return an empty list
 /// Available space and percent provisioned are considered
@@ -3330,6 +9043,17 @@ fn test_create_and_map_volume() {
             }
         },
         certificate: None,
+        drive_stats_properties: None,
+        sdc_stats_properties: None,
+        pool_stats_properties: None,
+        volume_stats_properties: None,
+        emit_fields_allow: None,
+        emit_fields_deny: None,
+        management_user: None,
+        management_password: None,
+        accept_invalid_certs: None,
+        verify_hostname: true,
+        sds_stats_concurrency: None,
     };
 
     debug!("Config is {:#?}", scaleio_config);
@@ -3358,3 +9082,31 @@ fn test_create_and_map_volume() {
 
     map_volumes(&web_client, &scaleio_config, vols, &sdc_hostname).unwrap();
 } */
+
+#[test]
+fn test_from_config_hostname_verification() {
+    // The mock's self signed certificate is for localhost and it's
+    // reached by IP
+    let port = crate::mock_https_server(|req| match req.path.as_str() {
+        "/api/login" => (200, String::new(), b"\"token\"".to_vec()),
+        _ => (404, String::new(), vec![]),
+    });
+    let config = |verify_hostname: bool| {
+        let mut config: ScaleioConfig = serde_json::from_value(json!({
+            "endpoint": format!("127.0.0.1:{}", port),
+            "user": "admin",
+            "password": "secret",
+            "certificate": "tests/vnx/mock_certificate.der",
+            "region": "test",
+        }))
+        .unwrap();
+        assert!(config.verify_hostname);
+        config.verify_hostname = verify_hostname;
+        config
+    };
+
+    // Full verification refuses the certificate for the IP
+    assert!(Scaleio::from_config(config(true)).is_err());
+    let scaleio = Scaleio::from_config(config(false)).unwrap();
+    assert_eq!(scaleio.config.password, "token");
+}
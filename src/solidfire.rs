@@ -18,6 +18,7 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
 
+use crate::collector::Collector;
 use crate::error::MetricsResult;
 use crate::ir::{TsPoint, TsValue};
 use crate::IntoPoint;
@@ -39,6 +40,9 @@ pub struct SolidfireConfig {
     pub certificate: Option<String>,
     /// The region this cluster is located in
     pub region: String,
+    /// Largest response body to read, in bytes.  Defaults to
+    /// DEFAULT_MAX_RESPONSE_BYTES
+    pub max_response_bytes: Option<u64>,
 }
 
 pub struct Solidfire {
@@ -55,6 +59,24 @@ impl Solidfire {
     }
 }
 
+impl Collector for Solidfire {
+    fn backend(&self) -> &str {
+        "solidfire"
+    }
+
+    fn array_id(&self) -> &str {
+        &self.config.endpoint
+    }
+
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut points = self.get_drive_hardware_info(t)?;
+        points.extend(self.get_cluster_capacity(t)?);
+        points.extend(self.get_cluster_fullness(t)?);
+        points.extend(self.get_cluster_stats(t)?);
+        Ok(points)
+    }
+}
+
 #[derive(Debug, Deserialize, IntoPoint)]
 #[serde(rename_all = "camelCase")]
 pub struct AddressBlock {
@@ -912,11 +934,12 @@ impl Solidfire {
                     .join(""),
             );
         }
-        let j: T = crate::get(
+        let j: T = crate::get_limited(
             &self.client,
             &url,
             &self.config.user,
             Some(&self.config.password),
+            self.config.max_response_bytes,
         )?;
 
         Ok(j)
@@ -0,0 +1,332 @@
+//! State that needs to outlive a single collection run, such as the
+//! previous sample used to work out a rate or how far through an event log
+//! a collector has read.
+/**
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+use std::collections::HashMap;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::{MetricsResult, StorageError};
+use chrono::Utc;
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_derive::{Deserialize, Serialize};
+
+/// A value saved under a namespace and key along with the version of the
+/// format it was written in
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct StateBlob {
+    pub version: u32,
+    pub value: serde_json::Value,
+}
+
+/// Somewhere to keep state between collection runs.  Namespaces keep
+/// unrelated users from stepping on each other's keys, ie one namespace per
+/// collector and array
+pub trait StateStore: Send + Sync {
+    fn get_blob(&self, namespace: &str, key: &str) -> MetricsResult<Option<StateBlob>>;
+    fn put_blob(&self, namespace: &str, key: &str, blob: StateBlob) -> MetricsResult<()>;
+}
+
+impl dyn StateStore + '_ {
+    /// Load the value saved under `key`.  A value written with a different
+    /// version is treated as missing so a format change starts over instead
+    /// of failing to deserialize
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        namespace: &str,
+        key: &str,
+        version: u32,
+    ) -> MetricsResult<Option<T>> {
+        match self.get_blob(namespace, key)? {
+            Some(blob) if blob.version == version => Ok(Some(serde_json::from_value(blob.value)?)),
+            Some(blob) => {
+                debug!(
+                    "ignoring {}/{} saved with version {}, expected {}",
+                    namespace, key, blob.version, version
+                );
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn put<T: Serialize>(
+        &self,
+        namespace: &str,
+        key: &str,
+        version: u32,
+        value: &T,
+    ) -> MetricsResult<()> {
+        let blob = StateBlob {
+            version,
+            value: serde_json::to_value(value)?,
+        };
+        self.put_blob(namespace, key, blob)
+    }
+}
+
+type Namespaces = HashMap<String, HashMap<String, StateBlob>>;
+
+/// Keeps state for the life of the process only
+#[derive(Debug, Default)]
+pub struct MemoryStateStore {
+    namespaces: Mutex<Namespaces>,
+}
+
+impl MemoryStateStore {
+    pub fn new() -> Self {
+        MemoryStateStore::default()
+    }
+}
+
+impl StateStore for MemoryStateStore {
+    fn get_blob(&self, namespace: &str, key: &str) -> MetricsResult<Option<StateBlob>> {
+        let namespaces = self.namespaces.lock().unwrap();
+        Ok(namespaces.get(namespace).and_then(|n| n.get(key)).cloned())
+    }
+
+    fn put_blob(&self, namespace: &str, key: &str, blob: StateBlob) -> MetricsResult<()> {
+        let mut namespaces = self.namespaces.lock().unwrap();
+        namespaces
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(key.to_string(), blob);
+        Ok(())
+    }
+}
+
+/// Keeps state in a json file so it survives a restart.  Every put rewrites
+/// the whole file by writing a temporary file next to it and renaming it
+/// over the old one, so a crash leaves either the old or the new state
+/// behind.  Puts from several threads are serialized by an internal lock.
+/// Only one process should use a given file.
+#[derive(Debug)]
+pub struct FileStateStore {
+    path: PathBuf,
+    namespaces: Mutex<Namespaces>,
+    recovered_from: Option<PathBuf>,
+}
+
+impl FileStateStore {
+    /// Load the state saved at `path`, or start empty if there isn't any.
+    /// A file that can't be parsed is moved aside and the store starts empty.
+    /// The backup keeps the file name but its extension is replaced by
+    /// `corrupt-<UTC timestamp>` (`state.json` becomes
+    /// `state.corrupt-20190101T000000.123`), so repeated failures don't
+    /// overwrite each other
+    pub fn open<P: AsRef<Path>>(path: P) -> MetricsResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut recovered_from = None;
+        let namespaces = match fs::read_to_string(&path) {
+            Ok(s) => match serde_json::from_str::<Namespaces>(&s) {
+                Ok(namespaces) => namespaces,
+                Err(e) => {
+                    let backup = path.with_extension(format!(
+                        "corrupt-{}",
+                        Utc::now().format("%Y%m%dT%H%M%S%.f")
+                    ));
+                    warn!(
+                        "state file {} is corrupt ({}), moving it to {} and starting over",
+                        path.display(),
+                        e,
+                        backup.display()
+                    );
+                    fs::rename(&path, &backup).map_err(|e| {
+                        StorageError::from(e)
+                            .with_context(format!("backing up state file {}", path.display()))
+                    })?;
+                    recovered_from = Some(backup);
+                    Namespaces::new()
+                }
+            },
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Namespaces::new(),
+            Err(e) => {
+                return Err(StorageError::from(e)
+                    .with_context(format!("reading state file {}", path.display())))
+            }
+        };
+        Ok(FileStateStore {
+            path,
+            namespaces: Mutex::new(namespaces),
+            recovered_from,
+        })
+    }
+
+    /// Where a corrupt state file was moved to when the store was opened
+    pub fn recovered_from(&self) -> Option<&Path> {
+        self.recovered_from.as_deref()
+    }
+
+    // The temporary file is synced before the rename so a crash can't leave
+    // an empty or partial file in place of the state, and the directory
+    // after so the rename itself isn't lost
+    fn save(&self, namespaces: &Namespaces) -> MetricsResult<()> {
+        let tmp = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec(namespaces)?)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&tmp, &self.path)?;
+        #[cfg(unix)]
+        {
+            let dir = match self.path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+impl StateStore for FileStateStore {
+    fn get_blob(&self, namespace: &str, key: &str) -> MetricsResult<Option<StateBlob>> {
+        let namespaces = self.namespaces.lock().unwrap();
+        Ok(namespaces.get(namespace).and_then(|n| n.get(key)).cloned())
+    }
+
+    fn put_blob(&self, namespace: &str, key: &str, blob: StateBlob) -> MetricsResult<()> {
+        // Hold the lock while saving so the file is written in put order
+        let mut namespaces = self.namespaces.lock().unwrap();
+        namespaces
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(key.to_string(), blob);
+        self.save(&namespaces)
+    }
+}
+
+#[cfg(test)]
+fn test_state_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("libstorage-state-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.json", name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_state_versions() {
+    let store: &dyn StateStore = &MemoryStateStore::new();
+    assert_eq!(store.get::<u64>("vnx/array1", "samples", 1).unwrap(), None);
+    store.put("vnx/array1", "samples", 1, &42u64).unwrap();
+    assert_eq!(
+        store.get::<u64>("vnx/array1", "samples", 1).unwrap(),
+        Some(42)
+    );
+    assert_eq!(store.get::<u64>("vnx/array2", "samples", 1).unwrap(), None);
+    // A newer reader ignores what an older version wrote
+    assert_eq!(store.get::<u64>("vnx/array1", "samples", 2).unwrap(), None);
+}
+
+#[test]
+fn test_file_state_restart() {
+    let path = test_state_path("restart");
+    {
+        let store = FileStateStore::open(&path).unwrap();
+        let store: &dyn StateStore = &store;
+        store
+            .put("solidfire/a", "cursor", 1, &"1234".to_string())
+            .unwrap();
+        store
+            .put("solidfire/b", "cursor", 1, &"99".to_string())
+            .unwrap();
+    }
+    let store = FileStateStore::open(&path).unwrap();
+    assert!(store.recovered_from().is_none());
+    let store: &dyn StateStore = &store;
+    assert_eq!(
+        store.get::<String>("solidfire/a", "cursor", 1).unwrap(),
+        Some("1234".to_string())
+    );
+    assert_eq!(
+        store.get::<String>("solidfire/b", "cursor", 1).unwrap(),
+        Some("99".to_string())
+    );
+    assert!(!path.with_extension("tmp").exists());
+}
+
+#[test]
+fn test_file_state_corrupt() {
+    let path = test_state_path("corrupt");
+    fs::write(&path, "{\"solidfire/a\": {\"cursor\": ").unwrap();
+    let store = FileStateStore::open(&path).unwrap();
+    let backup = store.recovered_from().unwrap().to_path_buf();
+    assert_eq!(backup.parent(), path.parent());
+    let backup_name = backup.file_name().unwrap().to_str().unwrap();
+    assert!(backup_name.starts_with("corrupt.corrupt-"));
+    assert!(!backup_name.contains(".json"));
+    assert_eq!(
+        fs::read_to_string(&backup).unwrap(),
+        "{\"solidfire/a\": {\"cursor\": "
+    );
+    assert!(!path.exists());
+
+    let store: &dyn StateStore = &store;
+    assert_eq!(
+        store.get::<String>("solidfire/a", "cursor", 1).unwrap(),
+        None
+    );
+    store
+        .put("solidfire/a", "cursor", 1, &"5".to_string())
+        .unwrap();
+    let store = FileStateStore::open(&path).unwrap();
+    assert!(store.recovered_from().is_none());
+    let _ = fs::remove_file(backup);
+}
+
+#[test]
+fn test_file_state_concurrent_writers() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let path = test_state_path("concurrent");
+    let store = Arc::new(FileStateStore::open(&path).unwrap());
+    let handles: Vec<_> = (0..8)
+        .map(|t| {
+            let store = Arc::clone(&store);
+            thread::spawn(move || {
+                let store: &dyn StateStore = &*store;
+                for i in 0..20u64 {
+                    store
+                        .put(&format!("thread{}", t), &i.to_string(), 1, &(t * 100 + i))
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        h.join().unwrap();
+    }
+
+    let store = FileStateStore::open(&path).unwrap();
+    let store: &dyn StateStore = &store;
+    for t in 0..8u64 {
+        for i in 0..20u64 {
+            let v = store
+                .get::<u64>(&format!("thread{}", t), &i.to_string(), 1)
+                .unwrap();
+            assert_eq!(v, Some(t * 100 + i));
+        }
+    }
+}
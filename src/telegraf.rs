@@ -17,10 +17,13 @@
 */
 use crate::error::MetricsResult;
 use crate::ir::{TsPoint, TsValue};
+use crate::read_text;
 
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use serde_json::{json, Map, Value};
+
 /*
 Text format:
 
@@ -59,6 +62,9 @@ pub struct TelegrafConfig {
     pub password: String,
     /// The region this cluster is located in
     pub region: String,
+    /// Largest response body to read, in bytes.  Defaults to
+    /// DEFAULT_MAX_RESPONSE_BYTES
+    pub max_response_bytes: Option<u64>,
 }
 
 #[test]
@@ -147,7 +153,10 @@ fn parse_telegraf(output: &str, point_name: Option<&str>) -> MetricsResult<TsPoi
                 TsValue::Float(f64::from_str(counter_value)?),
             );
         } else {
-            point.add_field(counter_name, TsValue::Float(f64::from_str(counter_value)?));
+            point.add_field(
+                counter_name.to_string(),
+                TsValue::Float(f64::from_str(counter_value)?),
+            );
         }
     }
     Ok(point)
@@ -160,12 +169,117 @@ pub fn get_metrics(
     endpoint: &str,
 ) -> MetricsResult<TsPoint> {
     let url = format!("http://{}:{}/metrics", endpoint, config.port);
-    let text = client
+    let resp = client
         .get(&url)
         .basic_auth(&config.user, Some(&config.password))
         .send()?
-        .error_for_status()?
-        .text()?;
+        .error_for_status()?;
+    let text = read_text(resp, config.max_response_bytes)?;
     let points = parse_telegraf(&text, Some("ceph_telegraf"))?;
     Ok(points)
 }
+
+#[test]
+fn test_to_exec_json() {
+    use chrono::{TimeZone, Utc};
+
+    let mut p = TsPoint::new("scaleio_sds_stat", true);
+    p.timestamp = Some(Utc.timestamp_opt(1_577_836_800, 5).unwrap());
+    p.add_tag("sds_id", TsValue::String("6c9b2a8c00000001".into()));
+    p.add_tag("port", TsValue::Short(7072));
+    p.add_field("read_bwc", TsValue::Long(1024));
+    p.add_field("latency", TsValue::Float(1.5));
+    p.add_field("rmcache_enabled", TsValue::Boolean(true));
+    p.add_field("state", TsValue::String("Normal".into()));
+    p.add_field("ip_list", TsValue::StringVec(vec!["10.0.0.1".into()]));
+    p.add_field(
+        "rebuild_time",
+        TsValue::Duration(std::time::Duration::from_millis(2500)),
+    );
+    p.add_field(
+        "last_seen",
+        TsValue::Timestamp(Utc.timestamp_opt(1_577_836_800, 0).unwrap()),
+    );
+
+    let mut q = TsPoint::new("vnx_disk_info", false);
+    q.add_field("capacity", TsValue::SignedLong(-1));
+
+    let output = to_exec_json(&[p, q]);
+    println!("exec json: {}", output);
+    let v: Value = serde_json::from_str(&output).unwrap();
+    let metrics = v["metrics"].as_array().unwrap();
+    assert_eq!(metrics.len(), 2);
+
+    assert_eq!(metrics[0]["name"], "scaleio_sds_stat");
+    assert_eq!(metrics[0]["timestamp"], 1_577_836_800_000_000_005i64);
+    assert_eq!(metrics[0]["tags"]["sds_id"], "6c9b2a8c00000001");
+    assert_eq!(metrics[0]["tags"]["port"], "7072");
+    assert_eq!(metrics[0]["fields"]["read_bwc"], 1024);
+    assert_eq!(metrics[0]["fields"]["latency"], 1.5);
+    assert_eq!(metrics[0]["fields"]["rmcache_enabled"], true);
+    assert_eq!(metrics[0]["fields"]["state"], "Normal");
+    assert_eq!(metrics[0]["fields"]["rebuild_time"], 2.5);
+    assert_eq!(
+        metrics[0]["fields"]["last_seen"],
+        "2020-01-01T00:00:00+00:00"
+    );
+    // Telegraf fields can't hold arrays
+    assert!(metrics[0]["fields"].get("ip_list").is_none());
+
+    assert_eq!(metrics[1]["name"], "vnx_disk_info");
+    assert!(metrics[1].get("timestamp").is_none());
+    assert!(metrics[1]["tags"].as_object().unwrap().is_empty());
+    assert_eq!(metrics[1]["fields"]["capacity"], -1);
+}
+
+// Telegraf fields must be scalars so vectors are skipped
+fn exec_field_value(value: &TsValue) -> Option<Value> {
+    match value {
+        TsValue::Boolean(b) => Some(json!(b)),
+        TsValue::Byte(b) => Some(json!(b)),
+        TsValue::Integer(i) => Some(json!(i)),
+        TsValue::Float(f) => Some(json!(f)),
+        TsValue::Long(l) => Some(json!(l)),
+        TsValue::Short(s) => Some(json!(s)),
+        TsValue::SignedLong(l) => Some(json!(l)),
+        TsValue::String(s) => Some(json!(s)),
+        TsValue::Duration(d) => Some(json!(d.as_secs_f64())),
+        TsValue::Timestamp(t) => Some(json!(t.to_rfc3339())),
+        _ => None,
+    }
+}
+
+/// Render points in the json format the telegraf exec input plugin
+/// understands:
+/// {"metrics": [{"name": .., "tags": {..}, "fields": {..}, "timestamp": ..}]}
+/// Tags are rendered as strings.  Timestamps are in epoch nanoseconds and
+/// left off if the point has none
+pub fn to_exec_json(points: &[TsPoint]) -> String {
+    let metrics: Vec<Value> = points
+        .iter()
+        .map(|point| {
+            let tags: Map<String, Value> = point
+                .tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+                .collect();
+            let fields: Map<String, Value> = point
+                .fields
+                .iter()
+                .filter_map(|(k, v)| exec_field_value(v).map(|v| (k.to_string(), v)))
+                .collect();
+            let mut metric = json!({
+                "name": point.measurement,
+                "tags": tags,
+                "fields": fields,
+            });
+            if let Some(t) = point.timestamp {
+                metric["timestamp"] =
+                    json!(t.timestamp() * 1_000_000_000 + i64::from(t.timestamp_subsec_nanos()));
+            }
+            metric
+        })
+        .collect();
+
+    json!({ "metrics": metrics }).to_string()
+}
@@ -15,15 +15,21 @@
 *
 * SPDX-License-Identifier: Apache-2.0
 */
-use crate::error::MetricsResult;
+use crate::collector::Collector;
+use crate::credentials::{with_credential, Credential, CredentialsProvider, StaticCredentials};
+use crate::decode_json;
+use crate::error::{redact_url, MetricsResult, StorageError};
+use crate::read_text;
 use crate::ChildPoint;
 use crate::IntoPoint;
 
 use std::fmt::Debug;
 use std::str;
+use std::sync::Arc;
 
 use crate::ir::{TsPoint, TsValue};
-use log::{debug, trace};
+use chrono::{DateTime, Utc};
+use log::{debug, trace, warn};
 use reqwest::header::ACCEPT;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
@@ -41,20 +47,139 @@ pub struct VmaxConfig {
     pub certificate: Option<String>,
     /// The region this cluster is located in
     pub region: String,
+    /// Largest response body to read, in bytes.  Defaults to
+    /// DEFAULT_MAX_RESPONSE_BYTES
+    pub max_response_bytes: Option<u64>,
+    /// Arrays `Collector::collect` gathers, ie "000196702346".  Defaults
+    /// to every array this Unisphere manages
+    pub symmetrix_ids: Option<Vec<String>>,
 }
 
 pub struct Vmax {
     client: reqwest::blocking::Client,
     config: VmaxConfig,
+    credentials: Arc<dyn CredentialsProvider>,
 }
 
 impl Vmax {
+    /// Use the user and password from config for every request
     pub fn new(client: &reqwest::blocking::Client, config: VmaxConfig) -> Self {
+        let credentials = StaticCredentials::new(config.user.as_str(), config.password.as_str());
+        Vmax::with_credentials(client, config, Arc::new(credentials))
+    }
+
+    /// Fetch the credential for each request from credentials instead of
+    /// using the user and password in config
+    pub fn with_credentials(
+        client: &reqwest::blocking::Client,
+        config: VmaxConfig,
+        credentials: Arc<dyn CredentialsProvider>,
+    ) -> Self {
         Vmax {
             client: client.clone(),
             config,
+            credentials,
         }
     }
+
+    // Unisphere takes basic auth on every request so each one asks the
+    // provider for the credential
+    fn with_credential<T, F>(&self, f: F) -> MetricsResult<T>
+    where
+        F: FnMut(&Credential) -> MetricsResult<T>,
+    {
+        with_credential(&*self.credentials, "vmax", &self.config.endpoint, f)
+    }
+}
+
+impl Collector for Vmax {
+    fn backend(&self) -> &str {
+        "vmax"
+    }
+
+    fn array_id(&self) -> &str {
+        &self.config.endpoint
+    }
+
+    /// The capacity, srps and srdf groups of each of symmetrix_ids
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let symmetrix_ids = match self.config.symmetrix_ids {
+            Some(ref ids) => ids.clone(),
+            None => self.get_slo_arrays()?,
+        };
+        let mut points = Vec::new();
+        for id in &symmetrix_ids {
+            points.extend(self.get_vmax_array_raw(id)?);
+            for srp in self.get_slo_array_srps(id)? {
+                points.extend(self.get_slo_array_srp(id, &srp)?.into_iter().map(|mut p| {
+                    p.add_tag("symmetrix_id", TsValue::String(id.clone()));
+                    p
+                }));
+            }
+            points.extend(self.get_rdf_groups(id, t)?);
+        }
+        Ok(points)
+    }
+}
+
+#[test]
+fn test_vmax_credential_rotation() {
+    use crate::credentials::CallbackCredentials;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Unisphere only takes the rotated password, admin:new
+    let port = crate::mock_https_server(|req| {
+        if req.head.contains("Basic YWRtaW46bmV3") {
+            (200, String::new(), br#"{"count": 0}"#.to_vec())
+        } else {
+            (401, String::new(), vec![])
+        }
+    });
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetched = fetches.clone();
+    let provider = CallbackCredentials::new(move |backend: &str, array: &str| {
+        assert_eq!(backend, "vmax");
+        assert!(array.starts_with("127.0.0.1:"));
+        match fetched.fetch_add(1, Ordering::SeqCst) {
+            0 => Ok(Credential::new("admin", "old")),
+            _ => Ok(Credential::new("admin", "new")),
+        }
+    });
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let config = VmaxConfig {
+        endpoint: format!("127.0.0.1:{}", port),
+        user: String::new(),
+        password: String::new(),
+        certificate: None,
+        region: "test".into(),
+        max_response_bytes: None,
+        symmetrix_ids: None,
+    };
+    let vmax = Vmax::with_credentials(&client, config, Arc::new(provider));
+    // The first request fails with the old password and is retried
+    assert!(vmax.get_all_slo_volumes("000196702346").unwrap().is_empty());
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    // Later requests go through with the new one
+    assert!(vmax.get_all_slo_volumes("000196702346").unwrap().is_empty());
+    assert_eq!(fetches.load(Ordering::SeqCst), 3);
+
+    // The credential from the config is used as is and not retried
+    let config = VmaxConfig {
+        endpoint: format!("127.0.0.1:{}", port),
+        user: "admin".into(),
+        password: "old".into(),
+        certificate: None,
+        region: "test".into(),
+        max_response_bytes: None,
+        symmetrix_ids: None,
+    };
+    let err = Vmax::new(&client, config)
+        .get_all_slo_volumes("000196702346")
+        .unwrap_err();
+    assert!(err.is_unauthorized(), "{}", err);
 }
 
 #[derive(Debug, Deserialize)]
@@ -916,6 +1041,408 @@ fn test_get_vmax_json_volumes() {
     println!("Volume IDs: {:?}", all_volume_ids);
 }
 
+/// Masking views wider than this many (initiator, port) combinations are
+/// truncated by get_masking_topology so one monster view can't flood the
+/// time series database
+pub const MAX_MASKING_TOPOLOGY_COMBINATIONS: usize = 10_000;
+
+// https://{server}/univmax/restapi/90/sloprovisioning/symmetrix/{symmetrixId}/maskingview/{maskingViewId}
+// A view masks either a single host or a host group
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaskingView {
+    pub masking_view_id: String,
+    pub host_id: Option<String>,
+    pub host_group_id: Option<String>,
+    pub port_group_id: String,
+    pub storage_group_id: String,
+}
+
+// https://{server}/univmax/restapi/90/sloprovisioning/symmetrix/{symmetrixId}/host/{hostId}
+#[derive(Debug, Deserialize)]
+pub struct SloHost {
+    #[serde(rename = "hostId")]
+    pub host_id: String,
+    pub num_of_masking_views: Option<i64>,
+    pub num_of_initiators: Option<i64>,
+    pub num_of_host_groups: Option<i64>,
+    #[serde(rename = "type")]
+    pub host_type: Option<String>,
+    /// Initiator WWNs or IQNs
+    #[serde(default)]
+    pub initiator: Vec<String>,
+    #[serde(default)]
+    pub maskingview: Vec<String>,
+    /// Host groups this host belongs to
+    #[serde(default)]
+    pub hostgroup: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymmetrixPortKey {
+    pub director_id: String,
+    pub port_id: String,
+}
+
+// https://{server}/univmax/restapi/90/sloprovisioning/symmetrix/{symmetrixId}/portgroup/{portGroupId}
+#[derive(Debug, Deserialize)]
+pub struct SloPortGroup {
+    #[serde(rename = "portGroupId")]
+    pub port_group_id: String,
+    #[serde(rename = "symmetrixPortKey", default)]
+    pub symmetrix_port_key: Vec<SymmetrixPortKey>,
+    pub num_of_ports: Option<i64>,
+    pub num_of_masking_views: Option<i64>,
+    #[serde(rename = "type")]
+    pub port_group_type: Option<String>,
+    #[serde(default)]
+    pub maskingview: Vec<String>,
+}
+
+// https://{server}/univmax/restapi/90/sloprovisioning/symmetrix/{symmetrixId}/maskingview/{maskingViewId}/connections
+#[derive(Debug, Deserialize)]
+pub struct MaskingViewConnections {
+    #[serde(rename = "maskingViewConnection", default)]
+    pub masking_view_connection: Vec<MaskingViewConnection>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaskingViewConnection {
+    #[serde(rename = "volumeId")]
+    pub volume_id: String,
+    pub host_lun_address: Option<String>,
+    pub cap_gb: Option<String>,
+    #[serde(rename = "initiatorId")]
+    pub initiator_id: String,
+    pub alias: Option<String>,
+    /// director:port, ie FA-1D:4
+    pub dir_port: String,
+    pub logged_in: Option<bool>,
+    pub on_fabric: Option<bool>,
+}
+
+/// The consistency field of SRDF groups and pairs for a pair state:
+/// Consistent (or Synchronized, its synchronous mode equivalent) 0,
+/// Suspended 1, SyncInProg 2, Partitioned 3 and anything else 9
+pub fn rdf_consistency(state: &str) -> u64 {
+    match state {
+        "Consistent" | "Synchronized" => 0,
+        "Suspended" => 1,
+        "SyncInProg" => 2,
+        "Partitioned" => 3,
+        _ => 9,
+    }
+}
+
+// https://{server}/univmax/restapi/90/replication/symmetrix/{symmetrixId}/rdf_group
+#[derive(Debug, Deserialize)]
+pub struct RdfGroupList {
+    #[serde(rename = "rdfGroupID", default)]
+    pub rdf_group_id: Vec<RdfGroupId>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RdfGroupId {
+    pub rdfg_number: u32,
+    pub label: Option<String>,
+}
+
+// https://{server}/univmax/restapi/90/replication/symmetrix/{symmetrixId}/rdf_group/{rdfgNumber}
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RdfGroup {
+    pub rdfg_number: u32,
+    pub label: String,
+    pub remote_symmetrix: Option<String>,
+    pub remote_rdfg_number: Option<u32>,
+    pub num_devices: u64,
+    pub rdf_mode: Option<String>,
+    /// The distinct states of the group's device pairs
+    #[serde(default)]
+    pub states: Vec<String>,
+}
+
+impl RdfGroup {
+    /// The first pair state that isn't consistent, if there is one
+    pub fn inconsistent_state(&self) -> Option<&str> {
+        self.states
+            .iter()
+            .map(|s| s.as_str())
+            .find(|s| rdf_consistency(s) != 0)
+    }
+
+    /// get_rdf_groups only asks for the pairs of groups where this is false
+    pub fn is_consistent(&self) -> bool {
+        self.inconsistent_state().is_none()
+    }
+}
+
+impl IntoPoint for RdfGroup {
+    /// consistency is that of the first inconsistent pair state, or 0 when
+    /// every pair is consistent
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("vmax_rdf_group"), is_time_series);
+        p.add_tag("rdf_group", TsValue::String(self.rdfg_number.to_string()));
+        p.add_tag("label", TsValue::String(self.label.clone()));
+        if let Some(mode) = &self.rdf_mode {
+            p.add_tag("rdf_mode", TsValue::String(mode.clone()));
+        }
+        if let Some(remote) = &self.remote_symmetrix {
+            p.add_tag("remote_symmetrix_id", TsValue::String(remote.clone()));
+        }
+        p.add_field("num_devices", TsValue::Long(self.num_devices));
+        p.add_field(
+            "consistency",
+            TsValue::Long(self.inconsistent_state().map(rdf_consistency).unwrap_or(0)),
+        );
+        p.add_field("states", TsValue::StringVec(self.states.clone()));
+
+        vec![p]
+    }
+}
+
+// https://{server}/univmax/restapi/90/replication/symmetrix/{symmetrixId}/rdf_group/{rdfgNumber}/volume/{volumeId}
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RdfPair {
+    pub local_volume_name: String,
+    pub remote_volume_name: String,
+    pub local_volume_state: Option<String>,
+    pub remote_volume_state: Option<String>,
+    pub rdf_mode: Option<String>,
+    pub rdfpair_state: String,
+}
+
+impl IntoPoint for RdfPair {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("vmax_rdf_pair"), is_time_series);
+        p.add_tag("volume_id", TsValue::String(self.local_volume_name.clone()));
+        p.add_tag(
+            "remote_volume_id",
+            TsValue::String(self.remote_volume_name.clone()),
+        );
+        if let Some(mode) = &self.rdf_mode {
+            p.add_tag("rdf_mode", TsValue::String(mode.clone()));
+        }
+        p.add_field("state", TsValue::String(self.rdfpair_state.clone()));
+        p.add_field(
+            "consistency",
+            TsValue::Long(rdf_consistency(&self.rdfpair_state)),
+        );
+        if let Some(state) = &self.local_volume_state {
+            p.add_field("local_volume_state", TsValue::String(state.clone()));
+        }
+        if let Some(state) = &self.remote_volume_state {
+            p.add_field("remote_volume_state", TsValue::String(state.clone()));
+        }
+
+        vec![p]
+    }
+}
+
+#[test]
+fn test_rdf_group_consistency() {
+    let consistent: RdfGroup = read_vmax_fixture("rdf_group_consistent.json");
+    let suspended: RdfGroup = read_vmax_fixture("rdf_group_suspended.json");
+    assert!(consistent.is_consistent());
+    assert!(!suspended.is_consistent());
+    assert_eq!(suspended.inconsistent_state(), Some("Suspended"));
+
+    let p = &consistent.into_point(None, true)[0];
+    assert_eq!(p.tags["label"], TsValue::String("DR_ORACLE".into()));
+    assert_eq!(p.tags["rdf_mode"], TsValue::String("Asynchronous".into()));
+    assert_eq!(p.fields["consistency"], TsValue::Long(0));
+    assert_eq!(p.fields["num_devices"], TsValue::Long(24));
+    let p = &suspended.into_point(None, true)[0];
+    assert_eq!(p.fields["consistency"], TsValue::Long(1));
+
+    for (state, code) in &[
+        ("Consistent", 0),
+        ("Synchronized", 0),
+        ("Suspended", 1),
+        ("SyncInProg", 2),
+        ("Partitioned", 3),
+        ("Failed Over", 9),
+    ] {
+        assert_eq!(rdf_consistency(state), *code, "{}", state);
+    }
+}
+
+// Expand each masking view into one point per (initiator, port) it allows.
+// Views past the limit are truncated
+fn masking_topology(
+    symmetrix_id: &str,
+    views: &[MaskingView],
+    hosts: &[SloHost],
+    port_groups: &[SloPortGroup],
+    t: DateTime<Utc>,
+    limit: usize,
+) -> Vec<TsPoint> {
+    let mut points: Vec<TsPoint> = Vec::new();
+    for view in views {
+        let view_hosts: Vec<&SloHost> = hosts
+            .iter()
+            .filter(|h| match (&view.host_id, &view.host_group_id) {
+                (Some(host_id), _) => &h.host_id == host_id,
+                (None, Some(group_id)) => h.hostgroup.contains(group_id),
+                (None, None) => false,
+            })
+            .collect();
+        let ports: &[SymmetrixPortKey] = match port_groups
+            .iter()
+            .find(|pg| pg.port_group_id == view.port_group_id)
+        {
+            Some(pg) => &pg.symmetrix_port_key,
+            None => &[],
+        };
+        let combinations = view_hosts
+            .iter()
+            .map(|h| h.initiator.len() * ports.len())
+            .sum::<usize>();
+        if combinations > limit {
+            warn!(
+                "masking view {} has {} initiator/port combinations.  Only the first {} are reported",
+                view.masking_view_id, combinations, limit
+            );
+        }
+
+        let mut emitted = 0;
+        'view: for host in view_hosts {
+            for initiator in &host.initiator {
+                for port in ports {
+                    if emitted == limit {
+                        break 'view;
+                    }
+                    let mut p = TsPoint::new("vmax_masking_topology", false);
+                    p.timestamp = Some(t);
+                    p.add_tag("symmetrix_id", TsValue::String(symmetrix_id.to_string()));
+                    p.add_tag(
+                        "masking_view",
+                        TsValue::String(view.masking_view_id.clone()),
+                    );
+                    p.add_tag("host", TsValue::String(host.host_id.clone()));
+                    p.add_tag("initiator", TsValue::String(initiator.clone()));
+                    p.add_tag("director", TsValue::String(port.director_id.clone()));
+                    p.add_tag("port", TsValue::String(port.port_id.clone()));
+                    p.add_field(
+                        "storage_group",
+                        TsValue::String(view.storage_group_id.clone()),
+                    );
+                    points.push(p);
+                    emitted += 1;
+                }
+            }
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+fn read_vmax_fixture<T: DeserializeOwned>(name: &str) -> T {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f = File::open(format!("tests/vmax/{}", name)).unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+    serde_json::from_str(&buff).unwrap()
+}
+
+#[test]
+fn test_masking_topology() {
+    let view: MaskingView = read_vmax_fixture("slo_maskingview.json");
+    let host: SloHost = read_vmax_fixture("slo_host.json");
+    let pg: SloPortGroup = read_vmax_fixture("slo_portgroup.json");
+    let connections: MaskingViewConnections = read_vmax_fixture("slo_maskingview_connections.json");
+    println!("connections: {:#?}", connections);
+    assert_eq!(connections.masking_view_connection[1].dir_port, "FA-2D:4");
+
+    let t = Utc::now();
+    let points = masking_topology(
+        "000196702346",
+        &[view],
+        &[host],
+        &[pg],
+        t,
+        MAX_MASKING_TOPOLOGY_COMBINATIONS,
+    );
+    println!("points: {:#?}", points);
+    // 2 initiators x 2 ports
+    assert_eq!(points.len(), 4);
+    assert_eq!(points[0].timestamp, Some(t));
+    assert_eq!(
+        points[0].tags["masking_view"],
+        TsValue::String("db01_mv".into())
+    );
+    assert_eq!(points[3].tags["director"], TsValue::String("FA-2D".into()));
+    assert_eq!(
+        points[3].fields["storage_group"],
+        TsValue::String("db01_sg".into())
+    );
+}
+
+#[test]
+fn test_masking_topology_truncation() {
+    // A host group view with 2 hosts of 100 initiators and 64 ports
+    let hosts: Vec<SloHost> = (0..2)
+        .map(|h| SloHost {
+            host_id: format!("host{}", h),
+            num_of_masking_views: None,
+            num_of_initiators: None,
+            num_of_host_groups: None,
+            host_type: None,
+            initiator: (0..100).map(|i| format!("{}:{}", h, i)).collect(),
+            maskingview: vec![],
+            hostgroup: vec!["cluster_hg".into()],
+        })
+        .collect();
+    let pg = SloPortGroup {
+        port_group_id: "all_ports".into(),
+        symmetrix_port_key: (0..64)
+            .map(|p| SymmetrixPortKey {
+                director_id: format!("FA-{}D", p / 4),
+                port_id: (p % 4).to_string(),
+            })
+            .collect(),
+        num_of_ports: None,
+        num_of_masking_views: None,
+        port_group_type: None,
+        maskingview: vec![],
+    };
+    let views = vec![
+        MaskingView {
+            masking_view_id: "huge_mv".into(),
+            host_id: None,
+            host_group_id: Some("cluster_hg".into()),
+            port_group_id: "all_ports".into(),
+            storage_group_id: "huge_sg".into(),
+        },
+        MaskingView {
+            masking_view_id: "small_mv".into(),
+            host_id: Some("host1".into()),
+            host_group_id: None,
+            port_group_id: "missing_pg".into(),
+            storage_group_id: "small_sg".into(),
+        },
+    ];
+    let points = masking_topology(
+        "000196702346",
+        &views,
+        &hosts,
+        &[pg],
+        Utc::now(),
+        MAX_MASKING_TOPOLOGY_COMBINATIONS,
+    );
+    // 12,800 combinations are capped.  The view with an unknown port
+    // group has nothing to report
+    assert_eq!(points.len(), MAX_MASKING_TOPOLOGY_COMBINATIONS);
+    assert!(points
+        .iter()
+        .all(|p| p.tags["masking_view"] == TsValue::String("huge_mv".into())));
+}
+
 impl Vmax {
     /* These are the GET and POST functions depending upon the output needed
      */
@@ -932,12 +1459,17 @@ impl Vmax {
             "https://{}/univmax/restapi/{}",
             self.config.endpoint, api_endpoint,
         );
-        let j: T = crate::get(
-            &self.client,
-            &url,
-            &self.config.user,
-            Some(&self.config.password),
-        )?;
+        let j: T = self
+            .with_credential(|c| {
+                crate::get_limited(
+                    &self.client,
+                    &url,
+                    &c.user,
+                    Some(&c.password),
+                    self.config.max_response_bytes,
+                )
+            })
+            .map_err(|e| e.with_context("vmax"))?;
 
         Ok(j.into_point(Some(point_name), is_time_series))
     }
@@ -955,22 +1487,7 @@ impl Vmax {
         T: DeserializeOwned + Debug + IntoPoint,
         U: Serialize + ?Sized,
     {
-        let array_output = self
-            .client
-            .post(&format!(
-                "https://{}/univmax/restapi/{}",
-                self.config.endpoint, api_endpoint,
-            ))
-            .basic_auth(&self.config.user, Some(&self.config.password))
-            .header(ACCEPT, "application/json")
-            .json(body)
-            .send()?
-            .error_for_status()?
-            .text()?;
-        trace!("{}", array_output);
-        let json_res: Result<T, serde_json::Error> = serde_json::from_str(&array_output);
-        trace!("json result: {:?}", json_res);
-        let json_res = json_res?;
+        let json_res: T = self.post_data(api_endpoint, body)?;
         Ok(json_res.into_point(Some(point_name), is_time_series))
     }
 
@@ -982,23 +1499,27 @@ impl Vmax {
         T: DeserializeOwned + Debug,
         U: Serialize + ?Sized,
     {
-        let array_output = self
-            .client
-            .post(&format!(
-                "https://{}/univmax/restapi/{}",
-                self.config.endpoint, api_endpoint,
-            ))
-            .basic_auth(&self.config.user, Some(&self.config.password))
-            .header(ACCEPT, "application/json")
-            .json(body)
-            .send()?
-            .error_for_status()?
-            .text()?;
+        let url = format!(
+            "https://{}/univmax/restapi/{}",
+            self.config.endpoint, api_endpoint,
+        );
+        let array_output = self.with_credential(|c| {
+            self.client
+                .post(&url)
+                .basic_auth(&c.user, Some(&c.password))
+                .header(ACCEPT, "application/json")
+                .json(body)
+                .send()
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| StorageError::from(e.without_url()))
+                .and_then(|r| read_text(r, self.config.max_response_bytes))
+                .map_err(|e| {
+                    e.with_context(format!("POST {}", redact_url(&url)))
+                        .with_context("vmax")
+                })
+        })?;
         trace!("{}", array_output);
-        let json_res: Result<T, serde_json::Error> = serde_json::from_str(&array_output);
-        trace!("json result: {:?}", json_res);
-        let json_res = json_res?;
-        Ok(json_res)
+        decode_json("POST", &url, &array_output).map_err(|e| e.with_context("vmax"))
     }
 
     // This function is for get_data only, the get_list was not needed. Note the '90' for v9 of the EMC Unisphere software
@@ -1014,15 +1535,19 @@ impl Vmax {
     // Grab the list of things to operate on from vmax
     fn get_list(&self, api_endpoint: &str, key: &str) -> MetricsResult<Vec<String>> {
         let data: Value = self
-            .client
-            .get(&format!(
-                "https://{}/univmax/restapi/{}",
-                self.config.endpoint, api_endpoint,
-            ))
-            .basic_auth(&self.config.user, Some(&self.config.password))
-            .send()?
-            .error_for_status()?
-            .json()?;
+            .with_credential(|c| {
+                crate::get_limited(
+                    &self.client,
+                    &format!(
+                        "https://{}/univmax/restapi/{}",
+                        self.config.endpoint, api_endpoint,
+                    ),
+                    &c.user,
+                    Some(&c.password),
+                    self.config.max_response_bytes,
+                )
+            })
+            .map_err(|e| e.with_context("vmax"))?;
 
         // Grab the list from the json
         match data[key].as_array() {
@@ -1234,15 +1759,20 @@ impl Vmax {
     /// Returns a list of volume IDs for this array
     // TODO: combine this with the other getters and generalize along with other metrics
     pub fn get_all_slo_volumes(&self, symmetrixid: &str) -> MetricsResult<Vec<String>> {
-        let data: Value = super::get(
-            &self.client,
-            &format!(
-                "https://{}/univmax/restapi/90/sloprovisioning/symmetrix/{}/volume",
-                self.config.endpoint, symmetrixid
-            ),
-            &self.config.user,
-            Some(&self.config.password),
-        )?;
+        let data: Value = self
+            .with_credential(|c| {
+                super::get_limited(
+                    &self.client,
+                    &format!(
+                        "https://{}/univmax/restapi/90/sloprovisioning/symmetrix/{}/volume",
+                        self.config.endpoint, symmetrixid
+                    ),
+                    &c.user,
+                    Some(&c.password),
+                    self.config.max_response_bytes,
+                )
+            })
+            .map_err(|e| e.with_context("vmax"))?;
 
         let vol_count = data["count"].as_u64().unwrap_or(0);
         let iterator_id = data["id"].as_str().unwrap_or("");
@@ -1282,15 +1812,20 @@ impl Vmax {
                 to = vol_count;
             }
             debug!("Gathering volumes from {} to {}", from, to);
-            let data: Value = super::get(
-                &self.client,
-                &format!(
-                    "https://{}/univmax/restapi/common/Iterator/{}/page?from={}&to={}",
-                    self.config.endpoint, iterator_id, from, to
-                ),
-                &self.config.user,
-                Some(&self.config.password),
-            )?;
+            let data: Value = self
+                .with_credential(|c| {
+                    super::get_limited(
+                        &self.client,
+                        &format!(
+                            "https://{}/univmax/restapi/common/Iterator/{}/page?from={}&to={}",
+                            self.config.endpoint, iterator_id, from, to
+                        ),
+                        &c.user,
+                        Some(&c.password),
+                        self.config.max_response_bytes,
+                    )
+                })
+                .map_err(|e| e.with_context("vmax"))?;
 
             let page_vols = match data["result"].as_array() {
                 Some(v) => v
@@ -1338,4 +1873,280 @@ impl Vmax {
             .collect();
         Ok(new_vol)
     }
+    fn get_object<T>(&self, api_endpoint: &str) -> MetricsResult<T>
+    where
+        T: DeserializeOwned + Debug,
+    {
+        self.with_credential(|c| {
+            crate::get_limited(
+                &self.client,
+                &format!(
+                    "https://{}/univmax/restapi/{}",
+                    self.config.endpoint, api_endpoint,
+                ),
+                &c.user,
+                Some(&c.password),
+                self.config.max_response_bytes,
+            )
+        })
+        .map_err(|e| e.with_context("vmax"))
+    }
+
+    pub fn get_masking_views(&self, symmetrix_id: &str) -> MetricsResult<Vec<MaskingView>> {
+        let ids = self.get_list(
+            &format!("90/sloprovisioning/symmetrix/{}/maskingview", symmetrix_id),
+            "maskingViewId",
+        )?;
+        ids.iter()
+            .map(|id| {
+                self.get_object(&format!(
+                    "90/sloprovisioning/symmetrix/{}/maskingview/{}",
+                    symmetrix_id, id
+                ))
+            })
+            .collect()
+    }
+
+    /// The volume, initiator and port paths for a masking view
+    pub fn get_masking_view_connections(
+        &self,
+        symmetrix_id: &str,
+        masking_view_id: &str,
+    ) -> MetricsResult<Vec<MaskingViewConnection>> {
+        let connections: MaskingViewConnections = self.get_object(&format!(
+            "90/sloprovisioning/symmetrix/{}/maskingview/{}/connections",
+            symmetrix_id, masking_view_id
+        ))?;
+        Ok(connections.masking_view_connection)
+    }
+
+    /// Hosts (initiator groups) and their initiators
+    pub fn get_hosts(&self, symmetrix_id: &str) -> MetricsResult<Vec<SloHost>> {
+        let ids = self.get_list(
+            &format!("90/sloprovisioning/symmetrix/{}/host", symmetrix_id),
+            "hostId",
+        )?;
+        ids.iter()
+            .map(|id| {
+                self.get_object(&format!(
+                    "90/sloprovisioning/symmetrix/{}/host/{}",
+                    symmetrix_id, id
+                ))
+            })
+            .collect()
+    }
+
+    /// Port groups and their director ports.  See get_portgroups for the
+    /// performance keys
+    pub fn get_port_groups(&self, symmetrix_id: &str) -> MetricsResult<Vec<SloPortGroup>> {
+        let ids = self.get_list(
+            &format!("90/sloprovisioning/symmetrix/{}/portgroup", symmetrix_id),
+            "portGroupId",
+        )?;
+        ids.iter()
+            .map(|id| {
+                self.get_object(&format!(
+                    "90/sloprovisioning/symmetrix/{}/portgroup/{}",
+                    symmetrix_id, id
+                ))
+            })
+            .collect()
+    }
+
+    /// The SRDF groups of an array.  Each group is a separate request
+    pub fn rdf_groups(&self, symmetrix_id: &str) -> MetricsResult<Vec<RdfGroup>> {
+        let list: RdfGroupList = self.get_object(&format!(
+            "90/replication/symmetrix/{}/rdf_group",
+            symmetrix_id
+        ))?;
+        list.rdf_group_id
+            .iter()
+            .map(|group| {
+                self.get_object(&format!(
+                    "90/replication/symmetrix/{}/rdf_group/{}",
+                    symmetrix_id, group.rdfg_number
+                ))
+            })
+            .collect()
+    }
+
+    /// A point per SRDF group followed by the pair points of every group
+    /// that isn't consistent, see `RdfGroup::is_consistent`.  Pairs take a
+    /// request per volume so they're skipped for healthy groups
+    pub fn get_rdf_groups(
+        &self,
+        symmetrix_id: &str,
+        t: DateTime<Utc>,
+    ) -> MetricsResult<Vec<TsPoint>> {
+        let groups = self.rdf_groups(symmetrix_id)?;
+        let mut points: Vec<TsPoint> = groups
+            .iter()
+            .flat_map(|g| g.into_point(Some("vmax_rdf_group"), true))
+            .collect();
+        for group in groups.iter().filter(|g| !g.is_consistent()) {
+            debug!(
+                "rdf group {} of {} is {:?}, getting its pairs",
+                group.label,
+                symmetrix_id,
+                group.inconsistent_state()
+            );
+            points.extend(
+                self.get_rdf_group_pairs(symmetrix_id, group.rdfg_number, t)?
+                    .into_iter()
+                    .map(|mut p| {
+                        p.add_tag("label", TsValue::String(group.label.clone()));
+                        p
+                    }),
+            );
+        }
+        Ok(points
+            .into_iter()
+            .map(|mut p| {
+                p.add_tag("symmetrix_id", TsValue::String(symmetrix_id.to_string()));
+                p.set_time(t)
+            })
+            .collect())
+    }
+
+    /// The device pairs of an SRDF group, one request per volume
+    pub fn get_rdf_group_pairs(
+        &self,
+        symmetrix_id: &str,
+        rdfg_number: u32,
+        t: DateTime<Utc>,
+    ) -> MetricsResult<Vec<TsPoint>> {
+        let volumes = self.get_list(
+            &format!(
+                "90/replication/symmetrix/{}/rdf_group/{}/volume",
+                symmetrix_id, rdfg_number
+            ),
+            "name",
+        )?;
+        let mut points = Vec::new();
+        for volume in volumes {
+            let pair: RdfPair = self.get_object(&format!(
+                "90/replication/symmetrix/{}/rdf_group/{}/volume/{}",
+                symmetrix_id, rdfg_number, volume
+            ))?;
+            points.extend(
+                pair.into_point(Some("vmax_rdf_pair"), true)
+                    .into_iter()
+                    .map(|mut p| {
+                        p.add_tag("symmetrix_id", TsValue::String(symmetrix_id.to_string()));
+                        p.add_tag("rdf_group", TsValue::String(rdfg_number.to_string()));
+                        p.set_time(t)
+                    }),
+            );
+        }
+        Ok(points)
+    }
+
+    /// One point per (masking view, initiator, port) showing which
+    /// initiators can reach the storage group through which ports.  Views
+    /// are capped at MAX_MASKING_TOPOLOGY_COMBINATIONS points
+    pub fn get_masking_topology(
+        &self,
+        symmetrix_id: &str,
+        t: DateTime<Utc>,
+    ) -> MetricsResult<Vec<TsPoint>> {
+        let views = self.get_masking_views(symmetrix_id)?;
+        let hosts = self.get_hosts(symmetrix_id)?;
+        let port_groups = self.get_port_groups(symmetrix_id)?;
+        Ok(masking_topology(
+            symmetrix_id,
+            &views,
+            &hosts,
+            &port_groups,
+            t,
+            MAX_MASKING_TOPOLOGY_COMBINATIONS,
+        ))
+    }
+}
+
+#[test]
+fn test_get_rdf_groups() {
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+
+    let fixture = |name: &str| {
+        let mut buff = String::new();
+        std::io::Read::read_to_string(
+            &mut std::fs::File::open(format!("tests/vmax/{}", name)).unwrap(),
+            &mut buff,
+        )
+        .unwrap();
+        buff.into_bytes()
+    };
+    let responses: Vec<(&str, Vec<u8>)> = vec![
+        ("rdf_group", fixture("rdf_groups.json")),
+        ("rdf_group/1", fixture("rdf_group_consistent.json")),
+        ("rdf_group/2", fixture("rdf_group_suspended.json")),
+        ("rdf_group/2/volume", fixture("rdf_group_volumes.json")),
+        (
+            "rdf_group/2/volume/00A10",
+            fixture("rdf_pair_synchronized.json"),
+        ),
+        (
+            "rdf_group/2/volume/00A11",
+            fixture("rdf_pair_suspended.json"),
+        ),
+    ];
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let port = crate::mock_https_server(move |req| {
+        let path = req
+            .path
+            .trim_start_matches("/univmax/restapi/90/replication/symmetrix/000197800123/")
+            .to_string();
+        recorded.lock().unwrap().push(path.clone());
+        match responses.iter().find(|(p, _)| *p == path) {
+            Some((_, body)) => (200, String::new(), body.clone()),
+            None => (404, String::new(), b"{}".to_vec()),
+        }
+    });
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let vmax = Vmax::new(
+        &client,
+        VmaxConfig {
+            endpoint: format!("127.0.0.1:{}", port),
+            user: "admin".into(),
+            password: "secret".into(),
+            certificate: None,
+            region: "test".into(),
+            max_response_bytes: None,
+            symmetrix_ids: None,
+        },
+    );
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+
+    let points = vmax.get_rdf_groups("000197800123", t).unwrap();
+    // The pairs are only asked for of the suspended group
+    assert_eq!(
+        *requests.lock().unwrap(),
+        vec![
+            "rdf_group",
+            "rdf_group/1",
+            "rdf_group/2",
+            "rdf_group/2/volume",
+            "rdf_group/2/volume/00A10",
+            "rdf_group/2/volume/00A11",
+        ]
+    );
+    assert_eq!(points.len(), 4);
+    assert!(points.iter().all(|p| p.timestamp == Some(t)
+        && p.tags["symmetrix_id"] == TsValue::String("000197800123".into())));
+    assert_eq!(points[0].measurement, "vmax_rdf_group");
+    assert_eq!(points[0].fields["consistency"], TsValue::Long(0));
+    assert_eq!(points[1].fields["consistency"], TsValue::Long(1));
+
+    let pair = &points[3];
+    assert_eq!(pair.measurement, "vmax_rdf_pair");
+    assert_eq!(pair.tags["label"], TsValue::String("DR_VMWARE".into()));
+    assert_eq!(pair.tags["rdf_group"], TsValue::String("2".into()));
+    assert_eq!(pair.tags["volume_id"], TsValue::String("00A11".into()));
+    assert_eq!(pair.fields["consistency"], TsValue::Long(1));
+    assert_eq!(points[2].fields["consistency"], TsValue::Long(0));
 }
@@ -17,23 +17,31 @@
 *
 * SPDX-License-Identifier: Apache-2.0
 */
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::{Infallible, TryFrom};
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::io::Write;
+use std::mem;
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use crate::collector::Collector;
 use crate::error::*;
-use crate::IntoPoint;
+pub use crate::Severity;
+use crate::{read_text, require_config_value, validate_endpoint, IntoPoint, LogPrefix};
 
 use crate::ir::{TsPoint, TsValue};
+use crate::state::StateStore;
 use chrono::offset::Utc;
-use chrono::DateTime;
+use chrono::{DateTime, TimeZone};
 use cookie::{Cookie, CookieJar};
 use log::{debug, error, trace, warn};
 use quick_xml::events::attributes::Attributes;
-use quick_xml::events::Event;
+use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
 use reqwest::header::{
     HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, SET_COOKIE,
@@ -54,6 +62,278 @@ pub trait FromXmlAttributes {
         Self: Sized;
 }
 
+// Unit suffixes parse_lenient_u64 and parse_lenient_f64 accept
+const LENIENT_UNITS: &[(&str, u64)] = &[("KB", 1 << 10), ("MB", 1 << 20), ("GB", 1 << 30)];
+
+// The number in val without thousands separators or whitespace and the
+// multiplier of its unit suffix, if any
+fn split_lenient_number(val: &str) -> (String, u64) {
+    let compact: String = val
+        .chars()
+        .filter(|c| *c != ',' && !c.is_whitespace())
+        .collect();
+    for (unit, multiplier) in LENIENT_UNITS {
+        if compact.len() > unit.len() && compact.to_ascii_uppercase().ends_with(unit) {
+            let number = compact[..compact.len() - unit.len()].to_string();
+            return (number, *multiplier);
+        }
+    }
+    (compact, 1)
+}
+
+/// Parse a counter that some firmware formats with thousands separators,
+/// ie "1,234,567", or a KB, MB or GB suffix, ie "512 MB", which multiply
+/// by 1024, 1024^2 and 1024^3.  Used by `#[xml(lenient_numbers)]` structs
+pub fn parse_lenient_u64(val: &str) -> MetricsResult<u64> {
+    parse_lenient_u64_in(val, 1)
+}
+
+/// parse_lenient_u64 for a value counted in units of `unit` bytes, ie 512
+/// byte blocks.  A bare number is already in those units, a suffixed one
+/// is converted to them.  Used by `#[xml(unit = N)]` fields
+pub fn parse_lenient_u64_in(val: &str, unit: u64) -> MetricsResult<u64> {
+    let (number, multiplier) = split_lenient_number(val);
+    let number = u64::from_str(&number)?;
+    if multiplier == 1 {
+        return Ok(number);
+    }
+    let bytes = number
+        .checked_mul(multiplier)
+        .ok_or_else(|| StorageError::new(format!("{} is too large for a u64", val)))?;
+    if bytes % unit != 0 {
+        return Err(StorageError::new(format!(
+            "{} is not a whole number of {} byte units",
+            val, unit
+        )));
+    }
+    Ok(bytes / unit)
+}
+
+/// The f64 version of parse_lenient_u64
+pub fn parse_lenient_f64(val: &str) -> MetricsResult<f64> {
+    let (number, multiplier) = split_lenient_number(val);
+    Ok(f64::from_str(&number)? * multiplier as f64)
+}
+
+#[test]
+fn test_parse_lenient_numbers() {
+    assert_eq!(parse_lenient_u64("1234567").unwrap(), 1_234_567);
+    assert_eq!(parse_lenient_u64("1,234,567").unwrap(), 1_234_567);
+    assert_eq!(parse_lenient_u64(" 42 ").unwrap(), 42);
+    assert_eq!(parse_lenient_u64("4 KB").unwrap(), 4096);
+    assert_eq!(parse_lenient_u64("512 MB").unwrap(), 512 << 20);
+    assert_eq!(parse_lenient_u64("512mb").unwrap(), 512 << 20);
+    assert_eq!(parse_lenient_u64("1,024 GB").unwrap(), 1024 << 30);
+    assert_eq!(parse_lenient_f64("1,234.5").unwrap(), 1234.5);
+    assert_eq!(
+        parse_lenient_f64("1.5 GB").unwrap(),
+        1.5 * (1u64 << 30) as f64
+    );
+
+    for invalid in &["", "MB", "12 TB", "1.5 MB", "-4", "twelve"] {
+        assert!(
+            parse_lenient_u64(invalid).is_err(),
+            "{} should not parse",
+            invalid
+        );
+    }
+    assert!(parse_lenient_f64("1.5 PB").is_err());
+    let err = parse_lenient_u64("18,446,744,073,709,551,615 KB").unwrap_err();
+    assert!(err.to_string().contains("too large"), "{}", err);
+
+    // Counted in 512 byte blocks
+    assert_eq!(
+        parse_lenient_u64_in("1,125,767,168", 512).unwrap(),
+        1_125_767_168
+    );
+    assert_eq!(parse_lenient_u64_in("536 GB", 512).unwrap(), 536 << 21);
+    assert_eq!(parse_lenient_u64_in("4 KB", 512).unwrap(), 8);
+    assert!(parse_lenient_u64_in("1 KB", 3).is_err());
+}
+
+/// A problem listed in a QueryStatus
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Problem {
+    pub message_code: String,
+    pub message: String,
+    pub severity: Severity,
+    pub description: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.message_code, self.message)?;
+        if !self.description.is_empty() {
+            write!(f, ": {}", self.description)?;
+        }
+        Ok(())
+    }
+}
+
+/// The QueryStatus each Response of a ResponsePacket carries.  A packet
+/// with several Responses, like a stats request covering many movers, is
+/// folded into one status with the worst severity and every problem.  A
+/// packet without any status is treated as ok.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryStatus {
+    pub max_severity: Severity,
+    pub problems: Vec<Problem>,
+}
+
+impl FromXml for QueryStatus {
+    fn from_xml(data: &str) -> MetricsResult<Self> {
+        let mut reader = Reader::from_str(data);
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+
+        let mut status = QueryStatus::default();
+        let mut in_description = false;
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => match e.name() {
+                    b"QueryStatus" | b"Status" => {
+                        for a in e.attributes() {
+                            let item = a?;
+                            if item.key == b"maxSeverity" {
+                                let severity =
+                                    Severity::from_str(&String::from_utf8_lossy(&item.value))?;
+                                status.max_severity = status.max_severity.max(severity);
+                            }
+                        }
+                    }
+                    b"Problem" => {
+                        let mut problem = Problem::default();
+                        for a in e.attributes() {
+                            let item = a?;
+                            let val = String::from_utf8_lossy(&item.value);
+                            match item.key {
+                                b"messageCode" => problem.message_code = val.to_string(),
+                                b"message" => problem.message = val.to_string(),
+                                b"severity" => problem.severity = Severity::from_str(&val)?,
+                                _ => {}
+                            }
+                        }
+                        status.problems.push(problem);
+                    }
+                    b"Description" => in_description = true,
+                    _ => {}
+                },
+                Ok(Event::Text(ref e)) if in_description => {
+                    if let Some(problem) = status.problems.last_mut() {
+                        problem.description = e.unescape_and_decode(&reader)?;
+                    }
+                }
+                Ok(Event::End(ref e)) if b"Description" == e.name() => {
+                    in_description = false;
+                }
+                Err(e) => {
+                    return Err(StorageError::new(format!(
+                        "invalid xml data  from server at position: {}: {:?}",
+                        reader.buffer_position(),
+                        e
+                    )));
+                }
+                Ok(Event::Eof) => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+        Ok(status)
+    }
+}
+
+// Any response the control station sends back in a ResponsePacket.  Used
+// by Vnx::health_check where only the fact that a parseable answer came
+// back matters
+struct SessionCheck;
+
+impl FromXml for SessionCheck {
+    fn from_xml(data: &str) -> MetricsResult<Self> {
+        let mut reader = Reader::from_str(data);
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                    if e.name() == b"ResponsePacket" =>
+                {
+                    return Ok(SessionCheck);
+                }
+                Err(e) => {
+                    return Err(StorageError::new(format!(
+                        "invalid xml data  from server at position: {}: {:?}",
+                        reader.buffer_position(),
+                        e
+                    )));
+                }
+                Ok(Event::Eof) => {
+                    return Err(StorageError::new(
+                        "no ResponsePacket in response from server".into(),
+                    ))
+                }
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+}
+
+impl QueryStatus {
+    /// Warnings are logged and the response is used anyway.  Errors fail
+    /// the request with the problems reported
+    fn check(&self, prefix: &LogPrefix) -> MetricsResult<()> {
+        match self.max_severity {
+            s if s < Severity::Warning => Ok(()),
+            Severity::Warning => {
+                for problem in &self.problems {
+                    warn!("{} query status warning: {}", prefix, problem);
+                }
+                Ok(())
+            }
+            _ => {
+                let problems: Vec<String> = self
+                    .problems
+                    .iter()
+                    .filter(|p| p.severity >= Severity::Error)
+                    .map(|p| p.to_string())
+                    .collect();
+                Err(StorageError::new(format!(
+                    "query status error: {}",
+                    problems.join("; ")
+                )))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_query_status() {
+    let mut data = String::new();
+    File::open("tests/vnx/resource_stats_warning_query.xml")
+        .unwrap()
+        .read_to_string(&mut data)
+        .unwrap();
+    let status = QueryStatus::from_xml(&data).unwrap();
+    assert_eq!(status.max_severity, Severity::Warning);
+    assert_eq!(status.problems.len(), 1);
+    assert_eq!(status.problems[0].message_code, "13421840574");
+    assert_eq!(
+        status.problems[0].description,
+        "The data mover with id 3 did not respond to the statistics request in time."
+    );
+
+    let mut data = String::new();
+    File::open("tests/vnx/mounts_query.xml")
+        .unwrap()
+        .read_to_string(&mut data)
+        .unwrap();
+    assert_eq!(
+        QueryStatus::from_xml(&data).unwrap(),
+        QueryStatus::default()
+    );
+    assert_eq!(QueryStatus::from_xml("").unwrap(), QueryStatus::default());
+}
+
 pub enum MoverStatsRequest {
     Cifs,
     Network,
@@ -86,6 +366,117 @@ pub struct VnxConfig {
     pub certificate: Option<String>,
     /// Location of the XML dump files created by nas-xml
     pub shares_dump_location: Option<String>,
+    /// Data movers whose stats `Collector::collect` requests, ie "1".
+    /// Defaults to every mover with a network interface
+    pub mover_ids: Option<Vec<String>>,
+    /// Seconds added to the sample times of stats responses to correct for
+    /// the control station's clock, ie -120 when it runs two minutes fast
+    pub clock_skew_secs: Option<i64>,
+    /// Run `Vnx::health_check` right after logging in so a session the
+    /// control station won't serve fails `Vnx::new`.  Defaults to false
+    pub validate_session: Option<bool>,
+    /// Keep emitting numeric identifiers as Long fields, under their old
+    /// names, alongside the string tags they've moved to, see
+    /// `ids_as_tags`.  Defaults to true for this release so existing
+    /// queries keep working.  mover, filesystem_id and volume_id are then
+    /// both a tag and a field of the same name, see `ids_as_tags`
+    #[serde(default = "default_legacy_id_fields")]
+    pub legacy_id_fields: bool,
+    /// Largest response body to read, in bytes.  Defaults to
+    /// DEFAULT_MAX_RESPONSE_BYTES
+    pub max_response_bytes: Option<u64>,
+}
+
+fn default_legacy_id_fields() -> bool {
+    true
+}
+
+impl VnxConfig {
+    pub fn log_prefix(&self) -> LogPrefix<'_> {
+        LogPrefix {
+            endpoint: &self.endpoint,
+            region: &self.region,
+        }
+    }
+
+    /// Check the endpoint is a host or host:port and the credentials are
+    /// set.  Called by `Vnx::new` before logging in
+    pub fn validate(&self) -> MetricsResult<()> {
+        validate_endpoint(&self.endpoint)
+            .and_then(|_| require_config_value("user", &self.user))
+            .and_then(|_| require_config_value("password", &self.password))
+            .map_err(|e| e.with_context("vnx config"))
+    }
+}
+
+/// Move numeric identifiers the points carry as Long fields into string
+/// tags so they can be joined on.  `ids` pairs each field with the tag it
+/// becomes.  With legacy_id_fields the field is kept as well, under its
+/// old name so existing queries keep working.
+///
+/// Where the tag has the field's name, ie mover, filesystem_id and
+/// volume_id, legacy mode writes a tag and a field with the same key.
+/// InfluxDB 1.x accepts that but InfluxQL returns the field for the bare
+/// name, select the tag with `"mover"::tag`.  Turn legacy_id_fields off
+/// once queries have moved to the tags
+pub fn ids_as_tags(points: &mut [TsPoint], ids: &[(&str, &str)], legacy_id_fields: bool) {
+    for p in points.iter_mut() {
+        for (field, tag) in ids {
+            let id = match p.fields.get(*field) {
+                Some(TsValue::Long(id)) => id.to_string(),
+                _ => continue,
+            };
+            if !legacy_id_fields {
+                p.fields.remove(*field);
+            }
+            p.add_tag(tag.to_string(), TsValue::String(id));
+        }
+    }
+}
+
+#[test]
+fn test_vnx_config_validate() {
+    let config = || VnxConfig {
+        endpoint: "10.0.0.2".into(),
+        user: "nasadmin".into(),
+        password: "secret".into(),
+        region: "test".into(),
+        certificate: None,
+        shares_dump_location: None,
+        mover_ids: None,
+        clock_skew_secs: None,
+        validate_session: None,
+        legacy_id_fields: true,
+        max_response_bytes: None,
+    };
+    assert!(config().validate().is_ok());
+
+    let mut c = config();
+    c.endpoint = " ".into();
+    let err = c.validate().unwrap_err().to_string();
+    assert!(err.contains("endpoint must not be empty"), "{}", err);
+    assert!(err.contains("vnx config"), "{}", err);
+
+    let mut c = config();
+    c.endpoint = "10.0.0.2/Locale/Login".into();
+    assert!(c.validate().unwrap_err().to_string().contains("path"));
+
+    let mut c = config();
+    c.user = String::new();
+    let err = c.validate().unwrap_err().to_string();
+    assert!(err.contains("user must not be empty"), "{}", err);
+
+    let mut c = config();
+    c.password = String::new();
+    let err = c.validate().unwrap_err().to_string();
+    assert!(err.contains("password must not be empty"), "{}", err);
+
+    // Rejected before any request is made
+    let client = reqwest::blocking::Client::new();
+    let mut c = config();
+    c.endpoint = "vnx01 .example.com".into();
+    let err = Vnx::new(&client, c).err().unwrap().to_string();
+    assert!(err.contains("whitespace"), "{}", err);
 }
 
 fn parse_data_services_policies(s: &str) -> MetricsResult<HashMap<String, String>> {
@@ -121,10 +512,114 @@ pub enum VolumeType {
     Unknown,
 }
 
-#[derive(Clone, Debug)]
+impl From<&str> for VolumeType {
+    /// Maps the `type` attribute of a `<Volume>` to its variant.  Only the
+    /// child element carries the variant's data so it's left at its
+    /// defaults
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "disk" => VolumeType::Disk(DiskVolume::default()),
+            "meta" => VolumeType::Meta(MetaVolume::default()),
+            "pool" => VolumeType::Pool(PoolVolume::default()),
+            "slice" => VolumeType::Slice(SliceVolume::default()),
+            "stripe" => VolumeType::Stripe(StripeVolume::default()),
+            _ => VolumeType::Unknown,
+        }
+    }
+}
+
+impl FromStr for VolumeType {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(VolumeType::from(s))
+    }
+}
+
+impl VolumeType {
+    /// True if both are the same variant, ignoring their data
+    pub fn same_type(&self, other: &VolumeType) -> bool {
+        mem::discriminant(self) == mem::discriminant(other)
+    }
+}
+
+// The child element is authoritative since it carries the volume's data.
+// Fall back to the type attribute if there wasn't one and warn if they
+// disagree
+fn reconcile_volume_type(name: &str, element: VolumeType, declared: VolumeType) -> VolumeType {
+    match (element, declared) {
+        (VolumeType::Unknown, declared) => declared,
+        (element, VolumeType::Unknown) => element,
+        (element, declared) => {
+            if !element.same_type(&declared) {
+                warn!(
+                    "Volume {} has type attribute {:?} but {:?} data",
+                    name, declared, element
+                );
+            }
+            element
+        }
+    }
+}
+
+#[test]
+fn test_volume_type_from_str() {
+    let types = [
+        ("disk", VolumeType::Disk(DiskVolume::default())),
+        ("meta", VolumeType::Meta(MetaVolume::default())),
+        ("pool", VolumeType::Pool(PoolVolume::default())),
+        ("slice", VolumeType::Slice(SliceVolume::default())),
+        ("stripe", VolumeType::Stripe(StripeVolume::default())),
+        ("Stripe", VolumeType::Stripe(StripeVolume::default())),
+        ("thin", VolumeType::Unknown),
+        ("", VolumeType::Unknown),
+    ];
+    for (s, expected) in types.iter() {
+        let parsed = VolumeType::from_str(s).unwrap();
+        assert!(parsed.same_type(expected), "{}: {:?}", s, parsed);
+        let converted: VolumeType = (*s).into();
+        assert!(converted.same_type(expected), "{}: {:?}", s, converted);
+    }
+    assert!(!VolumeType::Unknown.same_type(&VolumeType::Disk(DiskVolume::default())));
+}
+
+#[test]
+fn test_volume_type_attribute() {
+    let xml = r#"<Volumes>
+<Volume name="d7" type="disk" size="2047" clientVolumes="" virtualProvisioning="false" volume="7">
+</Volume>
+<Volume name="s12" type="slice" size="100" clientVolumes="" virtualProvisioning="false" volume="12">
+<SliceVolumeData slicedVolume="d7" offset="0"/>
+</Volume>
+<Volume name="v9" type="disk" size="100" clientVolumes="" virtualProvisioning="false" volume="9">
+<StripeVolumeData stripeSize="256"/>
+</Volume>
+<Volume name="x1" type="tape" size="100" clientVolumes="" virtualProvisioning="false" volume="1">
+</Volume>
+</Volumes>"#;
+    let res = Volumes::from_xml(xml).unwrap();
+    println!("result: {:#?}", res);
+    // No child element so the attribute decides
+    assert!(res.volumes[0]
+        .vol_type
+        .same_type(&VolumeType::Disk(DiskVolume::default())));
+    match res.volumes[1].vol_type {
+        VolumeType::Slice(ref v) => assert_eq!(v.sliced_volume, "d7"),
+        ref other => panic!("expected a slice volume, got {:?}", other),
+    }
+    // The child element wins when they disagree
+    match res.volumes[2].vol_type {
+        VolumeType::Stripe(ref v) => assert_eq!(v.stripe_size, 256),
+        ref other => panic!("expected a stripe volume, got {:?}", other),
+    }
+    assert!(res.volumes[3].vol_type.same_type(&VolumeType::Unknown));
+}
+
+#[derive(Clone, Debug, Default)]
 pub enum DiskType {
     Clstd,
     Mixed,
+    #[default]
     Unknown,
 }
 
@@ -167,10 +662,44 @@ pub struct UdpCounter {
 #[derive(Clone, Debug, Default, FromXmlAttributes, IntoPoint)]
 pub struct DeviceCounter {
     device: String,
-    _in: u64,
+    #[xml(rename = "in")]
+    #[point(rename = "_in")]
+    inbound: u64,
     out: u64,
 }
 
+#[test]
+fn test_xml_rename() {
+    let e = BytesStart::borrowed(br#"DeviceTraffic device="cge0" in="12" out="34""#, 13);
+    let d = DeviceCounter::from_xml_attributes(e.attributes()).unwrap();
+    assert_eq!(d.device, "cge0");
+    assert_eq!(d.inbound, 12);
+    assert_eq!(d.out, 34);
+
+    let e = BytesStart::borrowed(br#"SMBCalls copy="3" move="7" echo="72""#, 8);
+    let smb = SmbCounter::from_xml_attributes(e.attributes()).unwrap();
+    assert_eq!(smb.copy, 3);
+    assert_eq!(smb.moves, 7);
+    assert_eq!(smb.echo, 72);
+
+    // The points keep the names the fields had before they were renamed
+    let p = &d.into_point(None, true)[0];
+    assert_eq!(p.fields["_in"], TsValue::Long(12));
+    assert!(!p.fields.contains_key("inbound"));
+    let p = &smb.into_point(None, true)[0];
+    assert_eq!(p.fields["_move"], TsValue::Long(7));
+    assert!(!p.fields.contains_key("moves"));
+}
+
+#[test]
+fn test_xml_attribute_parse_error() {
+    let e = BytesStart::borrowed(br#"SMBCalls copy="3" move="lots" echo="72""#, 8);
+    let err = SmbCounter::from_xml_attributes(e.attributes()).unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.contains("SmbCounter attribute move"), "{}", msg);
+    assert!(msg.contains("\"lots\""), "{}", msg);
+}
+
 #[test]
 fn test_nfs_mounted_shares() {
     use std::fs::File;
@@ -373,6 +902,39 @@ fn test_cifs_servers_parser() {
     println!("points: {:#?}", points);
 }
 
+#[test]
+fn test_cifs_server_interfaces() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let read = |path: &str| {
+        let mut s = String::new();
+        let mut f = File::open(path).unwrap();
+        f.read_to_string(&mut s).unwrap();
+        s
+    };
+    let res = CifsServers::from_xml(&read("tests/vnx/cifs_server_query.xml")).unwrap();
+    let points = res.into_point(Some("vnx_cifs_servers"), false);
+    assert_eq!(points.len(), 1);
+    assert_eq!(
+        points[0].tags["interfaces"],
+        TsValue::StringVec(vec!["1.1.1.1".into()])
+    );
+    assert_eq!(points[0].fields["moverIdIsVdm"], TsValue::Boolean(false));
+
+    let res = CifsServers::from_xml(&read("tests/vnx/cifs_server_interfaces.xml")).unwrap();
+    let points = res.into_point(Some("vnx_cifs_servers"), false);
+    assert_eq!(points.len(), 1);
+    assert_eq!(
+        points[0].tags["interfaces"],
+        TsValue::StringVec(vec!["10.1.1.5".into(), "10.1.2.5".into()])
+    );
+    assert_eq!(points[0].tags["mover"], TsValue::String("7".into()));
+    // The flags stay fields
+    assert_eq!(points[0].fields["localUsers"], TsValue::Boolean(true));
+    assert_eq!(points[0].fields["moverIdIsVdm"], TsValue::Boolean(true));
+}
+
 #[derive(Clone, Debug)]
 pub struct CifsServers {
     pub cifs_servers: Vec<CifsServer>,
@@ -423,6 +985,8 @@ impl FromXml for CifsServers {
     }
 }
 
+// localUsers and moverIdIsVdm are left as fields.  Tags would split a
+// server's series whenever a flag changes
 #[derive(Clone, Debug, Default, FromXmlAttributes, IntoPoint)]
 pub struct CifsServer {
     pub mover: String,
@@ -430,9 +994,25 @@ pub struct CifsServer {
     //pub r#type: String,
     pub localUsers: bool,
     pub moverIdIsVdm: bool,
+    /// Comma separated interface addresses
+    #[point(flatten_with = "cifs_interfaces_point")]
     pub interfaces: String,
 }
 
+/// Adapter for `#[point(flatten_with)]` that emits the comma separated
+/// interfaces as a list so they can be queried one at a time
+pub fn cifs_interfaces_point(interfaces: &str, p: &mut TsPoint) {
+    let interfaces: Vec<String> = interfaces
+        .split(',')
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .map(String::from)
+        .collect();
+    if !interfaces.is_empty() {
+        p.add_tag("interfaces", TsValue::StringVec(interfaces));
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct FileSystemCapacities {
     pub capacity: Vec<FileSystemCapacity>,
@@ -467,6 +1047,12 @@ pub struct FileSystemCapacity {
     volume_size: u64,
 }
 
+impl FileSystemCapacity {
+    /// Identifier fields and the tags `ids_as_tags` moves them to
+    pub const ID_TAGS: &'static [(&'static str, &'static str)] =
+        &[("filesystem_id", "filesystem_id"), ("volume", "volume_id")];
+}
+
 impl IntoPoint for FileSystemCapacities {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
         let capacity_points: Vec<TsPoint> = self
@@ -700,6 +1286,102 @@ fn test_mount_parser() {
     println!("points: {:#?}", points);
 }
 
+#[test]
+fn test_ids_as_tags() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let read = |path: &str| {
+        let mut s = String::new();
+        File::open(path).unwrap().read_to_string(&mut s).unwrap();
+        s
+    };
+    let mounts = Mounts::from_xml(&read("tests/vnx/mounts_query.xml")).unwrap();
+    let capacities =
+        FileSystemCapacities::from_xml(&read("tests/vnx/filesystem_capacity_query.xml")).unwrap();
+    let volume = Volume {
+        name: "d7".into(),
+        vol_type: VolumeType::Unknown,
+        size: 2047,
+        client_volumes: vec![],
+        virtual_provisioning: false,
+        volume_id: 7,
+        free_space: vec![],
+    };
+    let points = |legacy_id_fields: bool| {
+        let mut mount = mounts.into_point(None, false);
+        ids_as_tags(&mut mount, Mount::ID_TAGS, legacy_id_fields);
+        let mut capacity = capacities.into_point(None, true);
+        ids_as_tags(&mut capacity, FileSystemCapacity::ID_TAGS, legacy_id_fields);
+        let mut vol = volume.into_point(None, true);
+        ids_as_tags(&mut vol, Volume::ID_TAGS, legacy_id_fields);
+        (mount, capacity, vol)
+    };
+
+    let (mount, capacity, vol) = points(false);
+    assert_eq!(
+        mount[0].tags["filesystem_id"],
+        TsValue::String("11434".into())
+    );
+    assert_eq!(mount[0].tags["mover"], TsValue::String("7".into()));
+    assert!(!mount[0].fields.contains_key("file_system"));
+    assert!(!mount[0].fields.contains_key("mover"));
+    let fs = capacity
+        .iter()
+        .find(|p| p.tags["filesystem_id"] == TsValue::String("8161".into()))
+        .unwrap();
+    assert_eq!(fs.tags["volume_id"], TsValue::String("12399".into()));
+    assert!(!fs.fields.contains_key("filesystem_id"));
+    assert!(!fs.fields.contains_key("volume"));
+    // Measurements stay fields
+    assert!(fs.fields.contains_key("volume_size"));
+    assert_eq!(vol[0].tags["volume_id"], TsValue::String("7".into()));
+    assert!(!vol[0].fields.contains_key("volume_id"));
+    assert_eq!(vol[0].fields["size"], TsValue::Long(2047));
+
+    // Legacy mode emits both, the fields under their old names
+    let (mount, capacity, vol) = points(true);
+    assert_eq!(
+        mount[0].tags["filesystem_id"],
+        TsValue::String("11434".into())
+    );
+    assert_eq!(mount[0].fields["file_system"], TsValue::Long(11434));
+    assert_eq!(mount[0].tags["mover"], TsValue::String("7".into()));
+    assert_eq!(mount[0].fields["mover"], TsValue::Long(7));
+    let fs = capacity
+        .iter()
+        .find(|p| p.tags["filesystem_id"] == TsValue::String("8161".into()))
+        .unwrap();
+    assert_eq!(fs.fields["filesystem_id"], TsValue::Long(8161));
+    assert_eq!(fs.fields["volume"], TsValue::Long(12399));
+    assert_eq!(vol[0].tags["volume_id"], TsValue::String("7".into()));
+    assert_eq!(vol[0].fields["volume_id"], TsValue::Long(7));
+
+    // Without legacy fields no key is both a tag and a field
+    let (mount, capacity, vol) = points(false);
+    for p in mount.iter().chain(capacity.iter()).chain(vol.iter()) {
+        assert!(p.tags.keys().all(|k| !p.fields.contains_key(k)));
+    }
+
+    // Every id tag is a string, in both modes
+    for legacy_id_fields in &[false, true] {
+        let (mount, capacity, vol) = points(*legacy_id_fields);
+        for p in mount.iter().chain(capacity.iter()).chain(vol.iter()) {
+            for tag in &["filesystem_id", "mover", "volume_id"] {
+                if let Some(value) = p.tags.get(*tag) {
+                    assert!(matches!(value, TsValue::String(_)));
+                }
+            }
+        }
+    }
+
+    let config: VnxConfig = serde_json::from_str(
+        r#"{"endpoint": "10.0.0.2", "user": "nasadmin", "password": "secret", "region": "test"}"#,
+    )
+    .unwrap();
+    assert!(config.legacy_id_fields);
+}
+
 #[derive(Clone, Debug)]
 pub struct Mounts {
     pub mounts: Vec<Mount>,
@@ -725,6 +1407,12 @@ pub struct Mount {
     pub mover_is_vdm: bool,
 }
 
+impl Mount {
+    /// Identifier fields and the tags `ids_as_tags` moves them to
+    pub const ID_TAGS: &'static [(&'static str, &'static str)] =
+        &[("file_system", "filesystem_id"), ("mover", "mover")];
+}
+
 impl FromXml for Mounts {
     fn from_xml(data: &str) -> MetricsResult<Self> {
         let mut reader = Reader::from_str(data);
@@ -796,6 +1484,115 @@ impl FromXml for Mounts {
     }
 }
 
+// The mover id attribute of a MoverXxxStats element
+fn mover_attribute(e: &BytesStart<'_>, element: &str) -> MetricsResult<String> {
+    let mut mover = String::new();
+    for a in e.attributes() {
+        let item = a?;
+        match item.key {
+            b"mover" => {
+                mover = String::from_utf8_lossy(&item.value).to_string();
+            }
+            _ => {
+                debug!(
+                    "unknown xml attribute: {} for {}",
+                    String::from_utf8_lossy(item.key),
+                    element
+                );
+            }
+        }
+    }
+    Ok(mover)
+}
+
+// The time and stamp attributes of a Sample element
+fn sample_attributes(e: &BytesStart<'_>) -> MetricsResult<(u64, u64)> {
+    let mut time = 0;
+    let mut stamp = 0;
+    for a in e.attributes() {
+        let item = a?;
+        let val = String::from_utf8_lossy(&item.value);
+        match item.key {
+            b"time" => {
+                time = u64::from_str(&val)?;
+            }
+            b"stamp" => {
+                stamp = u64::from_str(&val)?;
+            }
+            _ => {
+                debug!(
+                    "unknown xml attribute: {} for Sample",
+                    String::from_utf8_lossy(item.key)
+                );
+            }
+        }
+    }
+    Ok((time, stamp))
+}
+
+// Sample times further than this from the collector's clock are bogus
+const MAX_SAMPLE_DRIFT_SECS: i64 = 86_400;
+
+/// The time of a stats sample, in seconds since the epoch of the control
+/// station, corrected by skew_secs.  None when the response had no time or
+/// it's more than a day away from the collection time
+pub fn corrected_sample_time(
+    time: u64,
+    skew_secs: Option<i64>,
+    collected: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if time == 0 {
+        return None;
+    }
+    let secs = i64::try_from(time)
+        .ok()?
+        .checked_add(skew_secs.unwrap_or(0))?;
+    if secs.checked_sub(collected.timestamp())?.unsigned_abs() > MAX_SAMPLE_DRIFT_SECS as u64 {
+        return None;
+    }
+    Utc.timestamp_opt(secs, 0).single()
+}
+
+#[test]
+fn test_corrected_sample_time() {
+    let collected = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let time = 1_577_836_800 + 130;
+    assert_eq!(
+        corrected_sample_time(time, None, collected),
+        Some(Utc.timestamp_opt(1_577_836_930, 0).unwrap())
+    );
+    // A control station two minutes fast
+    assert_eq!(
+        corrected_sample_time(time, Some(-120), collected),
+        Some(Utc.timestamp_opt(1_577_836_810, 0).unwrap())
+    );
+    // Within a day is plausible, anything further off isn't
+    assert!(corrected_sample_time(1_577_836_800 - 86_400, None, collected).is_some());
+    assert!(corrected_sample_time(1_577_836_800 - 86_401, None, collected).is_none());
+    assert!(corrected_sample_time(time, Some(86_400), collected).is_none());
+    assert!(corrected_sample_time(0, Some(1_577_836_800), collected).is_none());
+    // Malformed times are rejected instead of wrapping or overflowing
+    assert!(corrected_sample_time(u64::MAX, None, collected).is_none());
+    assert!(corrected_sample_time(i64::MAX as u64 + 1, None, collected).is_none());
+    assert!(corrected_sample_time(1, Some(i64::MIN), collected).is_none());
+}
+
+// Stats samples carry the time and tick stamp the control station took
+// them at
+trait StatsSample {
+    fn time_and_stamp(&self) -> (u64, u64);
+}
+
+// The sample of the mover element being parsed.  Counters that show up
+// before any mover element go into a sample without a mover.
+fn current_sample<T: Default>(samples: &mut Vec<T>) -> &mut T {
+    if samples.is_empty() {
+        samples.push(T::default());
+    }
+    let last = samples.len() - 1;
+    &mut samples[last]
+}
+
 #[test]
 fn test_network_all_parser() {
     use std::fs::File;
@@ -807,112 +1604,302 @@ fn test_network_all_parser() {
         f.read_to_string(&mut s).unwrap();
         s
     };
-    let res = NetworkAllSample::from_xml(&data).unwrap();
-    let points = res.into_point(None, true);
+    let res = Vec::<NetworkAllSample>::from_xml(&data).unwrap();
+    let points = res[0].into_point(None, true);
     println!("result: {:#?}", points);
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].devices.len(), 4);
 }
 
-/// All CIFS related counters
-#[derive(Clone, Debug)]
-pub struct NetworkAllSample {
-    pub mover: String,
-    pub time: u64,
-    pub stamp: u64,
-    pub ip: IpCounter,
-    pub tcp: TcpCounter,
-    pub udp: UdpCounter,
-    pub devices: Vec<DeviceCounter>,
+// The mover tag of every point
+#[cfg(test)]
+fn mover_tags(points: &[TsPoint]) -> Vec<String> {
+    points.iter().map(|p| p.tags["mover"].to_string()).collect()
 }
 
-impl IntoPoint for NetworkAllSample {
-    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
-        let mut p = TsPoint::new(name.unwrap_or("networking_usage"), true);
-        p.add_tag("mover", TsValue::String(self.mover.clone()));
-        // Turn these counters into point arrays, get the first one and merge
-        // the fields with this point
-        p.fields
-            .extend(self.ip.into_point(None, is_time_series)[0].fields.clone());
-        p.fields
-            .extend(self.tcp.into_point(None, is_time_series)[0].fields.clone());
-        p.fields
-            .extend(self.udp.into_point(None, is_time_series)[0].fields.clone());
-        for device in &self.devices {
-            p.add_tag("device", TsValue::String(device.device.clone()));
-            p.add_field(
-                format!("{}_in", device.device.clone()),
-                TsValue::Long(device._in),
-            );
-            p.add_field(format!("{}_out", device.device), TsValue::Long(device.out));
-        }
+#[test]
+fn test_network_multi_mover_parser() {
+    use std::fs::File;
+    use std::io::Read;
 
-        vec![p]
-    }
+    let data = {
+        let mut s = String::new();
+        let mut f = File::open("tests/vnx/network_stats_multi_mover_query.xml").unwrap();
+        f.read_to_string(&mut s).unwrap();
+        s
+    };
+    let res = Vec::<NetworkAllSample>::from_xml(&data).unwrap();
+    let points: Vec<TsPoint> = res.iter().flat_map(|s| s.into_point(None, true)).collect();
+    println!("result: {:#?}", points);
+    assert_eq!(mover_tags(&points), vec!["1", "2", "3"]);
+    assert_eq!(points[1].fields["connReq"], TsValue::Long(1500));
+    // Devices stay with their own mover
+    assert!(res.iter().all(|s| s.devices.len() == 4));
 }
 
-impl FromXml for NetworkAllSample {
-    fn from_xml(data: &str) -> MetricsResult<Self> {
+#[test]
+fn test_mover_interface_parser() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let data = {
+        let mut s = String::new();
+        let mut f = File::open("tests/vnx/mover_interface_query.xml").unwrap();
+        f.read_to_string(&mut s).unwrap();
+        s
+    };
+    let res = MoverInterfaces::from_xml(&data).unwrap();
+    assert_eq!(res.interfaces.len(), 3);
+    let tagged = &res.interfaces[0];
+    assert_eq!(tagged.mover, "1");
+    assert_eq!(tagged.device, "fxg-1-0");
+    assert_eq!(tagged.ip_address, "10.100.1.5");
+    assert_eq!(tagged.vlan_id, 100);
+    assert_eq!(tagged.mtu, 9000);
+    assert!(tagged.up);
+    assert_eq!(tagged.speed, "10000FD");
+    assert_eq!(res.interfaces[1].vlan_id, 0);
+    assert!(!res.interfaces[2].up);
+
+    let points = res.into_point(Some("vnx_mover_interface"), true);
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[0].tags["mover"], TsValue::String("1".into()));
+    assert_eq!(points[0].tags["device"], TsValue::String("fxg-1-0".into()));
+    assert_eq!(points[0].fields["vlan_id"], TsValue::Long(100));
+}
+
+#[test]
+fn test_tag_device_interfaces() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let read = |path: &str| {
+        let mut s = String::new();
+        File::open(path).unwrap().read_to_string(&mut s).unwrap();
+        s
+    };
+    let interfaces = MoverInterfaces::from_xml(&read("tests/vnx/mover_interface_query.xml"))
+        .unwrap()
+        .interfaces;
+    let samples =
+        Vec::<NetworkAllSample>::from_xml(&read("tests/vnx/network_stats_query.xml")).unwrap();
+    let mut points: Vec<TsPoint> = samples
+        .iter()
+        .flat_map(|s| s.into_point(None, true))
+        .collect();
+    tag_device_interfaces(&mut points, &interfaces);
+
+    let tags = &points[0].tags;
+    // The vlan tagged interface
+    assert_eq!(
+        tags["fxg-1-0_ip_address"],
+        TsValue::StringVec(vec!["10.100.1.5".into()])
+    );
+    assert_eq!(tags["fxg-1-0_vlan_id"], TsValue::LongVec(vec![100]));
+    // The untagged one only gets its address
+    assert_eq!(
+        tags["mge0_ip_address"],
+        TsValue::StringVec(vec!["192.168.10.21".into()])
+    );
+    assert!(!tags.contains_key("mge0_vlan_id"));
+    // Devices without an interface and interfaces of other movers are
+    // left out
+    assert!(!tags.contains_key("fxg-1-1_ip_address"));
+    assert!(!tags.contains_key("mge1_ip_address"));
+    assert!(tags
+        .values()
+        .all(|v| *v != TsValue::StringVec(vec!["10.100.1.6".into()])));
+}
+
+/// A network interface of a data mover
+#[derive(Clone, Debug, Default, FromXmlAttributes, IntoPoint)]
+pub struct MoverInterface {
+    pub mover: String,
+    pub name: String,
+    pub device: String,
+    #[xml(rename = "ipAddress")]
+    pub ip_address: String,
+    #[xml(rename = "netMask")]
+    pub netmask: String,
+    /// 0 when the interface isn't vlan tagged
+    #[xml(rename = "vlanid")]
+    pub vlan_id: u64,
+    pub mtu: u64,
+    pub up: bool,
+    pub speed: String,
+}
+
+impl MoverInterface {
+    /// Identifier fields and the tags `ids_as_tags` moves them to
+    pub const ID_TAGS: &'static [(&'static str, &'static str)] = &[("vlan_id", "vlan_id")];
+}
+
+#[derive(Clone, Debug)]
+pub struct MoverInterfaces {
+    pub interfaces: Vec<MoverInterface>,
+}
+
+impl IntoPoint for MoverInterfaces {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        self.interfaces
+            .iter()
+            .flat_map(|i| i.into_point(name, is_time_series))
+            .collect()
+    }
+}
+
+impl FromXml for MoverInterfaces {
+    fn from_xml(data: &str) -> MetricsResult<Self> {
+        let mut reader = Reader::from_str(data);
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+
+        let mut interfaces: Vec<MoverInterface> = Vec::new();
+
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                    if b"MoverInterface" == e.name() =>
+                {
+                    interfaces.push(MoverInterface::from_xml_attributes(e.attributes())?);
+                }
+                Ok(Event::End(_e)) => {}
+                Err(e) => {
+                    return Err(StorageError::new(format!(
+                        "invalid xml data  from server at position: {}: {:?}",
+                        reader.buffer_position(),
+                        e
+                    )));
+                }
+                Ok(Event::Eof) => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+        Ok(MoverInterfaces { interfaces })
+    }
+}
+
+/// Tag the networking_usage points with the addresses and vlans of the
+/// interfaces on each device they have traffic counters for, as
+/// `{device}_ip_address` and `{device}_vlan_id`.  Interfaces are matched
+/// on mover and device, untagged interfaces add no vlan
+pub fn tag_device_interfaces(points: &mut [TsPoint], interfaces: &[MoverInterface]) {
+    for p in points.iter_mut() {
+        let mover = match p.tags.get("mover") {
+            Some(TsValue::String(mover)) => mover.clone(),
+            _ => continue,
+        };
+        let mut by_device: HashMap<&str, (Vec<String>, Vec<u64>)> = HashMap::new();
+        for i in interfaces.iter().filter(|i| i.mover == mover) {
+            if !p.fields.contains_key(format!("{}_in", i.device).as_str()) {
+                continue;
+            }
+            let (ips, vlans) = by_device.entry(&i.device).or_default();
+            ips.push(i.ip_address.clone());
+            if i.vlan_id != 0 {
+                vlans.push(i.vlan_id);
+            }
+        }
+        for (device, (mut ips, mut vlans)) in by_device {
+            ips.sort();
+            ips.dedup();
+            vlans.sort_unstable();
+            vlans.dedup();
+            p.add_tag(format!("{}_ip_address", device), TsValue::StringVec(ips));
+            if !vlans.is_empty() {
+                p.add_tag(format!("{}_vlan_id", device), TsValue::LongVec(vlans));
+            }
+        }
+    }
+}
+
+/// All CIFS related counters
+#[derive(Clone, Debug, Default)]
+pub struct NetworkAllSample {
+    pub mover: String,
+    pub time: u64,
+    pub stamp: u64,
+    pub ip: IpCounter,
+    pub tcp: TcpCounter,
+    pub udp: UdpCounter,
+    pub devices: Vec<DeviceCounter>,
+}
+
+impl StatsSample for NetworkAllSample {
+    fn time_and_stamp(&self) -> (u64, u64) {
+        (self.time, self.stamp)
+    }
+}
+
+impl IntoPoint for NetworkAllSample {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("networking_usage"), true);
+        p.add_tag("mover", TsValue::String(self.mover.clone()));
+        // Turn these counters into point arrays, get the first one and merge
+        // the fields with this point
+        p.fields
+            .extend(self.ip.into_point(None, is_time_series)[0].fields.clone());
+        p.fields
+            .extend(self.tcp.into_point(None, is_time_series)[0].fields.clone());
+        p.fields
+            .extend(self.udp.into_point(None, is_time_series)[0].fields.clone());
+        for device in &self.devices {
+            p.add_tag("device", TsValue::String(device.device.clone()));
+            p.add_field(
+                format!("{}_in", device.device.clone()),
+                TsValue::Long(device.inbound),
+            );
+            p.add_field(format!("{}_out", device.device), TsValue::Long(device.out));
+        }
+
+        vec![p]
+    }
+}
+
+impl FromXml for Vec<NetworkAllSample> {
+    fn from_xml(data: &str) -> MetricsResult<Self> {
         let mut reader = Reader::from_str(data);
         reader.trim_text(true);
         let mut buf = Vec::new();
 
-        let mut mover = String::new();
-        let mut time = 0;
-        let mut stamp = 0;
-        let mut ip = IpCounter::default();
-        let mut tcp = TcpCounter::default();
-        let mut udp = UdpCounter::default();
-        let mut devices = Vec::new();
+        // One sample per mover element in the response
+        let mut samples: Vec<NetworkAllSample> = Vec::new();
 
         loop {
             match reader.read_event(&mut buf) {
                 Ok(Event::Start(ref e)) => {
                     if b"MoverNetStats" == e.name() {
-                        for a in e.attributes() {
-                            let item = a?;
-                            let val = String::from_utf8_lossy(&item.value);
-                            match item.key {
-                                b"mover" => {
-                                    mover = val.to_string();
-                                }
-                                _ => {
-                                    debug!(
-                                        "unknown xml attribute: {} for MoverNetStats",
-                                        String::from_utf8_lossy(item.key)
-                                    );
-                                }
-                            }
-                        }
+                        samples.push(NetworkAllSample {
+                            mover: mover_attribute(e, "MoverNetStats")?,
+                            ..Default::default()
+                        });
                     } else if b"Sample" == e.name() {
-                        for a in e.attributes() {
-                            let item = a?;
-                            let val = String::from_utf8_lossy(&item.value);
-                            match item.key {
-                                b"time" => {
-                                    time = u64::from_str(&val)?;
-                                }
-                                b"stamp" => {
-                                    stamp = u64::from_str(&val)?;
-                                }
-                                _ => {
-                                    debug!(
-                                        "unknown xml attribute: {} for Sample",
-                                        String::from_utf8_lossy(item.key)
-                                    );
-                                }
-                            }
-                        }
+                        let (time, stamp) = sample_attributes(e)?;
+                        let sample = current_sample(&mut samples);
+                        sample.time = time;
+                        sample.stamp = stamp;
                     }
                 }
-                Ok(Event::Empty(e)) => {
+                Ok(Event::Empty(ref e)) => {
                     if b"Ip" == e.name() {
-                        ip = IpCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).ip =
+                            IpCounter::from_xml_attributes(e.attributes())?;
                     } else if b"Tcp" == e.name() {
-                        tcp = TcpCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).tcp =
+                            TcpCounter::from_xml_attributes(e.attributes())?;
                     } else if b"Udp" == e.name() {
-                        udp = UdpCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).udp =
+                            UdpCounter::from_xml_attributes(e.attributes())?;
                     } else if b"DeviceTraffic" == e.name() {
                         let d = DeviceCounter::from_xml_attributes(e.attributes())?;
-                        devices.push(d);
+                        current_sample(&mut samples).devices.push(d);
+                    } else if b"MoverNetStats" == e.name() {
+                        // A mover without any counters
+                        samples.push(NetworkAllSample {
+                            mover: mover_attribute(e, "MoverNetStats")?,
+                            ..Default::default()
+                        });
                     } else {
                         debug!("Unknown empty tag: {}", String::from_utf8_lossy(e.name()));
                     }
@@ -930,15 +1917,7 @@ impl FromXml for NetworkAllSample {
             }
             buf.clear();
         }
-        Ok(NetworkAllSample {
-            mover,
-            time,
-            stamp,
-            ip,
-            tcp,
-            udp,
-            devices,
-        })
+        Ok(samples)
     }
 }
 
@@ -1009,7 +1988,9 @@ pub struct SmbCounter {
     pub trans: u64,
     pub transSec: u64,
     pub copy: u64,
-    pub _move: u64,
+    #[xml(rename = "move")]
+    #[point(rename = "_move")]
+    pub moves: u64,
     pub echo: u64,
     pub writeClose: u64,
     pub openX: u64,
@@ -1052,12 +2033,34 @@ fn test_cifs_all_parser() {
         f.read_to_string(&mut s).unwrap();
         s
     };
-    let res = CifsAllSample::from_xml(&data).unwrap();
+    let res = Vec::<CifsAllSample>::from_xml(&data).unwrap();
     println!("result: {:#?}", res);
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].mover, "1");
+}
+
+#[test]
+fn test_cifs_multi_mover_parser() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let data = {
+        let mut s = String::new();
+        let mut f = File::open("tests/vnx/cifs_stats_multi_mover_query.xml").unwrap();
+        f.read_to_string(&mut s).unwrap();
+        s
+    };
+    let res = Vec::<CifsAllSample>::from_xml(&data).unwrap();
+    let points: Vec<TsPoint> = res.iter().flat_map(|s| s.into_point(None, true)).collect();
+    println!("result: {:#?}", points);
+    assert_eq!(mover_tags(&points), vec!["1", "2", "3"]);
+    assert_eq!(points[0].fields["openConnections"], TsValue::Long(4));
+    assert_eq!(points[1].fields["openConnections"], TsValue::Long(9));
+    assert_eq!(points[2].fields["openConnections"], TsValue::Long(0));
 }
 
 /// All CIFS related counters
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct CifsAllSample {
     pub mover: String,
     pub time: u64,
@@ -1072,6 +2075,12 @@ pub struct CifsAllSample {
     pub totals: TotalsCounter,
 }
 
+impl StatsSample for CifsAllSample {
+    fn time_and_stamp(&self) -> (u64, u64) {
+        (self.time, self.stamp)
+    }
+}
+
 impl IntoPoint for CifsAllSample {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
         let mut p = TsPoint::new(name.unwrap_or("cifs_usage"), true);
@@ -1123,97 +2132,61 @@ impl IntoPoint for CifsAllSample {
     }
 }
 
-impl FromXml for CifsAllSample {
+impl FromXml for Vec<CifsAllSample> {
     fn from_xml(data: &str) -> MetricsResult<Self> {
         let mut reader = Reader::from_str(data);
         reader.trim_text(true);
         let mut buf = Vec::new();
 
-        let mut mover = String::new();
-        let mut time = 0;
-        let mut stamp = 0;
-        let mut smb_calls = SmbCounter::default();
-        let mut smb_time = SmbCounter::default();
-        let mut trans2_calls = Trans2Counter::default();
-        let mut trans2_time = Trans2Counter::default();
-        let mut nt_calls = NtCounter::default();
-        let mut nt_time = NtCounter::default();
-        let mut state = StateCounter::default();
-        let mut totals = TotalsCounter::default();
+        // One sample per mover element in the response
+        let mut samples: Vec<CifsAllSample> = Vec::new();
 
         loop {
             match reader.read_event(&mut buf) {
                 Ok(Event::Start(ref e)) => {
                     if b"MoverCifsStats" == e.name() {
-                        for a in e.attributes() {
-                            let item = a?;
-                            let val = String::from_utf8_lossy(&item.value);
-                            match item.key {
-                                b"mover" => {
-                                    mover = val.to_string();
-                                }
-                                _ => {
-                                    debug!(
-                                        "unknown xml attribute: {} for MoverCifsStats",
-                                        String::from_utf8_lossy(item.key)
-                                    );
-                                }
-                            }
-                        }
+                        samples.push(CifsAllSample {
+                            mover: mover_attribute(e, "MoverCifsStats")?,
+                            ..Default::default()
+                        });
                     } else if b"Sample" == e.name() {
-                        for a in e.attributes() {
-                            let item = a?;
-                            let val = String::from_utf8_lossy(&item.value);
-                            match item.key {
-                                b"time" => {
-                                    time = u64::from_str(&val)?;
-                                }
-                                b"stamp" => {
-                                    stamp = u64::from_str(&val)?;
-                                }
-                                _ => {
-                                    debug!(
-                                        "unknown xml attribute: {} for Sample",
-                                        String::from_utf8_lossy(item.key)
-                                    );
-                                }
-                            }
-                        }
+                        let (time, stamp) = sample_attributes(e)?;
+                        let sample = current_sample(&mut samples);
+                        sample.time = time;
+                        sample.stamp = stamp;
                     }
                 }
-                Ok(Event::Empty(e)) => {
+                Ok(Event::Empty(ref e)) => {
                     if b"SMBCalls" == e.name() {
-                        smb_calls = SmbCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).smb_calls =
+                            SmbCounter::from_xml_attributes(e.attributes())?;
                     } else if b"SMBTime" == e.name() {
-                        smb_time = SmbCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).smb_time =
+                            SmbCounter::from_xml_attributes(e.attributes())?;
                     } else if b"Trans2Calls" == e.name() {
-                        trans2_calls = Trans2Counter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).trans2_calls =
+                            Trans2Counter::from_xml_attributes(e.attributes())?;
                     } else if b"Trans2Time" == e.name() {
-                        trans2_time = Trans2Counter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).trans2_time =
+                            Trans2Counter::from_xml_attributes(e.attributes())?;
                     } else if b"NTCalls" == e.name() {
-                        nt_calls = NtCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).nt_calls =
+                            NtCounter::from_xml_attributes(e.attributes())?;
                     } else if b"NTTime" == e.name() {
-                        nt_time = NtCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).nt_time =
+                            NtCounter::from_xml_attributes(e.attributes())?;
                     } else if b"State" == e.name() {
-                        state = StateCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).state =
+                            StateCounter::from_xml_attributes(e.attributes())?;
                     } else if b"Totals" == e.name() {
-                        totals = TotalsCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).totals =
+                            TotalsCounter::from_xml_attributes(e.attributes())?;
                     } else if b"MoverCifsStats" == e.name() {
-                        for a in e.attributes() {
-                            let item = a?;
-                            let val = String::from_utf8_lossy(&item.value);
-                            match item.key {
-                                b"mover" => {
-                                    mover = val.to_string();
-                                }
-                                _ => {
-                                    debug!(
-                                        "unknown xml attribute: {} for MoverNfsStats",
-                                        String::from_utf8_lossy(item.key)
-                                    );
-                                }
-                            }
-                        }
+                        // A mover without any counters
+                        samples.push(CifsAllSample {
+                            mover: mover_attribute(e, "MoverCifsStats")?,
+                            ..Default::default()
+                        });
                     } else {
                         //warn!("Unknown empty tag: {}", String::from_utf8_lossy(e.name()));
                     }
@@ -1231,19 +2204,7 @@ impl FromXml for CifsAllSample {
             }
             buf.clear();
         }
-        Ok(CifsAllSample {
-            mover,
-            time,
-            stamp,
-            smb_calls,
-            smb_time,
-            trans2_calls,
-            trans2_time,
-            nt_calls,
-            nt_time,
-            state,
-            totals,
-        })
+        Ok(samples)
     }
 }
 
@@ -1322,12 +2283,34 @@ fn test_nfs_all_parser() {
         f.read_to_string(&mut s).unwrap();
         s
     };
-    let res = NfsAllSample::from_xml(&data).unwrap();
+    let res = Vec::<NfsAllSample>::from_xml(&data).unwrap();
     println!("result: {:#?}", res);
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].mover, "1");
+}
+
+#[test]
+fn test_nfs_multi_mover_parser() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let data = {
+        let mut s = String::new();
+        let mut f = File::open("tests/vnx/nfs_stats_multi_mover_query.xml").unwrap();
+        f.read_to_string(&mut s).unwrap();
+        s
+    };
+    let res = Vec::<NfsAllSample>::from_xml(&data).unwrap();
+    let points: Vec<TsPoint> = res.iter().flat_map(|s| s.into_point(None, true)).collect();
+    println!("result: {:#?}", points);
+    assert_eq!(mover_tags(&points), vec!["1", "2", "3"]);
+    assert_eq!(points[0].fields["badAuth"], TsValue::Long(3606));
+    assert_eq!(points[1].fields["badAuth"], TsValue::Long(12));
+    assert_eq!(points[2].fields["badAuth"], TsValue::Long(0));
 }
 
 /// All NFS related counters
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct NfsAllSample {
     pub mover: String,
     pub time: u64,
@@ -1342,6 +2325,12 @@ pub struct NfsAllSample {
     pub rpc: RpcCounter,
 }
 
+impl StatsSample for NfsAllSample {
+    fn time_and_stamp(&self) -> (u64, u64) {
+        (self.time, self.stamp)
+    }
+}
+
 impl IntoPoint for NfsAllSample {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
         let mut p = TsPoint::new(name.unwrap_or("nfs_usage"), true);
@@ -1390,97 +2379,61 @@ impl IntoPoint for NfsAllSample {
     }
 }
 
-impl FromXml for NfsAllSample {
+impl FromXml for Vec<NfsAllSample> {
     fn from_xml(data: &str) -> MetricsResult<Self> {
         let mut reader = Reader::from_str(data);
         reader.trim_text(true);
         let mut buf = Vec::new();
 
-        let mut mover = String::new();
-        let mut time = 0;
-        let mut stamp = 0;
-        let mut proc_v2_calls = NfsV2Counter::default();
-        let mut proc_v2_time = NfsV2Counter::default();
-        let mut proc_v2_failures = NfsV2Counter::default();
-        let mut proc_v3_failures = NfsV3Counter::default();
-        let mut proc_v3_calls = NfsV3Counter::default();
-        let mut proc_v3_time = NfsV3Counter::default();
-        let mut cache = CacheCounter::default();
-        let mut rpc = RpcCounter::default();
+        // One sample per mover element in the response
+        let mut samples: Vec<NfsAllSample> = Vec::new();
 
         loop {
             match reader.read_event(&mut buf) {
                 Ok(Event::Start(ref e)) => {
                     if b"MoverNfsStats" == e.name() {
-                        for a in e.attributes() {
-                            let item = a?;
-                            let val = String::from_utf8_lossy(&item.value);
-                            match item.key {
-                                b"mover" => {
-                                    mover = val.to_string();
-                                }
-                                _ => {
-                                    debug!(
-                                        "unknown xml attribute: {} for MoverNfsStats",
-                                        String::from_utf8_lossy(item.key)
-                                    );
-                                }
-                            }
-                        }
+                        samples.push(NfsAllSample {
+                            mover: mover_attribute(e, "MoverNfsStats")?,
+                            ..Default::default()
+                        });
                     } else if b"Sample" == e.name() {
-                        for a in e.attributes() {
-                            let item = a?;
-                            let val = String::from_utf8_lossy(&item.value);
-                            match item.key {
-                                b"time" => {
-                                    time = u64::from_str(&val)?;
-                                }
-                                b"stamp" => {
-                                    stamp = u64::from_str(&val)?;
-                                }
-                                _ => {
-                                    debug!(
-                                        "unknown xml attribute: {} for Sample",
-                                        String::from_utf8_lossy(item.key)
-                                    );
-                                }
-                            }
-                        }
+                        let (time, stamp) = sample_attributes(e)?;
+                        let sample = current_sample(&mut samples);
+                        sample.time = time;
+                        sample.stamp = stamp;
                     }
                 }
-                Ok(Event::Empty(e)) => {
+                Ok(Event::Empty(ref e)) => {
                     if b"ProcV2Calls" == e.name() {
-                        proc_v2_calls = NfsV2Counter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).proc_v2_calls =
+                            NfsV2Counter::from_xml_attributes(e.attributes())?;
                     } else if b"ProcV2Time" == e.name() {
-                        proc_v2_time = NfsV2Counter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).proc_v2_time =
+                            NfsV2Counter::from_xml_attributes(e.attributes())?;
                     } else if b"ProcV2Failures" == e.name() {
-                        proc_v2_failures = NfsV2Counter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).proc_v2_failures =
+                            NfsV2Counter::from_xml_attributes(e.attributes())?;
                     } else if b"ProcV3Calls" == e.name() {
-                        proc_v3_calls = NfsV3Counter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).proc_v3_calls =
+                            NfsV3Counter::from_xml_attributes(e.attributes())?;
                     } else if b"ProcV3Time" == e.name() {
-                        proc_v3_time = NfsV3Counter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).proc_v3_time =
+                            NfsV3Counter::from_xml_attributes(e.attributes())?;
                     } else if b"ProcV3Failures" == e.name() {
-                        proc_v3_failures = NfsV3Counter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).proc_v3_failures =
+                            NfsV3Counter::from_xml_attributes(e.attributes())?;
                     } else if b"Cache" == e.name() {
-                        cache = CacheCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).cache =
+                            CacheCounter::from_xml_attributes(e.attributes())?;
                     } else if b"Rpc" == e.name() {
-                        rpc = RpcCounter::from_xml_attributes(e.attributes())?;
+                        current_sample(&mut samples).rpc =
+                            RpcCounter::from_xml_attributes(e.attributes())?;
                     } else if b"MoverNfsStats" == e.name() {
-                        for a in e.attributes() {
-                            let item = a?;
-                            let val = String::from_utf8_lossy(&item.value);
-                            match item.key {
-                                b"mover" => {
-                                    mover = val.to_string();
-                                }
-                                _ => {
-                                    debug!(
-                                        "unknown xml attribute: {} for MoverNfsStats",
-                                        String::from_utf8_lossy(item.key)
-                                    );
-                                }
-                            }
-                        }
+                        // A mover without any counters
+                        samples.push(NfsAllSample {
+                            mover: mover_attribute(e, "MoverNfsStats")?,
+                            ..Default::default()
+                        });
                     } else {
                         //warn!("Unknown empty tag: {}", String::from_utf8_lossy(e.name()));
                     }
@@ -1498,19 +2451,7 @@ impl FromXml for NfsAllSample {
             }
             buf.clear();
         }
-        Ok(NfsAllSample {
-            mover,
-            time,
-            stamp,
-            proc_v2_calls,
-            proc_v2_time,
-            proc_v2_failures,
-            proc_v3_calls,
-            proc_v3_time,
-            proc_v3_failures,
-            cache,
-            rpc,
-        })
+        Ok(samples)
     }
 }
 
@@ -1534,18 +2475,30 @@ pub struct DiskInfo {
     pub disks: Vec<Disk>,
 }
 
+// Some arrays report capacity with a unit suffix.  capacity and
+// usedCapacity count 512 byte blocks so suffixed values are converted to
+// blocks
 #[derive(Clone, Debug, FromXmlAttributes, IntoPoint)]
+#[xml(lenient_numbers)]
 pub struct Disk {
     pub bus: u64,
+    #[xml(rename = "enclosureNumber")]
     pub enclosure_number: u64,
+    #[xml(rename = "diskNumber")]
     pub disk_number: u64,
     pub state: String,
+    #[xml(rename = "vendorId")]
     pub vendor_id: String,
+    #[xml(rename = "productId")]
     pub product_id: String,
     pub revision: String,
+    #[xml(rename = "serialNumber")]
     pub serial_number: String,
+    #[xml(unit = 512)]
     pub capacity: u64,
+    #[xml(rename = "usedCapacity", unit = 512)]
     pub used_capacity: u64,
+    #[xml(rename = "remappedBlocks")]
     pub remapped_blocks: u64,
     pub storage: String,
     pub name: String,
@@ -1584,15 +2537,466 @@ impl FromXml for DiskInfo {
     }
 }
 
-impl IntoPoint for DiskInfo {
-    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
-        let mut points: Vec<TsPoint> = Vec::new();
-        for disk in &self.disks {
-            points.extend(disk.into_point(name, is_time_series));
-        }
+/// Disk states that don't need an operator's attention.  Anything else
+/// (removed, failed, faulted, rebuilding, equalizing, powering up, ...) is
+/// reported as unhealthy.
+pub const HEALTHY_DISK_STATES: &[&str] = &["enabled", "ready", "unbound", "hot spare ready"];
+
+// The control station reports this for disks that don't track remapped
+// blocks, unbound disks mostly
+const REMAPPED_BLOCKS_UNKNOWN: u64 = u64::MAX;
+
+impl Disk {
+    /// Whether the state is one of HEALTHY_DISK_STATES, ignoring case
+    pub fn is_healthy(&self) -> bool {
+        let state = self.state.trim();
+        HEALTHY_DISK_STATES
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(state))
+    }
+
+    /// The remapped block count or None if the disk doesn't report one
+    pub fn remapped_blocks(&self) -> Option<u64> {
+        if self.remapped_blocks == REMAPPED_BLOCKS_UNKNOWN {
+            None
+        } else {
+            Some(self.remapped_blocks)
+        }
+    }
+}
+
+impl DiskInfo {
+    /// Same as into_point but also adds a remapped_blocks_delta field to
+    /// every disk that was in the previous sample.  A growing remapped block
+    /// count is usually the first sign of a failing disk.  A disk that was
+    /// swapped out between samples starts over at 0 so the delta is
+    /// clamped at 0 rather than going negative.
+    pub fn into_point_since(
+        &self,
+        previous: Option<&DiskInfo>,
+        name: Option<&str>,
+        is_time_series: bool,
+    ) -> Vec<TsPoint> {
+        let mut points: Vec<TsPoint> = Vec::new();
+        for disk in &self.disks {
+            let previous_blocks = previous
+                .and_then(|p| {
+                    p.disks
+                        .iter()
+                        .find(|d| d.storage == disk.storage && d.name == disk.name)
+                })
+                .and_then(|d| d.remapped_blocks());
+            for mut p in disk.into_point(name, is_time_series) {
+                p.add_field("is_healthy", TsValue::Boolean(disk.is_healthy()));
+                if let (Some(current), Some(before)) = (disk.remapped_blocks(), previous_blocks) {
+                    p.add_field(
+                        "remapped_blocks_delta",
+                        TsValue::Long(current.saturating_sub(before)),
+                    );
+                }
+                points.push(p);
+            }
+        }
+
+        points
+    }
+}
+
+impl IntoPoint for DiskInfo {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        self.into_point_since(None, name, is_time_series)
+    }
+}
+
+#[test]
+fn test_disk_health() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let data = {
+        let mut s = String::new();
+        let mut f = File::open("tests/vnx/clariion_disk_health_query.xml").unwrap();
+        f.read_to_string(&mut s).unwrap();
+        s
+    };
+    let res = DiskInfo::from_xml(&data).unwrap();
+    let healthy: Vec<&str> = res
+        .disks
+        .iter()
+        .filter(|d| d.is_healthy())
+        .map(|d| d.name.as_str())
+        .collect();
+    assert_eq!(healthy, vec!["0_0_0", "0_0_1", "0_0_2"]);
+    assert_eq!(res.disks[2].remapped_blocks(), None);
+    assert_eq!(res.disks[3].disk_number, 3);
+    assert_eq!(res.disks[3].serial_number, "KWG4JR9J");
+
+    let points = res.into_point(Some("vnx_disk_info"), true);
+    assert_eq!(points.len(), 5);
+    assert_eq!(points[0].fields["is_healthy"], TsValue::Boolean(true));
+    assert_eq!(points[3].fields["is_healthy"], TsValue::Boolean(false));
+    assert_eq!(points[4].fields["is_healthy"], TsValue::Boolean(false));
+    assert!(points
+        .iter()
+        .all(|p| !p.fields.contains_key("remapped_blocks_delta")));
+
+    // An hour earlier the failed disk had fewer remapped blocks and 0_0_1
+    // was a different disk with more of them
+    let mut previous = res.clone();
+    previous.disks[1].remapped_blocks = 50;
+    previous.disks[3].remapped_blocks = 12;
+    previous.disks.remove(4);
+    let points = res.into_point_since(Some(&previous), Some("vnx_disk_info"), true);
+    println!("points: {:#?}", points);
+    assert_eq!(points[0].fields["remapped_blocks_delta"], TsValue::Long(0));
+    assert_eq!(points[1].fields["remapped_blocks_delta"], TsValue::Long(0));
+    assert!(!points[2].fields.contains_key("remapped_blocks_delta"));
+    assert_eq!(
+        points[3].fields["remapped_blocks_delta"],
+        TsValue::Long(196)
+    );
+    assert!(!points[4].fields.contains_key("remapped_blocks_delta"));
+}
+
+#[test]
+fn test_disk_lenient_numbers() {
+    let data = r#"<ResponsePacket>
+        <ResponseEx>
+            <ClariionDiskConfig bus="0" enclosureNumber="1" diskNumber="12" state="enabled" serialNumber="KSJY9LHJ" capacity="536 GB" usedCapacity="1,125,767,168" remappedBlocks="3" storage="1" name="0_1_12"/>
+        </ResponseEx>
+    </ResponsePacket>"#;
+    let res = DiskInfo::from_xml(data).unwrap();
+    assert_eq!(res.disks[0].disk_number, 12);
+    // 536 GB in 512 byte blocks, the unit of an unsuffixed capacity
+    assert_eq!(res.disks[0].capacity, (536 << 30) / 512);
+    assert_eq!(res.disks[0].used_capacity, 1_125_767_168);
+
+    let err = DiskInfo::from_xml(&data.replace("536 GB", "536 zettabytes")).unwrap_err();
+    assert!(err.to_string().contains("capacity"), "{}", err);
+
+    // Structs without #[xml(lenient_numbers)] stay strict
+    let data = r#"<ResponsePacket>
+        <ResponseEx>
+            <MoverInterface mover="1" name="mge0-mgmt" device="mge0" mtu="1,500" up="true"/>
+        </ResponseEx>
+    </ResponsePacket>"#;
+    assert!(MoverInterfaces::from_xml(data).is_err());
+}
+
+/// Measurement of the DiskStateTracker events
+pub const DISK_STATE_CHANGE_MEASUREMENT: &str = "vnx_disk_state_change";
+
+// Bump when TrackedDisks changes shape so old saved state is ignored
+const DISK_STATE_VERSION: u32 = 1;
+const DISK_STATE_KEY: &str = "disk_states";
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct TrackedDisk {
+    state: String,
+    bus: u64,
+    enclosure_number: u64,
+    disk_number: u64,
+    // Polls in a row the disk has been missing from
+    missed: u32,
+}
+
+// Keyed by serial number
+type TrackedDisks = BTreeMap<String, TrackedDisk>;
+
+/// Turns successive DiskInfo polls into vnx_disk_state_change points so
+/// operators see a disk going from Enabled to Removed once instead of a
+/// stream of identical states.  Each point has previous_state and new_state
+/// fields, bus, enclosure_number, disk_number and serial_number tags and an
+/// event tag of:
+///
+/// * state_change: the disk reports a different state than last poll
+/// * first_seen: a serial that wasn't there before, ie a replacement disk.
+///   Not raised for the disks found by the very first poll.
+/// * disappeared: the serial was missing from two polls in a row.  A disk
+///   missing from a single poll and then back is treated as a flap and
+///   ignored.
+///
+/// With a StateStore the states are saved after every update so a restart
+/// carries on from the last poll rather than starting over.
+pub struct DiskStateTracker {
+    disks: Option<TrackedDisks>,
+    store: Option<(Arc<dyn StateStore>, String)>,
+}
+
+impl Default for DiskStateTracker {
+    fn default() -> Self {
+        DiskStateTracker::new()
+    }
+}
+
+impl DiskStateTracker {
+    /// A tracker that only remembers states for the life of the process
+    pub fn new() -> Self {
+        DiskStateTracker {
+            disks: None,
+            store: None,
+        }
+    }
+
+    /// A tracker that loads and saves its states in `store` under
+    /// `namespace`, ie one namespace per array
+    pub fn with_store(store: Arc<dyn StateStore>, namespace: &str) -> MetricsResult<Self> {
+        let disks = store.get::<TrackedDisks>(namespace, DISK_STATE_KEY, DISK_STATE_VERSION)?;
+        Ok(DiskStateTracker {
+            disks,
+            store: Some((store, namespace.to_string())),
+        })
+    }
+
+    /// Compare a poll against the previous one and return the events
+    pub fn update(&mut self, info: &DiskInfo, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut points: Vec<TsPoint> = Vec::new();
+        let baseline = self.disks.is_none();
+        let previous = self.disks.take().unwrap_or_default();
+        let mut current = TrackedDisks::new();
+
+        for disk in &info.disks {
+            // Empty slots and some failed disks come back without a serial
+            let serial = if disk.serial_number.is_empty() {
+                &disk.name
+            } else {
+                &disk.serial_number
+            };
+            let tracked = TrackedDisk {
+                state: disk.state.clone(),
+                bus: disk.bus,
+                enclosure_number: disk.enclosure_number,
+                disk_number: disk.disk_number,
+                missed: 0,
+            };
+            match previous.get(serial) {
+                Some(before) if before.state != tracked.state => {
+                    points.push(disk_state_point(
+                        "state_change",
+                        serial,
+                        &tracked,
+                        Some(&before.state),
+                        Some(&tracked.state),
+                        t,
+                    ));
+                }
+                Some(_) => {}
+                None if !baseline => {
+                    points.push(disk_state_point(
+                        "first_seen",
+                        serial,
+                        &tracked,
+                        None,
+                        Some(&tracked.state),
+                        t,
+                    ));
+                }
+                None => {}
+            }
+            current.insert(serial.clone(), tracked);
+        }
+
+        for (serial, mut before) in previous {
+            if current.contains_key(&serial) {
+                continue;
+            }
+            before.missed += 1;
+            if before.missed >= 2 {
+                points.push(disk_state_point(
+                    "disappeared",
+                    &serial,
+                    &before,
+                    Some(&before.state),
+                    None,
+                    t,
+                ));
+            } else {
+                current.insert(serial, before);
+            }
+        }
+
+        if let Some((store, namespace)) = &self.store {
+            store.put(namespace, DISK_STATE_KEY, DISK_STATE_VERSION, &current)?;
+        }
+        self.disks = Some(current);
+
+        Ok(points)
+    }
+}
+
+fn disk_state_point(
+    event: &str,
+    serial: &str,
+    disk: &TrackedDisk,
+    previous_state: Option<&str>,
+    new_state: Option<&str>,
+    t: DateTime<Utc>,
+) -> TsPoint {
+    let mut p = TsPoint::new(DISK_STATE_CHANGE_MEASUREMENT, true).set_time(t);
+    p.add_tag("event", TsValue::String(event.to_string()));
+    p.add_tag("bus", TsValue::String(disk.bus.to_string()));
+    p.add_tag(
+        "enclosure_number",
+        TsValue::String(disk.enclosure_number.to_string()),
+    );
+    p.add_tag("disk_number", TsValue::String(disk.disk_number.to_string()));
+    p.add_tag("serial_number", TsValue::String(serial.to_string()));
+    if let Some(state) = previous_state {
+        p.add_field("previous_state", TsValue::String(state.to_string()));
+    }
+    if let Some(state) = new_state {
+        p.add_field("new_state", TsValue::String(state.to_string()));
+    }
+    p
+}
+
+#[cfg(test)]
+fn test_disk(slot: u64, serial: &str, state: &str) -> Disk {
+    Disk {
+        bus: 0,
+        enclosure_number: 0,
+        disk_number: slot,
+        state: state.to_string(),
+        vendor_id: "SEAGATE".to_string(),
+        product_id: "ST3600057SS".to_string(),
+        revision: "ES66".to_string(),
+        serial_number: serial.to_string(),
+        capacity: 549_691,
+        used_capacity: 0,
+        remapped_blocks: 0,
+        storage: "APM00140741384".to_string(),
+        name: format!("0_0_{}", slot),
+    }
+}
+
+#[cfg(test)]
+fn disk_events(points: &[TsPoint]) -> Vec<(String, String, Option<String>, Option<String>)> {
+    let text = |v: Option<&TsValue>| match v {
+        Some(TsValue::String(s)) => Some(s.clone()),
+        _ => None,
+    };
+    points
+        .iter()
+        .map(|p| {
+            (
+                text(p.tags.get("event")).unwrap(),
+                text(p.tags.get("serial_number")).unwrap(),
+                text(p.fields.get("previous_state")),
+                text(p.fields.get("new_state")),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_disk_state_tracker() {
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let mut tracker = DiskStateTracker::new();
+    let event = |e: &str, serial: &str, before: Option<&str>, after: Option<&str>| {
+        (
+            e.to_string(),
+            serial.to_string(),
+            before.map(String::from),
+            after.map(String::from),
+        )
+    };
 
-        points
-    }
+    // The first poll is the baseline
+    let poll = DiskInfo {
+        disks: vec![
+            test_disk(0, "SERIAL0", "Enabled"),
+            test_disk(1, "SERIAL1", "Enabled"),
+            test_disk(2, "SERIAL2", "Enabled"),
+        ],
+    };
+    assert!(tracker.update(&poll, t).unwrap().is_empty());
+
+    // Disk 0 fails and disk 2 drops out of a single poll
+    let poll = DiskInfo {
+        disks: vec![
+            test_disk(0, "SERIAL0", "Removed"),
+            test_disk(1, "SERIAL1", "Enabled"),
+        ],
+    };
+    let points = tracker.update(&poll, t).unwrap();
+    assert_eq!(
+        disk_events(&points),
+        vec![event(
+            "state_change",
+            "SERIAL0",
+            Some("Enabled"),
+            Some("Removed")
+        )]
+    );
+    assert_eq!(points[0].tags["bus"], TsValue::String("0".into()));
+    assert_eq!(points[0].tags["disk_number"], TsValue::String("0".into()));
+    assert_eq!(points[0].timestamp, Some(t));
+
+    // Disk 0 is swapped for a new one that's binding and disk 2 is back
+    let poll = DiskInfo {
+        disks: vec![
+            test_disk(0, "SERIAL9", "Binding"),
+            test_disk(1, "SERIAL1", "Enabled"),
+            test_disk(2, "SERIAL2", "Enabled"),
+        ],
+    };
+    assert_eq!(
+        disk_events(&tracker.update(&poll, t).unwrap()),
+        vec![event("first_seen", "SERIAL9", None, Some("Binding"))]
+    );
+
+    // The failed disk has now been gone twice in a row
+    let poll = DiskInfo {
+        disks: vec![
+            test_disk(0, "SERIAL9", "Enabled"),
+            test_disk(1, "SERIAL1", "Enabled"),
+            test_disk(2, "SERIAL2", "Enabled"),
+        ],
+    };
+    assert_eq!(
+        disk_events(&tracker.update(&poll, t).unwrap()),
+        vec![
+            event("state_change", "SERIAL9", Some("Binding"), Some("Enabled")),
+            event("disappeared", "SERIAL0", Some("Removed"), None),
+        ]
+    );
+    assert!(tracker.update(&poll, t).unwrap().is_empty());
+}
+
+#[test]
+fn test_disk_state_tracker_restart() {
+    use crate::state::MemoryStateStore;
+
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let store: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+    let poll = DiskInfo {
+        disks: vec![
+            test_disk(0, "SERIAL0", "Enabled"),
+            test_disk(1, "SERIAL1", "Enabled"),
+        ],
+    };
+    let mut tracker = DiskStateTracker::with_store(Arc::clone(&store), "vnx1").unwrap();
+    assert!(tracker.update(&poll, t).unwrap().is_empty());
+
+    // A restarted tracker doesn't treat every disk as new and still sees
+    // the change
+    let poll = DiskInfo {
+        disks: vec![
+            test_disk(0, "SERIAL0", "Enabled"),
+            test_disk(1, "SERIAL1", "Hot Spare Ready"),
+        ],
+    };
+    let mut tracker = DiskStateTracker::with_store(Arc::clone(&store), "vnx1").unwrap();
+    let points = tracker.update(&poll, t).unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(
+        points[0].fields["new_state"],
+        TsValue::String("Hot Spare Ready".into())
+    );
+
+    // Another array's namespace starts with a fresh baseline
+    let mut tracker = DiskStateTracker::with_store(store, "vnx2").unwrap();
+    assert!(tracker.update(&poll, t).unwrap().is_empty());
 }
 
 #[test]
@@ -1606,11 +3010,31 @@ fn test_resources_all_parser() {
         f.read_to_string(&mut s).unwrap();
         s
     };
-    let res = ResourceUsageSample::from_xml(&data).unwrap();
+    let res = Vec::<ResourceUsageSample>::from_xml(&data).unwrap();
     println!("result: {:#?}", res);
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].time, 1520353386);
 }
 
-#[derive(Clone, Debug)]
+#[test]
+fn test_resources_multi_mover_parser() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let data = {
+        let mut s = String::new();
+        let mut f = File::open("tests/vnx/resource_stats_multi_mover_query.xml").unwrap();
+        f.read_to_string(&mut s).unwrap();
+        s
+    };
+    let res = Vec::<ResourceUsageSample>::from_xml(&data).unwrap();
+    let points: Vec<TsPoint> = res.iter().flat_map(|s| s.into_point(None, true)).collect();
+    println!("result: {:#?}", points);
+    assert_eq!(mover_tags(&points), vec!["1", "2", "3"]);
+    assert_eq!(points[2].fields["cpu"], TsValue::Float(97.25));
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct ResourceUsageSample {
     pub mover: String,
     pub cpu: f64,
@@ -1619,6 +3043,12 @@ pub struct ResourceUsageSample {
     pub stamp: u64,
 }
 
+impl StatsSample for ResourceUsageSample {
+    fn time_and_stamp(&self) -> (u64, u64) {
+        (self.time, self.stamp)
+    }
+}
+
 impl IntoPoint for ResourceUsageSample {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
         let mut p = TsPoint::new(name.unwrap_or("resource_usage"), is_time_series);
@@ -1630,55 +3060,43 @@ impl IntoPoint for ResourceUsageSample {
     }
 }
 
-impl FromXml for ResourceUsageSample {
+impl FromXml for Vec<ResourceUsageSample> {
     fn from_xml(data: &str) -> MetricsResult<Self> {
         let mut reader = Reader::from_str(data);
         reader.trim_text(true);
         let mut buf = Vec::new();
 
-        let mut mover = String::new();
-        let mut cpu = 0.0;
-        let mut mem = 0.0;
-        let mut time = 0;
-        let mut stamp = 0;
+        // One sample per mover element in the response
+        let mut samples: Vec<ResourceUsageSample> = Vec::new();
+
         loop {
             match reader.read_event(&mut buf) {
                 Ok(Event::Start(ref e)) => {
                     if b"MoverResourceUsage" == e.name() {
-                        for a in e.attributes() {
-                            let item = a?;
-                            let val = String::from_utf8_lossy(&item.value);
-                            match item.key {
-                                b"mover" => {
-                                    mover = val.to_string();
-                                }
-                                _ => {
-                                    debug!(
-                                        "unknown xml attribute: {} for MoverResourceUsage",
-                                        String::from_utf8_lossy(item.key)
-                                    );
-                                }
-                            }
-                        }
+                        samples.push(ResourceUsageSample {
+                            mover: mover_attribute(e, "MoverResourceUsage")?,
+                            ..Default::default()
+                        });
                     }
                 }
-                Ok(Event::Empty(e)) => {
+                Ok(Event::Empty(ref e)) => {
                     if b"Sample" == e.name() {
+                        let sample = current_sample(&mut samples);
                         for a in e.attributes() {
                             let item = a?;
                             let val = String::from_utf8_lossy(&item.value);
                             match item.key {
                                 b"cpu" => {
-                                    cpu = f64::from_str(&val)?;
+                                    sample.cpu = f64::from_str(&val)?;
                                 }
                                 b"mem" => {
-                                    mem = f64::from_str(&val)?;
+                                    sample.mem = f64::from_str(&val)?;
                                 }
                                 b"time" => {
-                                    time = u64::from_str(&val)?;
+                                    sample.time = u64::from_str(&val)?;
                                 }
                                 b"stamp" => {
-                                    stamp = u64::from_str(&val)?;
+                                    sample.stamp = u64::from_str(&val)?;
                                 }
                                 _ => {
                                     debug!(
@@ -1705,13 +3123,7 @@ impl FromXml for ResourceUsageSample {
             }
             buf.clear();
         }
-        Ok(ResourceUsageSample {
-            mover,
-            cpu,
-            mem,
-            time,
-            stamp,
-        })
+        Ok(samples)
     }
 }
 
@@ -1836,7 +3248,7 @@ impl FromXml for FilesystemUsage {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct DiskVolume {
     pub storage_system_id: u64,
     pub lun: String,
@@ -1851,25 +3263,25 @@ pub struct FreeSpace {
     offset: u64,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct MetaVolume {
     member_volumes: Vec<String>,
     client_file_systems: Vec<String>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct PoolVolume {
     pub client_file_systems: Vec<String>,
     pub member_volumes: Vec<String>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct SliceVolume {
     sliced_volume: String,
     offset: u64,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct StripeVolume {
     striped_volumes: Vec<String>,
     stripe_size: u16,
@@ -1886,6 +3298,11 @@ pub struct Volume {
     pub free_space: Vec<FreeSpace>,
 }
 
+impl Volume {
+    /// Identifier fields and the tags `ids_as_tags` moves them to
+    pub const ID_TAGS: &'static [(&'static str, &'static str)] = &[("volume_id", "volume_id")];
+}
+
 impl IntoPoint for Volume {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
         let mut p = TsPoint::new(name.unwrap_or("volume"), is_time_series);
@@ -1899,7 +3316,7 @@ impl IntoPoint for Volume {
                 p.add_tag("disk_type", TsValue::String(v.disk_type.to_string()));
                 // TODO: Should we add the movers?
                 for (key, value) in &v.data_service_policies {
-                    p.add_tag(key, TsValue::String(value.clone()));
+                    p.add_tag(key.clone(), TsValue::String(value.clone()));
                 }
             }
             VolumeType::Meta(ref _v) => {
@@ -1966,6 +3383,7 @@ impl FromXml for Volumes {
 
         let mut name = String::new();
         let mut vol_type = VolumeType::Unknown;
+        let mut declared_type = VolumeType::Unknown;
         let mut size: u64 = 0;
         let mut client_volumes = Vec::new();
         let mut virtual_provisioning = false;
@@ -1979,6 +3397,7 @@ impl FromXml for Volumes {
                         // Clear the placeholders
                         name = String::new();
                         vol_type = VolumeType::Unknown;
+                        declared_type = VolumeType::Unknown;
                         size = 0;
                         client_volumes = Vec::new();
                         virtual_provisioning = false;
@@ -1993,7 +3412,7 @@ impl FromXml for Volumes {
                                     name = val.to_string();
                                 }
                                 b"type" => {
-                                    //VolumeType::from_str(val).map_err(|e| e.to_string())?;
+                                    declared_type = VolumeType::from(val.as_ref());
                                 }
                                 b"size" => {
                                     size = u64::from_str(&val)?;
@@ -2211,7 +3630,11 @@ impl FromXml for Volumes {
                     if b"Volume" == e.name() {
                         volumes.push(Volume {
                             name: name.clone(),
-                            vol_type: vol_type.clone(),
+                            vol_type: reconcile_volume_type(
+                                &name,
+                                vol_type.clone(),
+                                declared_type.clone(),
+                            ),
                             size,
                             client_volumes: client_volumes.clone(),
                             virtual_provisioning,
@@ -2250,7 +3673,14 @@ fn test_storage_pool_query_parser() {
     };
     let res = StoragePools::from_xml(&data).unwrap();
     println!("result: {:#?}", res);
-    let _ = res.into_point(None, true);
+    let points = res.into_point(None, true);
+    assert!(points
+        .iter()
+        .all(|p| p.fields.contains_key("used_percent") && p.fields.contains_key("free_size")));
+    assert_eq!(
+        points[0].fields["serial_number"],
+        TsValue::String("APM00123701049".into())
+    );
 }
 
 #[derive(Debug)]
@@ -2276,31 +3706,112 @@ pub struct StoragePool {
     pub is_homogeneous: bool,
 }
 
+// VNX and CLARiiON serials are 3 letters followed by 11 digits, ie
+// APM00121300890 or FNM00151200123
+fn is_array_serial(token: &str) -> bool {
+    token.len() == 14
+        && token[..3].chars().all(|c| c.is_ascii_uppercase())
+        && token[3..].chars().all(|c| c.is_ascii_digit())
+}
+
+impl StoragePool {
+    /// The serial number of the backing array, picked out of descriptions
+    /// like "Mapped Pool Pool 0 on APM00121300890"
+    pub fn serial_number(&self) -> Option<&str> {
+        self.description
+            .split_whitespace()
+            .map(|token| token.trim_matches(|c: char| !c.is_ascii_alphanumeric()))
+            .find(|token| is_array_serial(token))
+    }
+
+    pub fn free_size(&self) -> u64 {
+        self.size.saturating_sub(self.used_size)
+    }
+
+    /// used_size as a percentage of size, None for an empty pool
+    pub fn used_percent(&self) -> Option<f64> {
+        if self.size == 0 {
+            None
+        } else {
+            Some(self.used_size as f64 / self.size as f64 * 100.0)
+        }
+    }
+}
+
+#[test]
+fn test_storage_pool_serial_number() {
+    let pool = |description: &str| StoragePool {
+        description: description.into(),
+        ..Default::default()
+    };
+    assert_eq!(
+        pool("Mapped Pool Pool 0 on APM00121300890").serial_number(),
+        Some("APM00121300890")
+    );
+    assert_eq!(
+        pool("Mapped Pool FAST_VP on FNM00151200123.").serial_number(),
+        Some("FNM00151200123")
+    );
+    assert_eq!(
+        pool("APM00121300890 (tier 1 pool)").serial_number(),
+        Some("APM00121300890")
+    );
+    assert_eq!(pool("Mapped Pool on APM0012").serial_number(), None);
+    assert_eq!(pool("Mapped Pool on ARRAY01").serial_number(), None);
+    assert_eq!(pool("").serial_number(), None);
+}
+
+#[test]
+fn test_storage_pool_used_percent() {
+    let pool = StoragePool {
+        size: 214957916,
+        used_size: 93945439,
+        ..Default::default()
+    };
+    assert_eq!(pool.free_size(), 121012477);
+    let used = pool.used_percent().unwrap();
+    assert!((used - 43.7041).abs() < 0.0001, "{}", used);
+    let p = &pool.into_point(None, true)[0];
+    assert_eq!(p.fields["used_percent"], TsValue::Float(used));
+    assert_eq!(p.fields["free_size"], TsValue::Long(121012477));
+    assert_eq!(p.fields["serial_number"], TsValue::String("Unknown".into()));
+
+    let full = StoragePool {
+        size: 100,
+        used_size: 100,
+        ..Default::default()
+    };
+    assert_eq!(full.used_percent(), Some(100.0));
+    assert_eq!(full.free_size(), 0);
+
+    let empty = StoragePool::default();
+    assert_eq!(empty.used_percent(), None);
+    let p = &empty.into_point(None, true)[0];
+    assert!(!p.fields.contains_key("used_percent"));
+    assert_eq!(p.fields["free_size"], TsValue::Long(0));
+}
+
 impl IntoPoint for StoragePool {
     fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
         let mut p = TsPoint::new(name.unwrap_or("pool"), is_time_series);
         p.add_tag("pool", TsValue::String(self.pool.clone()));
-        /* get serial number from description. Assuming this is last token */
-        let mut serial_number = self
-            .description
-            .split_whitespace()
-            .last()
-            .unwrap_or("Unknown")
-            .to_string();
-        /* Assuming starts with APM always */
-        if !serial_number.starts_with("APM") {
-            serial_number = "Unknown".to_string();
-        }
         p.add_field("name", TsValue::String(self.name.clone()));
-        p.add_field("serial_number", TsValue::String(serial_number));
+        p.add_field(
+            "serial_number",
+            TsValue::String(self.serial_number().unwrap_or("Unknown").to_string()),
+        );
         p.add_field("size", TsValue::Long(self.size));
         p.add_field("used_size", TsValue::Long(self.used_size));
+        p.add_field("free_size", TsValue::Long(self.free_size()));
+        if let Some(used_percent) = self.used_percent() {
+            p.add_field("used_percent", TsValue::Float(used_percent));
+        }
         p.add_field("auto_size", TsValue::Long(self.auto_size));
         p.add_field("stripe_count", TsValue::Short(self.stripe_count));
         p.add_field("stripe_size", TsValue::Short(self.stripe_size));
 
         for (key, value) in &self.data_service_policies {
-            p.add_tag(key, TsValue::String(value.clone()));
+            p.add_tag(key.clone(), TsValue::String(value.clone()));
         }
         vec![p]
     }
@@ -2444,16 +3955,22 @@ fn login_request(
     params.insert("password", config.password.clone());
     params.insert("Login", "Login".into());
 
+    let url = format!("https://{}/Login", config.endpoint);
     let s = client
-        .post(&format!("https://{}/Login", config.endpoint))
+        .post(&url)
         .form(&params)
-        .send()?
-        .error_for_status()?;
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| {
+            StorageError::from(e.without_url())
+                .with_context(format!("POST {}", redact_url(&url)))
+                .with_context("vnx")
+        })?;
 
     // From here we should get back a cookie
     match s.headers().get(SET_COOKIE) {
         Some(cookie) => {
-            debug!("cookie: {:?}", cookie);
+            debug!("{} cookie: {:?}", config.log_prefix(), cookie);
             let parsed = Cookie::parse(cookie.to_str()?.to_owned())?;
             cookie_jar.add(parsed);
             Ok(())
@@ -2464,32 +3981,563 @@ fn login_request(
     }
 }
 
+#[cfg(test)]
+// Requests received by a mock server, in order
+type RecordedBodies = std::sync::Arc<std::sync::Mutex<Vec<String>>>;
+
+#[cfg(test)]
+// Spawn a tls server on localhost that answers every request with an
+// empty 200 OK.  Returns the port and a count of requests served
+fn mock_vnx_server() -> (u16, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    let (port, requests, _) = mock_vnx_api(|_| vec![]);
+    (port, requests)
+}
+
+#[cfg(test)]
+// Spawn a tls server on localhost that answers every request with a 200 OK
+// and whatever respond returns for the request body.  Returns the port, a
+// count of requests served and the request bodies
+fn mock_vnx_api<F>(
+    respond: F,
+) -> (
+    u16,
+    std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    RecordedBodies,
+)
+where
+    F: Fn(&str) -> Vec<u8> + Send + 'static,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let served = requests.clone();
+    let bodies: RecordedBodies = Arc::new(Mutex::new(Vec::new()));
+    let recorded = bodies.clone();
+    let port = crate::mock_https_server(move |req| {
+        let response = respond(&req.body);
+        recorded.lock().unwrap().push(req.body.clone());
+        served.fetch_add(1, Ordering::SeqCst);
+        (200, String::new(), response)
+    });
+    (port, requests, bodies)
+}
+
+#[cfg(test)]
+fn mock_vnx(port: u16) -> Vnx {
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let mut cookie_jar = CookieJar::new();
+    cookie_jar.add(Cookie::new("Ticket", "mock-ticket"));
+    cookie_jar.add(Cookie::new("JSESSIONID", "mock-session"));
+    Vnx {
+        client,
+        config: VnxConfig {
+            endpoint: format!("127.0.0.1:{}", port),
+            user: "admin".into(),
+            password: "hunter2".into(),
+            region: "test".into(),
+            certificate: None,
+            shares_dump_location: None,
+            mover_ids: None,
+            clock_skew_secs: None,
+            validate_session: None,
+            legacy_id_fields: true,
+            max_response_bytes: None,
+        },
+        cookie_jar,
+        closed: false,
+        session: Arc::new(()),
+    }
+}
+
+#[test]
+fn test_vnx_health_check_relogin() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    let logins = Arc::new(AtomicUsize::new(0));
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let port = crate::mock_https_server(move |req| {
+        if req.path == "/Login" {
+            let n = logins.fetch_add(1, Ordering::SeqCst) + 1;
+            recorded.lock().unwrap().push(format!("login {}", n));
+            return (
+                200,
+                format!("Set-Cookie: Ticket=ticket-{}; Path=/\r\n", n),
+                vec![],
+            );
+        }
+        let ticket = if req.head.contains("Ticket=ticket-1") {
+            1
+        } else {
+            2
+        };
+        if req
+            .head
+            .to_lowercase()
+            .contains("celerraconnector-ctl: disconnect")
+        {
+            recorded.lock().unwrap().push(format!("logout {}", ticket));
+            return (200, String::new(), vec![]);
+        }
+        recorded.lock().unwrap().push(format!("query {}", ticket));
+        let cookie = format!("Set-Cookie: JSESSIONID=session-{}; Path=/\r\n", ticket);
+        if ticket == 1 {
+            // The first session is accepted at login but answers with a
+            // login page
+            (200, cookie, b"<html><body>Login</body></html>".to_vec())
+        } else {
+            (
+                200,
+                cookie,
+                br#"<ResponsePacket xmlns="http://www.emc.com/schemas/celerra/xml_api"><Response><QueryStatus maxSeverity="ok"/></Response></ResponsePacket>"#.to_vec(),
+            )
+        }
+    });
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let config = VnxConfig {
+        endpoint: format!("127.0.0.1:{}", port),
+        user: "admin".into(),
+        password: "hunter2".into(),
+        region: "test".into(),
+        certificate: None,
+        shares_dump_location: None,
+        mover_ids: None,
+        clock_skew_secs: None,
+        validate_session: Some(true),
+        legacy_id_fields: true,
+        max_response_bytes: None,
+    };
+    let mut vnx = Vnx::new(&client, config).unwrap();
+    // The failed session is logged out before logging in again
+    assert_eq!(
+        *requests.lock().unwrap(),
+        vec!["login 1", "query 1", "logout 1", "login 2", "query 2"]
+    );
+
+    // A working session is left alone
+    vnx.health_check().unwrap();
+    assert_eq!(requests.lock().unwrap().len(), 6);
+    assert_eq!(requests.lock().unwrap()[5], "query 2");
+    vnx.close().unwrap();
+    assert_eq!(requests.lock().unwrap()[6], "logout 2");
+}
+
+#[test]
+fn test_session_check_requires_response_packet() {
+    assert!(SessionCheck::from_xml("").is_err());
+    assert!(SessionCheck::from_xml("<html><body>Login</body></html>").is_err());
+    assert!(SessionCheck::from_xml(r#"<ResponsePacket xmlns="x"/>"#).is_ok());
+}
+
+#[test]
+fn test_vnx_close() {
+    use std::sync::atomic::Ordering;
+
+    let (port, requests) = mock_vnx_server();
+    let vnx = mock_vnx(port);
+    vnx.close().unwrap();
+    // Drop after close must not log out a second time
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_vnx_drop_without_close() {
+    use std::sync::atomic::Ordering;
+
+    let (port, requests) = mock_vnx_server();
+    {
+        let _vnx = mock_vnx(port);
+    }
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_vnx_log_endpoint() {
+    crate::capture_logs();
+    let (port, _) = mock_vnx_server();
+    let mut vnx = mock_vnx(port);
+    let _ = vnx.cifs_server_request();
+    let logs = crate::captured_logs(&format!("[test/127.0.0.1:{}] api_request", port));
+    println!("logs: {:#?}", logs);
+    assert_eq!(logs.len(), 1);
+}
+
+#[test]
+fn test_vnx_get_disks() {
+    use chrono::TimeZone;
+
+    let mut fixture = vec![];
+    File::open("tests/vnx/clariion_disk_query.xml")
+        .unwrap()
+        .read_to_end(&mut fixture)
+        .unwrap();
+    let (port, _, bodies) = mock_vnx_api(move |body| {
+        if body.contains("ClariionDiskQueryParams") {
+            fixture.clone()
+        } else {
+            vec![]
+        }
+    });
+    let mut vnx = mock_vnx(port);
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let points = vnx.get_disks(t).unwrap();
+    println!("points: {:#?}", points);
+    assert!(bodies.lock().unwrap()[0].contains("<ClariionDiskQueryParams />"));
+    assert!(!points.is_empty());
+    assert!(points
+        .iter()
+        .all(|p| p.measurement == "vnx_disk_info" && p.timestamp == Some(t)));
+    assert_eq!(points[0].tags["name"], TsValue::String("0_0_0".into()));
+    assert_eq!(points[0].fields["capacity"], TsValue::Long(1125767168));
+
+    vnx.disk_info_request("1").unwrap();
+    assert!(bodies.lock().unwrap()[1].contains(r#"clariion="1""#));
+}
+
+#[test]
+fn test_vnx_get_mover_interfaces() {
+    use chrono::TimeZone;
+
+    let mut fixture = vec![];
+    File::open("tests/vnx/mover_interface_query.xml")
+        .unwrap()
+        .read_to_end(&mut fixture)
+        .unwrap();
+    let (port, _, bodies) = mock_vnx_api(move |body| {
+        if body.contains("MoverQueryParams") {
+            fixture.clone()
+        } else {
+            vec![]
+        }
+    });
+    let mut vnx = mock_vnx(port);
+    let t = Utc.timestamp_opt(1_577_836_800, 0).unwrap();
+    let points = vnx.get_mover_interfaces(t).unwrap();
+    assert!(bodies.lock().unwrap()[0].contains(r#"<AspectSelection moverInterfaces="true" />"#));
+    assert_eq!(points.len(), 3);
+    assert!(points
+        .iter()
+        .all(|p| p.measurement == "vnx_mover_interface" && p.timestamp == Some(t)));
+    assert_eq!(
+        points[1].tags["ip_address"],
+        TsValue::String("192.168.10.21".into())
+    );
+}
+
+#[test]
+fn test_vnx_mover_stats_single_request() {
+    use std::sync::atomic::Ordering;
+
+    let mut fixture = vec![];
+    File::open("tests/vnx/resource_stats_multi_mover_query.xml")
+        .unwrap()
+        .read_to_end(&mut fixture)
+        .unwrap();
+    let (port, requests, bodies) = mock_vnx_api(move |_| fixture.clone());
+    let mut vnx = mock_vnx(port);
+    let points = vnx.mover_resource_stats_request(&["1", "2", "3"]).unwrap();
+    println!("points: {:#?}", points);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    let body = bodies.lock().unwrap()[0].clone();
+    assert_eq!(body.matches("<MoverStats").count(), 3);
+    assert!(body.contains(r#"mover="3" statsSet="ResourceUsage""#));
+    assert_eq!(mover_tags(&points), vec!["1", "2", "3"]);
+    // The fixture was captured in 2018 so its sample times are too old
+    let before = Utc::now();
+    for p in &points {
+        assert!(p.timestamp.unwrap() <= before);
+        assert_eq!(p.fields["stamp"], TsValue::Long(360));
+        assert_eq!(p.fields["timestamp_fallback"], TsValue::Boolean(true));
+    }
+
+    // Nothing to ask for
+    assert!(vnx.mover_nfs_stats_request(&[]).unwrap().is_empty());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_vnx_sample_time() {
+    let mut fixture = String::new();
+    File::open("tests/vnx/resource_stats_multi_mover_query.xml")
+        .unwrap()
+        .read_to_string(&mut fixture)
+        .unwrap();
+    // Sampled a minute ago by a control station running two minutes fast
+    let sampled = Utc::now().timestamp() - 60;
+    let fixture = fixture.replace(
+        r#"time="1520353386""#,
+        &format!(r#"time="{}""#, sampled + 120),
+    );
+    let (port, _, _) = mock_vnx_api(move |_| fixture.clone().into_bytes());
+    let mut vnx = mock_vnx(port);
+    vnx.config.clock_skew_secs = Some(-120);
+    let points = vnx.mover_resource_stats_request(&["1", "2", "3"]).unwrap();
+    assert_eq!(points.len(), 3);
+    for p in &points {
+        assert_eq!(p.timestamp.unwrap().timestamp(), sampled);
+        assert_eq!(p.fields["timestamp_fallback"], TsValue::Boolean(false));
+        assert_eq!(p.fields["stamp"], TsValue::Long(360));
+    }
+}
+
+#[test]
+fn test_vnx_query_status() {
+    let mut warning = vec![];
+    File::open("tests/vnx/resource_stats_warning_query.xml")
+        .unwrap()
+        .read_to_end(&mut warning)
+        .unwrap();
+    let (port, _, _) = mock_vnx_api(move |_| warning.clone());
+    let mut vnx = mock_vnx(port);
+    crate::capture_logs();
+    let points = vnx.mover_resource_stats_request(&["1", "2", "3"]).unwrap();
+    // The unreachable mover is missing but the rest come through
+    assert_eq!(mover_tags(&points), vec!["1", "2"]);
+    let logs = crate::captured_logs(&format!(
+        "127.0.0.1:{}] query status warning: 13421840574",
+        port
+    ));
+    assert_eq!(logs.len(), 1);
+
+    let mut error = vec![];
+    File::open("tests/vnx/resource_stats_error_query.xml")
+        .unwrap()
+        .read_to_end(&mut error)
+        .unwrap();
+    let (port, _, _) = mock_vnx_api(move |_| error.clone());
+    let mut vnx = mock_vnx(port);
+    let err = vnx
+        .mover_resource_stats_request(&["1", "2", "3"])
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("14227341323"), "{}", err);
+    assert!(
+        err.contains("The statistics service on the control station is not running."),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_vnx_clone() {
+    use std::sync::atomic::Ordering;
+
+    let (port, requests) = mock_vnx_server();
+    let vnx = mock_vnx(port);
+    let mut cloned = vnx.clone();
+    // The original going away leaves the session to the clone
+    drop(vnx);
+    assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+    // The mock answers with an empty body.  Whatever that parses to, the
+    // request has to reach the server
+    let _ = cloned.cifs_server_request();
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    cloned.close().unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+/// A logged in session with a Vnx.  Callers should prefer `close()` to end
+/// the session so any logout failure can be handled.  If a Vnx is dropped
+/// without being closed a best effort logout is attempted with a short
+/// timeout and any error is only logged.
+///
+/// Clones share the login session but get their own snapshot of the
+/// session cookies.  Only the last handle to be closed or dropped logs out.
+#[derive(Clone)]
 pub struct Vnx {
     client: reqwest::blocking::Client,
     config: VnxConfig,
     cookie_jar: CookieJar,
+    closed: bool,
+    // Shared by every clone of this session
+    session: Arc<()>,
 }
 
+// How long Drop waits on the logout request before giving up
+const DROP_LOGOUT_TIMEOUT: Duration = Duration::from_secs(2);
+
 impl Drop for Vnx {
     fn drop(&mut self) {
-        if let Err(e) = self.logout_request() {
-            error!("Vnx logout request failed: {}", e);
+        if self.closed {
+            return;
+        }
+        if !self.release_session() {
+            return;
+        }
+        if std::thread::panicking() {
+            // Don't block unwinding on a network request
+            error!(
+                "{} Vnx dropped during a panic.  Skipping logout",
+                self.config.log_prefix()
+            );
+            return;
+        }
+        if let Err(e) = self.logout(Some(DROP_LOGOUT_TIMEOUT)) {
+            error!(
+                "{} Vnx logout request failed: {}",
+                self.config.log_prefix(),
+                e
+            );
+        }
+    }
+}
+
+/// Drives a `Vnx` as a `Collector`.  Its requests need `&mut self` to keep
+/// the session cookies up to date, so collections take turns on the one
+/// session.  It's logged out when this is dropped
+pub struct VnxCollector {
+    endpoint: String,
+    vnx: Mutex<Vnx>,
+}
+
+impl VnxCollector {
+    pub fn new(vnx: Vnx) -> Self {
+        VnxCollector {
+            endpoint: vnx.config.endpoint.clone(),
+            vnx: Mutex::new(vnx),
         }
     }
 }
 
+impl Collector for VnxCollector {
+    fn backend(&self) -> &str {
+        "vnx"
+    }
+
+    fn array_id(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The mover interfaces, the network, cifs, nfs and resource stats of
+    /// mover_ids and the filesystem capacity and usage
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut vnx = match self.vnx.lock() {
+            Ok(vnx) => vnx,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let interfaces = vnx.mover_interface_query()?;
+        let mover_ids: Vec<String> = match vnx.config.mover_ids {
+            Some(ref ids) => ids.clone(),
+            None => {
+                let mut ids: Vec<String> = interfaces
+                    .interfaces
+                    .iter()
+                    .map(|i| i.mover.clone())
+                    .collect();
+                ids.sort();
+                ids.dedup();
+                ids
+            }
+        };
+        let mover_ids: Vec<&str> = mover_ids.iter().map(|id| id.as_str()).collect();
+
+        let mut points = vnx.mover_interface_points(&interfaces, t);
+        let mut network = vnx.mover_network_stats_request(&mover_ids)?;
+        tag_device_interfaces(&mut network, &interfaces.interfaces);
+        points.extend(network);
+        points.extend(vnx.mover_cifs_stats_request(&mover_ids)?);
+        points.extend(vnx.mover_nfs_stats_request(&mover_ids)?);
+        points.extend(vnx.mover_resource_stats_request(&mover_ids)?);
+        points.extend(vnx.filesystem_capacity_request()?);
+        points.extend(vnx.filesystem_usage_request()?);
+        Ok(points)
+    }
+}
+
 impl Vnx {
     pub fn new(client: &reqwest::blocking::Client, config: VnxConfig) -> MetricsResult<Self> {
+        config.validate()?;
         let mut cookie_jar = CookieJar::new();
         login_request(client, &config, &mut cookie_jar)?;
-        Ok(Vnx {
+        let mut vnx = Vnx {
             client: client.clone(),
             config,
             cookie_jar,
-        })
+            closed: false,
+            session: Arc::new(()),
+        };
+        if vnx.config.validate_session.unwrap_or(false) {
+            vnx.health_check()?;
+        }
+        Ok(vnx)
+    }
+
+    /// Confirm the session still works with a cheap query.  If the query
+    /// fails or the answer can't be parsed this logs out, logs in again and
+    /// retries once.  The new session replaces this handle's cookies only,
+    /// so the old one is only logged out if no clone still shares it
+    pub fn health_check(&mut self) -> MetricsResult<()> {
+        if let Err(e) = self.session_check() {
+            warn!(
+                "{} session check failed, logging in again: {}",
+                self.config.log_prefix(),
+                e
+            );
+            // The old session is already failing so a failed logout is
+            // only logged
+            if self.release_session() {
+                if let Err(e) = self.logout(None) {
+                    warn!(
+                        "{} logout of the failed session failed: {}",
+                        self.config.log_prefix(),
+                        e
+                    );
+                }
+            }
+            self.session = Arc::new(());
+            let mut cookie_jar = CookieJar::new();
+            login_request(&self.client, &self.config, &mut cookie_jar)
+                .map_err(|e| e.with_context("vnx health check"))?;
+            self.cookie_jar = cookie_jar;
+            self.session_check()
+                .map_err(|e| e.with_context("vnx health check"))?;
+        }
+        Ok(())
+    }
+
+    fn session_check(&mut self) -> MetricsResult<SessionCheck> {
+        let mut output: Vec<u8> = Vec::new();
+        {
+            let mut writer = EventWriter::new(&mut output);
+            begin_query_request(&mut writer)?;
+            start_element(&mut writer, "CelerraSystemQueryParams", None, None)?;
+            end_element(&mut writer, "CelerraSystemQueryParams")?;
+            end_query_request(&mut writer)?;
+        }
+        self.api_request(output)
+    }
+
+    /// Log out of the Vnx and end this session.  If clones of this Vnx
+    /// are still alive the session is left to them.
+    pub fn close(mut self) -> MetricsResult<()> {
+        self.closed = true;
+        if !self.release_session() {
+            return Ok(());
+        }
+        self.logout(None)
+    }
+
+    // Give up this handle's share of the session.  Returns true if this
+    // was the last handle and the session should be logged out
+    fn release_session(&mut self) -> bool {
+        Arc::into_inner(std::mem::take(&mut self.session)).is_some()
     }
 
     pub fn logout_request(&self) -> MetricsResult<()> {
+        self.logout(None)
+    }
+
+    fn logout(&self, timeout: Option<Duration>) -> MetricsResult<()> {
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_LENGTH, HeaderValue::from_str("0")?);
         headers.insert(CONTENT_TYPE, HeaderValue::from_str("application/xml")?);
@@ -2526,15 +4574,19 @@ impl Vnx {
         };
         headers.insert("CelerraConnector-Ctl", HeaderValue::from_str("DISCONNECT")?);
 
-        self.client
-            .post(&format!(
-                "https://{}/servlets/CelerraManagementServices",
-                self.config.endpoint
-            ))
-            .headers(headers)
-            .body("")
-            .send()?
-            .error_for_status()?;
+        let url = format!(
+            "https://{}/servlets/CelerraManagementServices",
+            self.config.endpoint
+        );
+        let mut req = self.client.post(&url).headers(headers).body("");
+        if let Some(timeout) = timeout {
+            req = req.timeout(timeout);
+        }
+        req.send().and_then(|r| r.error_for_status()).map_err(|e| {
+            StorageError::from(e.without_url())
+                .with_context(format!("POST {}", redact_url(&url)))
+                .with_context("vnx")
+        })?;
         Ok(())
     }
 
@@ -2572,7 +4624,11 @@ impl Vnx {
                     t.value(),
                     t.path().unwrap_or("/"),
                 );
-                debug!("session cookie: {}", session_cookie);
+                debug!(
+                    "{} session cookie: {}",
+                    self.config.log_prefix(),
+                    session_cookie
+                );
                 headers.insert(
                     HeaderName::from_str("Cookie")?,
                     HeaderValue::from_str(&session_cookie)?,
@@ -2581,77 +4637,121 @@ impl Vnx {
                     HeaderName::from_str("CelerraConnector-Sess")?,
                     HeaderValue::from_str(t.value())?,
                 );
-                debug!("headers: {:?}", headers);
+                debug!("{} headers: {:?}", self.config.log_prefix(), headers);
             }
             None => {
                 headers.insert(COOKIE, HeaderValue::from_str(&ticket_cookie)?);
             }
         };
 
+        let url = format!(
+            "https://{}/servlets/CelerraManagementServices",
+            self.config.endpoint
+        );
+        let with_context = |e: StorageError| {
+            e.with_context(format!("POST {}", redact_url(&url)))
+                .with_context("vnx")
+        };
         let s = self
             .client
-            .post(&format!(
-                "https://{}/servlets/CelerraManagementServices",
-                self.config.endpoint
-            ))
+            .post(&url)
             .body(req)
             .headers(headers)
-            .send()?
-            .error_for_status()?;
+            .send()
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| with_context(StorageError::from(e.without_url())))?;
 
         // From here we should get back a JSESSIONID cookie
         if let Some(cookie) = s.headers().get(SET_COOKIE) {
-            debug!("cookie: {:?}", cookie);
+            debug!("{} cookie: {:?}", self.config.log_prefix(), cookie);
             let parsed = Cookie::parse(cookie.to_str()?.to_owned())?;
             self.cookie_jar.add(parsed);
         };
 
-        let data = s.text()?;
-        debug!("api_request response: {}", data);
-        let res = T::from_xml(&data)?;
+        let data = read_text(s, self.config.max_response_bytes).map_err(with_context)?;
+        debug!(
+            "{} api_request response: {}",
+            self.config.log_prefix(),
+            data
+        );
+        QueryStatus::from_xml(&data)
+            .and_then(|status| status.check(&self.config.log_prefix()))
+            .map_err(with_context)?;
+        let res = T::from_xml(&data).map_err(|e| {
+            with_context(e.with_context(format!(
+                "unable to decode response `{}`",
+                body_snippet(&data)
+            )))
+        })?;
 
         Ok(res)
     }
 
-    pub fn mover_network_stats_request(&mut self, mover_id: &str) -> MetricsResult<Vec<TsPoint>> {
-        self.mover_stats_request::<NetworkAllSample>(mover_id, &MoverStatsRequest::Network)
+    pub fn mover_network_stats_request(
+        &mut self,
+        mover_ids: &[&str],
+    ) -> MetricsResult<Vec<TsPoint>> {
+        self.mover_stats_request::<NetworkAllSample>(mover_ids, &MoverStatsRequest::Network)
     }
 
-    pub fn mover_cifs_stats_request(&mut self, mover_id: &str) -> MetricsResult<Vec<TsPoint>> {
-        self.mover_stats_request::<CifsAllSample>(mover_id, &MoverStatsRequest::Cifs)
+    pub fn mover_cifs_stats_request(&mut self, mover_ids: &[&str]) -> MetricsResult<Vec<TsPoint>> {
+        self.mover_stats_request::<CifsAllSample>(mover_ids, &MoverStatsRequest::Cifs)
     }
 
-    pub fn mover_resource_stats_request(&mut self, mover_id: &str) -> MetricsResult<Vec<TsPoint>> {
-        self.mover_stats_request::<ResourceUsageSample>(mover_id, &MoverStatsRequest::ResourceUsage)
+    pub fn mover_resource_stats_request(
+        &mut self,
+        mover_ids: &[&str],
+    ) -> MetricsResult<Vec<TsPoint>> {
+        self.mover_stats_request::<ResourceUsageSample>(
+            mover_ids,
+            &MoverStatsRequest::ResourceUsage,
+        )
     }
 
-    pub fn mover_nfs_stats_request(&mut self, mover_id: &str) -> MetricsResult<Vec<TsPoint>> {
-        self.mover_stats_request::<NfsAllSample>(mover_id, &MoverStatsRequest::Nfs)
+    pub fn mover_nfs_stats_request(&mut self, mover_ids: &[&str]) -> MetricsResult<Vec<TsPoint>> {
+        self.mover_stats_request::<NfsAllSample>(mover_ids, &MoverStatsRequest::Nfs)
     }
 
-    // Helper function
+    // Helper function.  Every mover gets its own Request in the same
+    // packet so all of them come back in a single round trip.  Points are
+    // stamped with the sample time the control station reported, falling
+    // back to the collection time when it's missing or implausible
     fn mover_stats_request<T>(
         &mut self,
-        mover_id: &str,
+        mover_ids: &[&str],
         req_type: &MoverStatsRequest,
     ) -> MetricsResult<Vec<TsPoint>>
     where
-        T: FromXml + IntoPoint,
+        T: IntoPoint + StatsSample,
+        Vec<T>: FromXml,
     {
-        let mut output: Vec<u8> = Vec::new();
-        let req_type_str = req_type.to_string();
-        {
-            let mut writer = EventWriter::new(&mut output);
-            begin_query_stats_request(&mut writer)?;
-            let e = XmlEvent::start_element("MoverStats")
-                .attr("mover", mover_id)
-                .attr("statsSet", &req_type_str);
-            writer.write(e)?;
-            end_element(&mut writer, "MoverStats")?;
-            end_query_stats_request(&mut writer)?;
+        if mover_ids.is_empty() {
+            return Ok(vec![]);
         }
-        let res: T = self.api_request(output)?;
-        Ok(res.into_point(None, true))
+        let output = mover_stats_query(mover_ids, req_type)?;
+        let collected = Utc::now();
+        let res: Vec<T> = self.api_request(output)?;
+        let skew = self.config.clock_skew_secs;
+        let prefix = self.config.log_prefix();
+        Ok(res
+            .iter()
+            .flat_map(|sample| {
+                let (time, stamp) = sample.time_and_stamp();
+                let sampled = corrected_sample_time(time, skew, collected);
+                if sampled.is_none() {
+                    debug!(
+                        "{} sample time {} is implausible, using the collection time",
+                        prefix, time
+                    );
+                }
+                sample.into_point(None, true).into_iter().map(move |mut p| {
+                    p.timestamp = Some(sampled.unwrap_or(collected));
+                    p.add_field("stamp", TsValue::Long(stamp));
+                    p.add_field("timestamp_fallback", TsValue::Boolean(sampled.is_none()));
+                    p
+                })
+            })
+            .collect())
     }
 
     /*
@@ -2706,6 +4806,23 @@ impl Vnx {
     }
 
     pub fn disk_info_request(&mut self, mover_id: &str) -> MetricsResult<Vec<TsPoint>> {
+        let res = self.disk_query(Some(mover_id))?;
+        Ok(res.into_point(Some("vnx_disk_info"), true))
+    }
+
+    /// The disks of every backend storage system attached to this Vnx,
+    /// stamped with t
+    pub fn get_disks(&mut self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let res = self.disk_query(None)?;
+        Ok(res
+            .into_point(Some("vnx_disk_info"), true)
+            .into_iter()
+            .map(|p| p.set_time(t))
+            .collect())
+    }
+
+    // Query the disk config of one clariion, or of all of them if None
+    fn disk_query(&mut self, clariion: Option<&str>) -> MetricsResult<DiskInfo> {
         let mut output: Vec<u8> = Vec::new();
         {
             let mut writer = EventWriter::new(&mut output);
@@ -2715,16 +4832,63 @@ impl Vnx {
             writer.write(e)?;
             start_element(&mut writer, "RequestEx", None, None)?;
             start_element(&mut writer, "Query", None, None)?;
-            let e = XmlEvent::start_element("ClariionDiskQueryParams").attr("clariion", mover_id);
+            let mut e = XmlEvent::start_element("ClariionDiskQueryParams");
+            if let Some(clariion) = clariion {
+                e = e.attr("clariion", clariion);
+            }
             writer.write(e)?;
             end_element(&mut writer, "ClariionDiskQueryParams")?;
             end_element(&mut writer, "Query")?;
             end_element(&mut writer, "RequestEx")?;
             end_element(&mut writer, "RequestPacket")?;
         }
-        debug!("{}", String::from_utf8_lossy(&output));
-        let res: DiskInfo = self.api_request(output)?;
-        Ok(res.into_point(Some("vnx_disk_info"), true))
+        debug!(
+            "{} {}",
+            self.config.log_prefix(),
+            String::from_utf8_lossy(&output)
+        );
+        self.api_request(output)
+    }
+
+    /// The network interfaces of every data mover, stamped with t.  Pass
+    /// them to tag_device_interfaces to label the network stats devices
+    pub fn get_mover_interfaces(&mut self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let interfaces = self.mover_interface_query()?;
+        Ok(self.mover_interface_points(&interfaces, t))
+    }
+
+    fn mover_interface_points(
+        &self,
+        interfaces: &MoverInterfaces,
+        t: DateTime<Utc>,
+    ) -> Vec<TsPoint> {
+        let mut points: Vec<TsPoint> = interfaces
+            .into_point(Some("vnx_mover_interface"), true)
+            .into_iter()
+            .map(|p| p.set_time(t))
+            .collect();
+        ids_as_tags(
+            &mut points,
+            MoverInterface::ID_TAGS,
+            self.config.legacy_id_fields,
+        );
+        points
+    }
+
+    /// The network interfaces of every data mover
+    pub fn mover_interface_query(&mut self) -> MetricsResult<MoverInterfaces> {
+        let mut output: Vec<u8> = Vec::new();
+        {
+            let mut writer = EventWriter::new(&mut output);
+            begin_query_request(&mut writer)?;
+            start_element(&mut writer, "MoverQueryParams", None, None)?;
+            let e = XmlEvent::start_element("AspectSelection").attr("moverInterfaces", "true");
+            writer.write(e)?;
+            end_element(&mut writer, "AspectSelection")?;
+            end_element(&mut writer, "MoverQueryParams")?;
+            end_query_request(&mut writer)?;
+        }
+        self.api_request(output)
     }
 
     pub fn cifs_server_request(&mut self) -> MetricsResult<Vec<TsPoint>> {
@@ -2757,7 +4921,13 @@ impl Vnx {
             end_query_request(&mut writer)?;
         }
         let res: FileSystemCapacities = self.api_request(output)?;
-        Ok(res.into_point(Some("vnx_filesystem_capacity"), true))
+        let mut points = res.into_point(Some("vnx_filesystem_capacity"), true);
+        ids_as_tags(
+            &mut points,
+            FileSystemCapacity::ID_TAGS,
+            self.config.legacy_id_fields,
+        );
+        Ok(points)
     }
 
     pub fn filesystem_usage_request(&mut self) -> MetricsResult<Vec<TsPoint>> {
@@ -2791,7 +4961,8 @@ impl Vnx {
         // Request the mount info from the VNX
         let res = self.api_request::<Mounts>(output)?;
 
-        let points = res.into_point(Some("vnx_mounts"), false);
+        let mut points = res.into_point(Some("vnx_mounts"), false);
+        ids_as_tags(&mut points, Mount::ID_TAGS, self.config.legacy_id_fields);
         Ok(points)
     }
 
@@ -2839,6 +5010,28 @@ fn end_query_request<W: Write>(w: &mut EventWriter<W>) -> MetricsResult<()> {
     Ok(())
 }
 
+fn mover_stats_query(mover_ids: &[&str], req_type: &MoverStatsRequest) -> MetricsResult<Vec<u8>> {
+    let mut output: Vec<u8> = Vec::new();
+    let req_type_str = req_type.to_string();
+    {
+        let mut writer = EventWriter::new(&mut output);
+        start_request(&mut writer)?;
+        for mover_id in mover_ids {
+            start_element(&mut writer, "Request", None, None)?;
+            start_element(&mut writer, "QueryStats", None, None)?;
+            let e = XmlEvent::start_element("MoverStats")
+                .attr("mover", mover_id)
+                .attr("statsSet", &req_type_str);
+            writer.write(e)?;
+            end_element(&mut writer, "MoverStats")?;
+            end_element(&mut writer, "QueryStats")?;
+            end_element(&mut writer, "Request")?;
+        }
+        end_element(&mut writer, "RequestPacket")?;
+    }
+    Ok(output)
+}
+
 fn begin_query_stats_request<W: Write>(w: &mut EventWriter<W>) -> MetricsResult<()> {
     start_request(w)?;
     start_element(w, "Request", None, None)?;
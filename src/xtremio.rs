@@ -15,16 +15,18 @@
 *
 * SPDX-License-Identifier: Apache-2.0
 */
+use crate::collector::Collector;
 use crate::deserialize_string_or_float;
 use crate::deserialize_string_or_int;
 use crate::error::MetricsResult;
 use crate::IntoPoint;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::str;
 
 use crate::ir::{TsPoint, TsValue};
+use chrono::{DateTime, Utc};
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
@@ -43,6 +45,9 @@ pub struct XtremIOConfig {
     pub root_certificate: Option<String>,
     /// The region this cluster is located in
     pub region: String,
+    /// Largest response body to read, in bytes.  Defaults to
+    /// DEFAULT_MAX_RESPONSE_BYTES
+    pub max_response_bytes: Option<u64>,
 }
 
 pub struct XtremIo {
@@ -59,6 +64,27 @@ impl XtremIo {
     }
 }
 
+impl Collector for XtremIo {
+    fn backend(&self) -> &str {
+        "xtremio"
+    }
+
+    fn array_id(&self) -> &str {
+        &self.config.endpoint
+    }
+
+    fn collect(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let mut points = self.get_clusters()?;
+        points.extend(self.get_bricks(t)?);
+        points.extend(self.get_ssd_endurance(t)?);
+        points.extend(self.get_ssds()?);
+        points.extend(self.get_psus()?);
+        points.extend(self.get_volumes()?);
+        points.extend(self.get_xms()?);
+        Ok(points)
+    }
+}
+
 #[test]
 fn test_get_xtremio_volumes() {
     use std::fs::File;
@@ -307,6 +333,286 @@ pub struct Ssd {
     pub useful_ssd_space: i64,
 }
 
+// Object references such as brick-id and sys-id are [guid, name, index]
+fn reference_name(reference: &[Value]) -> Option<&str> {
+    reference.get(1).and_then(|name| name.as_str())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SsdDetails {
+    pub ssds: Vec<SsdDetail>,
+    pub links: Vec<Link>,
+}
+
+impl IntoPoint for SsdDetails {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut points: Vec<TsPoint> = Vec::new();
+        let n = name.unwrap_or("xtremio_ssd");
+
+        for v in &self.ssds {
+            points.extend(v.into_point(Some(n), is_time_series));
+        }
+
+        points
+    }
+}
+
+/// The v3 ssd detail object, trimmed to the wear and placement fields
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct SsdDetail {
+    pub name: String,
+    pub ssd_uid: String,
+    /// Not reported for uninitialized ssds
+    pub percent_endurance_remaining: Option<i64>,
+    pub ssd_size_in_kb: Option<i64>,
+    pub slot_num: Option<i64>,
+    pub fru_lifecycle_state: String,
+    #[serde(default)]
+    pub brick_id: Vec<Value>,
+    #[serde(default)]
+    pub sys_id: Vec<Value>,
+}
+
+impl SsdDetail {
+    /// Uninitialized ssds haven't joined a brick's raid group yet so their
+    /// endurance doesn't reflect any wear
+    pub fn is_uninitialized(&self) -> bool {
+        self.fru_lifecycle_state == "uninitialized"
+    }
+}
+
+impl IntoPoint for SsdDetail {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("xtremio_ssd"), is_time_series);
+        if let Some(cluster) = reference_name(&self.sys_id) {
+            p.add_tag("cluster", TsValue::String(cluster.to_string()));
+        }
+        if let Some(brick) = reference_name(&self.brick_id) {
+            p.add_tag("brick", TsValue::String(brick.to_string()));
+        }
+        if let Some(slot) = self.slot_num {
+            p.add_tag("slot", TsValue::String(slot.to_string()));
+        }
+        p.add_tag("ssd_uid", TsValue::String(self.ssd_uid.clone()));
+        p.add_tag("name", TsValue::String(self.name.clone()));
+        if let Some(endurance) = self.percent_endurance_remaining {
+            p.add_field(
+                "percent_endurance_remaining",
+                TsValue::Long(endurance.max(0) as u64),
+            );
+        }
+        if let Some(size) = self.ssd_size_in_kb {
+            p.add_field("ssd_size_in_kb", TsValue::Long(size.max(0) as u64));
+        }
+        p.add_field(
+            "fru_lifecycle_state",
+            TsValue::String(self.fru_lifecycle_state.clone()),
+        );
+        p.add_field("uninitialized", TsValue::Boolean(self.is_uninitialized()));
+
+        vec![p]
+    }
+}
+
+/// One point per cluster with the lowest and average endurance remaining
+/// over its ssds.  Uninitialized ssds are counted in `uninitialized_ssds`
+/// but left out of the endurance figures
+pub fn ssd_endurance_summary(ssds: &[SsdDetail], name: Option<&str>) -> Vec<TsPoint> {
+    // cluster -> (endurance of each initialized ssd, uninitialized count)
+    let mut clusters: BTreeMap<&str, (Vec<i64>, u64)> = BTreeMap::new();
+    for ssd in ssds {
+        let cluster = reference_name(&ssd.sys_id).unwrap_or("");
+        let entry = clusters.entry(cluster).or_insert_with(|| (Vec::new(), 0));
+        if ssd.is_uninitialized() {
+            entry.1 += 1;
+        } else if let Some(endurance) = ssd.percent_endurance_remaining {
+            entry.0.push(endurance);
+        }
+    }
+
+    clusters
+        .into_iter()
+        .map(|(cluster, (endurance, uninitialized))| {
+            let mut p = TsPoint::new(name.unwrap_or("xtremio_ssd_endurance"), true);
+            if !cluster.is_empty() {
+                p.add_tag("cluster", TsValue::String(cluster.to_string()));
+            }
+            p.add_field("ssds", TsValue::Long(endurance.len() as u64));
+            p.add_field("uninitialized_ssds", TsValue::Long(uninitialized));
+            if let Some(min) = endurance.iter().min() {
+                p.add_field(
+                    "min_percent_endurance_remaining",
+                    TsValue::Long((*min).max(0) as u64),
+                );
+                p.add_field(
+                    "avg_percent_endurance_remaining",
+                    TsValue::Float(endurance.iter().sum::<i64>() as f64 / endurance.len() as f64),
+                );
+            }
+            p
+        })
+        .collect()
+}
+
+#[test]
+fn test_get_xtremio_ssd_endurance() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f = File::open("tests/xtremio/ssds_v3.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+
+    let i: SsdDetails = serde_json::from_str(&buff).unwrap();
+    println!("result: {:#?}", i);
+
+    let points = i.into_point(None, true);
+    assert_eq!(points.len(), 6);
+    assert_eq!(
+        points[0].tags.get("cluster"),
+        Some(&TsValue::String("xbrick01".into()))
+    );
+    assert_eq!(
+        points[0].tags.get("brick"),
+        Some(&TsValue::String("X1".into()))
+    );
+    assert_eq!(
+        points[0].tags.get("slot"),
+        Some(&TsValue::String("0".into()))
+    );
+    assert_eq!(
+        points[0].fields.get("percent_endurance_remaining"),
+        Some(&TsValue::Long(98))
+    );
+    // The uninitialized ssd is still reported, just flagged
+    assert_eq!(
+        points[3].fields.get("fru_lifecycle_state"),
+        Some(&TsValue::String("uninitialized".into()))
+    );
+    assert_eq!(
+        points[3].fields.get("uninitialized"),
+        Some(&TsValue::Boolean(true))
+    );
+
+    let summary = ssd_endurance_summary(&i.ssds, None);
+    println!("summary: {:#?}", summary);
+    assert_eq!(summary.len(), 2);
+    assert_eq!(
+        summary[0].tags.get("cluster"),
+        Some(&TsValue::String("xbrick01".into()))
+    );
+    // 98, 91 and 86 with the uninitialized ssd left out
+    assert_eq!(
+        summary[0].fields.get("min_percent_endurance_remaining"),
+        Some(&TsValue::Long(86))
+    );
+    assert_eq!(
+        summary[0].fields.get("avg_percent_endurance_remaining"),
+        Some(&TsValue::Float(275.0 / 3.0))
+    );
+    assert_eq!(summary[0].fields.get("ssds"), Some(&TsValue::Long(3)));
+    assert_eq!(
+        summary[0].fields.get("uninitialized_ssds"),
+        Some(&TsValue::Long(1))
+    );
+    assert_eq!(
+        summary[1].fields.get("min_percent_endurance_remaining"),
+        Some(&TsValue::Long(77))
+    );
+    assert_eq!(
+        summary[1].fields.get("avg_percent_endurance_remaining"),
+        Some(&TsValue::Float(88.0))
+    );
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Bricks {
+    pub bricks: Vec<Brick>,
+    pub links: Vec<Link>,
+}
+
+impl IntoPoint for Bricks {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut points: Vec<TsPoint> = Vec::new();
+        let n = name.unwrap_or("xtremio_brick");
+
+        for v in &self.bricks {
+            points.extend(v.into_point(Some(n), is_time_series));
+        }
+
+        points
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Brick {
+    pub name: String,
+    pub index: i64,
+    pub guid: String,
+    #[serde(default)]
+    pub sys_id: Vec<Value>,
+    pub brick_state: Option<String>,
+    pub num_of_nodes: Option<i64>,
+    pub num_of_ssds: Option<i64>,
+    pub obj_severity: Option<String>,
+}
+
+impl IntoPoint for Brick {
+    fn into_point(&self, name: Option<&str>, is_time_series: bool) -> Vec<TsPoint> {
+        let mut p = TsPoint::new(name.unwrap_or("xtremio_brick"), is_time_series);
+        if let Some(cluster) = reference_name(&self.sys_id) {
+            p.add_tag("cluster", TsValue::String(cluster.to_string()));
+        }
+        p.add_tag("brick", TsValue::String(self.name.clone()));
+        p.add_tag("guid", TsValue::String(self.guid.clone()));
+        p.add_field("index", TsValue::SignedLong(self.index));
+        if let Some(ref state) = self.brick_state {
+            p.add_field("brick_state", TsValue::String(state.clone()));
+        }
+        if let Some(nodes) = self.num_of_nodes {
+            p.add_field("num_of_nodes", TsValue::SignedLong(nodes));
+        }
+        if let Some(ssds) = self.num_of_ssds {
+            p.add_field("num_of_ssds", TsValue::SignedLong(ssds));
+        }
+        if let Some(ref severity) = self.obj_severity {
+            p.add_field("obj_severity", TsValue::String(severity.clone()));
+        }
+
+        vec![p]
+    }
+}
+
+#[test]
+fn test_get_xtremio_bricks() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut f = File::open("tests/xtremio/bricks_v3.json").unwrap();
+    let mut buff = String::new();
+    f.read_to_string(&mut buff).unwrap();
+
+    let i: Bricks = serde_json::from_str(&buff).unwrap();
+    println!("result: {:#?}", i);
+
+    let points = i.into_point(None, true);
+    assert_eq!(points.len(), 3);
+    assert_eq!(
+        points[2].tags.get("cluster"),
+        Some(&TsValue::String("xbrick02".into()))
+    );
+    assert_eq!(
+        points[2].tags.get("brick"),
+        Some(&TsValue::String("X1".into()))
+    );
+    assert_eq!(
+        points[2].fields.get("num_of_ssds"),
+        Some(&TsValue::SignedLong(2))
+    );
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Psus {
     #[serde(rename = "storage-controller-psus")]
@@ -893,16 +1199,55 @@ impl XtremIo {
             "https://{}/api/json/v2/types/{}?full=1",
             self.config.endpoint, api_endpoint,
         );
-        let j: T = crate::get(
+        let j: T = crate::get_limited(
             &self.client,
             &url,
             &self.config.user,
             Some(&self.config.password),
+            self.config.max_response_bytes,
         )?;
 
         Ok(j.into_point(Some(point_name), true))
     }
 
+    fn get_v3<T>(&self, api_endpoint: &str) -> MetricsResult<T>
+    where
+        T: DeserializeOwned + Debug,
+    {
+        let url = format!(
+            "https://{}/api/json/v3/types/{}?full=1",
+            self.config.endpoint, api_endpoint,
+        );
+        crate::get_limited(
+            &self.client,
+            &url,
+            &self.config.user,
+            Some(&self.config.password),
+            self.config.max_response_bytes,
+        )
+    }
+
+    pub fn get_bricks(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let bricks = self.get_v3::<Bricks>("bricks")?;
+        Ok(bricks
+            .into_point(Some("xtremio_brick"), true)
+            .into_iter()
+            .map(|p| p.set_time(t))
+            .collect())
+    }
+
+    /// Per ssd endurance points followed by an endurance summary point for
+    /// each cluster
+    pub fn get_ssd_endurance(&self, t: DateTime<Utc>) -> MetricsResult<Vec<TsPoint>> {
+        let ssds = self.get_v3::<SsdDetails>("ssds")?;
+        let mut points = ssds.into_point(Some("xtremio_ssd"), true);
+        points.extend(ssd_endurance_summary(
+            &ssds.ssds,
+            Some("xtremio_ssd_endurance"),
+        ));
+        Ok(points.into_iter().map(|p| p.set_time(t)).collect())
+    }
+
     pub fn get_clusters(&self) -> MetricsResult<Vec<TsPoint>> {
         let points = self.get_data::<Clusters>("clusters", "cluster")?;
         Ok(points)
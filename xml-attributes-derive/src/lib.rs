@@ -24,9 +24,18 @@ extern crate syn;
 use proc_macro::TokenStream;
 use proc_macro2::{Literal, Spacing, Span, TokenNode, TokenTree};
 use quote::ToTokens;
-use syn::{Data, Ident, Type};
+use syn::{Data, Ident, Lit, Meta, NestedMeta, Type};
 
-#[proc_macro_derive(FromXmlAttributes)]
+/// Fields are read from the xml attribute of the same name, minus any
+/// leading `_`.  `#[xml(rename = "...")]` on a field names the attribute
+/// explicitly for keys that aren't usable as rust identifiers.
+/// `#[xml(lenient_numbers)]` on the struct reads u64 and f64 fields with
+/// `parse_lenient_u64` and `parse_lenient_f64`, for responses that format
+/// counters with separators or a unit suffix.  Without it they are parsed
+/// strictly.  `#[xml(unit = 512)]` on a u64 field counted in 512 byte
+/// blocks reads it with `parse_lenient_u64_in` so a suffixed value is
+/// converted to blocks rather than bytes.
+#[proc_macro_derive(FromXmlAttributes, attributes(xml))]
 pub fn from_xml_attributes(input: TokenStream) -> TokenStream {
     // Parse the input stream
     let ast = syn::parse(input).unwrap();
@@ -41,7 +50,10 @@ pub fn from_xml_attributes(input: TokenStream) -> TokenStream {
 fn impl_xml(ast: &syn::DeriveInput) -> quote::Tokens {
     let name = &ast.ident;
     match ast.data {
-        Data::Struct(ref data) => impl_struct_xml_fields(name, &data.fields),
+        Data::Struct(ref data) => {
+            let lenient = has_xml_word(&ast.attrs, "lenient_numbers");
+            impl_struct_xml_fields(name, &data.fields, lenient)
+        }
         Data::Enum(ref data) => enum_fields(name, data),
         Data::Union(ref data) => union_fields(name, data),
     }
@@ -67,7 +79,83 @@ fn enum_fields(_name: &syn::Ident, _variants: &syn::DataEnum) -> quote::Tokens {
     }
 }
 
-fn impl_struct_xml_fields(name: &syn::Ident, fields: &syn::Fields) -> quote::Tokens {
+// Look for #[xml(rename = "value")]
+fn find_xml_rename(attrs: &[syn::Attribute]) -> Option<String> {
+    for attr in attrs {
+        if let Some(Meta::List(list)) = attr.interpret_meta() {
+            if list.ident != "xml" {
+                continue;
+            }
+            for nested in list.nested.iter() {
+                if let NestedMeta::Meta(Meta::NameValue(ref nv)) = *nested {
+                    if nv.ident != "rename" {
+                        continue;
+                    }
+                    match nv.lit {
+                        Lit::Str(ref s) => return Some(s.value()),
+                        _ => panic!("#[xml(rename)] expects a string literal"),
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+// Look for #[xml(unit = N)]
+fn find_xml_unit(attrs: &[syn::Attribute]) -> Option<u64> {
+    for attr in attrs {
+        if let Some(Meta::List(list)) = attr.interpret_meta() {
+            if list.ident != "xml" {
+                continue;
+            }
+            for nested in list.nested.iter() {
+                if let NestedMeta::Meta(Meta::NameValue(ref nv)) = *nested {
+                    if nv.ident != "unit" {
+                        continue;
+                    }
+                    match nv.lit {
+                        Lit::Int(ref i) => return Some(i.value()),
+                        _ => panic!("#[xml(unit)] expects an integer literal"),
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+// Look for #[xml(word)]
+fn has_xml_word(attrs: &[syn::Attribute], word: &str) -> bool {
+    for attr in attrs {
+        if let Some(Meta::List(list)) = attr.interpret_meta() {
+            if list.ident != "xml" {
+                continue;
+            }
+            for nested in list.nested.iter() {
+                if let NestedMeta::Meta(Meta::Word(ref w)) = *nested {
+                    if w == word {
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+    false
+}
+
+// Name the struct, attribute and value in parse errors so a change in what
+// the server sends back can be tracked down
+fn parse_context(name: &syn::Ident, key: &str) -> quote::Tokens {
+    let name = name.as_ref();
+    quote! {
+        |e| StorageError::from(e).with_context(
+            format!("parsing {} attribute {} from {:?}", #name, #key, val)
+        )
+    }
+}
+
+fn impl_struct_xml_fields(name: &syn::Ident, fields: &syn::Fields, lenient: bool) -> quote::Tokens {
     let mut result = Vec::new();
     for field in fields.iter() {
         let ident = &field.ident;
@@ -162,13 +250,9 @@ fn impl_struct_xml_fields(name: &syn::Ident, fields: &syn::Fields) -> quote::Tok
         let boolean = Ident::new("bool", Span::def_site());
 
         let i = ident.unwrap();
-        let ident_name = {
-            let i = i.as_ref();
-            if i.starts_with("_") {
-                i.trim_start_matches("_")
-            } else {
-                i
-            }
+        let ident_name = match find_xml_rename(&field.attrs) {
+            Some(rename) => rename,
+            None => i.as_ref().trim_start_matches("_").to_string(),
         };
 
         match ident_type {
@@ -182,11 +266,26 @@ fn impl_struct_xml_fields(name: &syn::Ident, fields: &syn::Fields) -> quote::Tok
                         .into_tokens(),
                     );
 
-                    result.push(quote! {
-                        => {
-                            #ident = u64::from_str(&val)?;
-                        }
-                    });
+                    let context = parse_context(name, &ident_name);
+                    if let Some(unit) = find_xml_unit(&field.attrs) {
+                        result.push(quote! {
+                            => {
+                                #ident = parse_lenient_u64_in(&val, #unit).map_err(#context)?;
+                            }
+                        });
+                    } else if lenient {
+                        result.push(quote! {
+                            => {
+                                #ident = parse_lenient_u64(&val).map_err(#context)?;
+                            }
+                        });
+                    } else {
+                        result.push(quote! {
+                            => {
+                                #ident = u64::from_str(&val).map_err(#context)?;
+                            }
+                        });
+                    }
                 } else if i_type == f_64 {
                     result.push(
                         TokenTree {
@@ -196,11 +295,20 @@ fn impl_struct_xml_fields(name: &syn::Ident, fields: &syn::Fields) -> quote::Tok
                         .into_tokens(),
                     );
 
-                    result.push(quote! {
-                        => {
-                            #ident = f64::from_str(&val)?;
-                        }
-                    });
+                    let context = parse_context(name, &ident_name);
+                    if lenient {
+                        result.push(quote! {
+                            => {
+                                #ident = parse_lenient_f64(&val).map_err(#context)?;
+                            }
+                        });
+                    } else {
+                        result.push(quote! {
+                            => {
+                                #ident = f64::from_str(&val).map_err(#context)?;
+                            }
+                        });
+                    }
                 } else if i_type == string {
                     result.push(
                         TokenTree {
@@ -224,9 +332,10 @@ fn impl_struct_xml_fields(name: &syn::Ident, fields: &syn::Fields) -> quote::Tok
                         .into_tokens(),
                     );
 
+                    let context = parse_context(name, &ident_name);
                     result.push(quote! {
                         => {
-                            #ident = bool::from_str(&val)?;
+                            #ident = bool::from_str(&val).map_err(#context)?;
                         }
                     });
                 } else {